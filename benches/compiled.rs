@@ -0,0 +1,68 @@
+//! Compares evaluating one formula over many inputs with the interpreter and
+//! with a `CompiledExpr`. Run with `cargo bench --bench compiled`.
+
+use oxide::compiled::CompiledExpr;
+use oxide::interpreter::{Environment, Value};
+use oxide::{Stmt, parse_source};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const INPUTS: i64 = 1_000_000;
+
+const FORMULA: &str = "(rate * hours + bonus) * (100 - tax) / 100 - hours / 8 * fee + (2 * 3 - 1);";
+
+fn main() {
+    let expr = match parse_source(FORMULA).unwrap().statements.remove(0) {
+        Stmt::Expression(expr) => expr,
+        other => panic!("expected an expression statement, got {:?}", other),
+    };
+
+    // The inputs that stay the same are bound once, as a host would
+    let mut base = Environment::new();
+    for (name, value) in [("rate", 25), ("bonus", 150), ("tax", 22), ("fee", 3)] {
+        base.define(name, Value::Int(value));
+    }
+    let interpreted = time(|hours| {
+        let mut env = base.clone();
+        env.define("hours", Value::Int(hours));
+        env.evaluate(&expr)
+    });
+
+    let compiled = CompiledExpr::new(&expr);
+    let slot = compiled.slot("hours").unwrap();
+    let mut inputs: Vec<Value> = compiled
+        .slots()
+        .iter()
+        .map(|name| base.get(name).cloned().unwrap_or(Value::Int(0)))
+        .collect();
+    let compiled = time(|hours| {
+        inputs[slot] = Value::Int(hours);
+        compiled.evaluate(&inputs)
+    });
+
+    println!("{} evaluations of {}", INPUTS, FORMULA);
+    report("interpreter", interpreted);
+    report("compiled", compiled);
+    println!(
+        "compiled is {:.1}x faster",
+        interpreted.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
+
+// The time `evaluate` takes over every input
+fn time<T>(mut evaluate: impl FnMut(i64) -> T) -> Duration {
+    let start = Instant::now();
+    for hours in 0..INPUTS {
+        black_box(evaluate(black_box(hours)));
+    }
+    start.elapsed()
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!(
+        "{:<12} {:>8.2?} total, {:>6.1} ns per evaluation",
+        name,
+        elapsed,
+        elapsed.as_nanos() as f64 / INPUTS as f64
+    );
+}
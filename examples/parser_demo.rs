@@ -137,7 +137,7 @@ fn main() {
             }
 
             // Let's also show the detailed structure
-            if let Some(Stmt::Let { name, value }) = program.statements.first() {
+            if let Some(Stmt::Let { name, value, .. }) = program.statements.first() {
                 println!("\nDetailed AST structure for variable '{}':", name);
                 print_expr_structure(value, 0);
             }
@@ -150,7 +150,12 @@ fn print_expr_structure(expr: &Expr, indent: usize) {
     let indent_str = "  ".repeat(indent);
     match expr {
         Expr::Number(n) => println!("{}Number({})", indent_str, n),
+        Expr::Float(n) => println!("{}Float({:?})", indent_str, n),
+        Expr::BigNumber(digits) => println!("{}BigNumber({})", indent_str, digits),
         Expr::Identifier(name) => println!("{}Identifier({})", indent_str, name),
+        Expr::Suffixed { value, suffix } => {
+            println!("{}Suffixed({}, {})", indent_str, value, suffix)
+        }
         Expr::Binary {
             left,
             operator,
@@ -171,5 +176,11 @@ fn print_expr_structure(expr: &Expr, indent: usize) {
             println!("{}Grouping:", indent_str);
             print_expr_structure(inner, indent + 1);
         }
+        Expr::Tuple(elements) => {
+            println!("{}Tuple({}):", indent_str, elements.len());
+            for element in elements {
+                print_expr_structure(element, indent + 1);
+            }
+        }
     }
 }
@@ -0,0 +1,65 @@
+//! Node.js bindings for Oxide, built as a native addon with the napi-rs CLI
+//! (`napi build --release` in this directory). Function names are camelCased
+//! on the JavaScript side.
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use oxide::format::{FormatOptions, format_source};
+use oxide::workspace::{FileDiagnostic, Severity, check_source};
+use std::path::Path;
+
+/// A problem found in the source, with its location when known
+#[napi(object)]
+pub struct Diagnostic {
+    /// `"error"` or `"warning"`
+    pub severity: String,
+    pub message: String,
+    /// Byte offsets of the problem in the source
+    pub start: Option<u32>,
+    pub end: Option<u32>,
+    /// One-based line and column of `start`
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl From<&FileDiagnostic> for Diagnostic {
+    fn from(diagnostic: &FileDiagnostic) -> Self {
+        Self {
+            severity: diagnostic.severity.to_string(),
+            message: diagnostic.message.clone(),
+            start: diagnostic.span.map(|span| span.start as u32),
+            end: diagnostic.span.map(|span| span.end as u32),
+            line: diagnostic.position.map(|(line, _)| line as u32),
+            column: diagnostic.position.map(|(_, column)| column as u32),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct CompileResult {
+    /// Whether the source compiled without errors; warnings are allowed
+    pub ok: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Checks source code like `oxide check`
+#[napi]
+pub fn compile(source: String) -> CompileResult {
+    let diagnostics = check_source(Path::new("<string>"), &source);
+    CompileResult {
+        ok: !diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error),
+        diagnostics: diagnostics.iter().map(Diagnostic::from).collect(),
+    }
+}
+
+/// Formats source code, throwing if it does not parse
+#[napi]
+pub fn format(source: String, indent_width: Option<u32>) -> Result<String> {
+    let mut options = FormatOptions::default();
+    if let Some(width) = indent_width {
+        options.indent_width = width as usize;
+    }
+    format_source(&source, &options).map_err(|errors| Error::from_reason(errors.to_string()))
+}
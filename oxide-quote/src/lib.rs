@@ -0,0 +1,371 @@
+//! `quote_oxide!` turns Oxide source written inline in Rust into the code that
+//! builds its syntax tree, so hosts can construct programs without spelling
+//! out every `Expr` and `Stmt`. Use it through the `quote` feature of the
+//! `oxide` crate, which re-exports it.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned};
+
+/// Builds Oxide syntax at compile time. Statements, each ending in `;` or a
+/// block, produce a `Vec<oxide::Stmt>`; a single expression produces an
+/// `oxide::Expr`. `#name` splices in the Rust variable `name`, which must
+/// convert into an `oxide::Expr`:
+///
+/// ```ignore
+/// let offset = quote_oxide!(y * 2);
+/// let statements = quote_oxide! { let x = #offset + 1; x; };
+/// ```
+///
+/// Syntax errors are reported at compile time. Doc comments and literal
+/// suffixes are not supported.
+#[proc_macro]
+pub fn quote_oxide(input: TokenStream) -> TokenStream {
+    let tokens: Vec<TokenTree> = TokenStream2::from(input).into_iter().collect();
+    match Quoter::new(tokens, Span::call_site()).quote() {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+struct Error {
+    message: String,
+    span: Span,
+}
+
+impl Error {
+    fn to_compile_error(&self) -> TokenStream2 {
+        let message = &self.message;
+        quote_spanned!(self.span=> ::core::compile_error!(#message))
+    }
+}
+
+type QuoteResult = Result<TokenStream2, Error>;
+
+/// A recursive descent parser over Rust tokens that emits construction code.
+/// Parentheses and braces arrive as groups, each parsed by its own Quoter.
+struct Quoter {
+    tokens: Vec<TokenTree>,
+    current: usize,
+    /// Where to report a token missing at the end of the input
+    end: Span,
+}
+
+impl Quoter {
+    fn new(tokens: Vec<TokenTree>, end: Span) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            end,
+        }
+    }
+
+    fn quote(mut self) -> QuoteResult {
+        let is_statements = self.is_punct_anywhere(';')
+            || self.is_ident("let")
+            || matches!(self.peek(), Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace);
+
+        if is_statements {
+            let statements = self.statements()?;
+            Ok(quote!(::std::vec![#(#statements),*]))
+        } else {
+            let expr = self.expression(0)?;
+            self.expect_end()?;
+            Ok(expr)
+        }
+    }
+
+    fn statements(&mut self) -> Result<Vec<TokenStream2>, Error> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> QuoteResult {
+        if self.is_ident("let") {
+            self.advance();
+            return self.let_statement();
+        }
+
+        if let Some(TokenTree::Group(group)) = self.peek() {
+            if group.delimiter() == Delimiter::Brace {
+                let mut block =
+                    Quoter::new(group.stream().into_iter().collect(), group.span_close());
+                self.advance();
+                let statements = block.statements()?;
+                return Ok(quote!(::oxide::Stmt::block(::std::vec![#(#statements),*])));
+            }
+        }
+
+        let expr = self.expression(0)?;
+        self.expect_punct(';')?;
+        Ok(quote!(::oxide::Stmt::expression(#expr)))
+    }
+
+    // Mirrors the parser: a name makes a simple let, anything else a pattern
+    fn let_statement(&mut self) -> QuoteResult {
+        if let Some(TokenTree::Ident(name)) = self.peek() {
+            if name != "_" {
+                let name = name.to_string();
+                self.advance();
+                let value = self.initializer()?;
+                return Ok(quote!(
+                    ::oxide::Stmt::let_statement(::std::string::String::from(#name), #value)
+                ));
+            }
+        }
+
+        let span = self.span();
+        let (pattern, refutable) = self.pattern()?;
+        if refutable {
+            return Err(Error {
+                message:
+                    "a pattern in a let binding must not contain literals, which can fail to match"
+                        .to_string(),
+                span,
+            });
+        }
+        let value = self.initializer()?;
+        Ok(quote!(::oxide::Stmt::destructure(#pattern, #value)))
+    }
+
+    fn initializer(&mut self) -> QuoteResult {
+        self.expect_punct('=')?;
+        let value = self.expression(0)?;
+        self.expect_punct(';')?;
+        Ok(value)
+    }
+
+    // Returns the pattern's construction code and whether it contains a literal
+    fn pattern(&mut self) -> Result<(TokenStream2, bool), Error> {
+        let span = self.span();
+        match self.peek().cloned() {
+            Some(TokenTree::Ident(name)) => {
+                self.advance();
+                if name == "_" {
+                    return Ok((quote!(::oxide::Pattern::Wildcard), false));
+                }
+                let name = name.to_string();
+                Ok((
+                    quote!(::oxide::Pattern::identifier(::std::string::String::from(#name))),
+                    false,
+                ))
+            }
+            Some(TokenTree::Literal(_)) => {
+                let value = self.integer()?;
+                Ok((quote!(::oxide::Pattern::Literal(#value)), true))
+            }
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '-' => {
+                self.advance();
+                let value = self.integer()?;
+                Ok((quote!(::oxide::Pattern::Literal(-#value)), true))
+            }
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                self.advance();
+                let mut inner =
+                    Quoter::new(group.stream().into_iter().collect(), group.span_close());
+                let mut elements = Vec::new();
+                let mut refutable = false;
+                let mut trailing_comma = false;
+                while inner.peek().is_some() {
+                    let (element, element_refutable) = inner.pattern()?;
+                    elements.push(element);
+                    refutable |= element_refutable;
+                    trailing_comma = inner.is_punct(',');
+                    if !trailing_comma {
+                        break;
+                    }
+                    inner.advance();
+                }
+                inner.expect_end()?;
+                if elements.len() == 1 && !trailing_comma {
+                    return Err(Error {
+                        message: "a one-element tuple pattern needs a trailing comma".to_string(),
+                        span,
+                    });
+                }
+                Ok((
+                    quote!(::oxide::Pattern::tuple(::std::vec![#(#elements),*])),
+                    refutable,
+                ))
+            }
+            _ => Err(Error {
+                message: "expected a pattern".to_string(),
+                span,
+            }),
+        }
+    }
+
+    /// Parses binary expressions with the precedence and left associativity
+    /// of `BinaryOp`
+    fn expression(&mut self, min_precedence: u8) -> QuoteResult {
+        let mut left = self.unary()?;
+
+        while let Some((precedence, operator)) = self.binary_operator() {
+            if precedence < min_precedence {
+                break;
+            }
+            self.advance();
+            let right = self.expression(precedence + 1)?;
+            left = quote!(::oxide::Expr::binary(#left, ::oxide::BinaryOp::#operator, #right));
+        }
+
+        Ok(left)
+    }
+
+    fn binary_operator(&self) -> Option<(u8, TokenStream2)> {
+        match self.peek() {
+            Some(TokenTree::Punct(punct)) => match punct.as_char() {
+                '+' => Some((1, quote!(Add))),
+                '-' => Some((1, quote!(Subtract))),
+                '*' => Some((2, quote!(Multiply))),
+                '/' => Some((2, quote!(Divide))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn unary(&mut self) -> QuoteResult {
+        let operator = match self.peek() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '-' => quote!(Negate),
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '+' => quote!(Plus),
+            _ => return self.primary(),
+        };
+        self.advance();
+        let operand = self.unary()?;
+        Ok(quote!(::oxide::Expr::unary(::oxide::UnaryOp::#operator, #operand)))
+    }
+
+    fn primary(&mut self) -> QuoteResult {
+        let span = self.span();
+        match self.peek().cloned() {
+            Some(TokenTree::Literal(_)) => {
+                let value = self.integer()?;
+                Ok(quote!(::oxide::Expr::Number(#value)))
+            }
+            Some(TokenTree::Ident(name)) if name != "_" && name != "let" => {
+                self.advance();
+                let name = name.to_string();
+                Ok(quote!(::oxide::Expr::identifier(::std::string::String::from(#name))))
+            }
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                self.advance();
+                match self.peek().cloned() {
+                    Some(TokenTree::Ident(variable)) => {
+                        self.advance();
+                        Ok(quote_spanned!(variable.span()=>
+                            ::core::convert::Into::<::oxide::Expr>::into(#variable)
+                        ))
+                    }
+                    _ => Err(Error {
+                        message: "expected a Rust variable to splice in after '#'".to_string(),
+                        span: self.span(),
+                    }),
+                }
+            }
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                self.advance();
+                self.parenthesized(group.stream().into_iter().collect(), group.span_close())
+            }
+            _ => Err(Error {
+                message: "expected an expression".to_string(),
+                span,
+            }),
+        }
+    }
+
+    // `(x)` is a grouping; a comma, or nothing at all, makes a tuple
+    fn parenthesized(&mut self, tokens: Vec<TokenTree>, close: Span) -> QuoteResult {
+        let mut inner = Quoter::new(tokens, close);
+        let mut elements = Vec::new();
+        let mut is_tuple = true;
+        while inner.peek().is_some() {
+            elements.push(inner.expression(0)?);
+            if inner.is_punct(',') {
+                inner.advance();
+            } else {
+                is_tuple = elements.len() > 1;
+                break;
+            }
+        }
+        inner.expect_end()?;
+
+        match elements.pop() {
+            Some(expr) if !is_tuple => Ok(quote!(::oxide::Expr::grouping(#expr))),
+            last => {
+                elements.extend(last);
+                Ok(quote!(::oxide::Expr::tuple(::std::vec![#(#elements),*])))
+            }
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, Error> {
+        let span = self.span();
+        let value = match self.peek() {
+            Some(TokenTree::Literal(literal)) => literal.to_string().parse::<i64>().ok(),
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                self.advance();
+                Ok(value)
+            }
+            None => Err(Error {
+                message: "expected an integer literal that fits in 64 bits, without a suffix"
+                    .to_string(),
+                span,
+            }),
+        }
+    }
+
+    fn peek(&self) -> Option<&TokenTree> {
+        self.tokens.get(self.current)
+    }
+
+    fn advance(&mut self) {
+        self.current += 1;
+    }
+
+    fn span(&self) -> Span {
+        self.peek().map_or(self.end, TokenTree::span)
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        matches!(self.peek(), Some(TokenTree::Ident(ident)) if ident == name)
+    }
+
+    fn is_punct(&self, ch: char) -> bool {
+        matches!(self.peek(), Some(TokenTree::Punct(punct)) if punct.as_char() == ch)
+    }
+
+    fn is_punct_anywhere(&self, ch: char) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| matches!(token, TokenTree::Punct(punct) if punct.as_char() == ch))
+    }
+
+    fn expect_punct(&mut self, ch: char) -> Result<(), Error> {
+        if self.is_punct(ch) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(Error {
+                message: format!("expected '{}'", ch),
+                span: self.span(),
+            })
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), Error> {
+        match self.peek() {
+            None => Ok(()),
+            Some(token) => Err(Error {
+                message: "unexpected token".to_string(),
+                span: token.span(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::parser::{Expr, Pattern, Program, Stmt};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A tuple pattern in a destructuring `let` that is known to be matched
+/// against a tuple of another length, or against a value that is not a tuple
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArityMismatch {
+    /// The tuple pattern that cannot match, which may be nested in the `let`'s
+    pub pattern: Pattern,
+    /// Number of elements in the value, or None if the value is not a tuple
+    pub found: Option<usize>,
+}
+
+impl fmt::Display for ArityMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = match &self.pattern {
+            Pattern::Tuple(elements) => elements.len(),
+            _ => 0,
+        };
+        write!(
+            f,
+            "pattern `{}` expects a tuple of {} element(s), found ",
+            self.pattern, expected
+        )?;
+        match self.found {
+            Some(count) => write!(f, "a tuple of {} element(s)", count),
+            None => write!(f, "a value that is not a tuple"),
+        }
+    }
+}
+
+impl std::error::Error for ArityMismatch {}
+
+/// Finds destructuring lets that cannot succeed. Values are only followed
+/// through tuple literals, groupings and names bound to them, so a value whose
+/// shape is not evident from the source is never reported.
+pub fn arity_mismatches(program: &Program) -> Vec<ArityMismatch> {
+    let mut checker = Checker::default();
+    checker.statements(&program.statements);
+    checker.mismatches
+}
+
+/// What is known about the value of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Shape {
+    Scalar,
+    Tuple(Vec<Shape>),
+    Unknown,
+}
+
+#[derive(Default)]
+struct Checker {
+    /// Shapes of the names bound in each enclosing block, innermost last
+    scopes: Vec<HashMap<String, Shape>>,
+    mismatches: Vec<ArityMismatch>,
+}
+
+impl Checker {
+    fn statements(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                let shape = self.shape(value);
+                self.bind(name, shape);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                let shape = self.shape(value);
+                self.destructure(pattern, shape);
+            }
+            Stmt::Expression(_) => {}
+            Stmt::Block(statements) => self.statements(statements),
+        }
+    }
+
+    // Binds the names in a pattern to the parts of a value of the given shape
+    fn destructure(&mut self, pattern: &Pattern, shape: Shape) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Identifier(name) => self.bind(name, shape),
+            Pattern::Tuple(patterns) => {
+                let shapes = match shape {
+                    Shape::Tuple(shapes) if shapes.len() == patterns.len() => shapes,
+                    Shape::Unknown => vec![Shape::Unknown; patterns.len()],
+                    mismatched => {
+                        self.mismatches.push(ArityMismatch {
+                            pattern: pattern.clone(),
+                            found: match mismatched {
+                                Shape::Tuple(shapes) => Some(shapes.len()),
+                                _ => None,
+                            },
+                        });
+                        vec![Shape::Unknown; patterns.len()]
+                    }
+                };
+                for (pattern, shape) in patterns.iter().zip(shapes) {
+                    self.destructure(pattern, shape);
+                }
+            }
+        }
+    }
+
+    fn bind(&mut self, name: &str, shape: Shape) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), shape);
+        }
+    }
+
+    fn shape(&self, expr: &Expr) -> Shape {
+        match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {
+                Shape::Scalar
+            }
+            Expr::Identifier(name) => self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .cloned()
+                .unwrap_or(Shape::Unknown),
+            Expr::Grouping(inner) => self.shape(inner),
+            Expr::Tuple(elements) => {
+                Shape::Tuple(elements.iter().map(|element| self.shape(element)).collect())
+            }
+            // Arithmetic on tuples is not defined yet, so its result is not assumed
+            Expr::Binary { .. } | Expr::Unary { .. } => Shape::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn mismatches(source: &str) -> Vec<String> {
+        arity_mismatches(&parse_source(source).unwrap())
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_literal_tuples_of_the_wrong_length() {
+        assert_eq!(
+            mismatches("let (a, b) = (1, 2, 3); let (c,) = 4;"),
+            vec![
+                "pattern `(a, b)` expects a tuple of 2 element(s), found a tuple of 3 element(s)",
+                "pattern `(c,)` expects a tuple of 1 element(s), found a value that is not a tuple",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_follows_names_and_nested_tuples() {
+        let source = "let pair = ((1, 2), 3); let (inner, x) = pair; let (a, b, c) = inner;";
+        assert_eq!(mismatches(source).len(), 1);
+        assert_eq!(
+            mismatches("let ((a, _), _) = ((1, 2, 3), 4);"),
+            vec!["pattern `(a, _)` expects a tuple of 2 element(s), found a tuple of 3 element(s)"]
+        );
+        assert!(mismatches("let pair = (1, 2); { let pair = 5; } let (a, b) = pair;").is_empty());
+    }
+
+    #[test]
+    fn test_unknown_values_are_not_reported() {
+        assert!(mismatches("let (a, b) = input; let (c, d) = a; let (e, f) = 1 + 2;").is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use super::free::free_variables;
+use crate::parser::{Program, Stmt};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Which top-level bindings each top-level binding reads. Bindings are keyed
+/// by name, so a name that is bound twice gets the union of both initializers'
+/// dependencies. References to names the program never binds are not edges.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DependencyGraph {
+    edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Builds the dependency graph of a program's top-level `let` bindings.
+/// References may point forward, as cells in a spreadsheet do.
+pub fn dependency_graph(program: &Program) -> DependencyGraph {
+    let mut edges: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for stmt in &program.statements {
+        for name in stmt.bound_names() {
+            edges.entry(name.to_string()).or_default();
+        }
+    }
+
+    for stmt in &program.statements {
+        if let Stmt::Let { value, .. } | Stmt::Destructure { value, .. } = stmt {
+            let dependencies: Vec<String> = free_variables(value)
+                .into_iter()
+                .filter(|dependency| edges.contains_key(dependency))
+                .collect();
+            for name in stmt.bound_names() {
+                edges
+                    .entry(name.to_string())
+                    .or_default()
+                    .extend(dependencies.iter().cloned());
+            }
+        }
+    }
+
+    DependencyGraph { edges }
+}
+
+impl DependencyGraph {
+    /// Returns every binding name in sorted order
+    pub fn bindings(&self) -> impl Iterator<Item = &str> {
+        self.edges.keys().map(String::as_str)
+    }
+
+    /// Returns the bindings `name` reads directly, or None if it is not bound
+    pub fn dependencies(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.edges.get(name)
+    }
+
+    /// Returns the bindings that read `name` directly
+    pub fn dependents(&self, name: &str) -> BTreeSet<String> {
+        self.edges
+            .iter()
+            .filter(|(_, dependencies)| dependencies.contains(name))
+            .map(|(binding, _)| binding.clone())
+            .collect()
+    }
+
+    /// Returns a cycle as the path of bindings around it, starting and ending
+    /// with the same name, or None if the graph is acyclic
+    pub fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut state: BTreeMap<&str, Visit> = BTreeMap::new();
+        let mut path = Vec::new();
+
+        for name in self.bindings() {
+            if let Some(cycle) = self.visit(name, &mut state, &mut path) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Orders the bindings so that every binding comes after the ones it
+    /// depends on; ties are broken alphabetically
+    pub fn topological_order(&self) -> Result<Vec<String>, CycleError> {
+        if let Some(cycle) = self.find_cycle() {
+            return Err(CycleError { cycle });
+        }
+
+        let mut remaining: BTreeMap<&str, usize> = self
+            .edges
+            .iter()
+            .map(|(name, dependencies)| (name.as_str(), dependencies.len()))
+            .collect();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        while let Some(next) = remaining
+            .iter()
+            .find(|(_, unresolved)| **unresolved == 0)
+            .map(|(name, _)| *name)
+        {
+            remaining.remove(next);
+            for dependent in self.dependents(next) {
+                if let Some(unresolved) = remaining.get_mut(dependent.as_str()) {
+                    *unresolved -= 1;
+                }
+            }
+            order.push(next.to_string());
+        }
+
+        Ok(order)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        name: &'a str,
+        state: &mut BTreeMap<&'a str, Visit>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        match state.get(name) {
+            Some(Visit::Done) => return None,
+            Some(Visit::InProgress) => {
+                let start = path.iter().position(|entry| *entry == name)?;
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(name.to_string());
+                return Some(cycle);
+            }
+            None => {}
+        }
+
+        state.insert(name, Visit::InProgress);
+        path.push(name);
+        for dependency in self.edges.get(name).into_iter().flatten() {
+            if let Some(cycle) = self.visit(dependency, state, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        state.insert(name, Visit::Done);
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+/// Bindings that depend on each other in a loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleError {
+    /// Path around the cycle, starting and ending with the same binding
+    pub cycle: Vec<String>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dependency cycle: {}", self.cycle.join(" -> "))
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn graph(source: &str) -> DependencyGraph {
+        dependency_graph(&parse_source(source).unwrap())
+    }
+
+    #[test]
+    fn test_edges() {
+        let graph = graph("let sum = a + b; let a = 1; let b = a * 2; let c = outside;");
+
+        assert_eq!(
+            graph.bindings().collect::<Vec<_>>(),
+            vec!["a", "b", "c", "sum"]
+        );
+        assert_eq!(
+            graph.dependencies("sum"),
+            Some(&BTreeSet::from(["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(graph.dependencies("c"), Some(&BTreeSet::new()));
+        assert_eq!(graph.dependencies("outside"), None);
+        assert_eq!(
+            graph.dependents("a"),
+            BTreeSet::from(["b".to_string(), "sum".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let graph = graph("let total = net + tax; let tax = net / 5; let net = 100;");
+
+        assert_eq!(graph.find_cycle(), None);
+        assert_eq!(
+            graph.topological_order().unwrap(),
+            vec!["net", "tax", "total"]
+        );
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let graph = graph("let a = c; let b = a; let c = b + 1; let d = 2;");
+        let error = graph.topological_order().unwrap_err();
+
+        assert_eq!(error.cycle, vec!["a", "c", "b", "a"]);
+        assert_eq!(error.to_string(), "Dependency cycle: a -> c -> b -> a");
+    }
+
+    #[test]
+    fn test_self_reference_is_a_cycle() {
+        assert_eq!(
+            graph("let x = x + 1;").find_cycle(),
+            Some(vec!["x".to_string(), "x".to_string()])
+        );
+    }
+}
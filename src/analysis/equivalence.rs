@@ -0,0 +1,317 @@
+use super::free_variables;
+use crate::interpreter::{Environment, RuntimeError, Value};
+use crate::parser::{BinaryOp, Expr, UnaryOp};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Random assignments tried before two expressions are taken to be
+/// equivalent
+pub const TRIALS: usize = 200;
+
+/// Whether two expressions always evaluate to the same outcome
+#[derive(Debug, Clone, PartialEq)]
+pub enum Equivalence {
+    /// The expressions agree on every assignment of their variables
+    Proven,
+    /// The expressions agreed on `trials` random assignments, but could not
+    /// be shown to agree on all of them
+    Probable { trials: usize },
+    /// An assignment the expressions disagree on, with what each gave
+    Different {
+        assignment: BTreeMap<String, i64>,
+        left: Result<Value, RuntimeError>,
+        right: Result<Value, RuntimeError>,
+    },
+    /// Every assignment tried overflowed, so nothing is known
+    Unknown,
+}
+
+/// Compares two expressions, treating integers as unbounded: expressions that
+/// only differ in whether they overflow count as equivalent. Other failures,
+/// such as division by zero, must happen on the same assignments.
+///
+/// Both expressions are first normalized into sums of products, which makes
+/// `+` and `*` commutative and associative, distributes `*` over `+` and `-`,
+/// and folds constants; divisions other than of constants are kept as
+/// opaque terms. Equal normal forms prove equivalence. Otherwise both are
+/// evaluated on [`TRIALS`] random assignments to their free variables, with
+/// a fixed seed so results are reproducible.
+pub fn equivalent(a: &Expr, b: &Expr) -> Equivalence {
+    if let (Some(left), Some(right)) = (normalize(a), normalize(b)) {
+        if left == right {
+            return Equivalence::Proven;
+        }
+    }
+
+    let mut variables: BTreeSet<String> = free_variables(a);
+    variables.extend(free_variables(b));
+    // Without variables one evaluation decides
+    let trials = if variables.is_empty() { 1 } else { TRIALS };
+
+    let mut random = SplitMix64(0x0123_4567_89ab_cdef);
+    let mut agreed = 0;
+    for trial in 0..trials {
+        let mut env = Environment::new();
+        let mut assignment = BTreeMap::new();
+        for name in &variables {
+            let value = match trial {
+                // Small values find most differences, especially at zero
+                0..4 => [0, 1, -1, 2][trial],
+                _ => (random.next() % 2001) as i64 - 1000,
+            };
+            env.define(name, Value::Int(value));
+            assignment.insert(name.clone(), value);
+        }
+
+        let (left, right) = (env.evaluate(a), env.evaluate(b));
+        match (&left, &right) {
+            (Err(RuntimeError::Overflow), _) | (_, Err(RuntimeError::Overflow)) => continue,
+            (Ok(x), Ok(y)) if x == y => agreed += 1,
+            (Err(x), Err(y)) if std::mem::discriminant(x) == std::mem::discriminant(y) => {
+                agreed += 1
+            }
+            _ => {
+                return Equivalence::Different {
+                    assignment,
+                    left,
+                    right,
+                };
+            }
+        }
+    }
+
+    match agreed {
+        0 => Equivalence::Unknown,
+        _ if variables.is_empty() => Equivalence::Proven,
+        trials => Equivalence::Probable { trials },
+    }
+}
+
+/// A sum of products: each product of terms, sorted, with its coefficient.
+/// Terms are variable names and divisions, which are written out in normal
+/// form. Divisions are kept even when their coefficient cancels, since they
+/// can still fail.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Normal {
+    products: BTreeMap<Vec<String>, i64>,
+    divisions: BTreeSet<String>,
+}
+
+impl Normal {
+    fn constant(value: i64) -> Self {
+        let mut normal = Normal::default();
+        normal.add(Vec::new(), value);
+        normal
+    }
+
+    fn term(term: String) -> Self {
+        let mut normal = Normal::default();
+        normal.add(vec![term], 1);
+        normal
+    }
+
+    fn add(&mut self, product: Vec<String>, coefficient: i64) -> Option<()> {
+        let sum = self.products.get(&product).copied().unwrap_or(0);
+        match sum.checked_add(coefficient)? {
+            0 => self.products.remove(&product),
+            sum => self.products.insert(product, sum),
+        };
+        Some(())
+    }
+
+    fn sum(mut self, other: Normal, sign: i64) -> Option<Normal> {
+        for (product, coefficient) in other.products {
+            self.add(product, coefficient.checked_mul(sign)?)?;
+        }
+        self.divisions.extend(other.divisions);
+        Some(self)
+    }
+
+    fn product(self, other: Normal) -> Option<Normal> {
+        let mut result = Normal::default();
+        for (left, a) in &self.products {
+            for (right, b) in &other.products {
+                let mut product: Vec<String> = left.iter().chain(right).cloned().collect();
+                product.sort();
+                result.add(product, a.checked_mul(*b)?)?;
+            }
+        }
+        result.divisions = self.divisions;
+        result.divisions.extend(other.divisions);
+        Some(result)
+    }
+
+    // The value of a normal form without terms
+    fn as_constant(&self) -> Option<i64> {
+        match self.products.iter().next() {
+            None => Some(0),
+            Some((product, value)) if product.is_empty() && self.products.len() == 1 => {
+                Some(*value)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Normalizes an integer expression, or returns None for floats, tuples and
+// quantities, which are left to testing, and for constants that overflow
+fn normalize(expr: &Expr) -> Option<Normal> {
+    match expr {
+        Expr::Number(value) => Some(Normal::constant(*value)),
+        Expr::Identifier(name) => Some(Normal::term(name.clone())),
+        Expr::Grouping(inner) => normalize(inner),
+        Expr::Unary {
+            operator: UnaryOp::Plus,
+            operand,
+        } => normalize(operand),
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => Normal::default().sum(normalize(operand)?, -1),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let (left, right) = (normalize(left)?, normalize(right)?);
+            match operator {
+                BinaryOp::Add => left.sum(right, 1),
+                BinaryOp::Subtract => left.sum(right, -1),
+                BinaryOp::Multiply => left.product(right),
+                BinaryOp::Divide => match (left.as_constant(), right.as_constant()) {
+                    (Some(a), Some(b)) if b != 0 => {
+                        let mut quotient = Normal::constant(a.checked_div(b)?);
+                        quotient.divisions = left.divisions;
+                        quotient.divisions.extend(right.divisions);
+                        Some(quotient)
+                    }
+                    _ => {
+                        let term = format!("({} / {})", written(&left), written(&right));
+                        let mut quotient = Normal::term(term.clone());
+                        quotient.divisions = left.divisions;
+                        quotient.divisions.extend(right.divisions);
+                        quotient.divisions.insert(term);
+                        Some(quotient)
+                    }
+                },
+            }
+        }
+        Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } | Expr::Tuple(_) => None,
+    }
+}
+
+// A normal form written out, to name a division of it
+fn written(normal: &Normal) -> String {
+    let products: Vec<String> = normal
+        .products
+        .iter()
+        .map(|(product, coefficient)| {
+            std::iter::once(coefficient.to_string())
+                .chain(product.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("*")
+        })
+        .collect();
+    match products.len() {
+        0 => "0".to_string(),
+        _ => products.join(" + "),
+    }
+}
+
+// A small, fast generator; the trials only need a spread of values
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+    use crate::parser::Stmt;
+
+    fn compare(a: &str, b: &str) -> Equivalence {
+        let expression = |source: &str| match parse_source(&format!("{};", source))
+            .unwrap()
+            .statements
+            .remove(0)
+        {
+            Stmt::Expression(expr) => expr,
+            other => panic!("expected an expression statement, got {:?}", other),
+        };
+        equivalent(&expression(a), &expression(b))
+    }
+
+    #[test]
+    fn test_normalization_proves_equivalence() {
+        for (a, b) in [
+            ("a + b", "b + a"),
+            ("(a * b) * c", "c * (b * a)"),
+            ("2 * (x + 3)", "x + 6 + x"),
+            ("x - x + y", "+y"),
+            ("-(a - b)", "b - a"),
+            ("(x + 1) * (x - 1)", "x * x - 1"),
+            ("y / (1 + 1) + 0", "y / 2"),
+            ("(1 + 2) * 3", "9"),
+        ] {
+            assert_eq!(compare(a, b), Equivalence::Proven, "{} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_differences_have_counterexamples() {
+        match compare("x / 2 * 2", "x") {
+            Equivalence::Different {
+                assignment,
+                left,
+                right,
+            } => {
+                let x = assignment["x"];
+                assert_eq!(left, Ok(Value::Int(x / 2 * 2)));
+                assert_eq!(right, Ok(Value::Int(x)));
+                assert_ne!(x % 2, 0);
+            }
+            other => panic!("expected a difference, got {:?}", other),
+        }
+
+        // Division by zero is a difference of its own
+        assert!(matches!(
+            compare("x / x", "1"),
+            Equivalence::Different { assignment, left: Err(RuntimeError::DivisionByZero), .. }
+                if assignment["x"] == 0
+        ));
+        // A failing division that cancels out still fails
+        assert!(matches!(
+            compare("y / 0 * 0 + 1", "1"),
+            Equivalence::Different { .. }
+        ));
+        assert!(matches!(
+            compare("a - b", "b - a"),
+            Equivalence::Different { .. }
+        ));
+    }
+
+    #[test]
+    fn test_testing_covers_what_normalization_cannot() {
+        assert_eq!(compare("(x / 3) * 2", "2 * (x / 3)"), Equivalence::Proven);
+        assert_eq!(
+            compare("(x + x) / 2", "x"),
+            Equivalence::Probable { trials: TRIALS }
+        );
+        assert_eq!(
+            compare("(a, b + 1)", "(a, 1 + b)"),
+            Equivalence::Probable { trials: TRIALS }
+        );
+        assert_eq!(compare("(1, 2)", "(1, 4 / 2)"), Equivalence::Proven);
+        assert_eq!(
+            compare("9223372036854775807 + 1", "0"),
+            Equivalence::Unknown
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::parser::{Expr, Program, Stmt};
+use std::collections::{BTreeSet, HashSet};
+
+/// Syntax nodes whose free variables can be computed
+pub trait FreeVariables {
+    /// Returns the names this node reads without binding them first
+    fn free_variables(&self) -> BTreeSet<String>;
+}
+
+/// Returns the identifiers a node reads that are not bound by an earlier `let`
+/// in an enclosing scope. A binding is visible to the statements after it in
+/// its own block and in nested blocks; it is not visible in its own initializer,
+/// so `let x = x + 1;` reads a free `x`.
+pub fn free_variables<N: FreeVariables + ?Sized>(node: &N) -> BTreeSet<String> {
+    node.free_variables()
+}
+
+impl FreeVariables for Expr {
+    fn free_variables(&self) -> BTreeSet<String> {
+        let mut collector = Collector::default();
+        collector.expression(self);
+        collector.free
+    }
+}
+
+impl FreeVariables for Stmt {
+    fn free_variables(&self) -> BTreeSet<String> {
+        let mut collector = Collector::default();
+        collector.statement(self);
+        collector.free
+    }
+}
+
+impl FreeVariables for Program {
+    fn free_variables(&self) -> BTreeSet<String> {
+        let mut collector = Collector::default();
+        collector.statements(&self.statements);
+        collector.free
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    /// Names bound in each enclosing block, innermost last
+    scopes: Vec<HashSet<String>>,
+    free: BTreeSet<String>,
+}
+
+impl Collector {
+    fn statements(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashSet::new());
+        for stmt in statements {
+            self.statement(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                self.expression(value);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert(name.clone());
+                }
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.expression(value);
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.extend(pattern.bindings().into_iter().map(String::from));
+                }
+            }
+            Stmt::Expression(expr) => self.expression(expr),
+            Stmt::Block(statements) => self.statements(statements),
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {}
+            Expr::Identifier(name) => {
+                if !self.scopes.iter().any(|scope| scope.contains(name)) {
+                    self.free.insert(name.clone());
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Unary { operand, .. } => self.expression(operand),
+            Expr::Grouping(inner) => self.expression(inner),
+            Expr::Tuple(elements) => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn free_in(source: &str) -> Vec<String> {
+        free_variables(&parse_source(source).unwrap())
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_expression() {
+        let program = parse_source("rate * (hours + overtime) - rate;").unwrap();
+        let Stmt::Expression(expr) = &program.statements[0] else {
+            panic!("Expected expression statement");
+        };
+
+        assert_eq!(
+            free_variables(expr).into_iter().collect::<Vec<_>>(),
+            vec!["hours", "overtime", "rate"]
+        );
+    }
+
+    #[test]
+    fn test_let_binds_following_statements() {
+        assert_eq!(free_in("let a = b; a + c;"), vec!["b", "c"]);
+        assert_eq!(free_in("a; let a = 1;"), vec!["a"]);
+        assert_eq!(free_in("let x = x + 1;"), vec!["x"]);
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        assert_eq!(
+            free_in("let a = 1; { a; let b = 2; { b; } }"),
+            Vec::<String>::new()
+        );
+        assert_eq!(free_in("{ let b = 2; } b;"), vec!["b"]);
+    }
+
+    #[test]
+    fn test_statement() {
+        let program = parse_source("{ let y = 1; x + y; }").unwrap();
+
+        assert_eq!(
+            program.statements[0].free_variables(),
+            BTreeSet::from(["x".to_string()])
+        );
+    }
+}
@@ -0,0 +1,17 @@
+mod arity;
+mod deps;
+mod equivalence;
+mod free;
+mod patterns;
+mod purity;
+mod ranges;
+mod slice;
+
+pub use arity::{ArityMismatch, arity_mismatches};
+pub use deps::{CycleError, DependencyGraph, dependency_graph};
+pub use equivalence::{Equivalence, TRIALS, equivalent};
+pub use free::{FreeVariables, free_variables};
+pub use patterns::{is_exhaustive, unreachable_patterns};
+pub use purity::is_pure;
+pub use ranges::{Interval, check_ranges};
+pub use slice::slice;
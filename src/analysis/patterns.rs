@@ -0,0 +1,143 @@
+use crate::parser::Pattern;
+
+/// Returns true if every value matches at least one of the patterns, as the
+/// arms of a match must. Integers have no
+/// finite set of literals, so integer patterns are exhaustive only together
+/// with a wildcard or name in the same position.
+pub fn is_exhaustive(patterns: &[Pattern]) -> bool {
+    let rows: Vec<Vec<&Pattern>> = patterns.iter().map(|pattern| vec![pattern]).collect();
+    !is_useful(&rows, &[&Pattern::Wildcard])
+}
+
+/// Returns the indices of the patterns that can never match because the
+/// patterns before them already match everything they would
+pub fn unreachable_patterns(patterns: &[Pattern]) -> Vec<usize> {
+    let mut rows: Vec<Vec<&Pattern>> = Vec::new();
+    let mut unreachable = Vec::new();
+
+    for (index, pattern) in patterns.iter().enumerate() {
+        if !is_useful(&rows, &[pattern]) {
+            unreachable.push(index);
+        }
+        rows.push(vec![pattern]);
+    }
+    unreachable
+}
+
+// Whether some value matches `row` but none of `rows` (Maranget's usefulness).
+// Each row is a list of patterns for the same list of values.
+fn is_useful(rows: &[Vec<&Pattern>], row: &[&Pattern]) -> bool {
+    let Some((head, rest)) = row.split_first() else {
+        return rows.is_empty();
+    };
+
+    match head {
+        Pattern::Tuple(elements) => {
+            let specialized = specialize_tuple(rows, elements.len());
+            is_useful(
+                &specialized,
+                &[elements.iter().collect(), rest.to_vec()].concat(),
+            )
+        }
+        Pattern::Literal(value) => is_useful(&specialize_literal(rows, *value), rest),
+        Pattern::Wildcard | Pattern::Identifier(_) => {
+            // A tuple has a single constructor, so a column containing one is
+            // fully covered by its tuple patterns; literals never cover integers
+            let arity = rows.iter().find_map(|row| match row[0] {
+                Pattern::Tuple(elements) => Some(elements.len()),
+                _ => None,
+            });
+            match arity {
+                Some(arity) => {
+                    let wildcards = vec![&Pattern::Wildcard; arity];
+                    is_useful(
+                        &specialize_tuple(rows, arity),
+                        &[wildcards, rest.to_vec()].concat(),
+                    )
+                }
+                None => {
+                    let defaults: Vec<Vec<&Pattern>> = rows
+                        .iter()
+                        .filter(|row| matches!(row[0], Pattern::Wildcard | Pattern::Identifier(_)))
+                        .map(|row| row[1..].to_vec())
+                        .collect();
+                    is_useful(&defaults, rest)
+                }
+            }
+        }
+    }
+}
+
+// The rows that can match a tuple of `arity` elements, with the first column
+// replaced by the patterns for those elements
+fn specialize_tuple<'a>(rows: &[Vec<&'a Pattern>], arity: usize) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .filter_map(|row| {
+            let elements: Vec<&Pattern> = match row[0] {
+                Pattern::Tuple(elements) if elements.len() == arity => elements.iter().collect(),
+                Pattern::Wildcard | Pattern::Identifier(_) => vec![&Pattern::Wildcard; arity],
+                _ => return None,
+            };
+            Some([elements, row[1..].to_vec()].concat())
+        })
+        .collect()
+}
+
+// The rows that can match the integer `value`, without their first column
+fn specialize_literal<'a>(rows: &[Vec<&'a Pattern>], value: i64) -> Vec<Vec<&'a Pattern>> {
+    rows.iter()
+        .filter(|row| match row[0] {
+            Pattern::Literal(literal) => *literal == value,
+            Pattern::Wildcard | Pattern::Identifier(_) => true,
+            Pattern::Tuple(_) => false,
+        })
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> Pattern {
+        Pattern::identifier(name.to_string())
+    }
+
+    fn pair(first: Pattern, second: Pattern) -> Pattern {
+        Pattern::tuple(vec![first, second])
+    }
+
+    #[test]
+    fn test_integers_need_a_catch_all() {
+        assert!(!is_exhaustive(&[Pattern::Literal(0), Pattern::Literal(1)]));
+        assert!(is_exhaustive(&[Pattern::Literal(0), name("n")]));
+        assert!(!is_exhaustive(&[]));
+    }
+
+    #[test]
+    fn test_tuples_are_covered_column_by_column() {
+        assert!(is_exhaustive(&[pair(Pattern::Wildcard, name("a"))]));
+
+        let mut cases = vec![
+            pair(Pattern::Literal(0), Pattern::Wildcard),
+            pair(Pattern::Wildcard, Pattern::Literal(0)),
+        ];
+        assert!(!is_exhaustive(&cases));
+
+        cases.push(pair(name("a"), Pattern::Wildcard));
+        assert!(is_exhaustive(&cases));
+    }
+
+    #[test]
+    fn test_unreachable_patterns() {
+        let cases = vec![
+            pair(Pattern::Literal(0), Pattern::Wildcard),
+            pair(Pattern::Literal(0), Pattern::Literal(1)),
+            pair(Pattern::Literal(1), Pattern::Wildcard),
+            Pattern::Wildcard,
+            pair(Pattern::Literal(2), Pattern::Literal(2)),
+        ];
+
+        assert_eq!(unreachable_patterns(&cases), vec![1, 4]);
+    }
+}
@@ -0,0 +1,67 @@
+use crate::fold::fold_expression;
+use crate::parser::{BinaryOp, Expr};
+
+/// Returns true if evaluating the expression can have no effect besides
+/// producing its value, so it is safe to remove, duplicate, reorder or cache.
+///
+/// The answer is conservative: a false result only means the expression might
+/// be effectful. Reading a variable is pure; the only operation that can fail
+/// today is division, which is treated as pure only when the divisor is a
+/// non-zero constant.
+pub fn is_pure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => true,
+        Expr::Binary {
+            left,
+            operator: BinaryOp::Divide,
+            right,
+        } => is_pure(left) && is_pure(right) && is_nonzero_constant(right),
+        Expr::Binary { left, right, .. } => is_pure(left) && is_pure(right),
+        Expr::Unary { operand, .. } => is_pure(operand),
+        Expr::Grouping(inner) => is_pure(inner),
+        Expr::Tuple(elements) => elements.iter().all(is_pure),
+    }
+}
+
+fn is_nonzero_constant(expr: &Expr) -> bool {
+    match fold_expression(expr.clone()) {
+        Expr::Number(value) => value != 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+    use crate::parser::Stmt;
+
+    fn pure(source: &str) -> bool {
+        match &parse_source(source).unwrap().statements[0] {
+            Stmt::Expression(expr) => is_pure(expr),
+            _ => panic!("Expected expression statement"),
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_is_pure() {
+        assert!(pure("1 + x * -(y - 2);"));
+    }
+
+    #[test]
+    fn test_division_by_nonzero_constant_is_pure() {
+        assert!(pure("x / 2;"));
+        assert!(pure("x / (3 - 1);"));
+    }
+
+    #[test]
+    fn test_division_that_may_fail_is_not_pure() {
+        assert!(!pure("x / y;"));
+        assert!(!pure("x / (2 - 2);"));
+        assert!(!pure("1 + -(a / b);"));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::fold::{ConstError, ConstErrorKind};
+use crate::parser::{BinaryOp, Expr, Pattern, Program, Stmt, UnaryOp};
+use crate::rewrite::Step;
+use std::collections::HashMap;
+
+/// The integers a value may take, as a closed interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub min: i64,
+    pub max: i64,
+}
+
+impl Interval {
+    /// Every 64-bit integer, the range of a value nothing is known about
+    pub const FULL: Interval = Interval {
+        min: i64::MIN,
+        max: i64::MAX,
+    };
+
+    pub fn constant(value: i64) -> Self {
+        Interval {
+            min: value,
+            max: value,
+        }
+    }
+}
+
+/// What is known about a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Int(Interval),
+    Tuple(Vec<Value>),
+    /// A host value, or a value a failing operation would have produced
+    Unknown,
+}
+
+/// Propagates the ranges of bound values through arithmetic to find
+/// operations that fail however the program's free names are bound: division
+/// by a value that is always zero, and arithmetic whose every possible result
+/// overflows. `let d = n * 0; 10 / d;` divides by zero for any `n`.
+/// Operands are treated as independent, so `n - n` may be any integer.
+///
+/// Returns each failing statement's path with its first error, the same way
+/// as [`crate::fold::check_constants`], which finds the subset of these
+/// failures that do not depend on bindings.
+pub fn check_ranges(program: &Program) -> Vec<(Vec<usize>, ConstError)> {
+    let mut analysis = RangeAnalysis::default();
+    analysis.statements(&program.statements, &mut Vec::new());
+    analysis.errors
+}
+
+#[derive(Default)]
+struct RangeAnalysis {
+    /// Values bound in each enclosing block, innermost last
+    scopes: Vec<HashMap<String, Value>>,
+    /// Path to the subexpression being analysed, left at the failing one
+    path: Vec<Step>,
+    errors: Vec<(Vec<usize>, ConstError)>,
+}
+
+impl RangeAnalysis {
+    fn statements(&mut self, statements: &[Stmt], statement: &mut Vec<usize>) {
+        self.scopes.push(HashMap::new());
+        for (index, stmt) in statements.iter().enumerate() {
+            statement.push(index);
+            match stmt {
+                Stmt::Let { name, value, .. } => {
+                    let value = self.value(value, statement);
+                    self.bind(name, value);
+                }
+                Stmt::Destructure { pattern, value, .. } => {
+                    let value = self.value(value, statement);
+                    self.destructure(pattern, value);
+                }
+                Stmt::Expression(expr) => {
+                    self.value(expr, statement);
+                }
+                Stmt::Block(statements) => self.statements(statements, statement),
+            }
+            statement.pop();
+        }
+        self.scopes.pop();
+    }
+
+    // Analyses the value of a statement, recording its first error. A value
+    // that fails binds nothing useful, so it is unknown from then on.
+    fn value(&mut self, expr: &Expr, statement: &[usize]) -> Value {
+        self.path.clear();
+        self.expression(expr).unwrap_or_else(|kind| {
+            self.errors.push((
+                statement.to_vec(),
+                ConstError {
+                    kind,
+                    steps: self.path.clone(),
+                },
+            ));
+            Value::Unknown
+        })
+    }
+
+    fn bind(&mut self, name: &str, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    fn destructure(&mut self, pattern: &Pattern, value: Value) {
+        match (pattern, value) {
+            (Pattern::Identifier(name), value) => self.bind(name, value),
+            (Pattern::Tuple(patterns), Value::Tuple(values)) if patterns.len() == values.len() => {
+                for (pattern, value) in patterns.iter().zip(values) {
+                    self.destructure(pattern, value);
+                }
+            }
+            (pattern, _) => {
+                for name in pattern.bindings() {
+                    self.bind(name, Value::Unknown);
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<Value, ConstErrorKind> {
+        match expr {
+            Expr::Number(n) => Ok(Value::Int(Interval::constant(*n))),
+            Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => Ok(Value::Unknown),
+            Expr::Identifier(name) => Ok(self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(name))
+                .cloned()
+                // A free name may be bound to any integer by the host
+                .unwrap_or(Value::Int(Interval::FULL))),
+            Expr::Grouping(inner) => self.child(inner, Step::Inner),
+            Expr::Tuple(elements) => {
+                let mut values = Vec::new();
+                for (index, element) in elements.iter().enumerate() {
+                    values.push(self.child(element, Step::Element(index))?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            Expr::Unary { operator, operand } => match self.child(operand, Step::Operand)? {
+                Value::Int(range) if *operator == UnaryOp::Negate => {
+                    clamp(-(range.max as i128), -(range.min as i128))
+                }
+                value => Ok(value),
+            },
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.child(left, Step::Left)?;
+                let right = self.child(right, Step::Right)?;
+                match (left, right) {
+                    (_, Value::Int(divisor))
+                        if *operator == BinaryOp::Divide && divisor == Interval::constant(0) =>
+                    {
+                        Err(ConstErrorKind::DivisionByZero)
+                    }
+                    (Value::Int(left), Value::Int(right)) => binary(operator, left, right),
+                    _ => Ok(Value::Unknown),
+                }
+            }
+        }
+    }
+
+    // Analyses a child, keeping the path to it if it fails
+    fn child(&mut self, expr: &Expr, step: Step) -> Result<Value, ConstErrorKind> {
+        self.path.push(step);
+        let value = self.expression(expr)?;
+        self.path.pop();
+        Ok(value)
+    }
+}
+
+// The range of `left operator right`. Each operator takes its extremes at
+// the bounds of its operands, division only while the divisor keeps its
+// sign, so the range is exact for independent operands.
+fn binary(operator: &BinaryOp, left: Interval, right: Interval) -> Result<Value, ConstErrorKind> {
+    let mut results = Vec::new();
+    let mut bounds = |divisor: Interval| {
+        for a in [left.min, left.max] {
+            for b in [divisor.min, divisor.max] {
+                let (a, b) = (a as i128, b as i128);
+                results.push(match operator {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Subtract => a - b,
+                    BinaryOp::Multiply => a * b,
+                    BinaryOp::Divide => a / b,
+                });
+            }
+        }
+    };
+
+    if *operator == BinaryOp::Divide {
+        // Zero itself is left out: dividing by it fails rather than giving a result
+        if right.min < 0 {
+            bounds(Interval {
+                min: right.min,
+                max: right.max.min(-1),
+            });
+        }
+        if right.max > 0 {
+            bounds(Interval {
+                min: right.min.max(1),
+                max: right.max,
+            });
+        }
+    } else {
+        bounds(right);
+    }
+
+    let min = results.iter().copied().min().unwrap_or_default();
+    let max = results.iter().copied().max().unwrap_or_default();
+    clamp(min, max)
+}
+
+// The results in `min..=max` that fit in 64 bits, or an overflow if none do
+fn clamp(min: i128, max: i128) -> Result<Value, ConstErrorKind> {
+    if max < i64::MIN as i128 || min > i64::MAX as i128 {
+        return Err(ConstErrorKind::Overflow);
+    }
+    Ok(Value::Int(Interval {
+        min: min.max(i64::MIN as i128) as i64,
+        max: max.min(i64::MAX as i128) as i64,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    // Each failing statement's path and error message
+    fn failures(source: &str) -> Vec<(Vec<usize>, String)> {
+        check_ranges(&parse_source(source).unwrap())
+            .into_iter()
+            .map(|(statement, error)| (statement, error.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_division_by_a_value_that_is_always_zero() {
+        assert_eq!(
+            failures("let d = n * 0; let e = (d * 7, 1); let (z, _) = e; 1; { 10 / z; }"),
+            vec![(vec![4, 0], "this divides by zero".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_guaranteed_overflow() {
+        let errors = check_ranges(
+            &parse_source("let big = 9223372036854775807; let r = n / 1 * 0 + 1; big + r;")
+                .unwrap(),
+        );
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, vec![2]);
+        assert_eq!(errors[0].1.kind, ConstErrorKind::Overflow);
+    }
+
+    #[test]
+    fn test_possible_failures_are_not_reported() {
+        assert!(failures("let d = n - 1; 10 / d; n + 1; n * n; -n;").is_empty());
+        assert!(failures("let d = 0; { let d = 1; 10 / d; } (d, 1) / 1;").is_empty());
+    }
+
+    #[test]
+    fn test_divisor_ranges_skip_zero() {
+        // `10 / b` for `b` in -1..=3 without 0 lies in -10..=10, so this never overflows
+        assert!(failures("let b = n / 9223372036854775807 * 2 + 1; 10 / b;").is_empty());
+        assert_eq!(
+            failures("let b = n * 0; let q = n / (b + 1); q / b;"),
+            vec![(vec![2], "this divides by zero".to_string())]
+        );
+    }
+}
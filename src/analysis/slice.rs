@@ -0,0 +1,71 @@
+use super::free::free_variables;
+use crate::parser::{Program, Stmt};
+use std::collections::BTreeSet;
+
+/// Returns the smallest sub-program that still computes the final top-level
+/// binding of `target`: that `let` plus every earlier top-level `let` its value
+/// transitively reads, in their original order. Expression statements and
+/// blocks are left out, since nothing they bind is visible afterwards.
+///
+/// Returns None if `target` is not bound at the top level.
+pub fn slice(program: &Program, target: &str) -> Option<Program> {
+    let end = program
+        .statements
+        .iter()
+        .rposition(|stmt| stmt.bound_names().contains(&target))?;
+
+    let mut needed = BTreeSet::from([target.to_string()]);
+    let mut kept = Vec::new();
+
+    for stmt in program.statements[..=end].iter().rev() {
+        if let Stmt::Let { value, .. } | Stmt::Destructure { value, .. } = stmt {
+            let bound = stmt.bound_names();
+            if bound.iter().any(|name| needed.contains(*name)) {
+                for name in bound {
+                    needed.remove(name);
+                }
+                needed.extend(free_variables(value));
+                kept.push(stmt.clone());
+            }
+        }
+    }
+
+    kept.reverse();
+    Some(Program { statements: kept })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn slice_source(source: &str, target: &str) -> Option<String> {
+        slice(&parse_source(source).unwrap(), target).map(|program| program.to_string())
+    }
+
+    #[test]
+    fn test_keeps_only_what_the_target_needs() {
+        let source = "let a = 1; let b = 2; let unused = b; a + b; let c = a * 3; { let c = 9; } let result = c + 1;";
+
+        assert_eq!(
+            slice_source(source, "result").unwrap(),
+            "let a = 1;\nlet c = (a * 3);\nlet result = (c + 1);\n"
+        );
+    }
+
+    #[test]
+    fn test_follows_rebindings() {
+        let source = "let x = 1; let y = 5; let x = x + y; let r = x; let x = 100;";
+
+        assert_eq!(
+            slice_source(source, "r").unwrap(),
+            "let x = 1;\nlet y = 5;\nlet x = (x + y);\nlet r = x;\n"
+        );
+        assert_eq!(slice_source(source, "x").unwrap(), "let x = 100;\n");
+    }
+
+    #[test]
+    fn test_unknown_target() {
+        assert_eq!(slice_source("let a = 1; { let b = 2; }", "b"), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Arbitrary-precision integers, for scripts whose results outgrow 64 bits.
+//! Integer literals too large for 64 bits evaluate to [`Value::BigInt`]s, and
+//! arithmetic run with [`ArbitraryPrecision`] grows into them instead of
+//! overflowing. Hosts can also bind big integers before running a program.
+
+use crate::fold::apply_binary;
+use crate::interpreter::{Arithmetic, RuntimeError, Value};
+use crate::parser::BinaryOp;
+pub use num_bigint::BigInt;
+use num_bigint::Sign;
+
+/// Arithmetic where integer results that overflow 64 bits become
+/// [`Value::BigInt`]s instead of errors. Run programs with it through
+/// [`Environment::run_with`](crate::interpreter::Environment::run_with).
+/// Quantities and floats keep their usual rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ArbitraryPrecision;
+
+impl Arithmetic for ArbitraryPrecision {
+    fn binary(
+        &self,
+        operator: &BinaryOp,
+        left: &Value,
+        right: &Value,
+    ) -> Option<Result<Value, RuntimeError>> {
+        match (left, right) {
+            (Value::Int(a), Value::Int(b)) if apply_binary(operator, *a, *b).is_none() => {
+                Some(binary(operator, BigInt::from(*a), BigInt::from(*b)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Applies `operator` to two integers of any size. Division truncates toward
+/// zero, as it does for 64-bit integers.
+pub(crate) fn binary(operator: &BinaryOp, a: BigInt, b: BigInt) -> Result<Value, RuntimeError> {
+    let result = match operator {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide if b.sign() == Sign::NoSign => return Err(RuntimeError::DivisionByZero),
+        BinaryOp::Divide => a / b,
+    };
+    Ok(integer(result))
+}
+
+/// An integer as a [`Value::Int`] if it fits in 64 bits, so each integer has
+/// one representation and values compare equal whichever way they were made
+pub(crate) fn integer(value: BigInt) -> Value {
+    match i64::try_from(&value) {
+        Ok(value) => Value::Int(value),
+        Err(_) => Value::BigInt(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Environment;
+    use crate::parse_source;
+    use crate::testing::expression;
+
+    fn run(source: &str) -> Result<Option<Value>, RuntimeError> {
+        Environment::new().run_with(&parse_source(source).unwrap(), &ArbitraryPrecision)
+    }
+
+    fn big(digits: &str) -> Value {
+        Value::BigInt(digits.parse().unwrap())
+    }
+
+    #[test]
+    fn test_overflowing_results_grow() {
+        assert_eq!(
+            run("9223372036854775807 + 1;"),
+            Ok(Some(big("9223372036854775808")))
+        );
+        assert_eq!(
+            run("let f = 1000000000000; f * f * f;"),
+            Ok(Some(big("1000000000000000000000000000000000000")))
+        );
+        assert_eq!(
+            run("let n = 9223372036854775807 * 4; -n;"),
+            Ok(Some(big("-36893488147419103228")))
+        );
+        // Results that fit come back to 64 bits
+        assert_eq!(
+            run("(9223372036854775807 + 1) / 2 - 1;"),
+            Ok(Some(Value::Int(4611686018427387903)))
+        );
+        assert_eq!(
+            run("(9223372036854775807 * 3) / 0;"),
+            Err(RuntimeError::DivisionByZero)
+        );
+        assert_eq!(run("7 / 2 + 0.5;"), Ok(Some(Value::Float(3.5))));
+    }
+
+    #[test]
+    fn test_big_literals() {
+        assert_eq!(
+            run("99999999999999999999 + 1;"),
+            Ok(Some(big("100000000000000000000")))
+        );
+        assert_eq!(
+            Environment::new().run(&parse_source("-99999999999999999999 / 3;").unwrap()),
+            Ok(Some(big("-33333333333333333333")))
+        );
+        let program = crate::compile("let n = 99999999999999999999; n - n;").unwrap();
+        assert_eq!(Environment::new().run(&program), Ok(Some(Value::Int(0))));
+    }
+
+    #[test]
+    fn test_host_values_use_the_built_in_rules() {
+        let mut env = Environment::new();
+        env.define("n", big("-100000000000000000000"));
+        let program = parse_source("(n / 3, n - n, -n, n + 0.5);").unwrap();
+
+        assert_eq!(
+            env.run(&program),
+            Ok(Some(Value::Tuple(vec![
+                big("-33333333333333333333"),
+                Value::Int(0),
+                big("100000000000000000000"),
+                Value::Float(-1e20),
+            ])))
+        );
+        assert_eq!(env.get("n").unwrap().to_string(), "-100000000000000000000");
+        let trace = env.trace_evaluation(&expression("n"));
+        assert_eq!(trace.error, None);
+        assert_eq!(trace.value(), Some(big("-100000000000000000000")));
+        let trace = env.trace_evaluation(&expression("n * n + 1"));
+        assert_eq!(trace.error, None);
+        assert_eq!(
+            trace.value(),
+            Some(big("10000000000000000000000000000000000000001"))
+        );
+    }
+}
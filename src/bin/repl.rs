@@ -1,45 +1,187 @@
-use oxide::{parse_source, Expr, Stmt};
-use std::io::{self, Write};
+#[cfg(feature = "readline")]
+use oxide::completion::{complete, word_before_cursor};
+use oxide::interpreter::{Environment, RuntimeError, Snapshot, Value, ValueFormatter};
+use oxide::suffix::LiteralSuffixes;
+use oxide::types::type_map;
+use oxide::{parse_source, ParseErrors, Parser, Program, Span, Stmt};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
 
 fn main() {
     println!("Oxide Language REPL");
     println!("Type 'help' for commands, 'quit' to exit");
-    println!("Enter Oxide code to parse and see the AST\n");
+    println!("Enter Oxide code to see its AST and run it\n");
+
+    let mut session = Session::default();
+    let mut lines = Lines::new();
+
+    while let Some(line) = lines.next(&session.program) {
+        let input = line.trim();
+
+        if input.is_empty() {
+            continue;
+        }
+
+        match input {
+            "quit" | "exit" | "q" => {
+                println!("Goodbye!");
+                break;
+            }
+            "help" | "h" => {
+                print_help();
+                continue;
+            }
+            "clear" | "cls" => {
+                print!("\x1B[2J\x1B[1;1H");
+                continue;
+            }
+            _ if input.starts_with(':') => {
+                handle_command(input, &mut session);
+            }
+            _ => {
+                handle_input(input, &mut session);
+            }
+        }
+    }
+}
+
+/// Reads input lines, with line editing, history and Tab completion of
+/// keywords and session bindings when built with the `readline` feature
+struct Lines {
+    #[cfg(feature = "readline")]
+    editor: Option<rustyline::Editor<Completions, rustyline::history::DefaultHistory>>,
+}
+
+impl Lines {
+    fn new() -> Self {
+        #[cfg(feature = "readline")]
+        {
+            // Without a usable terminal, lines are read plainly
+            let editor = rustyline::Editor::new()
+                .map(|mut editor| {
+                    editor.set_helper(Some(Completions::default()));
+                    editor
+                })
+                .ok();
+            Self { editor }
+        }
+        #[cfg(not(feature = "readline"))]
+        Self {}
+    }
+
+    // Returns the next line, completing against the bindings of `program`,
+    // or None at the end of input
+    fn next(&mut self, program: &Program) -> Option<String> {
+        #[cfg(feature = "readline")]
+        if let Some(editor) = &mut self.editor {
+            use rustyline::error::ReadlineError;
+
+            if let Some(completions) = editor.helper_mut() {
+                completions.program = program.clone();
+            }
+            return match editor.readline("> ") {
+                Ok(line) => {
+                    let _ = editor.add_history_entry(line.as_str());
+                    Some(line)
+                }
+                // Ctrl-C abandons the line being edited
+                Err(ReadlineError::Interrupted) => Some(String::new()),
+                Err(ReadlineError::Eof) => None,
+                Err(error) => {
+                    eprintln!("Error reading input: {}", error);
+                    None
+                }
+            };
+        }
+        let _ = program;
 
-    loop {
         print!("> ");
         io::stdout().flush().unwrap();
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(line),
+            Err(error) => {
+                eprintln!("Error reading input: {}", error);
+                None
+            }
+        }
+    }
+}
 
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let input = input.trim();
+/// Completes the word before the cursor with keywords and the session's bindings
+#[cfg(feature = "readline")]
+#[derive(Default)]
+struct Completions {
+    program: Program,
+}
 
-                if input.is_empty() {
-                    continue;
-                }
+#[cfg(feature = "readline")]
+impl rustyline::completion::Completer for Completions {
+    type Candidate = String;
 
-                match input {
-                    "quit" | "exit" | "q" => {
-                        println!("Goodbye!");
-                        break;
-                    }
-                    "help" | "h" => {
-                        print_help();
-                        continue;
-                    }
-                    "clear" | "cls" => {
-                        print!("\x1B[2J\x1B[1;1H");
-                        continue;
-                    }
-                    _ => {
-                        handle_input(input);
-                    }
-                }
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let word = word_before_cursor(&line[..pos]);
+        Ok((pos - word.len(), complete(word, &self.program)))
+    }
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::hint::Hinter for Completions {
+    type Hint = String;
+}
+
+#[cfg(feature = "readline")]
+impl rustyline::highlight::Highlighter for Completions {}
+
+#[cfg(feature = "readline")]
+impl rustyline::validate::Validator for Completions {}
+
+#[cfg(feature = "readline")]
+impl rustyline::Helper for Completions {}
+
+/// Statements that parsed and ran successfully during this session, and the
+/// bindings they made
+#[derive(Default)]
+struct Session {
+    program: Program,
+    env: Environment,
+    /// The bindings and statement count before each input that ran, latest last
+    history: Vec<(Snapshot, usize)>,
+}
+
+impl Session {
+    // Runs statements after the session's, adding them to it only if they
+    // all succeed
+    fn run(&mut self, program: Program) -> Result<Option<Value>, RuntimeError> {
+        let before = self.env.snapshot();
+        match self.env.run(&program) {
+            Ok(value) => {
+                self.history.push((before, self.program.statements.len()));
+                self.program.extend(program);
+                Ok(value)
             }
             Err(error) => {
-                eprintln!("Error reading input: {}", error);
+                self.env.restore(&before);
+                Err(error)
+            }
+        }
+    }
+
+    // Forgets the latest input that ran, returning false if there is none
+    fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some((bindings, statements)) => {
+                self.env.restore(&bindings);
+                self.program.statements.truncate(statements);
+                true
             }
+            None => false,
         }
     }
 }
@@ -49,6 +191,14 @@ fn print_help() {
     println!("  help, h     - Show this help message");
     println!("  quit, exit, q - Exit the REPL");
     println!("  clear, cls  - Clear the screen");
+    println!("  :save <file> - Save this session's statements to a file");
+    println!("  :load <file> - Parse a file and add its statements to the session");
+    println!("  :type <expr> - Show the type of an expression using the session's bindings");
+    println!("  :trace <code> - Show the rules and tokens the parser goes through for code");
+    println!("  :steps <expr> - Evaluate an expression one step at a time");
+    println!("  :env        - List the session's bindings with their values and types");
+    println!("  :undo       - Forget the statements and bindings of the last input");
+    println!("  :reset      - Forget the session's statements and bindings");
     println!("\nExamples:");
     println!("  let x = 42;");
     println!("  1 + 2 * 3;");
@@ -58,7 +208,129 @@ fn print_help() {
     println!();
 }
 
-fn handle_input(input: &str) {
+fn handle_command(input: &str, session: &mut Session) {
+    let (command, argument) = match input.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (input, ""),
+    };
+
+    match (command, argument) {
+        (":save", path) if !path.is_empty() => save_session(path, &session.program),
+        (":load", path) if !path.is_empty() => load_file(path, session),
+        (":type", expr) if !expr.is_empty() => print_type(expr, &session.program),
+        (":trace", code) if !code.is_empty() => print_trace(code),
+        (":steps", expr) if !expr.is_empty() => print_steps(expr, &session.env),
+        (":env", "") => print_env(&session.env),
+        (":undo", "") if session.undo() => println!("Undid the last input\n"),
+        (":undo", "") => println!("Nothing to undo\n"),
+        (":reset", "") => {
+            *session = Session::default();
+            println!("Session cleared\n");
+        }
+        (":save", _) | (":load", _) => println!("Usage: {} <file>\n", command),
+        (":type", _) => println!("Usage: :type <expr>\n"),
+        (":trace", _) => println!("Usage: :trace <code>\n"),
+        (":steps", _) => println!("Usage: :steps <expr>\n"),
+        _ => println!("Unknown command: {} (type 'help' for commands)\n", command),
+    }
+}
+
+// Types the expression as a statement after the session's, so it sees the
+// session's bindings
+fn print_type(expr: &str, session: &Program) {
+    let expr = expr.trim_end_matches(';').trim_end();
+    let prefix = session.to_string();
+    let source = format!("{}{};", prefix, expr);
+    let span = Span::new(prefix.len(), prefix.len() + expr.len());
+
+    match type_map(&source, &LiteralSuffixes::new()) {
+        Ok(types) => match types.type_of(span) {
+            Some(ty) => println!("{} : {}\n", expr, ty.display()),
+            None => println!("✗ Not an expression: {}\n", expr),
+        },
+        Err(error) => println!("✗ {}\n", error),
+    }
+}
+
+fn print_env(env: &Environment) {
+    let bindings = env.bindings();
+    if bindings.is_empty() {
+        println!("No bindings\n");
+        return;
+    }
+    let formatter = ValueFormatter::default();
+    for (name, value) in bindings {
+        println!(
+            "{} = {} : {}",
+            name,
+            formatter.format(value),
+            value.type_of(&LiteralSuffixes::new())
+        );
+    }
+    println!();
+}
+
+// Parses code without adding it to the session, printing each step
+fn print_trace(code: &str) {
+    let mut parser = Parser::from_source(code).with_trace();
+    let result = parser.parse();
+
+    if let Some(trace) = parser.take_trace() {
+        print!("{}", trace.render());
+    }
+    match result {
+        Ok(_) => println!(),
+        Err(errors) => print_errors(&errors),
+    }
+}
+
+// Evaluates the expression step by step with the session's bindings
+fn print_steps(expr: &str, env: &Environment) {
+    let expr = expr.trim_end_matches(';').trim_end();
+    match parse_source(&format!("{};", expr)) {
+        Ok(mut program) => match program.statements.pop() {
+            Some(Stmt::Expression(expr)) if program.statements.is_empty() => {
+                println!("{}", env.trace_evaluation(&expr).render())
+            }
+            _ => println!("✗ Not an expression: {}\n", expr),
+        },
+        Err(errors) => print_errors(&errors),
+    }
+}
+
+fn save_session(path: &str, session: &Program) {
+    match fs::write(path, session.to_string()) {
+        Ok(()) => println!(
+            "Saved {} statement(s) to {}\n",
+            session.statements.len(),
+            path
+        ),
+        Err(error) => println!("✗ Could not write {}: {}\n", path, error),
+    }
+}
+
+fn load_file(path: &str, session: &mut Session) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            println!("✗ Could not read {}: {}\n", path, error);
+            return;
+        }
+    };
+
+    match Parser::from_source(&source).parse() {
+        Ok(program) => {
+            let count = program.statements.len();
+            match session.run(program) {
+                Ok(_) => println!("Loaded {} statement(s) from {}\n", count, path),
+                Err(error) => println!("✗ Runtime error: {}\n", error),
+            }
+        }
+        Err(errors) => print_errors(&errors),
+    }
+}
+
+fn handle_input(input: &str, session: &mut Session) {
     match parse_source(input) {
         Ok(program) => {
             if program.statements.is_empty() {
@@ -68,83 +340,64 @@ fn handle_input(input: &str) {
 
             println!("✓ Parsed successfully!");
             println!("AST:");
+            print!("{}", program.render_tree_with(io::stdout().is_terminal()));
+            println!();
 
-            for (i, stmt) in program.statements.iter().enumerate() {
-                if program.statements.len() > 1 {
-                    println!("  Statement {}:", i + 1);
-                }
-                print_statement(stmt, if program.statements.len() > 1 { 2 } else { 1 });
+            match session.run(program) {
+                Ok(Some(value)) => println!("= {}\n", ValueFormatter::default().format(&value)),
+                Ok(None) => {}
+                Err(error) => println!("✗ Runtime error: {}\n", error),
             }
-            println!();
         }
         Err(errors) => {
-            println!("✗ Parse failed:");
-            for (i, error) in errors.errors.iter().enumerate() {
-                if errors.errors.len() > 1 {
-                    println!("  Error {}: {}", i + 1, error);
-                } else {
-                    println!("  {}", error);
-                }
-            }
-            println!();
+            print_errors(&errors);
+            print_underlines(input, &errors);
         }
     }
 }
 
-fn print_statement(stmt: &Stmt, indent_level: usize) {
-    let indent = "  ".repeat(indent_level);
-
-    match stmt {
-        Stmt::Let { name, value } => {
-            println!("{}Let Statement:", indent);
-            println!("{}  Variable: {}", indent, name);
-            println!("{}  Value:", indent);
-            print_expression(value, indent_level + 2);
-        }
-        Stmt::Expression(expr) => {
-            println!("{}Expression Statement:", indent);
-            print_expression(expr, indent_level + 1);
-        }
-        Stmt::Block(statements) => {
-            println!("{}Block Statement:", indent);
-            println!("{}  Statements ({}):", indent, statements.len());
-            for (i, stmt) in statements.iter().enumerate() {
-                println!("{}    [{}]:", indent, i);
-                print_statement(stmt, indent_level + 3);
-            }
+fn print_errors(errors: &ParseErrors) {
+    println!("✗ Parse failed:");
+    for (i, error) in errors.errors.iter().enumerate() {
+        if errors.errors.len() > 1 {
+            println!("  Error {}: {}", i + 1, error);
+        } else {
+            println!("  {}", error);
         }
     }
+    println!();
 }
 
-fn print_expression(expr: &Expr, indent_level: usize) {
-    let indent = "  ".repeat(indent_level);
+// Echoes the input with carets beneath each error's span, numbered when there
+// are several errors. Related locations, such as where an unclosed delimiter
+// was opened, are marked too.
+fn print_underlines(input: &str, errors: &ParseErrors) {
+    let numbered = errors.errors.len() > 1;
+    let mut marks: Vec<(Span, String)> = Vec::new();
 
-    match expr {
-        Expr::Number(n) => {
-            println!("{}Number: {}", indent, n);
-        }
-        Expr::Identifier(name) => {
-            println!("{}Identifier: {}", indent, name);
-        }
-        Expr::Binary {
-            left,
-            operator,
-            right,
-        } => {
-            println!("{}Binary Expression ({:?}):", indent, operator);
-            println!("{}  Left:", indent);
-            print_expression(left, indent_level + 2);
-            println!("{}  Right:", indent);
-            print_expression(right, indent_level + 2);
+    for (i, error) in errors.errors.iter().enumerate() {
+        let number = if numbered {
+            format!(" {}", i + 1)
+        } else {
+            String::new()
+        };
+        if let Some(span) = error.span() {
+            marks.push((span, number.clone()));
         }
-        Expr::Unary { operator, operand } => {
-            println!("{}Unary Expression ({:?}):", indent, operator);
-            println!("{}  Operand:", indent);
-            print_expression(operand, indent_level + 2);
-        }
-        Expr::Grouping(inner) => {
-            println!("{}Grouped Expression:", indent);
-            print_expression(inner, indent_level + 1);
+        if let Some(span) = error.related_span() {
+            marks.push((span, format!("{} opened here", number)));
         }
     }
+
+    if marks.is_empty() {
+        return;
+    }
+
+    println!("  {}", input);
+    for (span, label) in marks {
+        let column = input[..span.start].chars().count();
+        let width = input[span.start..span.end].chars().count().max(1);
+        println!("  {}{}{}", " ".repeat(column), "^".repeat(width), label);
+    }
+    println!();
 }
@@ -0,0 +1,659 @@
+//! A compact binary encoding of syntax trees for build caches.
+//!
+//! An encoded program starts with a header: the magic bytes `OXAST`, the
+//! format version as a little-endian `u16`, and a hash of the source the
+//! program was parsed from as a little-endian `u64`. The tree follows, with
+//! integers as LEB128 varints (zigzag-encoded when signed), floats as the
+//! eight little-endian bytes of their bits, strings as their length and UTF-8
+//! bytes, and each node as a one-byte tag and its fields. A
+//! little-endian `u64` checksum of everything before it ends the encoding.
+//!
+//! Decoding checks the checksum and that the source hash matches the source
+//! the caller has now, so a cache entry for an edited file is rejected rather
+//! than yielding a tree whose positions no longer line up with its text.
+
+use crate::parser::{BinaryOp, Expr, Pattern, Program, Stmt, UnaryOp};
+use std::fmt;
+
+const MAGIC: &[u8; 5] = b"OXAST";
+
+/// Version of the encoding, raised whenever it changes incompatibly
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Nesting deeper than this is rejected, so a corrupt or hostile input cannot
+/// exhaust the stack. A chain of binary operations nested on the left, as
+/// `1 + 2 + 3` parses, counts as one level however long it is.
+pub const MAX_DEPTH: usize = 256;
+
+const HEADER_LEN: usize = MAGIC.len() + 2 + 8;
+const CHECKSUM_LEN: usize = 8;
+
+/// Why encoded bytes could not be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes do not start with the magic bytes of an encoded program
+    NotAnEncodedProgram,
+    /// The bytes were written by a different version of the encoding
+    UnsupportedVersion(u16),
+    /// The checksum does not match: the bytes were truncated or corrupted
+    ChecksumMismatch,
+    /// The program was parsed from different source text
+    SourceChanged,
+    /// The checksum matched but the contents are not a valid tree
+    Malformed(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NotAnEncodedProgram => write!(f, "not an encoded Oxide program"),
+            DecodeError::UnsupportedVersion(version) => write!(
+                f,
+                "encoding version {} is not supported; this is version {}",
+                version, FORMAT_VERSION
+            ),
+            DecodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+            DecodeError::SourceChanged => {
+                write!(f, "the program was parsed from different source text")
+            }
+            DecodeError::Malformed(message) => write!(f, "malformed encoding: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Why a program could not be encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The program nests deeper than [`MAX_DEPTH`], so decoding would reject it
+    NestingTooDeep,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::NestingTooDeep => write!(
+                f,
+                "the program nests deeper than the {} levels an encoding can hold",
+                MAX_DEPTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl Program {
+    /// Encodes the program, recording a hash of the `source` it was parsed
+    /// from. Fails rather than writing bytes [`Program::decode`] would reject.
+    pub fn encode(&self, source: &str) -> Result<Vec<u8>, EncodeError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(&hash(source.as_bytes()).to_le_bytes());
+
+        let mut encoder = Encoder { out, depth: 0 };
+        encoder.statements(&self.statements)?;
+        let mut out = encoder.out;
+
+        let checksum = hash(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+        Ok(out)
+    }
+
+    /// Decodes a program encoded by [`Program::encode`], checking that it was
+    /// parsed from `source`
+    pub fn decode(bytes: &[u8], source: &str) -> Result<Program, DecodeError> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(DecodeError::NotAnEncodedProgram);
+        }
+        if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(DecodeError::ChecksumMismatch);
+        }
+        let version = u16::from_le_bytes([bytes[5], bytes[6]]);
+        if version != FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let (contents, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if hash(contents).to_le_bytes() != checksum {
+            return Err(DecodeError::ChecksumMismatch);
+        }
+        if contents[7..HEADER_LEN] != hash(source.as_bytes()).to_le_bytes() {
+            return Err(DecodeError::SourceChanged);
+        }
+
+        let mut decoder = Decoder {
+            bytes: &contents[HEADER_LEN..],
+            position: 0,
+            depth: 0,
+        };
+        let statements = decoder.statements()?;
+        if decoder.position != decoder.bytes.len() {
+            return Err(decoder.malformed("trailing bytes after the program"));
+        }
+        Ok(Program { statements })
+    }
+}
+
+// 64-bit FNV-1a, which is stable across platforms and releases
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Node tags. Each enum numbers its variants from zero in declaration order;
+// new variants take the next free number.
+mod tag {
+    pub const LET: u8 = 0;
+    pub const DESTRUCTURE: u8 = 1;
+    pub const EXPRESSION: u8 = 2;
+    pub const BLOCK: u8 = 3;
+
+    pub const NUMBER: u8 = 0;
+    pub const IDENTIFIER: u8 = 1;
+    pub const BINARY: u8 = 2;
+    pub const UNARY: u8 = 3;
+    pub const GROUPING: u8 = 4;
+    pub const TUPLE: u8 = 5;
+    pub const SUFFIXED: u8 = 6;
+    pub const FLOAT: u8 = 7;
+    pub const BIG_NUMBER: u8 = 8;
+
+    pub const WILDCARD: u8 = 0;
+    pub const LITERAL: u8 = 1;
+    pub const NAME: u8 = 2;
+    pub const PATTERN_TUPLE: u8 = 3;
+}
+
+// Counts nesting the way the decoder does, so everything it writes can be
+// read back
+struct Encoder {
+    out: Vec<u8>,
+    depth: usize,
+}
+
+impl Encoder {
+    fn statements(&mut self, statements: &[Stmt]) -> Result<(), EncodeError> {
+        self.unsigned(statements.len() as u64);
+        for stmt in statements {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<(), EncodeError> {
+        self.nested(|encoder| match stmt {
+            Stmt::Let { name, value, doc } => {
+                encoder.out.push(tag::LET);
+                encoder.string(name);
+                encoder.expression(value)?;
+                encoder.doc(doc);
+                Ok(())
+            }
+            Stmt::Destructure {
+                pattern,
+                value,
+                doc,
+            } => {
+                encoder.out.push(tag::DESTRUCTURE);
+                encoder.pattern(pattern)?;
+                encoder.expression(value)?;
+                encoder.doc(doc);
+                Ok(())
+            }
+            Stmt::Expression(expr) => {
+                encoder.out.push(tag::EXPRESSION);
+                encoder.expression(expr)
+            }
+            Stmt::Block(statements) => {
+                encoder.out.push(tag::BLOCK);
+                encoder.statements(statements)
+            }
+        })
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<(), EncodeError> {
+        self.nested(|encoder| encoder.expression_contents(expr))
+    }
+
+    fn expression_contents(&mut self, expr: &Expr) -> Result<(), EncodeError> {
+        match expr {
+            Expr::Number(value) => {
+                self.out.push(tag::NUMBER);
+                self.signed(*value);
+            }
+            Expr::Float(value) => {
+                self.out.push(tag::FLOAT);
+                self.out.extend_from_slice(&value.to_bits().to_le_bytes());
+            }
+            Expr::BigNumber(digits) => {
+                self.out.push(tag::BIG_NUMBER);
+                self.string(digits);
+            }
+            Expr::Identifier(name) => {
+                self.out.push(tag::IDENTIFIER);
+                self.string(name);
+            }
+            Expr::Binary { .. } => {
+                // A chain nested on the left is written as its operators,
+                // outermost first, then its innermost left operand and the
+                // right operands, innermost first: the same bytes as writing
+                // each operation in turn, but within one level of nesting
+                let mut rights = Vec::new();
+                let mut leftmost = expr;
+                while let Expr::Binary {
+                    left,
+                    operator,
+                    right,
+                } = leftmost
+                {
+                    self.out.push(tag::BINARY);
+                    self.out.push(match operator {
+                        BinaryOp::Add => 0,
+                        BinaryOp::Subtract => 1,
+                        BinaryOp::Multiply => 2,
+                        BinaryOp::Divide => 3,
+                    });
+                    rights.push(right);
+                    leftmost = left;
+                }
+                self.expression(leftmost)?;
+                for right in rights.into_iter().rev() {
+                    self.expression(right)?;
+                }
+            }
+            Expr::Unary { operator, operand } => {
+                self.out.push(tag::UNARY);
+                self.out.push(match operator {
+                    UnaryOp::Negate => 0,
+                    UnaryOp::Plus => 1,
+                });
+                self.expression(operand)?;
+            }
+            Expr::Grouping(inner) => {
+                self.out.push(tag::GROUPING);
+                self.expression(inner)?;
+            }
+            Expr::Tuple(elements) => {
+                self.out.push(tag::TUPLE);
+                self.unsigned(elements.len() as u64);
+                for element in elements {
+                    self.expression(element)?;
+                }
+            }
+            Expr::Suffixed { value, suffix } => {
+                self.out.push(tag::SUFFIXED);
+                self.signed(*value);
+                self.string(suffix);
+            }
+        }
+        Ok(())
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> Result<(), EncodeError> {
+        self.nested(|encoder| {
+            match pattern {
+                Pattern::Wildcard => encoder.out.push(tag::WILDCARD),
+                Pattern::Literal(value) => {
+                    encoder.out.push(tag::LITERAL);
+                    encoder.signed(*value);
+                }
+                Pattern::Identifier(name) => {
+                    encoder.out.push(tag::NAME);
+                    encoder.string(name);
+                }
+                Pattern::Tuple(elements) => {
+                    encoder.out.push(tag::PATTERN_TUPLE);
+                    encoder.unsigned(elements.len() as u64);
+                    for element in elements {
+                        encoder.pattern(element)?;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn nested(
+        &mut self,
+        encode: impl FnOnce(&mut Self) -> Result<(), EncodeError>,
+    ) -> Result<(), EncodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(EncodeError::NestingTooDeep);
+        }
+        self.depth += 1;
+        let result = encode(self);
+        self.depth -= 1;
+        result
+    }
+
+    // A doc comment is a presence byte, then the text if there is one
+    fn doc(&mut self, doc: &Option<String>) {
+        match doc {
+            Some(text) => {
+                self.out.push(1);
+                self.string(text);
+            }
+            None => self.out.push(0),
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        self.unsigned(text.len() as u64);
+        self.out.extend_from_slice(text.as_bytes());
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.unsigned(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn unsigned(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.out.push(value as u8);
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn statements(&mut self) -> Result<Vec<Stmt>, DecodeError> {
+        let count = self.length()?;
+        (0..count).map(|_| self.statement()).collect()
+    }
+
+    fn statement(&mut self) -> Result<Stmt, DecodeError> {
+        self.nested(|decoder| match decoder.byte()? {
+            tag::LET => Ok(Stmt::Let {
+                name: decoder.string()?,
+                value: decoder.expression()?,
+                doc: decoder.doc()?,
+            }),
+            tag::DESTRUCTURE => Ok(Stmt::Destructure {
+                pattern: decoder.pattern()?,
+                value: decoder.expression()?,
+                doc: decoder.doc()?,
+            }),
+            tag::EXPRESSION => Ok(Stmt::Expression(decoder.expression()?)),
+            tag::BLOCK => Ok(Stmt::Block(decoder.statements()?)),
+            other => Err(decoder.malformed(&format!("unknown statement tag {}", other))),
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expr, DecodeError> {
+        self.nested(|decoder| match decoder.byte()? {
+            tag::NUMBER => Ok(Expr::Number(decoder.signed()?)),
+            tag::FLOAT => Ok(Expr::Float(decoder.float()?)),
+            tag::BIG_NUMBER => Ok(Expr::BigNumber(decoder.string()?)),
+            tag::IDENTIFIER => Ok(Expr::Identifier(decoder.string()?)),
+            tag::BINARY => {
+                // A chain nested on the left is read in a loop, as it was
+                // written, so only other nesting counts toward the limit
+                let mut operators = vec![decoder.operator()?];
+                while decoder.bytes.get(decoder.position) == Some(&tag::BINARY) {
+                    decoder.position += 1;
+                    operators.push(decoder.operator()?);
+                }
+                let mut left = decoder.expression()?;
+                for operator in operators.into_iter().rev() {
+                    left = Expr::binary(left, operator, decoder.expression()?);
+                }
+                Ok(left)
+            }
+            tag::UNARY => {
+                let operator = match decoder.byte()? {
+                    0 => UnaryOp::Negate,
+                    1 => UnaryOp::Plus,
+                    other => {
+                        return Err(decoder.malformed(&format!("unknown operator {}", other)));
+                    }
+                };
+                Ok(Expr::unary(operator, decoder.expression()?))
+            }
+            tag::GROUPING => Ok(Expr::grouping(decoder.expression()?)),
+            tag::TUPLE => {
+                let count = decoder.length()?;
+                let elements = (0..count)
+                    .map(|_| decoder.expression())
+                    .collect::<Result<_, _>>()?;
+                Ok(Expr::Tuple(elements))
+            }
+            tag::SUFFIXED => Ok(Expr::Suffixed {
+                value: decoder.signed()?,
+                suffix: decoder.string()?,
+            }),
+            other => Err(decoder.malformed(&format!("unknown expression tag {}", other))),
+        })
+    }
+
+    fn operator(&mut self) -> Result<BinaryOp, DecodeError> {
+        match self.byte()? {
+            0 => Ok(BinaryOp::Add),
+            1 => Ok(BinaryOp::Subtract),
+            2 => Ok(BinaryOp::Multiply),
+            3 => Ok(BinaryOp::Divide),
+            other => Err(self.malformed(&format!("unknown operator {}", other))),
+        }
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, DecodeError> {
+        self.nested(|decoder| match decoder.byte()? {
+            tag::WILDCARD => Ok(Pattern::Wildcard),
+            tag::LITERAL => Ok(Pattern::Literal(decoder.signed()?)),
+            tag::NAME => Ok(Pattern::Identifier(decoder.string()?)),
+            tag::PATTERN_TUPLE => {
+                let count = decoder.length()?;
+                let elements = (0..count)
+                    .map(|_| decoder.pattern())
+                    .collect::<Result<_, _>>()?;
+                Ok(Pattern::Tuple(elements))
+            }
+            other => Err(decoder.malformed(&format!("unknown pattern tag {}", other))),
+        })
+    }
+
+    fn nested<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T, DecodeError>,
+    ) -> Result<T, DecodeError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.malformed("nesting too deep"));
+        }
+        self.depth += 1;
+        let result = decode(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn doc(&mut self) -> Result<Option<String>, DecodeError> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.string()?)),
+            other => Err(self.malformed(&format!("invalid doc comment marker {}", other))),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let length = self.length()?;
+        let end = self.position + length;
+        let text = std::str::from_utf8(&self.bytes[self.position..end])
+            .map_err(|_| self.malformed("string is not UTF-8"))?;
+        self.position = end;
+        Ok(text.to_string())
+    }
+
+    // A count or byte length, which cannot exceed the bytes left since every
+    // element takes at least one
+    fn length(&mut self) -> Result<usize, DecodeError> {
+        let length = self.unsigned()?;
+        match usize::try_from(length) {
+            Ok(length) if length <= self.bytes.len() - self.position => Ok(length),
+            _ => Err(self.malformed("length runs past the end")),
+        }
+    }
+
+    fn signed(&mut self) -> Result<i64, DecodeError> {
+        let value = self.unsigned()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn unsigned(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(self.malformed("integer is too long"))
+    }
+
+    fn float(&mut self) -> Result<f64, DecodeError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + 8)
+            .ok_or_else(|| self.malformed("unexpected end"))?;
+        self.position += 8;
+        Ok(f64::from_bits(u64::from_le_bytes(
+            bytes.try_into().unwrap(),
+        )))
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| self.malformed("unexpected end"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn malformed(&self, message: &str) -> DecodeError {
+        DecodeError::Malformed(format!(
+            "{} at byte {}",
+            message,
+            HEADER_LEN + self.position
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_source;
+
+    const SOURCE: &str = "/// Bounds\nlet (lo, _, hi) = (0, x, (9223372036854775807,)); \
+                          { let y = -(lo + 2) * 3 / +y; {} } 2.5e-3 * 1e300;";
+
+    #[test]
+    fn test_round_trip() {
+        let mut program = parse_source(SOURCE).unwrap();
+        program
+            .statements
+            .push(Stmt::expression(Expr::suffixed(-10, "px".to_string())));
+        // Literal patterns only appear in trees built by hosts
+        program.statements.push(Stmt::destructure(
+            Pattern::tuple(vec![Pattern::Literal(i64::MIN)]),
+            Expr::Tuple(vec![Expr::Number(i64::MIN)]),
+        ));
+        let bytes = program.encode(SOURCE).unwrap();
+
+        assert!(bytes.starts_with(b"OXAST\x01\x00"));
+        assert_eq!(Program::decode(&bytes, SOURCE), Ok(program));
+    }
+
+    #[test]
+    fn test_long_chains_round_trip() {
+        let terms = vec!["1"; MAX_DEPTH + 44].join(" + ");
+        let source = format!("let total = {} * (x - 2 - 3);", terms);
+        let program = parse_source(&source).unwrap();
+        let bytes = program.encode(&source).unwrap();
+
+        assert_eq!(Program::decode(&bytes, &source), Ok(program));
+    }
+
+    #[test]
+    fn test_nesting_too_deep_to_decode_is_not_encoded() {
+        let mut expr = Expr::Number(1);
+        for _ in 0..MAX_DEPTH {
+            expr = Expr::grouping(expr);
+        }
+        let program = Program {
+            statements: vec![Stmt::expression(expr)],
+        };
+
+        assert_eq!(program.encode(""), Err(EncodeError::NestingTooDeep));
+    }
+
+    #[test]
+    fn test_stale_and_corrupt_encodings_are_rejected() {
+        let bytes = parse_source(SOURCE).unwrap().encode(SOURCE).unwrap();
+
+        assert_eq!(
+            Program::decode(&bytes, "let x = 1;"),
+            Err(DecodeError::SourceChanged)
+        );
+        assert_eq!(
+            Program::decode(&bytes[..bytes.len() - 1], SOURCE),
+            Err(DecodeError::ChecksumMismatch)
+        );
+        let mut corrupt = bytes.clone();
+        corrupt[HEADER_LEN + 3] ^= 1;
+        assert_eq!(
+            Program::decode(&corrupt, SOURCE),
+            Err(DecodeError::ChecksumMismatch)
+        );
+        let mut newer = bytes.clone();
+        newer[5] = 2;
+        assert_eq!(
+            Program::decode(&newer, SOURCE),
+            Err(DecodeError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            Program::decode(b"{\"statements\":[]}", SOURCE),
+            Err(DecodeError::NotAnEncodedProgram)
+        );
+    }
+
+    #[test]
+    fn test_malformed_contents_are_reported() {
+        // A valid header and checksum around contents that are not a tree
+        let encode = |contents: &[u8]| {
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+            bytes.extend_from_slice(&hash(b"").to_le_bytes());
+            bytes.extend_from_slice(contents);
+            let checksum = hash(&bytes);
+            bytes.extend_from_slice(&checksum.to_le_bytes());
+            bytes
+        };
+
+        assert_eq!(
+            Program::decode(&encode(&[1, 9]), "")
+                .unwrap_err()
+                .to_string(),
+            "malformed encoding: unknown statement tag 9 at byte 17"
+        );
+        assert!(matches!(
+            Program::decode(&encode(&[0x80, 0x80, 0x01]), ""),
+            Err(DecodeError::Malformed(message)) if message.starts_with("length runs past")
+        ));
+
+        let mut deep = vec![1, tag::EXPRESSION];
+        deep.extend([tag::GROUPING; MAX_DEPTH]);
+        assert!(matches!(
+            Program::decode(&encode(&deep), ""),
+            Err(DecodeError::Malformed(message)) if message.starts_with("nesting too deep")
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+//! Calculator-style syntax for embedders whose users type formulas the way
+//! they would on paper, where `2x` and `2(3 + 4)` are multiplications and
+//! `15%` is a percentage. It is opt-in: the standard grammar rejects all
+//! three, and every program it accepts parses the same way in calculator
+//! mode.
+
+use crate::error::Error;
+use crate::fold::apply_binary;
+use crate::interpreter::{Arithmetic, RuntimeError, Value};
+use crate::lexer::Lexer;
+use crate::parser::{BinaryOp, Parser, Program};
+
+/// The suffix of percent literals. Without fractional values, `15%` on its
+/// own evaluates to a quantity with this suffix; [`Calculator`] gives it its
+/// meaning in arithmetic.
+pub const PERCENT: &str = "%";
+
+/// How `a + b%` and `a - b%` are evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PercentAddition {
+    /// The percentage is of `a`, as on a pocket calculator: `200 + 10%` is
+    /// 220 and `200 - 10%` is 180
+    #[default]
+    OfLeftOperand,
+    /// Percentages only add to percentages: `200 + 10%` is a runtime error
+    /// and `10% + 5%` is 15%
+    Separate,
+}
+
+/// Calculator mode: implicit multiplication and percent literals, with the
+/// arithmetic percentages follow when a program runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Calculator {
+    percent_addition: PercentAddition,
+}
+
+impl Calculator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how percentages are added to and subtracted from numbers
+    pub fn percent_addition(mut self, rule: PercentAddition) -> Self {
+        self.percent_addition = rule;
+        self
+    }
+
+    /// Lexes strictly and parses `source` in calculator mode. Run the program
+    /// with [`Environment::run_with`](crate::interpreter::Environment::run_with)
+    /// and this calculator as its arithmetic.
+    pub fn parse(&self, source: &str) -> Result<Program, Error> {
+        let parse = || -> Result<Program, Error> {
+            let tokens = Lexer::new(source)
+                .with_implicit_multiplication()
+                .tokenize_strict()?;
+            Ok(Parser::from_spanned(tokens)
+                .with_implicit_multiplication()
+                .with_percent_literals()
+                .parse()?)
+        };
+        parse().map_err(|error| error.with_source(source))
+    }
+}
+
+/// Multiplying a number by a percentage, in either order, takes that
+/// percentage of it, and dividing by one divides by its fraction: `200 * 15%`
+/// is 30 and `30 / 15%` is 200. Results are truncated toward zero like
+/// integer division. Adding and subtracting follow the configured
+/// [`PercentAddition`]; everything else is left to the built-in rules.
+impl Arithmetic for Calculator {
+    fn binary(
+        &self,
+        operator: &BinaryOp,
+        left: &Value,
+        right: &Value,
+    ) -> Option<Result<Value, RuntimeError>> {
+        let percent = |value: &Value| match value {
+            Value::Quantity { value, suffix } if suffix == PERCENT => Some(*value),
+            _ => None,
+        };
+        let (number, rate) = match (left, operator, right) {
+            (Value::Int(number), _, rate) => (*number, percent(rate)?),
+            (rate, BinaryOp::Multiply, Value::Int(number)) => (*number, percent(rate)?),
+            _ => return None,
+        };
+        let of = |number: i64| {
+            number
+                .checked_mul(rate)
+                .map(|product| product / 100)
+                .ok_or(RuntimeError::Overflow)
+        };
+
+        let result = match operator {
+            BinaryOp::Multiply => of(number),
+            BinaryOp::Divide if rate == 0 => Err(RuntimeError::DivisionByZero),
+            BinaryOp::Divide => number
+                .checked_mul(100)
+                .and_then(|scaled| scaled.checked_div(rate))
+                .ok_or(RuntimeError::Overflow),
+            BinaryOp::Add | BinaryOp::Subtract => match self.percent_addition {
+                PercentAddition::OfLeftOperand => of(number).and_then(|part| {
+                    apply_binary(operator, number, part).ok_or(RuntimeError::Overflow)
+                }),
+                PercentAddition::Separate => return None,
+            },
+        };
+        Some(result.map(Value::Int))
+    }
+}
+
+/// Lexes strictly and parses `source`, reading adjacent factors as a
+/// multiplication as described at [`Parser::with_implicit_multiplication`]
+pub fn parse_with_implicit_multiplication(source: &str) -> Result<Program, Error> {
+    let parse = || -> Result<Program, Error> {
+        let tokens = Lexer::new(source)
+            .with_implicit_multiplication()
+            .tokenize_strict()?;
+        Ok(Parser::from_spanned(tokens)
+            .with_implicit_multiplication()
+            .parse()?)
+    };
+    parse().map_err(|error| error.with_source(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Environment;
+    use crate::parse_source;
+
+    // The implicit form must parse exactly like the explicit one
+    fn assert_parses_as(implicit: &str, explicit: &str) {
+        assert_eq!(
+            parse_with_implicit_multiplication(implicit).unwrap(),
+            parse_source(explicit).unwrap(),
+            "{}",
+            implicit
+        );
+    }
+
+    #[test]
+    fn test_adjacent_factors_multiply() {
+        assert_parses_as("2x;", "2 * x;");
+        assert_parses_as("2(3 + 4);", "2 * (3 + 4);");
+        assert_parses_as("(a + b)(a - b);", "(a + b) * (a - b);");
+        assert_parses_as("3 (x)(y);", "3 * (x) * (y);");
+        assert_parses_as("let area = 2(w + h)x;", "let area = 2 * (w + h) * x;");
+    }
+
+    // The parsed form, with every operation parenthesized
+    fn parenthesized(source: &str) -> String {
+        parse_with_implicit_multiplication(source)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_implicit_products_bind_tighter_than_operators() {
+        assert_eq!(parenthesized("1 / 2x;"), "(1 / (2 * x));\n");
+        assert_eq!(parenthesized("6 / 2(1 + 2);"), "(6 / (2 * (1 + 2)));\n");
+        assert_eq!(parenthesized("2x * 3y;"), "((2 * x) * (3 * y));\n");
+        assert_eq!(parenthesized("-2x;"), "(-(2 * x));\n");
+        assert_parses_as("1 + 2x - 3;", "1 + 2 * x - 3;");
+        assert_parses_as("2-x;", "2 - x;");
+    }
+
+    #[test]
+    fn test_names_are_never_the_left_factor() {
+        for source in ["x y;", "x(2);", "2x(3);", "2x y;", "2 3;"] {
+            assert!(
+                parse_with_implicit_multiplication(source).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    fn calculate(calculator: Calculator, source: &str) -> Result<Option<Value>, RuntimeError> {
+        let program = calculator.parse(source).unwrap();
+        Environment::new().run_with(&program, &calculator)
+    }
+
+    #[test]
+    fn test_percent_literals() {
+        let program = Calculator::new().parse("let tip = 15%; 2(10%);").unwrap();
+        assert_eq!(program.to_string(), "let tip = 15%;\n(2 * 10%);\n");
+
+        // `%` must touch its number, and is not an operator
+        assert!(Calculator::new().parse("15 %;").is_err());
+        assert!(Calculator::new().parse("x%;").is_err());
+        assert!(parse_with_implicit_multiplication("15%;").is_err());
+    }
+
+    #[test]
+    fn test_percent_arithmetic() {
+        let calculator = Calculator::new();
+        let value = |source| calculate(calculator, source).unwrap().unwrap().to_string();
+
+        assert_eq!(value("15%;"), "15%");
+        assert_eq!(value("200 * 15%;"), "30");
+        assert_eq!(value("15% * 200;"), "30");
+        assert_eq!(value("30 / 15%;"), "200");
+        assert_eq!(value("200 + 10%;"), "220");
+        assert_eq!(value("let price = 80; price - 25%;"), "60");
+        assert_eq!(value("10% + 5%;"), "15%");
+        assert_eq!(value("10% / 2;"), "5%");
+        assert_eq!(
+            calculate(calculator, "1 / 0%;"),
+            Err(RuntimeError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_separate_percent_addition() {
+        let calculator = Calculator::new().percent_addition(PercentAddition::Separate);
+
+        assert_eq!(
+            calculate(calculator, "200 + 10%;").unwrap_err().to_string(),
+            "`+` cannot be applied to `200` and `10%`"
+        );
+        assert_eq!(
+            calculate(calculator, "10% + 5% + 200 * 3%;"),
+            Err(RuntimeError::Unsupported {
+                operator: "+".to_string(),
+                operands: vec![
+                    Value::Quantity {
+                        value: 15,
+                        suffix: PERCENT.to_string()
+                    },
+                    Value::Int(6)
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_standard_programs_parse_the_same() {
+        let sources = [
+            "let x = 5 + 3 * 2; let y = -x;",
+            "let (a, _) = ((1, 2), 3); { a / 2 - -(4, 5); }",
+            "let pair = (1,); (x) + (y); ();",
+        ];
+        for source in sources {
+            assert_parses_as(source, source);
+            assert_eq!(
+                Calculator::new().parse(source).unwrap(),
+                parse_source(source).unwrap()
+            );
+        }
+        assert!(parse_source("2x;").is_err());
+        assert!(parse_source("2(3);").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{Parser, Program};
+use std::fmt;
+
+/// A malformed `@if` section
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfgError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for CfgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid conditional section at {}: {}",
+            self.span, self.message
+        )
+    }
+}
+
+impl std::error::Error for CfgError {}
+
+impl From<&CfgError> for Diagnostic {
+    fn from(error: &CfgError) -> Self {
+        Diagnostic::new(Some(error.span), error.to_string())
+    }
+}
+
+/// Resolves the conditional sections in a token stream against the flags an
+/// embedder has set.
+///
+/// `@if FLAG { ... }` keeps the statements between the braces when `FLAG` is
+/// one of `flags` and drops them otherwise; an `else { ... }` after it is kept
+/// in the opposite case. Kept statements are spliced in without their braces,
+/// so the names they bind stay visible after the section. Sections may nest.
+pub fn resolve(tokens: &[(Token, Span)], flags: &[&str]) -> Result<Vec<(Token, Span)>, CfgError> {
+    let mut resolved = Vec::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        if tokens[i].0 != Token::At {
+            resolved.push(tokens[i].clone());
+            i += 1;
+            continue;
+        }
+
+        let invalid = |message: &str, index: usize| CfgError {
+            message: message.to_string(),
+            span: span_at(tokens, index),
+        };
+        if !is_ident(tokens, i + 1, "if") {
+            return Err(invalid("expected 'if' after '@'", i + 1));
+        }
+        let Some((Token::Ident(flag), _)) = tokens.get(i + 2) else {
+            return Err(invalid("expected a flag name after '@if'", i + 2));
+        };
+        let enabled = flags.contains(&flag.as_str());
+
+        let (then, next) = section(tokens, i + 3)
+            .ok_or_else(|| invalid("expected '{ ... }' after the flag", i + 3))?;
+        i = next;
+        let mut otherwise = None;
+        if is_ident(tokens, i, "else") {
+            let (body, next) = section(tokens, i + 1)
+                .ok_or_else(|| invalid("expected '{ ... }' after 'else'", i + 1))?;
+            otherwise = Some(body);
+            i = next;
+        }
+
+        let kept = if enabled { Some(then) } else { otherwise };
+        if let Some(body) = kept {
+            resolved.extend(resolve(body, flags)?);
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Lexes `source`, resolves its conditional sections and parses the result
+pub fn parse_with_flags(source: &str, flags: &[&str]) -> Result<Program, Vec<Diagnostic>> {
+    let tokens = Lexer::new(source)
+        .tokenize_strict()
+        .map_err(|error| vec![Diagnostic::from(&error)])?;
+    let tokens = resolve(&tokens, flags).map_err(|error| vec![Diagnostic::from(&error)])?;
+
+    Parser::from_spanned(tokens)
+        .parse()
+        .map_err(|errors| errors.errors.iter().map(Diagnostic::from).collect())
+}
+
+fn is_ident(tokens: &[(Token, Span)], index: usize, name: &str) -> bool {
+    matches!(tokens.get(index), Some((Token::Ident(ident), _)) if ident == name)
+}
+
+fn span_at(tokens: &[(Token, Span)], index: usize) -> Span {
+    tokens
+        .get(index)
+        .or(tokens.last())
+        .map(|(_, span)| *span)
+        .unwrap_or_default()
+}
+
+// The tokens inside the braces opened at `open`, and the index after the
+// closing brace
+fn section(tokens: &[(Token, Span)], open: usize) -> Option<(&[(Token, Span)], usize)> {
+    if tokens.get(open).map(|(token, _)| token) != Some(&Token::LeftBrace) {
+        return None;
+    }
+
+    let mut depth = 0;
+    for (index, (token, _)) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::LeftBrace => depth += 1,
+            Token::RightBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&tokens[open + 1..index], index + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "@if web { let scale = 2; } else { let scale = 1; } \
+                          @if debug { @if web { scale; } } scale;";
+
+    fn resolved(flags: &[&str]) -> String {
+        parse_with_flags(SOURCE, flags).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_sections_follow_the_flags() {
+        assert_eq!(resolved(&[]), "let scale = 1;\nscale;\n");
+        assert_eq!(resolved(&["web"]), "let scale = 2;\nscale;\n");
+        assert_eq!(
+            resolved(&["web", "debug"]),
+            "let scale = 2;\nscale;\nscale;\n"
+        );
+    }
+
+    #[test]
+    fn test_malformed_sections() {
+        let message = |source: &str| {
+            parse_with_flags(source, &[]).unwrap_err()[0]
+                .message
+                .clone()
+        };
+
+        assert_eq!(
+            message("@when web { 1; }"),
+            "Invalid conditional section at 1..5: expected 'if' after '@'"
+        );
+        assert!(message("@if web 1;").contains("expected '{ ... }' after the flag"));
+        assert!(message("@if web { 1; } else { 2;").contains("after 'else'"));
+    }
+}
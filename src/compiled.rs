@@ -0,0 +1,303 @@
+//! Expressions compiled for evaluating many times over with different inputs.
+//!
+//! An [`Environment`](crate::interpreter::Environment) finds a variable by
+//! searching its bindings by name, and evaluation walks the syntax tree. A
+//! [`CompiledExpr`] resolves each variable to a slot once, so inputs are
+//! passed as a slice indexed by slot, and runs as a flat list of stack
+//! instructions with its constant parts already evaluated. Results and errors
+//! are the ones the interpreter gives.
+
+use crate::analysis::free_variables;
+use crate::fold::apply_binary;
+use crate::interpreter::{RuntimeError, Value, big_number, binary, unary};
+use crate::parser::{BinaryOp, Expr, UnaryOp};
+
+/// An expression compiled for repeated evaluation
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledExpr {
+    code: Vec<Instruction>,
+    slots: Vec<String>,
+    /// The most values on the stack at once
+    depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Instruction {
+    Push(Value),
+    Load(usize),
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+    /// Replaces the given number of values with a tuple of them
+    Tuple(usize),
+    /// Fails with an error known when compiling, such as for a literal this
+    /// build cannot hold
+    Fail(RuntimeError),
+}
+
+impl CompiledExpr {
+    /// Compiles `expr` with a slot for each of its variables, in alphabetical
+    /// order
+    pub fn new(expr: &Expr) -> Self {
+        let slots: Vec<String> = free_variables(expr).into_iter().collect();
+        Self::compile(expr, slots)
+    }
+
+    /// Compiles `expr` with slots for `names` in the order given, so hosts
+    /// can pass inputs in an order of their choosing. Names the expression
+    /// does not use still take a slot. Fails with [`RuntimeError::Unbound`]
+    /// for a variable of `expr` that is not among `names`.
+    pub fn with_slots(expr: &Expr, names: &[&str]) -> Result<Self, RuntimeError> {
+        if let Some(name) = free_variables(expr)
+            .into_iter()
+            .find(|name| !names.contains(&name.as_str()))
+        {
+            return Err(RuntimeError::Unbound(name));
+        }
+        let slots = names.iter().map(|name| name.to_string()).collect();
+        Ok(Self::compile(expr, slots))
+    }
+
+    fn compile(expr: &Expr, slots: Vec<String>) -> Self {
+        let mut compiled = Self {
+            code: Vec::new(),
+            slots,
+            depth: 0,
+        };
+        compiled.emit(expr);
+        compiled.depth = stack_depth(&compiled.code);
+        compiled
+    }
+
+    /// The variable each slot holds, by slot
+    pub fn slots(&self) -> &[String] {
+        &self.slots
+    }
+
+    /// The slot holding the variable `name`, if it has one
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot == name)
+    }
+
+    /// Evaluates the expression with `inputs[slot]` as the value of each
+    /// slot's variable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer inputs than slots.
+    pub fn evaluate(&self, inputs: &[Value]) -> Result<Value, RuntimeError> {
+        assert!(
+            inputs.len() >= self.slots.len(),
+            "{} inputs for {} slots",
+            inputs.len(),
+            self.slots.len()
+        );
+        let mut stack: Vec<Value> = Vec::with_capacity(self.depth);
+        for instruction in &self.code {
+            match instruction {
+                Instruction::Push(value) => stack.push(value.clone()),
+                Instruction::Load(slot) => stack.push(inputs[*slot].clone()),
+                Instruction::Unary(operator) => {
+                    let operand = stack.pop().expect("an operand");
+                    stack.push(unary(operator, operand)?);
+                }
+                Instruction::Binary(operator) => {
+                    let right = stack.pop().expect("a right operand");
+                    let left = stack.last_mut().expect("a left operand");
+                    // Integers that do not overflow are the common case;
+                    // anything else goes by the interpreter's rules
+                    let integer = match (&*left, &right) {
+                        (Value::Int(a), Value::Int(b)) => apply_binary(operator, *a, *b),
+                        _ => None,
+                    };
+                    *left = match integer {
+                        Some(result) => Value::Int(result),
+                        None => binary(operator, std::mem::replace(left, Value::Int(0)), right)?,
+                    };
+                }
+                Instruction::Tuple(size) => {
+                    let elements = stack.split_off(stack.len() - size);
+                    stack.push(Value::Tuple(elements));
+                }
+                Instruction::Fail(error) => return Err(error.clone()),
+            }
+        }
+        Ok(stack.pop().expect("a result"))
+    }
+
+    // Appends the instructions for `expr`, evaluating operations on
+    // constants now unless they fail, which is left for evaluation to report
+    fn emit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(value) => self.code.push(Instruction::Push(Value::Int(*value))),
+            Expr::Float(value) => self.code.push(Instruction::Push(Value::Float(*value))),
+            Expr::BigNumber(digits) => self.code.push(match big_number(digits) {
+                Ok(value) => Instruction::Push(value),
+                Err(error) => Instruction::Fail(error),
+            }),
+            Expr::Suffixed { value, suffix } => {
+                self.code.push(Instruction::Push(Value::Quantity {
+                    value: *value,
+                    suffix: suffix.clone(),
+                }))
+            }
+            Expr::Identifier(name) => {
+                let slot = self.slot(name).expect("every variable has a slot");
+                self.code.push(Instruction::Load(slot));
+            }
+            Expr::Grouping(inner) => self.emit(inner),
+            Expr::Unary { operator, operand } => {
+                self.emit(operand);
+                let constant = match self.code.last() {
+                    Some(Instruction::Push(value)) => unary(operator, value.clone()).ok(),
+                    _ => None,
+                };
+                match constant {
+                    Some(value) => *self.code.last_mut().unwrap() = Instruction::Push(value),
+                    None => self.code.push(Instruction::Unary(operator.clone())),
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                self.emit(left);
+                self.emit(right);
+                let constant = match &self.code[self.code.len() - 2..] {
+                    [Instruction::Push(left), Instruction::Push(right)] => {
+                        binary(operator, left.clone(), right.clone()).ok()
+                    }
+                    _ => None,
+                };
+                match constant {
+                    Some(value) => {
+                        self.code.truncate(self.code.len() - 2);
+                        self.code.push(Instruction::Push(value));
+                    }
+                    None => self.code.push(Instruction::Binary(operator.clone())),
+                }
+            }
+            Expr::Tuple(elements) => {
+                for element in elements {
+                    self.emit(element);
+                }
+                let start = self.code.len() - elements.len();
+                let constants: Option<Vec<Value>> = self.code[start..]
+                    .iter()
+                    .map(|instruction| match instruction {
+                        Instruction::Push(value) => Some(value.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                match constants {
+                    Some(values) => {
+                        self.code.truncate(start);
+                        self.code.push(Instruction::Push(Value::Tuple(values)));
+                    }
+                    None => self.code.push(Instruction::Tuple(elements.len())),
+                }
+            }
+        }
+    }
+}
+
+fn stack_depth(code: &[Instruction]) -> usize {
+    let mut depth: usize = 0;
+    let mut deepest = 0;
+    for instruction in code {
+        depth = match instruction {
+            Instruction::Push(_) | Instruction::Load(_) | Instruction::Fail(_) => depth + 1,
+            Instruction::Unary(_) => depth,
+            Instruction::Binary(_) => depth - 1,
+            Instruction::Tuple(size) => depth - size + 1,
+        };
+        deepest = deepest.max(depth);
+    }
+    deepest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Environment;
+    use crate::testing::expression;
+
+    #[test]
+    fn test_agrees_with_the_interpreter() {
+        let inputs = [
+            [Value::Int(3), Value::Int(-4)],
+            [Value::Int(i64::MAX), Value::Int(0)],
+            [Value::Float(0.5), Value::Int(2)],
+            [
+                Value::Tuple(vec![]),
+                Value::Quantity {
+                    value: 2,
+                    suffix: "px".to_string(),
+                },
+            ],
+        ];
+        for source in [
+            "(x + 1) * y - x / 2",
+            "-x * +(y, 10px / 5px)",
+            "y / x + x / y",
+            "x * 2 + 1.5 * 2",
+            "(x, (1, 2 * 3), -(4))",
+            "1 / 0 + x",
+        ] {
+            let expr = expression(source);
+            let compiled = CompiledExpr::with_slots(&expr, &["x", "y"]).unwrap();
+            for [x, y] in &inputs {
+                let mut env = Environment::new();
+                env.define("x", x.clone());
+                env.define("y", y.clone());
+                assert_eq!(
+                    compiled.evaluate(&[x.clone(), y.clone()]),
+                    env.evaluate(&expr),
+                    "{} with x = {}, y = {}",
+                    source,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_constants_are_evaluated_ahead() {
+        let compiled = CompiledExpr::new(&expression("(1 + 2) * x - (3, 4 * -5)"));
+
+        assert_eq!(compiled.slots(), ["x"]);
+        assert_eq!(
+            compiled.code,
+            [
+                Instruction::Push(Value::Int(3)),
+                Instruction::Load(0),
+                Instruction::Binary(BinaryOp::Multiply),
+                Instruction::Push(Value::Tuple(vec![Value::Int(3), Value::Int(-20)])),
+                Instruction::Binary(BinaryOp::Subtract),
+            ]
+        );
+        assert_eq!(compiled.depth, 2);
+        // Failing operations stay for evaluation to report
+        let failing = CompiledExpr::new(&expression("9223372036854775807 + 1"));
+        assert_eq!(failing.evaluate(&[]), Err(RuntimeError::Overflow));
+        assert_eq!(failing.code.len(), 3);
+    }
+
+    #[test]
+    fn test_slots_in_a_chosen_order() {
+        let expr = expression("rate * hours");
+        let compiled = CompiledExpr::with_slots(&expr, &["hours", "bonus", "rate"]).unwrap();
+
+        assert_eq!(compiled.slot("rate"), Some(2));
+        assert_eq!(compiled.slot("wage"), None);
+        assert_eq!(
+            compiled.evaluate(&[Value::Int(40), Value::Int(0), Value::Int(25)]),
+            Ok(Value::Int(1000))
+        );
+        assert_eq!(
+            CompiledExpr::with_slots(&expr, &["rate"]),
+            Err(RuntimeError::Unbound("hours".to_string()))
+        );
+    }
+}
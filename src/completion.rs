@@ -0,0 +1,180 @@
+use crate::lexer::{KEYWORDS, Lexer, Token};
+use crate::parser::Program;
+
+/// Returns the keywords and top-level `let` bindings of a program that start
+/// with `prefix`, sorted and without duplicates.
+pub fn complete(prefix: &str, program: &Program) -> Vec<String> {
+    let mut candidates: Vec<String> = KEYWORDS
+        .iter()
+        .map(|keyword| keyword.to_string())
+        .chain(
+            program
+                .statements
+                .iter()
+                .flat_map(|stmt| stmt.bound_names().into_iter().map(String::from)),
+        )
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Returns the keywords and the bindings in scope at byte `offset` of `source`
+/// that start with the word being typed there. Only the text before the cursor
+/// is looked at, and it is lexed rather than parsed, so this works in code that
+/// is incomplete or has syntax errors. A binding comes into scope after the
+/// `;` ending its `let` and goes out of scope at the `}` closing its block.
+/// Nothing is offered where a new binding is being named. An offset past the
+/// end of `source` or inside a character is moved back to the nearest
+/// character boundary.
+pub fn complete_at(source: &str, offset: usize) -> Vec<String> {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &source[..offset];
+    let prefix = word_before_cursor(before);
+
+    // Names bound in each enclosing block, innermost last
+    let mut scopes: Vec<Vec<String>> = vec![Vec::new()];
+    // Names bound by the `let` statement being scanned, with the depth it belongs to
+    let mut pending: Option<(Vec<String>, usize)> = None;
+    // Whether the scan is between a `let` and its `=`, where names are declared
+    let mut naming = false;
+
+    let tokens = Lexer::new(&before[..before.len() - prefix.len()]).tokenize();
+    for token in tokens {
+        match &token {
+            Token::Let => {
+                naming = true;
+                pending = Some((Vec::new(), scopes.len()));
+            }
+            Token::Ident(name) if naming && name != "_" => {
+                if let Some((names, _)) = &mut pending {
+                    names.push(name.clone());
+                }
+            }
+            Token::Equals => naming = false,
+            Token::LeftBrace => scopes.push(Vec::new()),
+            Token::RightBrace if scopes.len() > 1 => {
+                scopes.pop();
+            }
+            Token::Semicolon => {
+                if let Some((names, depth)) = pending.take_if(|(_, depth)| *depth == scopes.len()) {
+                    scopes[depth - 1].extend(names);
+                }
+                naming = false;
+            }
+            _ => {}
+        }
+    }
+
+    if naming {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<String> = KEYWORDS
+        .iter()
+        .map(|keyword| keyword.to_string())
+        .chain(scopes.into_iter().flatten())
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// Returns the identifier-like word at the end of `line`, which is the text a
+/// completion request applies to.
+pub fn word_before_cursor(line: &str) -> &str {
+    let start = line
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| ch.is_alphanumeric() || *ch == '_')
+        .last()
+        .map_or(line.len(), |(index, _)| index);
+    &line[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    #[test]
+    fn test_completes_keywords_and_bindings() {
+        let program = parse_source("let length = 1; let level = 2; let width = 3;").unwrap();
+
+        assert_eq!(complete("le", &program), vec!["length", "let", "level"]);
+        assert_eq!(complete("w", &program), vec!["width"]);
+        assert!(complete("z", &program).is_empty());
+    }
+
+    #[test]
+    fn test_block_bindings_are_out_of_scope() {
+        let program = parse_source("let a = 1; { let ab = 2; } let a = 3;").unwrap();
+
+        assert_eq!(complete("a", &program), vec!["a"]);
+    }
+
+    #[test]
+    fn test_word_before_cursor() {
+        assert_eq!(word_before_cursor("let y = wid"), "wid");
+        assert_eq!(word_before_cursor("1 + "), "");
+        assert_eq!(word_before_cursor("foo_bar2"), "foo_bar2");
+    }
+
+    fn complete_at_marker(source: &str) -> Vec<String> {
+        let offset = source.find('|').unwrap();
+        complete_at(&source.replace('|', ""), offset)
+    }
+
+    #[test]
+    fn test_complete_at_uses_scopes_at_cursor() {
+        let source = "let alpha = 1; { let apple = 2; { a| } } let avocado = 3;";
+
+        assert_eq!(complete_at_marker(source), vec!["alpha", "apple"]);
+        assert_eq!(
+            complete_at_marker("let alpha = 1; { let apple = 2; } a|"),
+            vec!["alpha"]
+        );
+    }
+
+    #[test]
+    fn test_complete_at_in_incomplete_code() {
+        assert_eq!(
+            complete_at_marker("let total = 1; let x = (to| + ;"),
+            vec!["total"]
+        );
+        // A binding is not in scope inside its own initializer
+        assert_eq!(complete_at_marker("let limit = li|"), Vec::<String>::new());
+        assert_eq!(complete_at_marker("let a = 1; l|"), vec!["let"]);
+    }
+
+    #[test]
+    fn test_complete_at_offers_nothing_for_new_names() {
+        assert!(complete_at_marker("let alpha = 1; let a|").is_empty());
+        assert!(complete_at_marker("let alpha = 1; let (beta, a|").is_empty());
+    }
+
+    #[test]
+    fn test_complete_at_sees_destructured_names() {
+        assert_eq!(
+            complete_at_marker("let (left, right) = (1, 2); l|"),
+            vec!["left", "let"]
+        );
+    }
+
+    #[test]
+    fn test_complete_at_clamps_offsets() {
+        let source = "let größe = 1; let grün = 2; grö";
+
+        assert_eq!(complete_at(source, source.len()), vec!["größe"]);
+        // Inside the two-byte `ö`, completing the `gr` before it
+        assert_eq!(complete_at(source, source.len() - 1), vec!["größe", "grün"]);
+        assert_eq!(complete_at(source, source.len() + 10), vec!["größe"]);
+    }
+}
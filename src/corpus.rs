@@ -0,0 +1,176 @@
+//! Replays a corpus of inputs, such as the crashes and slow cases a fuzzer
+//! found, through the lexer and parser. Each input's diagnostics are recorded
+//! next to it, so a corpus directory is a permanent regression suite: inputs
+//! must not panic, and their diagnostics must not change unnoticed.
+
+use crate::parse_source;
+use crate::workspace::check_source;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+/// Extension of the file recording an input's expected diagnostics, added to
+/// the input's own name
+pub const EXPECTED_EXTENSION: &str = "expected";
+
+/// Why a corpus input failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorpusFailure {
+    /// Lexing or parsing panicked, with the panic's message
+    Panicked(String),
+    /// No diagnostics are recorded for the input yet
+    Unrecorded,
+    /// The diagnostics differ from the recorded ones
+    Changed { expected: String, actual: String },
+}
+
+impl fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorpusFailure::Panicked(message) => write!(f, "panicked: {}", message),
+            CorpusFailure::Unrecorded => write!(f, "no recorded diagnostics; record them first"),
+            CorpusFailure::Changed { expected, actual } => write!(
+                f,
+                "diagnostics changed\n--- expected\n{}--- actual\n{}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+/// The result of replaying a corpus
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CorpusReport {
+    /// Number of inputs replayed
+    pub inputs: usize,
+    /// Number of inputs whose diagnostics were written
+    pub recorded: usize,
+    pub failures: Vec<(PathBuf, CorpusFailure)>,
+}
+
+/// Replays every file directly inside `dir`, except hidden files and
+/// recorded diagnostics, in sorted order. Inputs need not be valid UTF-8.
+///
+/// With `record`, the diagnostics of every input that does not panic are
+/// written to its `.expected` file instead of being compared with it. Panics
+/// are always failures.
+pub fn run_corpus(dir: &Path, record: bool) -> io::Result<CorpusReport> {
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let expected = path
+            .extension()
+            .is_some_and(|ext| ext == EXPECTED_EXTENSION);
+        if entry.file_type()?.is_file() && !hidden && !expected {
+            inputs.push(path);
+        }
+    }
+    inputs.sort();
+
+    let mut report = CorpusReport::default();
+    for input in inputs {
+        report.inputs += 1;
+        let bytes = fs::read(&input)?;
+        let name = input.file_name().unwrap_or_default().to_string_lossy();
+        let actual = match replay(&name, &String::from_utf8_lossy(&bytes)) {
+            Ok(actual) => actual,
+            Err(message) => {
+                report
+                    .failures
+                    .push((input, CorpusFailure::Panicked(message)));
+                continue;
+            }
+        };
+
+        let expected_path = expected_path(&input);
+        if record {
+            fs::write(&expected_path, &actual)?;
+            report.recorded += 1;
+            continue;
+        }
+        let failure = match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => continue,
+            Ok(expected) => CorpusFailure::Changed { expected, actual },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => CorpusFailure::Unrecorded,
+            Err(error) => return Err(error),
+        };
+        report.failures.push((input, failure));
+    }
+    Ok(report)
+}
+
+/// Where the expected diagnostics of `input` are recorded
+pub fn expected_path(input: &Path) -> PathBuf {
+    let mut name = input.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(EXPECTED_EXTENSION);
+    input.with_file_name(name)
+}
+
+// The diagnostics of an input, one per line and attributed to its file name
+// alone so they do not depend on where the corpus is. A panic gives its
+// message instead.
+fn replay(name: &str, source: &str) -> Result<String, String> {
+    panic::catch_unwind(|| {
+        // The recovering parser takes other paths than the strict check
+        let _ = parse_source(source);
+        check_source(Path::new(name), source)
+            .iter()
+            .map(|diagnostic| format!("{}\n", diagnostic))
+            .collect()
+    })
+    .map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_then_replay() {
+        let dir = std::env::temp_dir().join(format!("oxide-corpus-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("crash-1"), b"let x = (1, ;\xff").unwrap();
+        fs::write(dir.join("ok.ox"), "let x = 1;").unwrap();
+        fs::write(dir.join(".gitignore"), "*").unwrap();
+
+        let unrecorded = run_corpus(&dir, false).unwrap();
+        assert_eq!(unrecorded.inputs, 2);
+        assert_eq!(
+            unrecorded.failures,
+            vec![
+                (dir.join("crash-1"), CorpusFailure::Unrecorded),
+                (dir.join("ok.ox"), CorpusFailure::Unrecorded),
+            ]
+        );
+
+        assert_eq!(run_corpus(&dir, true).unwrap().recorded, 2);
+        assert_eq!(fs::read_to_string(dir.join("ok.ox.expected")).unwrap(), "");
+        assert!(
+            fs::read_to_string(dir.join("crash-1.expected"))
+                .unwrap()
+                .starts_with("crash-1:1:")
+        );
+        assert!(run_corpus(&dir, false).unwrap().failures.is_empty());
+
+        fs::write(dir.join("ok.ox"), "let x = ;").unwrap();
+        let changed = run_corpus(&dir, false).unwrap();
+        assert!(matches!(
+            &changed.failures[..],
+            [(path, CorpusFailure::Changed { expected, .. })] if *path == dir.join("ok.ox") && expected.is_empty()
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::lexer::{LexError, Span};
+use crate::parser::ParseError;
+use std::fmt;
+
+/// A problem in source code, independent of the stage that found it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Byte range the problem refers to, when known
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Option<Span>, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Messages of errors that carry a span, such as lex errors, name it already
+        match self.span {
+            Some(span) if !self.message.contains(&span.to_string()) => {
+                write!(f, "{} (at {})", self.message, span)
+            }
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        Diagnostic::new(error.span(), error.to_string())
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::new(error.span(), error.to_string())
+    }
+}
@@ -0,0 +1,59 @@
+use crate::parser::{Program, Stmt};
+
+/// Renders the documented top-level bindings of a program as Markdown.
+/// Undocumented bindings and statements nested in blocks are skipped.
+pub fn to_markdown(program: &Program, title: &str) -> String {
+    let mut out = format!("# {}\n", title);
+
+    for stmt in &program.statements {
+        if let Some(doc) = stmt.doc() {
+            let names: Vec<String> = stmt
+                .bound_names()
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect();
+            out.push_str(&format!("\n## {}\n\n", names.join(", ")));
+            out.push_str(doc);
+            out.push_str("\n\n```oxide\n");
+            out.push_str(&format!("{}\n", undocumented(stmt)));
+            out.push_str("```\n");
+        }
+    }
+
+    out
+}
+
+// The statement as written, without its doc comment lines
+fn undocumented(stmt: &Stmt) -> Stmt {
+    match stmt.clone() {
+        Stmt::Let { name, value, .. } => Stmt::let_statement(name, value),
+        Stmt::Destructure { pattern, value, .. } => Stmt::destructure(pattern, value),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    #[test]
+    fn test_documented_bindings() {
+        let source = "/// Width in pixels.\nlet width = 640;\nlet hidden = 1;\n/// Total area.\nlet area = width * 480;";
+        let program = parse_source(source).unwrap();
+
+        assert_eq!(
+            to_markdown(&program, "screen.ox"),
+            "# screen.ox\n\
+             \n## `width`\n\nWidth in pixels.\n\n```oxide\nlet width = 640;\n```\n\
+             \n## `area`\n\nTotal area.\n\n```oxide\nlet area = (width * 480);\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_no_documented_bindings() {
+        let program = parse_source("let x = 1; { /// Local.\n let y = 2; }").unwrap();
+
+        assert_eq!(to_markdown(&program, "empty"), "# empty\n");
+    }
+}
@@ -0,0 +1,49 @@
+use crate::lexer::Span;
+
+/// A replacement of one span of source text, as produced by the formatter and
+/// refactorings. Spans are byte offsets into the original text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn new(span: Span, new_text: impl Into<String>) -> Self {
+        Self {
+            span,
+            new_text: new_text.into(),
+        }
+    }
+}
+
+/// Applies non-overlapping edits to `source`. Edits may be given in any order.
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| edit.span.start);
+
+    let mut out = String::with_capacity(source.len());
+    let mut last_end = 0;
+    for edit in sorted {
+        out.push_str(&source[last_end..edit.span.start]);
+        out.push_str(&edit.new_text);
+        last_end = edit.span.end;
+    }
+    out.push_str(&source[last_end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_edits_in_any_order() {
+        let edits = [
+            TextEdit::new(Span::new(8, 9), "42"),
+            TextEdit::new(Span::new(4, 5), "answer"),
+        ];
+
+        assert_eq!(apply_edits("let x = 1;", &edits), "let answer = 42;");
+    }
+}
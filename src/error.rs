@@ -0,0 +1,244 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{LexError, Span};
+use crate::limits::LimitErrors;
+use crate::parser::ParseErrors;
+use std::fmt;
+
+/// Any error from compiling Oxide source, by the stage that found it.
+/// Errors returned by [`crate::compile`] carry the source text their spans
+/// refer to; errors converted with `From` have none until
+/// [`Error::with_source`] attaches it. New stages add variants, so matches
+/// on this enum need a wildcard arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    Lex {
+        error: LexError,
+        source_code: Option<String>,
+    },
+    Parse {
+        errors: ParseErrors,
+        source_code: Option<String>,
+    },
+    Limits {
+        errors: LimitErrors,
+        source_code: Option<String>,
+    },
+}
+
+impl Error {
+    /// Attaches the source text the error's spans refer to
+    pub fn with_source(mut self, source: &str) -> Self {
+        match &mut self {
+            Error::Lex { source_code, .. }
+            | Error::Parse { source_code, .. }
+            | Error::Limits { source_code, .. } => *source_code = Some(source.to_string()),
+        }
+        self
+    }
+
+    pub fn source_code(&self) -> Option<&str> {
+        match self {
+            Error::Lex { source_code, .. }
+            | Error::Parse { source_code, .. }
+            | Error::Limits { source_code, .. } => source_code.as_deref(),
+        }
+    }
+
+    /// Returns one stage-independent diagnostic per problem
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            Error::Lex { error, .. } => vec![Diagnostic::from(error)],
+            Error::Parse { errors, .. } => errors.errors.iter().map(Diagnostic::from).collect(),
+            Error::Limits { errors, .. } => errors
+                .errors
+                .iter()
+                .map(|error| Diagnostic::new(None, error.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Returns the spans of every problem that has one
+    pub fn spans(&self) -> Vec<Span> {
+        self.diagnostics()
+            .iter()
+            .filter_map(|diagnostic| diagnostic.span)
+            .collect()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Lex { error, .. } => write!(f, "{}", error),
+            Error::Parse { errors, .. } => write!(f, "{}", errors),
+            Error::Limits { errors, .. } => write!(f, "{}", errors),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Lex { error, .. } => Some(error),
+            Error::Parse { errors, .. } => Some(errors),
+            Error::Limits { errors, .. } => Some(errors),
+        }
+    }
+}
+
+/// Reports for applications using miette: one label per problem, plus help
+/// for the errors that have an obvious fix
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let code = match self {
+            Error::Lex { .. } => "oxide::lex",
+            Error::Parse { .. } => "oxide::parse",
+            Error::Limits { .. } => "oxide::limits",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = match self {
+            Error::Lex {
+                error: LexError::InvalidNumberSuffix { .. },
+                ..
+            } => "separate the number from the name, or register the suffix with `LiteralSuffixes`",
+            Error::Lex {
+                error: LexError::InvalidNumber { .. },
+                ..
+            } => "integer literals range from -9223372036854775808 to 9223372036854775807",
+            Error::Parse { errors, .. } => match errors.first()? {
+                crate::parser::ParseError::MissingSemicolon { .. } => "statements end with ';'",
+                crate::parser::ParseError::UnclosedDelimiter { .. } => {
+                    "close the delimiter opened here"
+                }
+                _ => return None,
+            },
+            Error::Limits { .. } => "simplify the program, or raise the limit it exceeds",
+            _ => return None,
+        };
+        Some(Box::new(help))
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        let source_code = match self {
+            Error::Lex { source_code, .. }
+            | Error::Parse { source_code, .. }
+            | Error::Limits { source_code, .. } => source_code.as_ref()?,
+        };
+        Some(source_code)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let label = |text: String, span: Span| {
+            miette::LabeledSpan::new(Some(text), span.start, span.end - span.start)
+        };
+        let labels: Vec<miette::LabeledSpan> = match self {
+            Error::Lex { error, .. } => {
+                let text = match error {
+                    LexError::InvalidNumber { .. } => "does not fit in 64 bits",
+                    LexError::InvalidNumberSuffix { .. } => "unknown suffix",
+                    _ => "not a valid token",
+                };
+                error
+                    .span()
+                    .map(|span| label(text.to_string(), span))
+                    .into_iter()
+                    .collect()
+            }
+            Error::Parse { errors, .. } => errors
+                .errors
+                .iter()
+                .flat_map(|error| {
+                    let primary = error.span().map(|span| label(error.to_string(), span));
+                    let related = error
+                        .related_span()
+                        .map(|span| label("opened here".to_string(), span));
+                    primary.into_iter().chain(related)
+                })
+                .collect(),
+            Error::Limits { .. } => Vec::new(),
+        };
+        Some(Box::new(labels.into_iter()))
+    }
+}
+
+impl From<LexError> for Error {
+    fn from(error: LexError) -> Self {
+        Error::Lex {
+            error,
+            source_code: None,
+        }
+    }
+}
+
+impl From<ParseErrors> for Error {
+    fn from(errors: ParseErrors) -> Self {
+        Error::Parse {
+            errors,
+            source_code: None,
+        }
+    }
+}
+
+impl From<LimitErrors> for Error {
+    fn from(errors: LimitErrors) -> Self {
+        Error::Limits {
+            errors,
+            source_code: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn test_compile_errors_carry_their_source() {
+        let source = "let x = 1 +; let = 2;";
+        let error = compile(source).unwrap_err();
+
+        assert!(matches!(error, Error::Parse { .. }));
+        assert_eq!(error.source_code(), Some(source));
+        assert_eq!(error.spans(), vec![Span::new(11, 12), Span::new(17, 18)]);
+    }
+
+    #[test]
+    fn test_from_conversions_have_no_source() {
+        let lex_error = LexError::IllegalCharacter {
+            ch: '$',
+            span: Span::new(0, 1),
+        };
+        let error = Error::from(lex_error.clone());
+
+        assert_eq!(error.source_code(), None);
+        assert_eq!(error.with_source("$").source_code(), Some("$"));
+        assert_eq!(
+            Error::from(lex_error.clone()).to_string(),
+            lex_error.to_string()
+        );
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn test_miette_reports() {
+        use miette::Diagnostic as _;
+
+        let error = compile("let x = (1 + 2").unwrap_err();
+        let labels: Vec<_> = error.labels().unwrap().collect();
+
+        assert_eq!(error.code().unwrap().to_string(), "oxide::parse");
+        assert_eq!(
+            error.help().unwrap().to_string(),
+            "close the delimiter opened here"
+        );
+        assert_eq!(labels[1].label(), Some("opened here"));
+        assert_eq!((labels[1].offset(), labels[1].len()), (8, 1));
+        assert!(miette::Diagnostic::source_code(&error).is_some());
+    }
+}
@@ -0,0 +1,350 @@
+use crate::parser::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+use crate::rewrite::Step;
+use std::fmt;
+
+/// Bounds on the work compile-time evaluation may do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstLimits {
+    /// Expression nodes one statement's value may visit
+    pub max_steps: usize,
+}
+
+impl Default for ConstLimits {
+    fn default() -> Self {
+        Self { max_steps: 10_000 }
+    }
+}
+
+/// Why a constant expression cannot be evaluated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstErrorKind {
+    Overflow,
+    DivisionByZero,
+    /// Evaluation would take more steps than the limit allows
+    StepLimit(usize),
+}
+
+/// A subexpression that would fail whenever it is evaluated, found at
+/// compile time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstError {
+    pub kind: ConstErrorKind,
+    /// Path from the evaluated expression to the subexpression that fails;
+    /// empty for the step limit, which applies to the whole expression
+    pub steps: Vec<Step>,
+}
+
+impl fmt::Display for ConstError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ConstErrorKind::Overflow => write!(f, "this arithmetic overflows a 64-bit integer"),
+            ConstErrorKind::DivisionByZero => write!(f, "this divides by zero"),
+            ConstErrorKind::StepLimit(limit) => write!(
+                f,
+                "this constant expression takes more than {} steps to evaluate",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConstError {}
+
+/// Folds constant subexpressions in every statement of a program
+pub fn fold_program(program: &mut Program) {
+    for stmt in &mut program.statements {
+        fold_statement(stmt);
+    }
+}
+
+fn fold_statement(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::Destructure { value, .. } => fold_in_place(value),
+        Stmt::Expression(expr) => fold_in_place(expr),
+        Stmt::Block(statements) => {
+            for stmt in statements {
+                fold_statement(stmt);
+            }
+        }
+    }
+}
+
+fn fold_in_place(expr: &mut Expr) {
+    let folded = fold_expression(std::mem::replace(expr, Expr::Number(0)));
+    *expr = folded;
+}
+
+/// Evaluates constant subexpressions and normalizes signs: unary plus is
+/// dropped and double negation cancels out. Operations whose result is not
+/// representable (overflow, division by zero) are left in place for the
+/// runtime to report.
+pub fn fold_expression(expr: Expr) -> Expr {
+    match expr {
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => expr,
+        Expr::Tuple(elements) => Expr::tuple(elements.into_iter().map(fold_expression).collect()),
+        Expr::Grouping(inner) => match fold_expression(*inner) {
+            Expr::Number(n) => Expr::Number(n),
+            folded => Expr::grouping(folded),
+        },
+        Expr::Unary {
+            operator: UnaryOp::Plus,
+            operand,
+        } => fold_expression(*operand),
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => match fold_expression(*operand) {
+            Expr::Number(n) => match n.checked_neg() {
+                Some(negated) => Expr::Number(negated),
+                None => Expr::unary(UnaryOp::Negate, Expr::Number(n)),
+            },
+            folded => match strip_grouping(folded) {
+                Expr::Unary {
+                    operator: UnaryOp::Negate,
+                    operand,
+                } => *operand,
+                other => Expr::unary(UnaryOp::Negate, other),
+            },
+        },
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let left = fold_expression(*left);
+            let right = fold_expression(*right);
+
+            match (&left, &right) {
+                (Expr::Number(a), Expr::Number(b)) => match apply_binary(&operator, *a, *b) {
+                    Some(value) => Expr::Number(value),
+                    None => Expr::binary(left, operator, right),
+                },
+                _ => Expr::binary(left, operator, right),
+            }
+        }
+    }
+}
+
+/// Evaluates an expression at compile time as far as its constant parts go.
+/// Returns the value if the whole expression is constant, None if it depends
+/// on a name, and an error if some subexpression fails however the names are
+/// bound: constant arithmetic that overflows, or division by a constant zero.
+pub fn evaluate_constant(expr: &Expr, limits: &ConstLimits) -> Result<Option<i64>, ConstError> {
+    let mut evaluator = ConstEvaluator {
+        max_steps: limits.max_steps,
+        steps_left: limits.max_steps,
+        path: Vec::new(),
+    };
+    evaluator.evaluate(expr).map_err(|kind| {
+        let steps = match kind {
+            ConstErrorKind::StepLimit(_) => Vec::new(),
+            _ => evaluator.path,
+        };
+        ConstError { kind, steps }
+    })
+}
+
+/// Evaluates the value of every statement with [`evaluate_constant`],
+/// returning each statement's path and first error
+pub fn check_constants(program: &Program, limits: &ConstLimits) -> Vec<(Vec<usize>, ConstError)> {
+    let mut errors = Vec::new();
+    check_statements(&program.statements, limits, &mut Vec::new(), &mut errors);
+    errors
+}
+
+fn check_statements(
+    statements: &[Stmt],
+    limits: &ConstLimits,
+    path: &mut Vec<usize>,
+    errors: &mut Vec<(Vec<usize>, ConstError)>,
+) {
+    for (index, stmt) in statements.iter().enumerate() {
+        path.push(index);
+        match stmt {
+            Stmt::Let { value, .. } | Stmt::Destructure { value, .. } | Stmt::Expression(value) => {
+                if let Err(error) = evaluate_constant(value, limits) {
+                    errors.push((path.clone(), error));
+                }
+            }
+            Stmt::Block(statements) => check_statements(statements, limits, path, errors),
+        }
+        path.pop();
+    }
+}
+
+struct ConstEvaluator {
+    max_steps: usize,
+    steps_left: usize,
+    /// Path to the subexpression being evaluated, left at the failing one
+    path: Vec<Step>,
+}
+
+impl ConstEvaluator {
+    fn evaluate(&mut self, expr: &Expr) -> Result<Option<i64>, ConstErrorKind> {
+        if self.steps_left == 0 {
+            return Err(ConstErrorKind::StepLimit(self.max_steps));
+        }
+        self.steps_left -= 1;
+
+        match expr {
+            Expr::Number(n) => Ok(Some(*n)),
+            // A suffixed literal stands for a host value, not an integer
+            Expr::Identifier(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {
+                Ok(None)
+            }
+            Expr::Grouping(inner) => self.child(inner, Step::Inner),
+            Expr::Tuple(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    self.child(element, Step::Element(index))?;
+                }
+                Ok(None)
+            }
+            Expr::Unary { operator, operand } => match self.child(operand, Step::Operand)? {
+                Some(n) if *operator == UnaryOp::Negate => {
+                    n.checked_neg().map(Some).ok_or(ConstErrorKind::Overflow)
+                }
+                value => Ok(value),
+            },
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.child(left, Step::Left)?;
+                let right = self.child(right, Step::Right)?;
+                match (left, right) {
+                    (_, Some(0)) if *operator == BinaryOp::Divide => {
+                        Err(ConstErrorKind::DivisionByZero)
+                    }
+                    (Some(a), Some(b)) => apply_binary(operator, a, b)
+                        .map(Some)
+                        .ok_or(ConstErrorKind::Overflow),
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+
+    // Evaluates a child, keeping the path to it if it fails
+    fn child(&mut self, expr: &Expr, step: Step) -> Result<Option<i64>, ConstErrorKind> {
+        self.path.push(step);
+        let value = self.evaluate(expr)?;
+        self.path.pop();
+        Ok(value)
+    }
+}
+
+/// Applies a binary operator to two constants, returning None on overflow or
+/// division by zero. Division truncates toward zero.
+pub fn apply_binary(operator: &BinaryOp, left: i64, right: i64) -> Option<i64> {
+    match operator {
+        BinaryOp::Add => left.checked_add(right),
+        BinaryOp::Subtract => left.checked_sub(right),
+        BinaryOp::Multiply => left.checked_mul(right),
+        BinaryOp::Divide => left.checked_div(right),
+    }
+}
+
+// Looks through any groupings around an expression
+fn strip_grouping(mut expr: Expr) -> Expr {
+    while let Expr::Grouping(inner) = expr {
+        expr = *inner;
+    }
+    expr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn fold_source(source: &str) -> String {
+        let mut program = parse_source(source).unwrap();
+        fold_program(&mut program);
+        program.to_string()
+    }
+
+    #[test]
+    fn test_folds_constant_arithmetic() {
+        assert_eq!(fold_source("let x = (1 + 2) * 3 - 4 / 2;"), "let x = 7;\n");
+        assert_eq!(fold_source("{ 7 / -2; }"), "{\n  -3;\n}\n");
+    }
+
+    fn evaluate(source: &str, max_steps: usize) -> Result<Option<i64>, ConstError> {
+        let program = parse_source(source).unwrap();
+        let Stmt::Expression(expr) = &program.statements[0] else {
+            panic!("expected an expression statement");
+        };
+        evaluate_constant(expr, &ConstLimits { max_steps })
+    }
+
+    #[test]
+    fn test_evaluate_constant() {
+        assert_eq!(evaluate("(1 + 2) * -3;", 100), Ok(Some(-9)));
+        assert_eq!(evaluate("x * 0 + 1;", 100), Ok(None));
+        assert_eq!(
+            evaluate("(1, x / (2 - 2));", 100),
+            Err(ConstError {
+                kind: ConstErrorKind::DivisionByZero,
+                steps: vec![Step::Element(1)],
+            })
+        );
+        assert_eq!(
+            evaluate("x + (9223372036854775807 + 1) * 2;", 100),
+            Err(ConstError {
+                kind: ConstErrorKind::Overflow,
+                steps: vec![Step::Right, Step::Left, Step::Inner],
+            })
+        );
+        assert_eq!(
+            evaluate("1 + 2 + 3;", 4).unwrap_err().to_string(),
+            "this constant expression takes more than 4 steps to evaluate"
+        );
+    }
+
+    #[test]
+    fn test_check_constants_names_statements() {
+        let program = parse_source("let a = 1 / 0; { a; -(-9223372036854775807 - 1); }").unwrap();
+        let errors: Vec<(Vec<usize>, ConstErrorKind)> =
+            check_constants(&program, &ConstLimits::default())
+                .into_iter()
+                .map(|(statement, error)| (statement, error.kind))
+                .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                (vec![0], ConstErrorKind::DivisionByZero),
+                (vec![1, 1], ConstErrorKind::Overflow),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keeps_non_constant_parts() {
+        assert_eq!(fold_source("x + 2 * 3;"), "(x + 6);\n");
+        assert_eq!(fold_source("(x);"), "(x);\n");
+    }
+
+    #[test]
+    fn test_normalizes_signs() {
+        assert_eq!(fold_source("+42;"), "42;\n");
+        assert_eq!(fold_source("--10;"), "10;\n");
+        assert_eq!(fold_source("- -x;"), "x;\n");
+        assert_eq!(fold_source("-(-(y + 1));"), "(y + 1);\n");
+        assert_eq!(fold_source("-+-+z;"), "z;\n");
+    }
+
+    #[test]
+    fn test_leaves_unrepresentable_results_for_runtime() {
+        assert_eq!(fold_source("1 / 0;"), "(1 / 0);\n");
+        assert_eq!(
+            fold_source("9223372036854775807 + 1;"),
+            "(9223372036854775807 + 1);\n"
+        );
+    }
+}
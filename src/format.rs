@@ -0,0 +1,666 @@
+use crate::edit::TextEdit;
+use crate::lexer::{Lexer, Span, Token};
+use crate::parse_source;
+use crate::parser::{Associativity, Expr, ParseErrors, Program, Stmt};
+use crate::pretty::{self, Doc, Indent};
+use crate::rewrite;
+use std::collections::HashMap;
+
+/// Settings that control the layout of formatted code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Spaces per level of block nesting, and the width of a tab when
+    /// measuring lines
+    pub indent_width: usize,
+    pub indent_style: IndentStyle,
+    /// Expressions that would make a line longer than this are wrapped:
+    /// binary chains before each operator, tuples after each comma
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            indent_style: IndentStyle::Spaces,
+            max_width: 100,
+        }
+    }
+}
+
+/// What a level of block nesting is indented with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Spaces,
+    Tabs,
+}
+
+/// Formats a whole source file. Unlike `Display` for the AST, the output
+/// keeps the parentheses the author wrote and adds none of its own, so the
+/// formatted code parses back to the same tree.
+///
+/// `//` comments stay with the nearest statement: a comment after a statement
+/// on the same line stays at the end of that line, and any other comment goes
+/// on its own line before the statement that follows it, or at the end of its
+/// block. A comment inside a statement moves to the line before it.
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String, ParseErrors> {
+    let program = parse_source(source)?;
+    let tokens = Lexer::new(source).tokenize_spanned();
+    let mut formatter = Formatter {
+        out: String::new(),
+        options,
+        comments: comments(source, &tokens),
+        next_comment: 0,
+        spans: rewrite::statement_spans(&program, &tokens),
+    };
+    formatter.statements(&program.statements, 0..program.statements.len());
+    formatter.remaining_comments(usize::MAX, "");
+    Ok(formatter.out)
+}
+
+/// Formats a program, one statement per line
+pub fn format_program(program: &Program, options: &FormatOptions) -> String {
+    let mut formatter = Formatter {
+        out: String::new(),
+        options,
+        comments: Vec::new(),
+        next_comment: 0,
+        spans: HashMap::new(),
+    };
+    formatter.statements(&program.statements, 0..program.statements.len());
+    formatter.out
+}
+
+/// Formats only the top-level statements that overlap `range`, returning an
+/// edit that replaces them. The rest of the file is left untouched, but it
+/// must still parse. Comments between the selected statements are kept as
+/// [`format_source`] keeps them. Returns no edits if the range touches no
+/// statement.
+pub fn format_range(
+    source: &str,
+    range: Span,
+    options: &FormatOptions,
+) -> Result<Vec<TextEdit>, ParseErrors> {
+    let program = parse_source(source)?;
+    let spans = statement_spans(source);
+
+    // An empty range is a cursor position and selects the statement under it
+    let range_end = range.end.max(range.start + 1);
+    let overlapping: Vec<usize> = spans
+        .iter()
+        .enumerate()
+        .filter(|(_, span)| span.start < range_end && range.start < span.end)
+        .map(|(index, _)| index)
+        .collect();
+
+    let (Some(&first), Some(&last)) = (overlapping.first(), overlapping.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let replaced = Span::new(spans[first].start, spans[last].end);
+    let tokens = Lexer::new(source).tokenize_spanned();
+    let mut formatter = Formatter {
+        out: String::new(),
+        options,
+        comments: comments(source, &tokens)
+            .into_iter()
+            .filter(|comment| {
+                replaced.start <= comment.span.start && comment.span.end <= replaced.end
+            })
+            .collect(),
+        next_comment: 0,
+        spans: rewrite::statement_spans(&program, &tokens),
+    };
+    formatter.statements(&program.statements, first..last + 1);
+
+    Ok(vec![TextEdit::new(replaced, formatter.out.trim_end())])
+}
+
+/// A `//` comment, which the lexer skips
+struct Comment {
+    text: String,
+    span: Span,
+    /// The end of the token before the comment, if it is on the same line
+    after_token: Option<usize>,
+}
+
+// The comments in the gaps between tokens, which hold only whitespace and
+// comments, in source order
+fn comments(source: &str, tokens: &[(Token, Span)]) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut gap_start = 0;
+    for (_, span) in tokens {
+        let mut offset = gap_start;
+        for line in source[gap_start..span.start].split_inclusive('\n') {
+            if let Some(start) = line.find("//") {
+                let text = line[start..].trim_end();
+                comments.push(Comment {
+                    text: text.to_string(),
+                    span: Span::new(offset + start, offset + start + text.len()),
+                    after_token: (offset == gap_start && gap_start > 0).then_some(gap_start),
+                });
+            }
+            offset += line.len();
+        }
+        gap_start = span.end;
+    }
+    comments
+}
+
+// Byte ranges of the top-level statements, doc comments included. Statements
+// end at a `;` or at the `}` that closes a top-level block.
+fn statement_spans(source: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+
+    for (token, span) in Lexer::new(source).tokenize_spanned() {
+        if token == Token::EOF {
+            break;
+        }
+
+        let statement_start = *start.get_or_insert(span.start);
+        match token {
+            Token::LeftBrace => depth += 1,
+            Token::RightBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        if depth == 0 && matches!(token, Token::Semicolon | Token::RightBrace) {
+            spans.push(Span::new(statement_start, span.end));
+            start = None;
+        }
+    }
+
+    spans
+}
+
+struct Formatter<'a> {
+    out: String,
+    options: &'a FormatOptions,
+    /// Comments to place, in source order; empty when formatting a tree
+    comments: Vec<Comment>,
+    next_comment: usize,
+    /// Where each statement was in the source, by statement path
+    spans: HashMap<Vec<usize>, Span>,
+}
+
+impl Formatter<'_> {
+    fn statements(&mut self, statements: &[Stmt], indices: std::ops::Range<usize>) {
+        let mut path = Vec::new();
+        for index in indices {
+            path.push(index);
+            self.statement(&statements[index], &mut path);
+            path.pop();
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, path: &mut Vec<usize>) {
+        let depth = path.len() - 1;
+        let indent = self.indent(depth);
+        let span = self.spans.get(path.as_slice()).copied();
+
+        // A block's own comments are placed around its statements
+        if let Some(span) = span {
+            let leading_end = match stmt {
+                Stmt::Block(_) => span.start,
+                _ => span.end,
+            };
+            self.remaining_comments(leading_end, &indent);
+        }
+
+        if let Some(doc) = stmt.doc() {
+            for line in doc.split('\n') {
+                self.line(&indent, &Token::DocComment(line.to_string()).to_string());
+            }
+        }
+
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                self.expression_line(depth, &format!("let {} = ", name), value);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.expression_line(depth, &format!("let {} = ", pattern), value);
+            }
+            Stmt::Expression(expr) => self.expression_line(depth, "", expr),
+            Stmt::Block(statements) => {
+                let end = span.map_or(0, |span| span.end);
+                if statements.is_empty() && !self.has_comment_before(end) {
+                    self.line(&indent, "{}");
+                } else {
+                    self.line(&indent, "{");
+                    for (index, stmt) in statements.iter().enumerate() {
+                        path.push(index);
+                        self.statement(stmt, path);
+                        path.pop();
+                    }
+                    let inner = self.indent(depth + 1);
+                    self.remaining_comments(end, &inner);
+                    self.line(&indent, "}");
+                }
+            }
+        }
+
+        if let Some(span) = span {
+            self.trailing_comment(span.end);
+        }
+    }
+
+    fn indent(&self, depth: usize) -> String {
+        match self.options.indent_style {
+            IndentStyle::Spaces => " ".repeat(depth * self.options.indent_width),
+            IndentStyle::Tabs => "\t".repeat(depth),
+        }
+    }
+
+    // Writes `prefix`, `expr` and a semicolon, wrapping the expression where
+    // it does not fit in the line width
+    fn expression_line(&mut self, depth: usize, prefix: &str, expr: &Expr) {
+        let doc = Doc::Concat(vec![
+            Doc::text(prefix),
+            expression_doc(expr),
+            Doc::text(";"),
+        ]);
+        let unit = self.indent(1);
+        let indent = Indent {
+            unit: &unit,
+            width: self.options.indent_width,
+        };
+        let text = pretty::render(&doc, self.options.max_width, depth, &indent);
+        self.line(&self.indent(depth), &text);
+    }
+
+    fn has_comment_before(&self, offset: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.span.start < offset)
+    }
+
+    // Writes the comments that start before `offset`, each on its own line
+    fn remaining_comments(&mut self, offset: usize, indent: &str) {
+        while self.has_comment_before(offset) {
+            let text = self.comments[self.next_comment].text.clone();
+            self.line(indent, &text);
+            self.next_comment += 1;
+        }
+    }
+
+    // Appends the next comment to the last line if it was written on the same
+    // line as a statement ending at `end`
+    fn trailing_comment(&mut self, end: usize) {
+        let Some(comment) = self.comments.get(self.next_comment) else {
+            return;
+        };
+        if comment.after_token == Some(end) {
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(&comment.text);
+            self.out.push('\n');
+            self.next_comment += 1;
+        }
+    }
+
+    fn line(&mut self, indent: &str, text: &str) {
+        self.out.push_str(indent);
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+}
+
+// Prints an expression with the minimum parentheses needed to preserve its
+// structure, in addition to those recorded as Grouping nodes
+pub(crate) fn expression(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(n) => n.to_string(),
+        Expr::Identifier(name) => name.clone(),
+        Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => expr.to_string(),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let precedence = binding_power(expr);
+            let (left_min, right_min) = match operator.associativity() {
+                Associativity::Left => (precedence, precedence + 1),
+                Associativity::Right => (precedence + 1, precedence),
+            };
+            format!(
+                "{} {} {}",
+                operand(left, left_min),
+                operator,
+                operand(right, right_min)
+            )
+        }
+        Expr::Unary {
+            operator,
+            operand: inner,
+        } => {
+            format!("{}{}", operator, operand(inner, binding_power(expr)))
+        }
+        Expr::Grouping(inner) => format!("({})", expression(inner)),
+        Expr::Tuple(elements) => {
+            let elements: Vec<String> = elements.iter().map(expression).collect();
+            match elements.as_slice() {
+                [only] => format!("({},)", only),
+                _ => format!("({})", elements.join(", ")),
+            }
+        }
+    }
+}
+
+// Lays an expression out like `expression`, as a document that breaks a
+// binary chain before each of its operators and a tuple after each comma when
+// they do not fit on one line
+fn expression_doc(expr: &Expr) -> Doc {
+    match expr {
+        Expr::Binary { .. } => {
+            let (first, rest) = chain(expr);
+            // The first operand is nested too, so a chain it breaks into
+            // indents past this one's operators
+            let docs = std::iter::once(first)
+                .chain(rest.into_iter().flat_map(|(operator, operand)| {
+                    [Doc::Line, Doc::text(format!("{} ", operator)), operand]
+                }))
+                .collect();
+            Doc::group(Doc::nest(Doc::Concat(docs)))
+        }
+        Expr::Unary {
+            operator,
+            operand: inner,
+        } => Doc::Concat(vec![
+            Doc::text(operator.to_string()),
+            operand_doc(inner, binding_power(expr)),
+        ]),
+        Expr::Grouping(inner) => parenthesized(expression_doc(inner)),
+        Expr::Tuple(elements) if !elements.is_empty() => {
+            let mut items = vec![Doc::SoftLine];
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    items.extend([Doc::text(","), Doc::Line]);
+                }
+                items.push(expression_doc(element));
+            }
+            if elements.len() == 1 {
+                items.push(Doc::text(","));
+            }
+            Doc::group(Doc::Concat(vec![
+                Doc::text("("),
+                Doc::nest(Doc::Concat(items)),
+                Doc::SoftLine,
+                Doc::text(")"),
+            ]))
+        }
+        _ => Doc::text(expression(expr)),
+    }
+}
+
+// Splits a binary expression into its first operand and the operators and
+// operands that follow it at the outermost precedence level, so `a + b * c - d`
+// gives `a` and `+ b * c`, `- d`
+fn chain(expr: &Expr) -> (Doc, Vec<(String, Doc)>) {
+    let Expr::Binary {
+        left,
+        operator,
+        right,
+    } = expr
+    else {
+        return (expression_doc(expr), Vec::new());
+    };
+
+    let precedence = operator.precedence();
+    let (first, mut rest, right_min) = match operator.associativity() {
+        Associativity::Left
+            if matches!(**left, Expr::Binary { .. }) && binding_power(left) == precedence =>
+        {
+            let (first, rest) = chain(left);
+            (first, rest, precedence + 1)
+        }
+        Associativity::Left => (operand_doc(left, precedence), Vec::new(), precedence + 1),
+        Associativity::Right => (operand_doc(left, precedence + 1), Vec::new(), precedence),
+    };
+    rest.push((operator.to_string(), operand_doc(right, right_min)));
+    (first, rest)
+}
+
+fn operand_doc(expr: &Expr, minimum: u8) -> Doc {
+    if binding_power(expr) < minimum {
+        parenthesized(expression_doc(expr))
+    } else {
+        expression_doc(expr)
+    }
+}
+
+fn parenthesized(doc: Doc) -> Doc {
+    Doc::Concat(vec![Doc::text("("), doc, Doc::text(")")])
+}
+
+// Prints a subexpression, parenthesizing it if it binds less tightly than `minimum`
+fn operand(expr: &Expr, minimum: u8) -> String {
+    if binding_power(expr) < minimum {
+        format!("({})", expression(expr))
+    } else {
+        expression(expr)
+    }
+}
+
+pub(crate) fn binding_power(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Binary { operator, .. } => operator.precedence(),
+        Expr::Unary { .. } => 3,
+        Expr::Number(n) if *n < 0 => 3,
+        Expr::Float(n) if n.is_sign_negative() => 3,
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. }
+        | Expr::Grouping(_)
+        | Expr::Tuple(_) => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::apply_edits;
+
+    fn format(source: &str) -> String {
+        format_source(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_keeps_only_written_parentheses() {
+        assert_eq!(
+            format("let   x=1+2*3 ;(1+2)*-(x);- -y;"),
+            "let x = 1 + 2 * 3;\n(1 + 2) * -(x);\n--y;\n"
+        );
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent_and_preserves_the_tree() {
+        let source = "/// Total.\nlet t = a - (b - c) / 2; { {} { 1; } }";
+        let once = format(source);
+
+        assert_eq!(format(&once), once);
+        assert_eq!(parse_source(&once).unwrap(), parse_source(source).unwrap());
+    }
+
+    // Comments in every position the grammar allows them
+    const COMMENTED: &[&str] = &[
+        "// header\nlet a = 1; // one\n",
+        "let a = 1;   //   spaced   \n\n\n// before b\nlet b = a;",
+        "/// Doc\n// between\nlet a = 1;",
+        "let a // name\n= 1 + // operator\n2;",
+        "{ // opening\n  a; // inside\n  // closing\n} // after",
+        "{\n// only\n}",
+        "{ { 1; } // inner\n // outer\n}",
+        "let (x, // first\n _) = (1, 2); //// four slashes",
+        "a;\n// trailing at the end",
+        "// only a comment",
+    ];
+
+    #[test]
+    fn test_comments_stay_with_their_statements() {
+        assert_eq!(format(COMMENTED[0]), "// header\nlet a = 1; // one\n");
+        assert_eq!(
+            format(COMMENTED[1]),
+            "let a = 1; //   spaced\n// before b\nlet b = a;\n"
+        );
+        assert_eq!(
+            format(COMMENTED[3]),
+            "// name\n// operator\nlet a = 1 + 2;\n"
+        );
+        assert_eq!(
+            format(COMMENTED[4]),
+            "{\n  // opening\n  a; // inside\n  // closing\n} // after\n"
+        );
+        assert_eq!(format(COMMENTED[5]), "{\n  // only\n}\n");
+        assert_eq!(
+            format(COMMENTED[6]),
+            "{\n  {\n    1;\n  } // inner\n  // outer\n}\n"
+        );
+        assert_eq!(format(COMMENTED[9]), "// only a comment\n");
+    }
+
+    #[test]
+    fn test_formatting_with_comments_is_idempotent() {
+        for source in COMMENTED {
+            let once = format(source);
+
+            assert_eq!(format(&once), once, "formatting {:?}", source);
+            assert_eq!(parse_source(&once).unwrap(), parse_source(source).unwrap());
+            for comment in source
+                .lines()
+                .filter_map(|line| line.find("//").map(|i| &line[i..]))
+            {
+                assert!(once.contains(comment.trim_end()), "lost {:?}", comment);
+            }
+        }
+    }
+
+    #[test]
+    fn test_adds_parentheses_for_constructed_trees() {
+        let mut program = parse_source("a - (b - c); -(x * y); (a * b) - c;").unwrap();
+        program.strip_groupings();
+
+        assert_eq!(
+            format_program(&program, &FormatOptions::default()),
+            "a - (b - c);\n-(x * y);\na * b - c;\n"
+        );
+    }
+
+    #[test]
+    fn test_indent_width() {
+        let options = FormatOptions {
+            indent_width: 4,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_source("{ let a = 1; { a; } }", &options).unwrap(),
+            "{\n    let a = 1;\n    {\n        a;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_indent_with_tabs() {
+        let options = FormatOptions {
+            indent_style: IndentStyle::Tabs,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            format_source("{ let a = 1; { a; } // end\n}", &options).unwrap(),
+            "{\n\tlet a = 1;\n\t{\n\t\ta;\n\t} // end\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_long_lines_wrap_before_operators() {
+        let options = FormatOptions {
+            max_width: 24,
+            ..FormatOptions::default()
+        };
+        let format = |source: &str| format_source(source, &options).unwrap();
+
+        assert_eq!(
+            format("let total = first + second * 2 - (third - fourth);"),
+            "let total = first\n  + second * 2\n  - (third - fourth);\n"
+        );
+        assert_eq!(
+            format("{ alpha * beta * gamma / delta; }"),
+            "{\n  alpha\n    * beta\n    * gamma\n    / delta;\n}\n"
+        );
+        assert_eq!(
+            format("let short = 1 + 2;\n(a_long_name, another_long_name);"),
+            "let short = 1 + 2;\n(\n  a_long_name,\n  another_long_name\n);\n"
+        );
+    }
+
+    #[test]
+    fn test_nested_chains_wrap_independently() {
+        let options = FormatOptions {
+            max_width: 30,
+            ..FormatOptions::default()
+        };
+        let source = "let total = first_value * second_value * third_value \
+                      + (fourth_value - fifth_value - sixth) + seventh;\n\
+                      { let t = (alpha_value + beta_value, gamma); }";
+        let once = format_source(source, &options).unwrap();
+
+        assert_eq!(
+            once,
+            "let total = first_value\n    * second_value\n    * third_value\n  \
+             + (fourth_value\n    - fifth_value\n    - sixth)\n  + seventh;\n\
+             {\n  let t = (\n    alpha_value + beta_value,\n    gamma\n  );\n}\n"
+        );
+        assert_eq!(format_source(&once, &options).unwrap(), once);
+        assert_eq!(parse_source(&once).unwrap(), parse_source(source).unwrap());
+    }
+
+    #[test]
+    fn test_format_range_touches_only_selected_statements() {
+        let source = "let a=1;\nlet b  =  a;  { b;b; }\nlet c=2;";
+        let start = source.find("let b").unwrap();
+        let edits = format_range(
+            source,
+            Span::new(start, start + 16),
+            &FormatOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            apply_edits(source, &edits),
+            "let a=1;\nlet b = a;\n{\n  b;\n  b;\n}\nlet c=2;"
+        );
+    }
+
+    #[test]
+    fn test_format_range_at_cursor_and_outside_statements() {
+        let source = "1 ;  \n2 ;";
+        let options = FormatOptions::default();
+
+        assert_eq!(
+            format_range(source, Span::new(0, 0), &options).unwrap(),
+            vec![TextEdit::new(Span::new(0, 3), "1;")]
+        );
+        assert_eq!(
+            format_range(source, Span::new(4, 4), &options).unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_format_range_keeps_comments_between_selected_statements() {
+        let source = "let a=1; // kept\nlet b=a; // one\n// two\nlet c=b; // three\nc;";
+        let start = source.find("let b").unwrap();
+        let end = source.find("let c").unwrap();
+        let edits =
+            format_range(source, Span::new(start, end + 1), &FormatOptions::default()).unwrap();
+
+        assert_eq!(
+            apply_edits(source, &edits),
+            "let a=1; // kept\nlet b = a; // one\n// two\nlet c = b; // three\nc;"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! The grammar the parser accepts, as EBNF. Expression rules are generated
+//! from the operator tables the parser itself climbs, and literal suffixes
+//! from the embedder's registrations, so the text follows any change to
+//! either.
+
+use crate::lexer::KEYWORDS;
+use crate::parser::{Associativity, BinaryOp, UnaryOp};
+use crate::suffix::LiteralSuffixes;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Returns the grammar of programs parsed with `suffixes` registered, in ISO
+/// EBNF: `=` defines a rule, `|` separates alternatives, `[ ]` is optional,
+/// `{ }` repeats, and `? ?` describes a token in prose.
+pub fn grammar_ebnf(suffixes: &LiteralSuffixes) -> String {
+    let mut out = String::new();
+    let mut rule = |name: &str, definition: &str| {
+        let _ = writeln!(out, "{} = {} ;", name, definition);
+    };
+
+    rule("program", "{ statement }");
+    rule("statement", "let_statement | block | expression , \";\"");
+    rule(
+        "let_statement",
+        "{ doc_comment } , \"let\" , ( identifier | pattern ) , \"=\" , expression , \";\"",
+    );
+    rule("block", "\"{\" , { statement } , \"}\"");
+    rule(
+        "pattern",
+        "\"_\" | identifier | [ \"-\" ] , number | \"(\" , [ pattern , { \",\" , pattern } , [ \",\" ] ] , \")\"",
+    );
+
+    // One rule per precedence level, loosest first, each over the next
+    let mut levels: BTreeMap<u8, Vec<BinaryOp>> = BTreeMap::new();
+    for op in BinaryOp::all() {
+        levels.entry(op.precedence()).or_default().push(op);
+    }
+    let names: Vec<String> = levels
+        .values()
+        .enumerate()
+        .map(|(index, ops)| match index {
+            0 => "expression".to_string(),
+            _ => level_name(ops),
+        })
+        .chain(["unary".to_string()])
+        .collect();
+    for (index, ops) in levels.values().enumerate() {
+        let (name, operand) = (&names[index], &names[index + 1]);
+        let operators = alternatives(ops.iter().map(ToString::to_string));
+        let definition = match ops[0].associativity() {
+            Associativity::Left => format!("{} , {{ {} , {} }}", operand, operators, operand),
+            Associativity::Right => format!("{} , [ {} , {} ]", operand, operators, name),
+        };
+        rule(name, &definition);
+    }
+
+    let signs = alternatives(UnaryOp::all().iter().map(ToString::to_string));
+    rule("unary", &format!("{{ {} }} , primary", signs));
+
+    let mut suffixes = suffixes.suffixes().peekable();
+    let literal = match suffixes.peek() {
+        Some(_) => "number , [ suffix ]",
+        None => "number",
+    };
+    rule(
+        "primary",
+        &format!(
+            "{} | float | identifier | \"(\" , [ expression , {{ \",\" , expression }} , [ \",\" ] ] , \")\"",
+            literal
+        ),
+    );
+    if suffixes.peek().is_some() {
+        rule("suffix", &alternatives(suffixes.map(String::from)));
+    }
+
+    let keywords = alternatives(KEYWORDS.iter().map(|keyword| keyword.to_string()));
+    rule(
+        "identifier",
+        &format!(
+            "? a letter or \"_\", then letters, digits and \"_\" ? - ( {} )",
+            keywords
+        ),
+    );
+    rule("number", "? decimal digits, at most 9223372036854775807 ?");
+    rule(
+        "float",
+        "? decimal digits with a fraction \".\" digits, an exponent ( \"e\" | \"E\" ) [ sign ] digits, or both ?",
+    );
+    rule("doc_comment", "? \"///\" and the rest of its line ?");
+
+    let _ = writeln!(
+        out,
+        "(* A suffix directly follows its number without whitespace. `( expression )`\n   \
+         is a grouping; a comma, or no expression at all, makes a tuple. *)"
+    );
+    out
+}
+
+// Quoted terminals separated by `|`, in parentheses if there are several
+fn alternatives(terminals: impl Iterator<Item = String>) -> String {
+    let quoted: Vec<String> = terminals
+        .map(|terminal| format!("\"{}\"", terminal))
+        .collect();
+    match quoted.len() {
+        1 => quoted[0].clone(),
+        _ => format!("( {} )", quoted.join(" | ")),
+    }
+}
+
+// A rule name for a precedence level from its operators, as in `add_subtract`
+fn level_name(ops: &[BinaryOp]) -> String {
+    ops.iter()
+        .map(|op| format!("{:?}", op).to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expression_rules_follow_the_operator_tables() {
+        let grammar = grammar_ebnf(&LiteralSuffixes::new());
+        let lines: Vec<&str> = grammar.lines().collect();
+
+        assert!(
+            lines.contains(
+                &"expression = multiply_divide , { ( \"+\" | \"-\" ) , multiply_divide } ;"
+            )
+        );
+        assert!(lines.contains(&"multiply_divide = unary , { ( \"*\" | \"/\" ) , unary } ;"));
+        assert!(lines.contains(&"unary = { ( \"-\" | \"+\" ) } , primary ;"));
+        assert!(!grammar.contains("suffix ="));
+    }
+
+    #[test]
+    fn test_registered_suffixes_are_part_of_the_grammar() {
+        let units = LiteralSuffixes::new()
+            .register("px", "Length")
+            .register("s", "Duration");
+        let grammar = grammar_ebnf(&units);
+
+        assert!(grammar.contains("primary = number , [ suffix ] | float | identifier"));
+        assert!(grammar.contains("\nsuffix = ( \"px\" | \"s\" ) ;\n"));
+    }
+
+    #[test]
+    fn test_every_rule_used_is_defined() {
+        let grammar = grammar_ebnf(&LiteralSuffixes::new().register("px", "Length"));
+        let defined: Vec<&str> = grammar
+            .lines()
+            .filter_map(|line| line.split_once(" = ").map(|(name, _)| name))
+            .collect();
+
+        for line in grammar.lines().filter(|line| line.contains(" = ")) {
+            let (_, definition) = line.split_once(" = ").unwrap();
+            // Names outside quotes and prose, which may itself quote
+            let mut outside = String::new();
+            let mut inside = None;
+            for ch in definition.chars() {
+                match (inside, ch) {
+                    (None, '"' | '?') => inside = Some(ch),
+                    (None, _) => outside.push(ch),
+                    (Some(open), _) if open == ch => inside = None,
+                    _ => {}
+                }
+            }
+            for name in outside
+                .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .filter(|word| !word.is_empty())
+            {
+                assert!(defined.contains(&name), "`{}` is not defined", name);
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use crate::lexer::{Lexer, Token};
+
+/// Syntactic category of a piece of source text, used to pick a highlighting style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Keyword,
+    Number,
+    Identifier,
+    Operator,
+    Punctuation,
+    Comment,
+    DocComment,
+    Error,
+}
+
+impl Category {
+    /// Returns the category of a token, or None for EOF
+    pub fn of(token: &Token) -> Option<Self> {
+        match token {
+            Token::Let => Some(Category::Keyword),
+            Token::Number(_) | Token::Float(_) | Token::BigNumber(_) => Some(Category::Number),
+            Token::Ident(_) => Some(Category::Identifier),
+            Token::Equals
+            | Token::Plus
+            | Token::Minus
+            | Token::Multiply
+            | Token::Divide
+            | Token::Percent
+            | Token::Arrow
+            | Token::FatArrow => Some(Category::Operator),
+            Token::Semicolon
+            | Token::Comma
+            | Token::Colon
+            | Token::Dot
+            | Token::At
+            | Token::LeftParen
+            | Token::RightParen
+            | Token::LeftBrace
+            | Token::RightBrace => Some(Category::Punctuation),
+            Token::DocComment(_) => Some(Category::DocComment),
+            Token::Illegal(_) => Some(Category::Error),
+            Token::EOF => None,
+        }
+    }
+
+    /// Returns the CSS class used for this category in HTML output
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            Category::Keyword => "ox-keyword",
+            Category::Number => "ox-number",
+            Category::Identifier => "ox-ident",
+            Category::Operator => "ox-operator",
+            Category::Punctuation => "ox-punct",
+            Category::Comment => "ox-comment",
+            Category::DocComment => "ox-doc-comment",
+            Category::Error => "ox-error",
+        }
+    }
+}
+
+/// Renders source code as HTML with every token wrapped in a `<span>` carrying
+/// its category's CSS class. Whitespace is preserved verbatim, so the result is
+/// meant to be placed inside a `<pre>` element.
+pub fn to_html(source: &str) -> String {
+    let mut out = String::with_capacity(source.len() * 2);
+    let mut lexer = Lexer::new(source);
+    let mut last_end = 0;
+
+    loop {
+        let (token, span) = lexer.next_spanned();
+
+        // Text between tokens is whitespace and ordinary comments
+        write_trivia(&mut out, &source[last_end..span.start]);
+
+        match Category::of(&token) {
+            Some(category) => write_span(&mut out, category, &source[span.start..span.end]),
+            None => break,
+        }
+
+        last_end = span.end;
+    }
+
+    out
+}
+
+// Writes whitespace as-is and wraps each `//` comment in a comment span
+fn write_trivia(out: &mut String, mut trivia: &str) {
+    while let Some(start) = trivia.find("//") {
+        out.push_str(&escape(&trivia[..start]));
+
+        let end = trivia[start..]
+            .find('\n')
+            .map_or(trivia.len(), |offset| start + offset);
+        write_span(out, Category::Comment, &trivia[start..end]);
+
+        trivia = &trivia[end..];
+    }
+
+    out.push_str(&escape(trivia));
+}
+
+fn write_span(out: &mut String, category: Category, text: &str) {
+    out.push_str(&format!(
+        "<span class=\"{}\">{}</span>",
+        category.css_class(),
+        escape(text)
+    ));
+}
+
+// Escapes the characters that are significant in HTML text
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_let_statement() {
+        assert_eq!(
+            to_html("let x = 42;"),
+            "<span class=\"ox-keyword\">let</span> \
+             <span class=\"ox-ident\">x</span> \
+             <span class=\"ox-operator\">=</span> \
+             <span class=\"ox-number\">42</span>\
+             <span class=\"ox-punct\">;</span>"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_whitespace_are_preserved() {
+        let html = to_html("/// Doc\n1; // trailing\n\t2;");
+
+        assert_eq!(
+            html,
+            "<span class=\"ox-doc-comment\">/// Doc</span>\n\
+             <span class=\"ox-number\">1</span><span class=\"ox-punct\">;</span> \
+             <span class=\"ox-comment\">// trailing</span>\n\t\
+             <span class=\"ox-number\">2</span><span class=\"ox-punct\">;</span>"
+        );
+    }
+
+    #[test]
+    fn test_html_is_escaped() {
+        assert_eq!(
+            to_html("<&"),
+            "<span class=\"ox-error\">&lt;</span><span class=\"ox-error\">&amp;</span>"
+        );
+    }
+
+    #[test]
+    fn test_empty_source() {
+        assert_eq!(to_html(""), "");
+        assert_eq!(to_html("  \n"), "  \n");
+    }
+}
@@ -0,0 +1,73 @@
+use crate::lexer::Span;
+use crate::suffix::LiteralSuffixes;
+use crate::types::{Type, type_map};
+
+/// Text an editor shows inline at a position without it being part of the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHint {
+    /// Where the hint goes; the span is empty and sits at the insertion point
+    pub span: Span,
+    pub label: String,
+}
+
+/// Returns a type hint after the name of every `let` binding, as in
+/// `let x /*: Int*/ = 5;`, using the types of [`crate::types::type_map`].
+/// Types are only known while the source parses, and bindings of unknown
+/// type get no hint.
+pub fn inlay_hints(source: &str) -> Vec<InlayHint> {
+    inlay_hints_with_suffixes(source, &LiteralSuffixes::new())
+}
+
+/// Like [`inlay_hints`], accepting the registered literal suffixes
+pub fn inlay_hints_with_suffixes(source: &str, suffixes: &LiteralSuffixes) -> Vec<InlayHint> {
+    let Ok(types) = type_map(source, suffixes) else {
+        return Vec::new();
+    };
+
+    types
+        .bindings()
+        .iter()
+        .filter(|(_, ty)| *ty != Type::Unknown)
+        .map(|(span, ty)| InlayHint {
+            span: Span::new(span.end, span.end),
+            label: format!(": {}", ty.display()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::{TextEdit, apply_edits};
+
+    #[test]
+    fn test_hint_after_each_binding() {
+        let source = "let x = 5; { let total = x * 2; } x;";
+        let edits: Vec<TextEdit> = inlay_hints(source)
+            .into_iter()
+            .map(|hint| TextEdit::new(hint.span, format!(" /*{}*/", hint.label)))
+            .collect();
+
+        assert_eq!(
+            apply_edits(source, &edits),
+            "let x /*: Int*/ = 5; { let total /*: Int*/ = x * 2; } x;"
+        );
+    }
+
+    #[test]
+    fn test_hints_for_tuples_and_suffixes() {
+        let units = LiteralSuffixes::new().register("px", "Length");
+        let source = "let (w, h) = (2px, 3); let z = w + 1;";
+        let labels: Vec<String> = inlay_hints_with_suffixes(source, &units)
+            .into_iter()
+            .map(|hint| hint.label)
+            .collect();
+
+        assert_eq!(labels, vec![": Length", ": Int"]);
+    }
+
+    #[test]
+    fn test_no_hints_without_bindings() {
+        assert!(inlay_hints("1 + 2; let").is_empty());
+    }
+}
@@ -0,0 +1,1134 @@
+//! A tree-walking interpreter. Programs run in an [`Environment`] holding the
+//! values of their bindings; hosts provide the values of free names by
+//! defining them before running a program, and read results back out of it.
+
+use crate::diagnostic::Diagnostic;
+use crate::fold::apply_binary;
+use crate::format;
+use crate::lexer::Lexer;
+use crate::parser::{BinaryOp, Expr, Pattern, Program, Stmt, UnaryOp};
+use crate::rewrite::statement_spans;
+use crate::suffix::LiteralSuffixes;
+use crate::types::Type;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+
+/// A runtime value. Features add variants, such as `BigInt` with `bigint`,
+/// so matches on this enum outside the crate need a wildcard arm.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    /// A number written with a literal suffix, such as `10px`. Quantities
+    /// combine like the host types their suffixes stand for: two with the
+    /// same suffix add, subtract and divide, and any can be scaled by an
+    /// integer.
+    Quantity {
+        value: i64,
+        suffix: String,
+    },
+    Tuple(Vec<Value>),
+    /// An integer beyond 64 bits, from a literal, [`ArbitraryPrecision`]
+    /// arithmetic or the host. Integers that fit in 64 bits are always
+    /// [`Value::Int`]s.
+    ///
+    /// [`ArbitraryPrecision`]: crate::bigint::ArbitraryPrecision
+    #[cfg(feature = "bigint")]
+    BigInt(crate::bigint::BigInt),
+}
+
+impl Value {
+    /// The value's type, naming quantities by the type their suffix is
+    /// registered with
+    pub fn type_of(&self, suffixes: &LiteralSuffixes) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            #[cfg(feature = "bigint")]
+            Value::BigInt(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Quantity { suffix, .. } => suffixes
+                .type_name(suffix)
+                .map_or(Type::Unknown, |name| Type::Named(name.to_string())),
+            Value::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| element.type_of(suffixes))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            #[cfg(feature = "bigint")]
+            Value::BigInt(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Quantity { value, suffix } => write!(f, "{}{}", value, suffix),
+            Value::Tuple(elements) => {
+                write!(f, "(")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Settings for showing values to people, so that a large value does not
+/// flood a terminal. `Display` for [`Value`] shows everything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueFormatter {
+    /// Tuple elements shown before the rest are summarised, as in
+    /// `(0, 1, … 998 more items)`
+    pub max_items: usize,
+    /// Tuples nested deeper than this are shown as `(…)`; the value itself is
+    /// at depth 0
+    pub max_depth: usize,
+}
+
+impl Default for ValueFormatter {
+    fn default() -> Self {
+        Self {
+            max_items: 100,
+            max_depth: 8,
+        }
+    }
+}
+
+impl ValueFormatter {
+    /// Shows a value within these limits. The output only depends on the
+    /// value and the settings.
+    pub fn format(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write(value, 0, &mut out);
+        out
+    }
+
+    fn write(&self, value: &Value, depth: usize, out: &mut String) {
+        let Value::Tuple(elements) = value else {
+            out.push_str(&value.to_string());
+            return;
+        };
+        if depth >= self.max_depth && !elements.is_empty() {
+            out.push_str("(…)");
+            return;
+        }
+
+        out.push('(');
+        for (index, element) in elements.iter().take(self.max_items).enumerate() {
+            if index > 0 {
+                out.push_str(", ");
+            }
+            self.write(element, depth + 1, out);
+        }
+        let hidden = elements.len().saturating_sub(self.max_items);
+        if hidden > 0 {
+            if self.max_items > 0 {
+                out.push_str(", ");
+            }
+            let items = if hidden == 1 { "item" } else { "items" };
+            out.push_str(&format!("… {} more {}", hidden, items));
+        } else if elements.len() == 1 {
+            out.push(',');
+        }
+        out.push(')');
+    }
+}
+
+/// Why running a program failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeError {
+    /// A name that is not bound was used
+    Unbound(String),
+    Overflow,
+    DivisionByZero,
+    /// An operator was applied to values it is not defined for
+    Unsupported {
+        operator: String,
+        operands: Vec<Value>,
+    },
+    /// A destructuring `let` was given a value its pattern does not match
+    PatternMismatch {
+        pattern: Pattern,
+        value: Value,
+    },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Unbound(name) => write!(f, "`{}` is not bound", name),
+            RuntimeError::Overflow => write!(f, "arithmetic overflowed a 64-bit integer"),
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::Unsupported { operator, operands } => {
+                let operands: Vec<String> = operands
+                    .iter()
+                    .map(|operand| format!("`{}`", operand))
+                    .collect();
+                write!(
+                    f,
+                    "`{}` cannot be applied to {}",
+                    operator,
+                    operands.join(" and ")
+                )
+            }
+            RuntimeError::PatternMismatch { pattern, value } => {
+                write!(f, "`{}` does not match `{}`", value, pattern)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// The bindings visible to running code.
+///
+/// Bindings are kept in a persistent list that environments share, so
+/// cloning an environment or taking a [`Snapshot`] of it is cheap whatever it
+/// holds. Hosts can branch from common base bindings, run something in each
+/// branch, and go back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment {
+    /// The latest binding, which links to the ones made before it
+    head: Option<Rc<Binding>>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Binding {
+    name: String,
+    value: Value,
+    next: Option<Rc<Binding>>,
+}
+
+// Unlinks the list iteratively, as dropping it recursively could overflow
+// the stack on long lists
+impl Drop for Binding {
+    fn drop(&mut self) {
+        let mut next = self.next.take();
+        while let Some(binding) = next {
+            match Rc::try_unwrap(binding) {
+                Ok(mut binding) => next = binding.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// The bindings of an [`Environment`] at one point, to return to with
+/// [`Environment::restore`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot(Option<Rc<Binding>>);
+
+impl Environment {
+    /// An environment with nothing bound
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds `name`, shadowing any earlier binding
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.head = Some(Rc::new(Binding {
+            name: name.to_string(),
+            value,
+            next: self.head.take(),
+        }));
+    }
+
+    /// The value `name` is bound to, if any
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.iter()
+            .find(|binding| binding.name == name)
+            .map(|binding| &binding.value)
+    }
+
+    /// Every visible binding, each name once with the value it has now, in
+    /// the order the bindings were made
+    pub fn bindings(&self) -> Vec<(&str, &Value)> {
+        let mut bindings: Vec<(&str, &Value)> = Vec::new();
+        for binding in self.iter() {
+            if !bindings.iter().any(|(name, _)| *name == binding.name) {
+                bindings.push((&binding.name, &binding.value));
+            }
+        }
+        bindings.reverse();
+        bindings
+    }
+
+    /// Removes every binding
+    pub fn clear(&mut self) {
+        self.head = None;
+    }
+
+    /// Records the current bindings, sharing them rather than copying them
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.head.clone())
+    }
+
+    /// Returns to the bindings recorded in `snapshot`, undoing every binding
+    /// made since. The snapshot may come from another environment.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.head = snapshot.0.clone();
+    }
+
+    // The bindings from the latest to the earliest
+    fn iter(&self) -> impl Iterator<Item = &Binding> {
+        std::iter::successors(self.head.as_deref(), |binding| binding.next.as_deref())
+    }
+
+    /// Runs a program's statements, keeping the bindings it makes at the top
+    /// level. Returns the value of the last statement if it is an expression
+    /// statement, or a block ending in one.
+    ///
+    /// A statement that fails stops the program; bindings made before it stay.
+    pub fn run(&mut self, program: &Program) -> Result<Option<Value>, RuntimeError> {
+        Machine::new(self).statements(&program.statements)
+    }
+
+    /// Like [`Environment::run`], with the host's `arithmetic` asked about
+    /// every binary operation first
+    pub fn run_with(
+        &mut self,
+        program: &Program,
+        arithmetic: &dyn Arithmetic,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let mut machine = Machine::new(self);
+        machine.arithmetic = Some(arithmetic);
+        machine.statements(&program.statements)
+    }
+
+    /// Evaluates an expression with the current bindings
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value, RuntimeError> {
+        Machine::new(&mut self.clone()).expression(expr)
+    }
+
+    /// Evaluates an expression one reduction at a time, leftmost innermost
+    /// first, recording the expression after each, as in `1 + 2 * 3`, then
+    /// `1 + 6`, then `7`. Evaluation stops at the first runtime error.
+    pub fn trace_evaluation(&self, expr: &Expr) -> EvaluationTrace {
+        let mut expr = expr.clone();
+        expr.strip_groupings();
+        negate_literals(&mut expr);
+
+        let start = expr.clone();
+        let mut steps = Vec::new();
+        loop {
+            let redex = match self.reduce(&mut expr) {
+                None => break,
+                Some(Ok(redex)) => redex,
+                Some(Err(error)) => {
+                    return EvaluationTrace {
+                        start,
+                        steps,
+                        error: Some(error),
+                    };
+                }
+            };
+            negate_literals(&mut expr);
+            steps.push(Step {
+                expr: expr.clone(),
+                redex,
+            });
+        }
+        EvaluationTrace {
+            start,
+            steps,
+            error: None,
+        }
+    }
+
+    // Performs the leftmost innermost reduction in `expr`, returning what it
+    // reduced, or None if `expr` is already a value
+    fn reduce(&self, expr: &mut Expr) -> Option<Result<Expr, RuntimeError>> {
+        let result = match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {
+                return None;
+            }
+            // A negative big integer, which has no literal of its own
+            Expr::Unary {
+                operator: UnaryOp::Negate,
+                operand,
+            } if matches!(operand.as_ref(), Expr::BigNumber(_)) => return None,
+            Expr::Grouping(inner) => return self.reduce(inner),
+            Expr::Tuple(elements) => {
+                return elements.iter_mut().find_map(|element| self.reduce(element));
+            }
+            Expr::Identifier(name) => match self.get(name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::Unbound(name.clone())),
+            },
+            Expr::Unary { operator, operand } => {
+                if let Some(step) = self.reduce(operand) {
+                    return Some(step);
+                }
+                unary(operator, value_of(operand)?)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                if let Some(step) = self.reduce(left).or_else(|| self.reduce(right)) {
+                    return Some(step);
+                }
+                binary(operator, value_of(left)?, value_of(right)?)
+            }
+        };
+        Some(result.map(|value| std::mem::replace(expr, expression_of(&value))))
+    }
+}
+
+/// Arithmetic a host adds to the interpreter's, such as rules for its own
+/// quantities, for [`Environment::run_with`]
+pub trait Arithmetic {
+    /// The result of `left operator right`, or None to leave the operation to
+    /// the built-in rules
+    fn binary(
+        &self,
+        operator: &BinaryOp,
+        left: &Value,
+        right: &Value,
+    ) -> Option<Result<Value, RuntimeError>>;
+}
+
+/// What running a program produced
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    /// The value of the last statement, if the program ran to the end and
+    /// ends in an expression statement or a block ending in one
+    pub value: Option<Value>,
+    /// Why the program did not run to the end: its syntax errors, or the
+    /// runtime error that stopped it, at the failing statement
+    pub diagnostics: Vec<Diagnostic>,
+    pub statistics: RunStatistics,
+}
+
+impl RunOutcome {
+    /// Whether the program ran to the end
+    pub fn succeeded(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// The work a run did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RunStatistics {
+    /// Statements started, including those in blocks
+    pub statements: usize,
+    /// Expression nodes evaluated
+    pub steps: usize,
+}
+
+/// Compiles and runs a program in `env`, keeping the bindings it makes, and
+/// reports the result as a whole instead of stopping at the first problem
+/// found. Source is lexed strictly, as by [`crate::compile`], so malformed
+/// literals and illegal characters are reported as such.
+pub fn run_source(source: &str, env: &mut Environment) -> RunOutcome {
+    let program = match crate::compile(source) {
+        Ok(program) => program,
+        Err(error) => {
+            return RunOutcome {
+                value: None,
+                diagnostics: error.diagnostics(),
+                statistics: RunStatistics::default(),
+            };
+        }
+    };
+
+    let mut machine = Machine::new(env);
+    let result = machine.statements(&program.statements);
+    let statistics = machine.statistics;
+    let failing = machine.statement;
+    match result {
+        Ok(value) => RunOutcome {
+            value,
+            diagnostics: Vec::new(),
+            statistics,
+        },
+        Err(error) => {
+            let mut tokens = Lexer::new(source).tokenize_spanned();
+            tokens.pop();
+            let span = statement_spans(&program, &tokens).get(&failing).copied();
+            RunOutcome {
+                value: None,
+                diagnostics: vec![Diagnostic::new(span, error.to_string())],
+                statistics,
+            }
+        }
+    }
+}
+
+// Runs code against an environment, counting the work it does
+struct Machine<'a> {
+    env: &'a mut Environment,
+    statistics: RunStatistics,
+    /// Path to the statement being run, left at the one that fails
+    statement: Vec<usize>,
+    arithmetic: Option<&'a dyn Arithmetic>,
+}
+
+impl<'a> Machine<'a> {
+    fn new(env: &'a mut Environment) -> Self {
+        Self {
+            env,
+            statistics: RunStatistics::default(),
+            statement: Vec::new(),
+            arithmetic: None,
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) -> Result<Option<Value>, RuntimeError> {
+        let mut last = None;
+        for (index, stmt) in statements.iter().enumerate() {
+            self.statement.push(index);
+            self.statistics.statements += 1;
+            last = match stmt {
+                Stmt::Let { name, value, .. } => {
+                    let value = self.expression(value)?;
+                    self.env.define(name, value);
+                    None
+                }
+                Stmt::Destructure { pattern, value, .. } => {
+                    let value = self.expression(value)?;
+                    if !self.destructure(pattern, &value) {
+                        return Err(RuntimeError::PatternMismatch {
+                            pattern: pattern.clone(),
+                            value,
+                        });
+                    }
+                    None
+                }
+                Stmt::Expression(expr) => Some(self.expression(expr)?),
+                Stmt::Block(statements) => {
+                    // The block's bindings end with it
+                    let outer = self.env.snapshot();
+                    let value = self.statements(statements);
+                    self.env.restore(&outer);
+                    value?
+                }
+            };
+            self.statement.pop();
+        }
+        Ok(last)
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.statistics.steps += 1;
+        match expr {
+            Expr::Number(value) => Ok(Value::Int(*value)),
+            Expr::Float(value) => Ok(Value::Float(*value)),
+            Expr::BigNumber(digits) => big_number(digits),
+            Expr::Suffixed { value, suffix } => Ok(Value::Quantity {
+                value: *value,
+                suffix: suffix.clone(),
+            }),
+            Expr::Identifier(name) => self
+                .env
+                .get(name)
+                .cloned()
+                .ok_or_else(|| RuntimeError::Unbound(name.clone())),
+            Expr::Grouping(inner) => self.expression(inner),
+            Expr::Tuple(elements) => Ok(Value::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect::<Result<_, _>>()?,
+            )),
+            Expr::Unary { operator, operand } => unary(operator, self.expression(operand)?),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.expression(left)?;
+                let right = self.expression(right)?;
+                match self
+                    .arithmetic
+                    .and_then(|arithmetic| arithmetic.binary(operator, &left, &right))
+                {
+                    Some(result) => result,
+                    None => binary(operator, left, right),
+                }
+            }
+        }
+    }
+
+    // Binds the names in `pattern` if it matches, which it must as a whole
+    // before anything is bound
+    fn destructure(&mut self, pattern: &Pattern, value: &Value) -> bool {
+        let mut bindings = Vec::new();
+        if !matches(pattern, value, &mut bindings) {
+            return false;
+        }
+        for (name, value) in bindings {
+            self.env.define(name, value.clone());
+        }
+        true
+    }
+}
+
+fn matches<'a>(
+    pattern: &'a Pattern,
+    value: &'a Value,
+    bindings: &mut Vec<(&'a str, &'a Value)>,
+) -> bool {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Literal(expected), Value::Int(actual)) => expected == actual,
+        (Pattern::Identifier(name), _) => {
+            bindings.push((name, value));
+            true
+        }
+        (Pattern::Tuple(patterns), Value::Tuple(values)) if patterns.len() == values.len() => {
+            patterns
+                .iter()
+                .zip(values)
+                .all(|(pattern, value)| matches(pattern, value, bindings))
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn unary(operator: &UnaryOp, operand: Value) -> Result<Value, RuntimeError> {
+    match (operator, operand) {
+        (UnaryOp::Plus, operand @ (Value::Int(_) | Value::Float(_) | Value::Quantity { .. })) => {
+            Ok(operand)
+        }
+        (UnaryOp::Negate, Value::Float(value)) => Ok(Value::Float(-value)),
+        #[cfg(feature = "bigint")]
+        (UnaryOp::Negate, Value::BigInt(value)) => Ok(crate::bigint::integer(-value)),
+        (UnaryOp::Negate, Value::Int(value)) => value
+            .checked_neg()
+            .map(Value::Int)
+            .ok_or(RuntimeError::Overflow),
+        (UnaryOp::Negate, Value::Quantity { value, suffix }) => value
+            .checked_neg()
+            .map(|value| Value::Quantity { value, suffix })
+            .ok_or(RuntimeError::Overflow),
+        (operator, operand) => Err(RuntimeError::Unsupported {
+            operator: operator.to_string(),
+            operands: vec![operand],
+        }),
+    }
+}
+
+pub(crate) fn binary(
+    operator: &BinaryOp,
+    left: Value,
+    right: Value,
+) -> Result<Value, RuntimeError> {
+    let apply = |a: i64, b: i64| {
+        apply_binary(operator, a, b).ok_or(match (operator, b) {
+            (BinaryOp::Divide, 0) => RuntimeError::DivisionByZero,
+            _ => RuntimeError::Overflow,
+        })
+    };
+    let quantity = |value, suffix: &String| Value::Quantity {
+        value,
+        suffix: suffix.clone(),
+    };
+
+    match (&left, operator, &right) {
+        (Value::Int(a), _, Value::Int(b)) => apply(*a, *b).map(Value::Int),
+        #[cfg(feature = "bigint")]
+        (Value::Int(_) | Value::BigInt(_), _, Value::Int(_) | Value::BigInt(_)) => {
+            crate::bigint::binary(operator, big(left), big(right))
+        }
+        (Value::Int(_) | Value::Float(_), _, Value::Int(_) | Value::Float(_)) => {
+            float_binary(operator, as_float(&left), as_float(&right))
+        }
+        #[cfg(feature = "bigint")]
+        (Value::BigInt(_), _, Value::Float(_)) | (Value::Float(_), _, Value::BigInt(_)) => {
+            float_binary(operator, as_float(&left), as_float(&right))
+        }
+        (
+            Value::Quantity { value: a, suffix },
+            BinaryOp::Add | BinaryOp::Subtract,
+            Value::Quantity {
+                value: b,
+                suffix: other,
+            },
+        ) if suffix == other => Ok(quantity(apply(*a, *b)?, suffix)),
+        (
+            Value::Quantity { value: a, suffix },
+            BinaryOp::Divide,
+            Value::Quantity {
+                value: b,
+                suffix: other,
+            },
+        ) if suffix == other => apply(*a, *b).map(Value::Int),
+        (
+            Value::Quantity { value: a, suffix },
+            BinaryOp::Multiply | BinaryOp::Divide,
+            Value::Int(b),
+        ) => Ok(quantity(apply(*a, *b)?, suffix)),
+        (Value::Int(a), BinaryOp::Multiply, Value::Quantity { value: b, suffix }) => {
+            Ok(quantity(apply(*a, *b)?, suffix))
+        }
+        _ => Err(RuntimeError::Unsupported {
+            operator: operator.to_string(),
+            operands: vec![left, right],
+        }),
+    }
+}
+
+/// An expression evaluated step by step, from
+/// [`Environment::trace_evaluation`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationTrace {
+    /// The expression before the first step, without groupings
+    pub start: Expr,
+    pub steps: Vec<Step>,
+    /// Why evaluation stopped before reaching a value, if it did
+    pub error: Option<RuntimeError>,
+}
+
+/// One reduction of an expression being evaluated step by step
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// The whole expression after the reduction
+    pub expr: Expr,
+    /// The part of the expression before the reduction that was replaced by
+    /// its value: a name, or an operator applied to values, as in `2 * 3`
+    pub redex: Expr,
+}
+
+impl Step {
+    /// The rule the step applied, as in `multiply` or `look up`
+    pub fn rule(&self) -> String {
+        match &self.redex {
+            Expr::Identifier(_) => "look up".to_string(),
+            Expr::Unary {
+                operator: UnaryOp::Negate,
+                ..
+            } => "negate".to_string(),
+            Expr::Unary {
+                operator: UnaryOp::Plus,
+                ..
+            } => "unary plus".to_string(),
+            Expr::Binary { operator, .. } => format!("{:?}", operator).to_lowercase(),
+            _ => "evaluate".to_string(),
+        }
+    }
+}
+
+impl EvaluationTrace {
+    /// The value evaluation reached, if it did not fail
+    pub fn value(&self) -> Option<Value> {
+        match self.error {
+            Some(_) => None,
+            None => value_of(self.steps.last().map_or(&self.start, |step| &step.expr)),
+        }
+    }
+
+    /// Renders the trace one expression per line, each after the first with
+    /// the rule that produced it and what it reduced, as in
+    /// `= 1 + 6    multiply 2 * 3`. A failure is marked on the last line.
+    pub fn render(&self) -> String {
+        let lines: Vec<(String, String)> = self
+            .steps
+            .iter()
+            .map(|step| {
+                let reduced = format!("{} {}", step.rule(), format::expression(&step.redex));
+                (format::expression(&step.expr), reduced)
+            })
+            .collect();
+        let width = lines
+            .iter()
+            .map(|(expr, _)| expr.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut out = format!("  {}\n", format::expression(&self.start));
+        for (expr, reduced) in lines {
+            let _ = writeln!(out, "= {:width$}    {}", expr, reduced, width = width);
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(out, "✗ {}", error);
+        }
+        out
+    }
+}
+
+// An integer or float as a float
+fn as_float(value: &Value) -> f64 {
+    match value {
+        Value::Int(value) => *value as f64,
+        Value::Float(value) => *value,
+        #[cfg(feature = "bigint")]
+        Value::BigInt(value) => num_traits::ToPrimitive::to_f64(value).unwrap_or(f64::NAN),
+        _ => f64::NAN,
+    }
+}
+
+// An integer of any size as a big integer
+#[cfg(feature = "bigint")]
+fn big(value: Value) -> crate::bigint::BigInt {
+    match value {
+        Value::Int(value) => value.into(),
+        Value::BigInt(value) => value,
+        _ => unreachable!("only integers are made big"),
+    }
+}
+
+// Float arithmetic, failing where it would produce an infinity or NaN rather
+// than a number, as integer arithmetic does
+fn float_binary(operator: &BinaryOp, a: f64, b: f64) -> Result<Value, RuntimeError> {
+    let result = match operator {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide if b == 0.0 => return Err(RuntimeError::DivisionByZero),
+        BinaryOp::Divide => a / b,
+    };
+    match result.is_finite() {
+        true => Ok(Value::Float(result)),
+        false => Err(RuntimeError::Overflow),
+    }
+}
+
+// Folds the negation of a number literal into a negative number, so that a
+// negative value reads as one rather than as a step still to take
+fn negate_literals(expr: &mut Expr) {
+    match expr {
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => match operand.as_ref() {
+            Expr::Number(value) if *value >= 0 => *expr = Expr::Number(-value),
+            Expr::Float(value) if value.is_sign_positive() => *expr = Expr::Float(-value),
+            _ => negate_literals(operand),
+        },
+        Expr::Unary { operand, .. } | Expr::Grouping(operand) => negate_literals(operand),
+        Expr::Binary { left, right, .. } => {
+            negate_literals(left);
+            negate_literals(right);
+        }
+        Expr::Tuple(elements) => elements.iter_mut().for_each(negate_literals),
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => {}
+    }
+}
+
+// The value of an integer literal beyond 64 bits, which only builds with the
+// `bigint` feature can hold
+pub(crate) fn big_number(digits: &str) -> Result<Value, RuntimeError> {
+    #[cfg(feature = "bigint")]
+    if let Ok(value) = digits.parse() {
+        return Ok(crate::bigint::integer(value));
+    }
+    let _ = digits;
+    Err(RuntimeError::Overflow)
+}
+
+// The value an expression stands for, if it is written as one
+fn value_of(expr: &Expr) -> Option<Value> {
+    match expr {
+        Expr::Number(value) => Some(Value::Int(*value)),
+        Expr::Float(value) => Some(Value::Float(*value)),
+        Expr::BigNumber(digits) => big_number(digits).ok(),
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } if matches!(operand.as_ref(), Expr::BigNumber(_)) => {
+            unary(&UnaryOp::Negate, value_of(operand)?).ok()
+        }
+        Expr::Suffixed { value, suffix } => Some(Value::Quantity {
+            value: *value,
+            suffix: suffix.clone(),
+        }),
+        Expr::Tuple(elements) => elements
+            .iter()
+            .map(value_of)
+            .collect::<Option<_>>()
+            .map(Value::Tuple),
+        _ => None,
+    }
+}
+
+// The expression that stands for a value
+fn expression_of(value: &Value) -> Expr {
+    match value {
+        Value::Int(value) => Expr::number(*value),
+        Value::Float(value) => Expr::Float(*value),
+        Value::Quantity { value, suffix } => Expr::suffixed(*value, suffix.clone()),
+        Value::Tuple(elements) => Expr::tuple(elements.iter().map(expression_of).collect()),
+        // Literals are unsigned, so negative big integers are negated ones
+        #[cfg(feature = "bigint")]
+        Value::BigInt(value) => {
+            let digits = Expr::BigNumber(value.magnitude().to_string());
+            match value.sign() {
+                num_bigint::Sign::Minus => Expr::unary(UnaryOp::Negate, digits),
+                _ => digits,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Span;
+    use crate::parse_source;
+    use crate::suffix::parse_with_suffixes;
+
+    fn run(source: &str) -> Result<Option<Value>, RuntimeError> {
+        Environment::new().run(&parse_source(source).unwrap())
+    }
+
+    #[test]
+    fn test_last_expression_is_the_result() {
+        assert_eq!(run("let x = 6; x * 7;"), Ok(Some(Value::Int(42))));
+        assert_eq!(
+            run("let x = 1; { let x = 2; (x, -x); }")
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "(2, -2)"
+        );
+        assert_eq!(run("1; let y = 2;"), Ok(None));
+    }
+
+    #[test]
+    fn test_bindings_in_definition_order() {
+        let mut env = Environment::new();
+        env.define("n", Value::Int(5));
+        env.run(&parse_source("let (a, _) = (n, 2); { let b = 1; } let n = a + 1;").unwrap())
+            .unwrap();
+
+        let bindings: Vec<String> = env
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        assert_eq!(bindings, vec!["a = 5", "n = 6"]);
+
+        env.clear();
+        assert!(env.bindings().is_empty());
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let error = |source| run(source).unwrap_err().to_string();
+
+        assert_eq!(error("y + 1;"), "`y` is not bound");
+        assert_eq!(error("let z = 0; 1 / z;"), "division by zero");
+        assert_eq!(
+            error("9223372036854775807 + 1;"),
+            "arithmetic overflowed a 64-bit integer"
+        );
+        assert_eq!(
+            error("(1, 2) * 3;"),
+            "`*` cannot be applied to `(1, 2)` and `3`"
+        );
+        assert_eq!(
+            error("let (a, b) = (1, 2, 3);"),
+            "`(1, 2, 3)` does not match `(a, b)`"
+        );
+    }
+
+    #[test]
+    fn test_restoring_a_snapshot_branches_from_it() {
+        let mut env = Environment::new();
+        env.run(&parse_source("let rate = 3; let base = 100;").unwrap())
+            .unwrap();
+        let base = env.snapshot();
+
+        let what_if = parse_source("let rate = 5; base * rate;").unwrap();
+        assert_eq!(env.run(&what_if), Ok(Some(Value::Int(500))));
+        env.restore(&base);
+        assert_eq!(env.get("rate"), Some(&Value::Int(3)));
+
+        let mut branch = Environment::new();
+        branch.restore(&base);
+        branch.define("base", Value::Int(1));
+        assert_eq!(branch.get("base"), Some(&Value::Int(1)));
+        assert_eq!(env.get("base"), Some(&Value::Int(100)));
+    }
+
+    #[test]
+    fn test_long_environments_drop() {
+        let mut env = Environment::new();
+        for value in 0..200_000 {
+            env.define("x", Value::Int(value));
+        }
+        let snapshot = env.snapshot();
+        drop(env);
+        drop(snapshot);
+    }
+
+    #[test]
+    fn test_formatter_limits_large_values() {
+        let numbers = Value::Tuple((0..1000).map(Value::Int).collect());
+        let formatter = ValueFormatter {
+            max_items: 3,
+            max_depth: 2,
+        };
+        assert_eq!(formatter.format(&numbers), "(0, 1, 2, … 997 more items)");
+
+        let nested = run("((1, (2, (3,))), ());");
+        let nested = Value::Tuple(vec![nested.unwrap().unwrap(), Value::Tuple(Vec::new())]);
+        assert_eq!(formatter.format(&nested), "(((…), ()), ())");
+        assert_eq!(
+            ValueFormatter::default().format(&nested),
+            "(((1, (2, (3,))), ()), ())"
+        );
+
+        let four = Value::Tuple((0..4).map(Value::Int).collect());
+        assert_eq!(formatter.format(&four), "(0, 1, 2, … 1 more item)");
+    }
+
+    #[test]
+    fn test_run_outcome() {
+        let mut env = Environment::new();
+        let outcome = run_source("let x = 2; { x * (3 + 4); }", &mut env);
+        assert_eq!(outcome.value, Some(Value::Int(14)));
+        assert!(outcome.succeeded());
+        assert_eq!(
+            outcome.statistics,
+            RunStatistics {
+                statements: 3,
+                steps: 7
+            }
+        );
+
+        let source = "let y = x; { let z = y - 2; 1 / z; }";
+        let outcome = run_source(source, &mut env);
+        assert_eq!(outcome.value, None);
+        assert_eq!(outcome.diagnostics.len(), 1);
+        let span = outcome.diagnostics[0].span.unwrap();
+        assert_eq!(&source[span.start..span.end], "1 / z;");
+        assert_eq!(outcome.diagnostics[0].message, "division by zero");
+        assert_eq!(env.get("y"), Some(&Value::Int(2)));
+        assert_eq!(env.get("z"), None);
+
+        let outcome = run_source("let = 1;", &mut env);
+        assert!(!outcome.succeeded());
+        assert_eq!(outcome.statistics, RunStatistics::default());
+    }
+
+    #[test]
+    fn test_run_outcome_reports_lex_errors() {
+        let mut env = Environment::new();
+
+        let outcome = run_source("9223372036854775808;", &mut env);
+        #[cfg(feature = "bigint")]
+        assert!(outcome.succeeded());
+        #[cfg(not(feature = "bigint"))]
+        {
+            assert_eq!(outcome.diagnostics.len(), 1);
+            assert_eq!(
+                outcome.diagnostics[0].message,
+                "Integer literal 9223372036854775808 at 0..19 does not fit in 64 bits"
+            );
+            assert_eq!(outcome.diagnostics[0].span, Some(Span::new(0, 19)));
+        }
+
+        let outcome = run_source("1 + $;", &mut env);
+        assert_eq!(
+            outcome.diagnostics[0].message,
+            "Illegal character '$' at 4..5"
+        );
+        assert_eq!(outcome.diagnostics[0].span, Some(Span::new(4, 5)));
+        // The message names the span already
+        assert_eq!(
+            outcome.diagnostics[0].to_string(),
+            "Illegal character '$' at 4..5"
+        );
+        assert_eq!(outcome.statistics, RunStatistics::default());
+    }
+
+    #[test]
+    fn test_quantities_follow_their_types() {
+        let units = LiteralSuffixes::new()
+            .register("px", "Length")
+            .register("s", "Duration");
+        let run = |source| {
+            let program = parse_with_suffixes(source, &units).unwrap();
+            Environment::new().run(&program).map(|value| value.unwrap())
+        };
+
+        let width = run("(10px + 2px) * 3;").unwrap();
+        assert_eq!(width.to_string(), "36px");
+        assert_eq!(width.type_of(&units), Type::Named("Length".to_string()));
+        assert_eq!(run("10px / 5px;"), Ok(Value::Int(2)));
+        assert!(run("1px + 1s;").is_err());
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        assert_eq!(run("1 + 0.5;"), Ok(Some(Value::Float(1.5))));
+        assert_eq!(run("let r = 2.0; -r * 3;"), Ok(Some(Value::Float(-6.0))));
+        assert_eq!(run("7 / 2.0;").unwrap().unwrap().to_string(), "3.5");
+        assert_eq!(run("1.5 / 0;"), Err(RuntimeError::DivisionByZero));
+        assert_eq!(run("1e300 * 1e300;"), Err(RuntimeError::Overflow));
+        assert_eq!(run("7 / 2;"), Ok(Some(Value::Int(3))));
+    }
+
+    fn trace(env: &Environment, source: &str) -> EvaluationTrace {
+        match parse_source(source).unwrap().statements.remove(0) {
+            Stmt::Expression(expr) => env.trace_evaluation(&expr),
+            other => panic!("expected an expression statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_evaluation_steps() {
+        let mut env = Environment::new();
+        env.define("x", Value::Int(4));
+
+        assert_eq!(
+            trace(&env, "1 + 2 * 3;").render(),
+            "  1 + 2 * 3
+= 1 + 6    multiply 2 * 3
+= 7        add 1 + 6
+"
+        );
+        assert_eq!(
+            trace(&env, "(1 - x) * -(x, +2);").render(),
+            "  (1 - x) * -(x, +2)
+= (1 - 4) * -(x, +2)    look up x
+= -3 * -(x, +2)         subtract 1 - 4
+= -3 * -(4, +2)         look up x
+= -3 * -(4, 2)          unary plus +2
+✗ `-` cannot be applied to `(4, 2)`
+"
+        );
+
+        let steps = trace(&env, "-x + --3;");
+        assert_eq!(
+            steps
+                .steps
+                .iter()
+                .map(|step| step.rule())
+                .collect::<Vec<_>>(),
+            vec!["look up", "negate", "add"]
+        );
+        assert_eq!(steps.value(), Some(Value::Int(-1)));
+    }
+
+    #[test]
+    fn test_values_take_no_steps() {
+        let steps = trace(&Environment::new(), "(-1, (2,), ());");
+
+        assert!(steps.steps.is_empty());
+        assert_eq!(steps.render(), "  (-1, (2,), ())\n");
+        assert_eq!(
+            trace(&Environment::new(), "1 / (2 - 2);").error,
+            Some(RuntimeError::DivisionByZero)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Alternative spellings of keywords, for embedders whose users expect their
+//! own wording, such as `set x = 1;` in place of `let x = 1;`. Aliases are
+//! resolved by the lexer, so the parser and everything after it see the
+//! ordinary keyword.
+
+use crate::error::Error;
+use crate::lexer::{KEYWORDS, Lexer};
+use crate::parser::{Parser, Program};
+use std::collections::BTreeMap;
+
+/// Alternative spellings of keywords, none by default. The keywords
+/// themselves keep working next to their aliases.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeywordAliases {
+    keywords: BTreeMap<String, String>,
+}
+
+impl KeywordAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lexes `alias` as `keyword`. The alias must be spelled like an
+    /// identifier that is not a keyword, and `keyword` must be one; anything
+    /// else is ignored. An alias is no longer usable as a name.
+    pub fn register(mut self, alias: &str, keyword: &str) -> Self {
+        let mut chars = alias.chars();
+        let identifier = chars
+            .next()
+            .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && chars.all(|ch| ch.is_alphanumeric() || ch == '_');
+        if identifier && !KEYWORDS.contains(&alias) && KEYWORDS.contains(&keyword) {
+            self.keywords.insert(alias.to_string(), keyword.to_string());
+        }
+        self
+    }
+
+    /// Returns the keyword an alias stands for
+    pub fn keyword(&self, alias: &str) -> Option<&str> {
+        self.keywords.get(alias).map(String::as_str)
+    }
+
+    /// Returns every alias with its keyword, in sorted order of the aliases
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.keywords
+            .iter()
+            .map(|(alias, keyword)| (alias.as_str(), keyword.as_str()))
+    }
+}
+
+/// Lexes strictly and parses `source`, accepting the registered aliases
+pub fn parse_with_aliases(source: &str, aliases: &KeywordAliases) -> Result<Program, Error> {
+    let parse = || -> Result<Program, Error> {
+        let tokens = Lexer::new(source)
+            .with_keyword_aliases(aliases)
+            .tokenize_strict()?;
+        Ok(Parser::from_spanned(tokens).parse()?)
+    };
+    parse().map_err(|error| error.with_source(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Token;
+
+    #[test]
+    fn test_aliases_lex_as_their_keyword() {
+        let aliases = KeywordAliases::new().register("set", "let");
+        let program = parse_with_aliases("set x = 1; let y = x;", &aliases).unwrap();
+
+        assert_eq!(program.to_string(), "let x = 1;\nlet y = x;\n");
+        assert_eq!(
+            Lexer::new("set")
+                .with_keyword_aliases(&aliases)
+                .next_token(),
+            Token::Let
+        );
+    }
+
+    #[test]
+    fn test_aliases_are_off_by_default() {
+        assert_eq!(
+            Lexer::new("set").next_token(),
+            Token::Ident("set".to_string())
+        );
+        assert!(parse_with_aliases("set x = 1;", &KeywordAliases::new()).is_err());
+    }
+
+    #[test]
+    fn test_registration() {
+        let aliases = KeywordAliases::new()
+            .register("soit", "let")
+            .register("set", "let")
+            .register("let", "let")
+            .register("def", "fn")
+            .register("2x", "let");
+
+        assert_eq!(
+            aliases.aliases().collect::<Vec<_>>(),
+            vec![("set", "let"), ("soit", "let")]
+        );
+        assert_eq!(aliases.keyword("def"), None);
+    }
+}
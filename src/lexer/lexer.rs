@@ -1,9 +1,19 @@
+use super::span::Span;
+use crate::keywords::KeywordAliases;
+use crate::suffix::LiteralSuffixes;
 use std::fmt;
 
+/// Reserved words of the language
+pub const KEYWORDS: &[&str] = &["let"];
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     // Literals
     Number(i64),
+    Float(f64),
+    /// An integer literal too large for 64 bits, as its digits; with the
+    /// `bigint` feature only, as such literals are otherwise illegal
+    BigNumber(String),
     Ident(String),
 
     // Keywords
@@ -15,6 +25,7 @@ pub enum Token {
     Minus,
     Multiply,
     Divide,
+    Percent,
 
     // Delimiters
     Semicolon,
@@ -22,6 +33,15 @@ pub enum Token {
     RightParen,
     LeftBrace,
     RightBrace,
+    Comma,
+    Colon,
+    Dot,
+    Arrow,
+    FatArrow,
+    At,
+
+    // Comments
+    DocComment(String),
 
     // Special
     EOF,
@@ -32,6 +52,8 @@ impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::Number(n) => write!(f, "{}", n),
+            Token::Float(n) => write!(f, "{:?}", n),
+            Token::BigNumber(digits) => write!(f, "{}", digits),
             Token::Ident(s) => write!(f, "{}", s),
             Token::Let => write!(f, "let"),
             Token::Equals => write!(f, "="),
@@ -39,52 +61,185 @@ impl fmt::Display for Token {
             Token::Minus => write!(f, "-"),
             Token::Multiply => write!(f, "*"),
             Token::Divide => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
             Token::Semicolon => write!(f, ";"),
             Token::LeftParen => write!(f, "("),
             Token::RightParen => write!(f, ")"),
             Token::LeftBrace => write!(f, "{{"),
             Token::RightBrace => write!(f, "}}"),
+            Token::Comma => write!(f, ","),
+            Token::Colon => write!(f, ":"),
+            Token::Dot => write!(f, "."),
+            Token::Arrow => write!(f, "->"),
+            Token::FatArrow => write!(f, "=>"),
+            Token::At => write!(f, "@"),
+            Token::DocComment(text) if text.is_empty() => write!(f, "///"),
+            Token::DocComment(text) => write!(f, "/// {}", text),
             Token::EOF => write!(f, "EOF"),
             Token::Illegal(c) => write!(f, "ILLEGAL({})", c),
         }
     }
 }
 
-#[derive(Debug)]
+impl Token {
+    /// Returns the source text that lexes back to this token. Unlike Display,
+    /// illegal characters are spelled as themselves and EOF as nothing.
+    pub fn spelling(&self) -> String {
+        match self {
+            Token::Illegal(c) => c.to_string(),
+            Token::EOF => String::new(),
+            token => token.to_string(),
+        }
+    }
+}
+
+/// Spellings of every operator and delimiter. Multi-character operators only
+/// need an entry here: when several spellings match, the longest one wins.
+const OPERATORS: &[(&str, Token)] = &[
+    ("=", Token::Equals),
+    ("+", Token::Plus),
+    ("-", Token::Minus),
+    ("*", Token::Multiply),
+    ("/", Token::Divide),
+    ("%", Token::Percent),
+    (";", Token::Semicolon),
+    ("(", Token::LeftParen),
+    (")", Token::RightParen),
+    ("{", Token::LeftBrace),
+    ("}", Token::RightBrace),
+    (",", Token::Comma),
+    (":", Token::Colon),
+    (".", Token::Dot),
+    ("->", Token::Arrow),
+    ("=>", Token::FatArrow),
+    ("@", Token::At),
+];
+
+/// Finds the longest spelling in `table` that starts `input`, returning its
+/// length in characters and its token (maximal munch)
+fn match_operator(table: &[(&str, Token)], input: &[char]) -> Option<(usize, Token)> {
+    table
+        .iter()
+        .filter(|(spelling, _)| {
+            let length = spelling.chars().count();
+            input.len() >= length && spelling.chars().zip(input).all(|(a, b)| a == *b)
+        })
+        .max_by_key(|(spelling, _)| spelling.chars().count())
+        .map(|(spelling, token)| (spelling.chars().count(), token.clone()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LexError {
-    InvalidNumber(String),
+    /// A number literal too large for 64 bits, or a float beyond `f64`
+    InvalidNumber {
+        literal: String,
+        span: Span,
+    },
     UnterminatedString,
+    IllegalCharacter {
+        ch: char,
+        span: Span,
+    },
+    /// A number runs straight into identifier characters, as in `123abc`
+    InvalidNumberSuffix {
+        suffix: String,
+        span: Span,
+    },
+}
+
+impl LexError {
+    /// Returns the span of source text the error refers to, when known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LexError::InvalidNumber { span, .. }
+            | LexError::IllegalCharacter { span, .. }
+            | LexError::InvalidNumberSuffix { span, .. } => Some(*span),
+            LexError::UnterminatedString => None,
+        }
+    }
 }
 
 impl fmt::Display for LexError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LexError::InvalidNumber(s) => write!(f, "Invalid number: {}", s),
+            LexError::InvalidNumber { literal, span } if literal.contains(['.', 'e', 'E']) => {
+                write!(f, "Float literal {} at {} is out of range", literal, span)
+            }
+            LexError::InvalidNumber { literal, span } => {
+                write!(
+                    f,
+                    "Integer literal {} at {} does not fit in 64 bits",
+                    literal, span
+                )
+            }
             LexError::UnterminatedString => write!(f, "Unterminated string literal"),
+            LexError::IllegalCharacter { ch, span } => {
+                write!(f, "Illegal character '{}' at {}", ch, span)
+            }
+            LexError::InvalidNumberSuffix { suffix, span } => {
+                write!(f, "Invalid numeric literal suffix '{}' at {}", suffix, span)
+            }
         }
     }
 }
 
 impl std::error::Error for LexError {}
 
+/// A saved lexer position that can be restored to rewind the lexer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    position: usize,
+    offset: usize,
+}
+
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
+    offset: usize,
     current_char: Option<char>,
+    /// Names accepted directly after a number by `tokenize_strict`
+    suffixes: Vec<String>,
+    /// Whether `tokenize_strict` accepts any name directly after a number
+    implicit_multiplication: bool,
+    aliases: KeywordAliases,
 }
 
 impl Lexer {
     pub fn new(input: &str) -> Self {
         let chars: Vec<char> = input.chars().collect();
-        let current_char = chars.get(0).copied();
+        let current_char = chars.first().copied();
 
         Self {
             input: chars,
             position: 0,
+            offset: 0,
             current_char,
+            suffixes: Vec::new(),
+            implicit_multiplication: false,
+            aliases: KeywordAliases::new(),
         }
     }
 
+    /// Lets `tokenize_strict` accept the registered literal suffixes, lexing
+    /// `10px` as a number followed by the identifier `px`
+    pub fn with_suffixes(mut self, suffixes: &LiteralSuffixes) -> Self {
+        self.suffixes = suffixes.suffixes().map(String::from).collect();
+        self
+    }
+
+    /// Lets `tokenize_strict` accept any name directly after a number, as in
+    /// `2x`, for parsers that read it as a multiplication
+    pub fn with_implicit_multiplication(mut self) -> Self {
+        self.implicit_multiplication = true;
+        self
+    }
+
+    /// Lexes the registered aliases as the keywords they stand for
+    pub fn with_keyword_aliases(mut self, aliases: &KeywordAliases) -> Self {
+        self.aliases = aliases.clone();
+        self
+    }
+
     /// Returns the current character without advancing the position
     fn peek(&self) -> Option<char> {
         self.current_char
@@ -99,6 +254,7 @@ impl Lexer {
     fn advance(&mut self) -> Option<char> {
         let current = self.current_char;
         self.position += 1;
+        self.offset += current.map_or(0, char::len_utf8);
         self.current_char = self.input.get(self.position).copied();
         current
     }
@@ -139,21 +295,89 @@ impl Lexer {
         self.skip_while(|ch| ch.is_whitespace());
     }
 
-    /// Reads a number token
-    fn read_number(&mut self) -> Result<Token, LexError> {
-        let number_str = self.collect_while(|ch| ch.is_ascii_digit());
+    /// Skips whitespace and `//` line comments, stopping at doc comments
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_whitespace();
 
+            if self.peek() == Some('/') && self.peek_ahead(1) == Some('/') && !self.at_doc_comment()
+            {
+                self.skip_while(|ch| ch != '\n');
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Returns true if the input at the current position starts a `///` doc comment.
+    /// As in Rust, four or more slashes make an ordinary comment.
+    fn at_doc_comment(&self) -> bool {
+        self.peek() == Some('/')
+            && self.peek_ahead(1) == Some('/')
+            && self.peek_ahead(2) == Some('/')
+            && self.peek_ahead(3) != Some('/')
+    }
+
+    /// Reads a `///` doc comment, dropping the marker and a single leading space
+    fn read_doc_comment(&mut self) -> Token {
+        for _ in 0..3 {
+            self.advance();
+        }
+        if self.peek() == Some(' ') {
+            self.advance();
+        }
+
+        let text = self.collect_while(|ch| ch != '\n');
+        Token::DocComment(text.trim_end().to_string())
+    }
+
+    /// Reads a number token. An integer literal too large for an i64 becomes
+    /// a BigNumber with the `bigint` feature, and otherwise an Illegal token
+    /// holding its first digit and spanning all of them.
+    fn read_number(&mut self) -> Token {
+        let start = self.position;
+        let mut number_str = self.collect_while(|ch| ch.is_ascii_digit());
+        let is_digit = |ch: Option<char>| ch.is_some_and(|ch| ch.is_ascii_digit());
+
+        // A fraction needs digits after the point, so `1.x` stays a number
+        // and a dot, and an exponent needs digits after `e` and its sign, so
+        // `2em` stays a number and a name
+        let mut is_float = false;
+        if self.peek() == Some('.') && is_digit(self.peek_ahead(1)) {
+            number_str.push(self.advance().unwrap_or('.'));
+            number_str.push_str(&self.collect_while(|ch| ch.is_ascii_digit()));
+            is_float = true;
+        }
+        let signed = matches!(self.peek_ahead(1), Some('+' | '-'));
+        if matches!(self.peek(), Some('e' | 'E'))
+            && is_digit(self.peek_ahead(if signed { 2 } else { 1 }))
+        {
+            for _ in 0..if signed { 2 } else { 1 } {
+                number_str.extend(self.advance());
+            }
+            number_str.push_str(&self.collect_while(|ch| ch.is_ascii_digit()));
+            is_float = true;
+        }
+
+        if is_float {
+            return match number_str.parse::<f64>() {
+                Ok(num) if num.is_finite() => Token::Float(num),
+                _ => Token::Illegal(self.input[start]),
+            };
+        }
         match number_str.parse::<i64>() {
-            Ok(num) => Ok(Token::Number(num)),
-            Err(_) => Err(LexError::InvalidNumber(number_str)),
+            Ok(num) => Token::Number(num),
+            Err(_) if cfg!(feature = "bigint") => Token::BigNumber(number_str),
+            Err(_) => Token::Illegal(self.input[start]),
         }
     }
 
     /// Reads an identifier or keyword
     fn read_identifier(&mut self) -> Token {
         let ident = self.collect_while(|ch| ch.is_alphanumeric() || ch == '_');
+        let keyword = self.aliases.keyword(&ident).unwrap_or(&ident);
 
-        match ident.as_str() {
+        match keyword {
             "let" => Token::Let,
             _ => Token::Ident(ident),
         }
@@ -161,63 +385,38 @@ impl Lexer {
 
     /// Gets the next token from the input
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        self.next_spanned().0
+    }
+
+    /// Gets the next token together with the span of source text it was read from
+    pub fn next_spanned(&mut self) -> (Token, Span) {
+        self.skip_trivia();
+
+        let start = self.offset;
+        let token = self.read_token();
+        (token, Span::new(start, self.offset))
+    }
 
+    /// Reads the token starting at the current character
+    fn read_token(&mut self) -> Token {
         match self.peek() {
             None => Token::EOF,
             Some(ch) => match ch {
-                '=' => {
-                    self.advance();
-                    Token::Equals
-                }
-                '+' => {
-                    self.advance();
-                    Token::Plus
-                }
-                '-' => {
-                    self.advance();
-                    Token::Minus
-                }
-                '*' => {
-                    self.advance();
-                    Token::Multiply
-                }
-                '/' => {
-                    self.advance();
-                    Token::Divide
-                }
-                ';' => {
-                    self.advance();
-                    Token::Semicolon
-                }
-                '(' => {
-                    self.advance();
-                    Token::LeftParen
-                }
-                ')' => {
-                    self.advance();
-                    Token::RightParen
-                }
-                '{' => {
-                    self.advance();
-                    Token::LeftBrace
-                }
-                '}' => {
-                    self.advance();
-                    Token::RightBrace
-                }
-                '0'..='9' => match self.read_number() {
-                    Ok(token) => token,
-                    Err(_) => {
+                '/' if self.at_doc_comment() => self.read_doc_comment(),
+                '0'..='9' => self.read_number(),
+                'a'..='z' | 'A'..='Z' | '_' => self.read_identifier(),
+                _ => match match_operator(OPERATORS, &self.input[self.position..]) {
+                    Some((length, token)) => {
+                        for _ in 0..length {
+                            self.advance();
+                        }
+                        token
+                    }
+                    None => {
                         self.advance();
                         Token::Illegal(ch)
                     }
                 },
-                'a'..='z' | 'A'..='Z' | '_' => self.read_identifier(),
-                _ => {
-                    self.advance();
-                    Token::Illegal(ch)
-                }
             },
         }
     }
@@ -239,6 +438,89 @@ impl Lexer {
         tokens
     }
 
+    /// Tokenizes the entire input, pairing each token with its span
+    pub fn tokenize_spanned(&mut self) -> Vec<(Token, Span)> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let (token, span) = self.next_spanned();
+            let is_eof = token == Token::EOF;
+            tokens.push((token, span));
+
+            if is_eof {
+                break;
+            }
+        }
+
+        tokens
+    }
+
+    /// Tokenizes the entire input like [`Lexer::tokenize_spanned`], but fails
+    /// at the first illegal character or out-of-range integer instead of
+    /// producing an Illegal token, and at the first number directly followed
+    /// by identifier characters other than a suffix registered with
+    /// [`Lexer::with_suffixes`], unless implicit multiplication is on
+    pub fn tokenize_strict(&mut self) -> Result<Vec<(Token, Span)>, LexError> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let (token, span) = self.next_spanned();
+            let is_eof = token == Token::EOF;
+
+            if let Token::Illegal(ch) = token {
+                if ch.is_ascii_digit() {
+                    // Digits are ASCII, so the span's length counts characters too
+                    let literal = self.input[self.position - span.len()..self.position]
+                        .iter()
+                        .collect();
+                    return Err(LexError::InvalidNumber { literal, span });
+                }
+                return Err(LexError::IllegalCharacter { ch, span });
+            }
+            if matches!(
+                token,
+                Token::Number(_) | Token::Float(_) | Token::BigNumber(_)
+            ) && self
+                .peek()
+                .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+            {
+                let checkpoint = self.checkpoint();
+                let suffix = self.collect_while(|ch| ch.is_alphanumeric() || ch == '_');
+                // Suffixes only follow integers
+                let registered =
+                    matches!(token, Token::Number(_)) && self.suffixes.contains(&suffix);
+                if !registered && !self.implicit_multiplication {
+                    let span = Span::new(span.start, self.offset);
+                    return Err(LexError::InvalidNumberSuffix { suffix, span });
+                }
+                // The suffix is lexed again as the identifier that follows
+                self.restore(checkpoint);
+            }
+            tokens.push((token, span));
+
+            if is_eof {
+                break;
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    /// Saves the current position so lexing can later resume from it
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            position: self.position,
+            offset: self.offset,
+        }
+    }
+
+    /// Rewinds (or fast-forwards) the lexer to a previously saved checkpoint
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.position;
+        self.offset = checkpoint.offset;
+        self.current_char = self.input.get(self.position).copied();
+    }
+
     /// Returns the current position in the input
     pub fn position(&self) -> usize {
         self.position
@@ -250,6 +532,50 @@ impl Lexer {
     }
 }
 
+/// Rebuilds source text from spanned tokens. Text between tokens (whitespace
+/// and comments) is copied from `original`, as is the text of every token that
+/// still matches its span, so `tokens_to_source(&Lexer::new(s).tokenize_spanned(), s)`
+/// returns `s` unchanged. Tokens that were replaced, or inserted with an empty
+/// span, are written with their canonical spelling, which allows token-level
+/// rewrites without going through the AST.
+pub fn tokens_to_source(tokens: &[(Token, Span)], original: &str) -> String {
+    let mut out = String::with_capacity(original.len());
+    let mut last_end = 0;
+
+    for (token, span) in tokens {
+        let in_bounds = span.start <= span.end
+            && original.is_char_boundary(span.start)
+            && original.is_char_boundary(span.end)
+            && span.end <= original.len();
+
+        if !in_bounds {
+            out.push_str(&token.spelling());
+            continue;
+        }
+
+        if span.start >= last_end {
+            out.push_str(&original[last_end..span.start]);
+        }
+
+        let text = &original[span.start..span.end];
+        if !span.is_empty() && lexes_to(text, token) {
+            out.push_str(text);
+        } else {
+            out.push_str(&token.spelling());
+        }
+
+        last_end = last_end.max(span.end);
+    }
+
+    out
+}
+
+// Returns true if `text` lexes to exactly `token` and nothing else
+fn lexes_to(text: &str, token: &Token) -> bool {
+    let mut lexer = Lexer::new(text);
+    lexer.next_token() == *token && lexer.next_token() == Token::EOF
+}
+
 impl Iterator for Lexer {
     type Item = Token;
 
@@ -360,11 +686,316 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_line_comments_are_skipped() {
+        let mut lexer = Lexer::new("let x // the answer\n= 42; //// not a doc comment");
+
+        assert_eq!(lexer.next_token(), Token::Let);
+        assert_eq!(lexer.next_token(), Token::Ident("x".to_string()));
+        assert_eq!(lexer.next_token(), Token::Equals);
+        assert_eq!(lexer.next_token(), Token::Number(42));
+        assert_eq!(lexer.next_token(), Token::Semicolon);
+        assert_eq!(lexer.next_token(), Token::EOF);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let mut lexer = Lexer::new("/// First line\n///second  \n///\nlet x = 8 / 2;");
+
+        assert_eq!(
+            lexer.next_token(),
+            Token::DocComment("First line".to_string())
+        );
+        assert_eq!(lexer.next_token(), Token::DocComment("second".to_string()));
+        assert_eq!(lexer.next_token(), Token::DocComment(String::new()));
+        assert_eq!(lexer.next_token(), Token::Let);
+        assert_eq!(lexer.next_token(), Token::Ident("x".to_string()));
+        assert_eq!(lexer.next_token(), Token::Equals);
+        assert_eq!(lexer.next_token(), Token::Number(8));
+        assert_eq!(lexer.next_token(), Token::Divide);
+        assert_eq!(lexer.next_token(), Token::Number(2));
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut lexer = Lexer::new("let é = 10; // note");
+        let tokens = lexer.tokenize_spanned();
+
+        // Spans are byte offsets, so the two-byte 'é' widens everything after it
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Let, Span::new(0, 3)),
+                (Token::Illegal('é'), Span::new(4, 6)),
+                (Token::Equals, Span::new(7, 8)),
+                (Token::Number(10), Span::new(9, 11)),
+                (Token::Semicolon, Span::new(11, 12)),
+                (Token::EOF, Span::new(20, 20)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_and_restore() {
+        let mut lexer = Lexer::new("(a, b) + c");
+        assert_eq!(lexer.next_token(), Token::LeftParen);
+
+        let checkpoint = lexer.checkpoint();
+        assert_eq!(lexer.next_token(), Token::Ident("a".to_string()));
+        assert_eq!(lexer.next_token(), Token::Comma);
+
+        lexer.restore(checkpoint);
+        assert_eq!(
+            lexer.next_spanned(),
+            (Token::Ident("a".to_string()), Span::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn test_restore_after_end() {
+        let mut lexer = Lexer::new("1 2");
+        let start = lexer.checkpoint();
+
+        assert_eq!(lexer.tokenize().len(), 3);
+        assert!(lexer.is_at_end());
+
+        lexer.restore(start);
+        assert_eq!(lexer.position(), 0);
+        assert_eq!(lexer.next_token(), Token::Number(1));
+    }
+
+    #[test]
+    fn test_operator_table_prefers_longest_match() {
+        let table = &[
+            ("=", Token::Equals),
+            ("==", Token::Plus),
+            ("===", Token::Minus),
+            ("-", Token::Multiply),
+        ];
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+
+        assert_eq!(match_operator(table, &chars("==x")), Some((2, Token::Plus)));
+        assert_eq!(
+            match_operator(table, &chars("====")),
+            Some((3, Token::Minus))
+        );
+        assert_eq!(
+            match_operator(table, &chars("= =")),
+            Some((1, Token::Equals))
+        );
+        assert_eq!(
+            match_operator(table, &chars("-")),
+            Some((1, Token::Multiply))
+        );
+        assert_eq!(match_operator(table, &chars("x")), None);
+        assert_eq!(match_operator(table, &[]), None);
+    }
+
+    #[test]
+    fn test_every_operator_spelling_lexes_to_its_token() {
+        for (spelling, token) in OPERATORS {
+            let mut lexer = Lexer::new(spelling);
+
+            assert_eq!(&lexer.next_token(), token, "spelling: {}", spelling);
+            assert_eq!(lexer.next_token(), Token::EOF);
+        }
+    }
+
+    #[test]
+    fn test_tokens_round_trip_to_original_source() {
+        let sources = [
+            "let x = 42;",
+            "  let   spaced =\t007 ;\n\n",
+            "/// Doc.\nlet y = (x + 1) * -2; // trailing\n//// plain\n",
+            "{ a; { b / c; } }",
+            "invalid@chars#here$ é",
+            "",
+        ];
+
+        for source in sources {
+            let tokens = Lexer::new(source).tokenize_spanned();
+            assert_eq!(tokens_to_source(&tokens, source), source);
+        }
+    }
+
+    #[test]
+    fn test_tokens_to_source_applies_rewrites() {
+        let source = "let a = b  -  c; // keep";
+        let tokens: Vec<(Token, Span)> = Lexer::new(source)
+            .tokenize_spanned()
+            .into_iter()
+            .flat_map(|(token, span)| match token {
+                Token::Minus => vec![
+                    (Token::Plus, span),
+                    (Token::Minus, Span::new(span.end, span.end)),
+                ],
+                Token::Ident(name) if name == "b" => {
+                    vec![(Token::Ident("total".to_string()), span)]
+                }
+                token => vec![(token, span)],
+            })
+            .collect();
+
+        assert_eq!(
+            tokens_to_source(&tokens, source),
+            "let a = total  +-  c; // keep"
+        );
+    }
+
+    #[test]
+    fn test_punctuation_and_arrows() {
+        let mut lexer = Lexer::new("f(a, b): x.y -> z => w - > = > @");
+
+        assert_eq!(
+            lexer.tokenize(),
+            vec![
+                Token::Ident("f".to_string()),
+                Token::LeftParen,
+                Token::Ident("a".to_string()),
+                Token::Comma,
+                Token::Ident("b".to_string()),
+                Token::RightParen,
+                Token::Colon,
+                Token::Ident("x".to_string()),
+                Token::Dot,
+                Token::Ident("y".to_string()),
+                Token::Arrow,
+                Token::Ident("z".to_string()),
+                Token::FatArrow,
+                Token::Ident("w".to_string()),
+                Token::Minus,
+                Token::Illegal('>'),
+                Token::Equals,
+                Token::Illegal('>'),
+                Token::At,
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_tokenize_rejects_illegal_characters() {
+        let error = Lexer::new("invalid$chars#here")
+            .tokenize_strict()
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            LexError::IllegalCharacter {
+                ch: '$',
+                span: Span::new(7, 8)
+            }
+        );
+        assert_eq!(error.span(), Some(Span::new(7, 8)));
+        assert_eq!(error.to_string(), "Illegal character '$' at 7..8");
+    }
+
+    #[test]
+    fn test_strict_tokenize_rejects_numeric_suffixes() {
+        let error = Lexer::new("let x = 123abc;").tokenize_strict().unwrap_err();
+
+        assert_eq!(
+            error,
+            LexError::InvalidNumberSuffix {
+                suffix: "abc".to_string(),
+                span: Span::new(8, 14)
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid numeric literal suffix 'abc' at 8..14"
+        );
+        assert!(Lexer::new("1_000;").tokenize_strict().is_err());
+        assert!(Lexer::new("123 abc; 1+a;").tokenize_strict().is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_out_of_range_integers() {
+        let source = "x = 99999999999999999999;";
+        let tokens = Lexer::new(source).tokenize_spanned();
+
+        assert_eq!(tokens[2], (Token::Illegal('9'), Span::new(4, 24)));
+        assert_eq!(tokens[3].0, Token::Semicolon);
+        assert_eq!(
+            Lexer::new(source).tokenize_strict(),
+            Err(LexError::InvalidNumber {
+                literal: "99999999999999999999".to_string(),
+                span: Span::new(4, 24)
+            })
+        );
+        assert_eq!(
+            Lexer::new("9223372036854775807;").tokenize()[0],
+            Token::Number(i64::MAX)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    fn test_big_integers() {
+        let tokens = Lexer::new("x = 99999999999999999999;").tokenize_spanned();
+
+        assert_eq!(
+            tokens[2],
+            (
+                Token::BigNumber("99999999999999999999".to_string()),
+                Span::new(4, 24)
+            )
+        );
+        assert!(
+            Lexer::new("99999999999999999999abc;")
+                .tokenize_strict()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let tokens = Lexer::new("0.75 1e5 2.5e-3 6E+2 1.x 2em").tokenize();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Float(0.75),
+                Token::Float(1e5),
+                Token::Float(2.5e-3),
+                Token::Float(600.0),
+                Token::Number(1),
+                Token::Dot,
+                Token::Ident("x".to_string()),
+                Token::Number(2),
+                Token::Ident("em".to_string()),
+                Token::EOF,
+            ]
+        );
+        assert_eq!(
+            Lexer::new("1e999;").tokenize_strict(),
+            Err(LexError::InvalidNumber {
+                literal: "1e999".to_string(),
+                span: Span::new(0, 5)
+            })
+        );
+        assert!(matches!(
+            Lexer::new("1.5px;").tokenize_strict(),
+            Err(LexError::InvalidNumberSuffix { .. })
+        ));
+    }
+
+    #[test]
+    fn test_strict_tokenize_accepts_valid_input() {
+        let source = "let x = (1 + 2) * 3; // fine";
+
+        assert_eq!(
+            Lexer::new(source).tokenize_strict(),
+            Ok(Lexer::new(source).tokenize_spanned())
+        );
+    }
+
     #[test]
     fn test_illegal_characters() {
-        let mut lexer = Lexer::new("@#$");
+        let mut lexer = Lexer::new("?#$");
 
-        assert_eq!(lexer.next_token(), Token::Illegal('@'));
+        assert_eq!(lexer.next_token(), Token::Illegal('?'));
         assert_eq!(lexer.next_token(), Token::Illegal('#'));
         assert_eq!(lexer.next_token(), Token::Illegal('$'));
         assert_eq!(lexer.next_token(), Token::EOF);
@@ -1,3 +1,8 @@
+#[allow(clippy::module_inception)]
 pub mod lexer;
+pub mod span;
+pub mod stream;
 
-pub use lexer::{LexError, Lexer, Token};
+pub use lexer::{Checkpoint, KEYWORDS, LexError, Lexer, Token, tokens_to_source};
+pub use span::Span;
+pub use stream::StreamLexer;
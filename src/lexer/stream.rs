@@ -0,0 +1,146 @@
+use super::lexer::{Lexer, Token};
+use super::span::Span;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Lexes a stream one line at a time, so only the current line is held in
+/// memory. No token spans a line break, which makes each line independently
+/// lexable; spans are still byte offsets from the start of the stream.
+///
+/// Yields `(Token, Span)` pairs like [`Lexer::next_spanned`], without the final
+/// EOF token. Reading stops at the first I/O error, including input that is
+/// not valid UTF-8.
+pub struct StreamLexer<R> {
+    reader: R,
+    line: String,
+    /// Tokens of the current line that have not been yielded yet
+    pending: VecDeque<(Token, Span)>,
+    /// Byte offset of the start of the current line
+    offset: usize,
+    done: bool,
+}
+
+impl Lexer {
+    /// Creates a lexer that reads its input incrementally from `reader`
+    pub fn from_reader<R: Read>(reader: R) -> StreamLexer<BufReader<R>> {
+        StreamLexer::new(BufReader::new(reader))
+    }
+}
+
+impl<R: BufRead> StreamLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            pending: VecDeque::new(),
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the number of bytes consumed from the stream so far
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // Lexes the next line into `pending`, returning false at the end of the stream
+    fn fill(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+
+        let base = self.offset;
+        self.pending.extend(
+            Lexer::new(&self.line)
+                .tokenize_spanned()
+                .into_iter()
+                .filter(|(token, _)| *token != Token::EOF)
+                .map(|(token, span)| (token, Span::new(base + span.start, base + span.end))),
+        );
+        self.offset += self.line.len();
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Iterator for StreamLexer<R> {
+    type Item = io::Result<(Token, Span)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => self.done = true,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_lexing_the_whole_string() {
+        let source = "/// Doc\nlet x = 10; // note\n\n  { y -> 2 => é; }\nlast";
+        let streamed: Vec<(Token, Span)> = Lexer::from_reader(source.as_bytes())
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        let mut expected = Lexer::new(source).tokenize_spanned();
+        expected.pop();
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn test_small_reads() {
+        // A reader that returns one byte per read call
+        struct Trickle<'a>(&'a [u8]);
+
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.split_first() {
+                    Some((first, rest)) if !buf.is_empty() => {
+                        buf[0] = *first;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let mut lexer = Lexer::from_reader(Trickle(b"let value = 12345;\n"));
+        let tokens: Vec<Token> = lexer.by_ref().map(|item| item.unwrap().0).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Let,
+                Token::Ident("value".to_string()),
+                Token::Equals,
+                Token::Number(12345),
+                Token::Semicolon
+            ]
+        );
+        assert_eq!(lexer.offset(), 19);
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_an_error() {
+        let mut lexer = Lexer::from_reader(&b"1;\n\xff\n"[..]);
+
+        assert_eq!(lexer.next().unwrap().unwrap().0, Token::Number(1));
+        assert_eq!(lexer.next().unwrap().unwrap().0, Token::Semicolon);
+        assert_eq!(
+            lexer.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(lexer.next().is_none());
+    }
+}
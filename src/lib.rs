@@ -1,18 +1,93 @@
+pub mod analysis;
+#[cfg(feature = "bigint")]
+pub mod bigint;
+#[cfg(feature = "binary")]
+pub mod binary;
+pub mod calculator;
+pub mod cfg;
+pub mod compiled;
+pub mod completion;
+pub mod corpus;
+pub mod diagnostic;
+pub mod doc;
+pub mod edit;
+pub mod error;
+pub mod fold;
+pub mod format;
+pub mod grammar;
+pub mod highlight;
+pub mod hints;
+pub mod interpreter;
+pub(crate) mod json;
+pub mod keywords;
 pub mod lexer;
+pub mod limits;
+pub mod lint;
+pub mod macros;
+pub mod manifest;
+pub mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod notation;
 pub mod parser;
+mod pretty;
+#[cfg(feature = "python")]
+mod python;
+pub mod reduce;
+pub mod refactor;
+pub mod resolve;
+pub mod rewrite;
+pub mod schema;
+pub mod suffix;
+pub mod symbolic;
+pub mod symbols;
+pub mod testing;
+pub mod types;
+pub mod workspace;
 
-pub use lexer::{LexError, Lexer, Token};
+pub use diagnostic::Diagnostic;
+pub use error::Error;
+pub use lexer::{LexError, Lexer, Span, Token};
+pub use limits::Limits;
+#[cfg(feature = "quote")]
+pub use oxide_quote::quote_oxide;
 pub use parser::{
-    BinaryOp, Expr, ParseError, ParseErrors, Parser, Program, Stmt, UnaryOp, parse_source,
-    parse_tokens,
+    Associativity, BinaryOp, Expr, ParseError, ParseErrors, Parser, Pattern, Program, Stmt,
+    UnaryOp, parse_source, parse_tokens,
 };
 
-// Convenience function to parse source code in one step
-pub fn compile(source: &str) -> Result<Program, Box<dyn std::error::Error>> {
-    match parse_source(source) {
-        Ok(program) => Ok(program),
-        Err(errors) => Err(Box::new(errors)),
-    }
+// Lets the `::oxide` paths that quote_oxide! expands to resolve in this crate's tests
+#[cfg(all(test, feature = "quote"))]
+extern crate self as oxide;
+
+// Convenience function to lex and parse source code in one step.
+// Illegal characters are rejected up front rather than surfacing as parse errors.
+pub fn compile(source: &str) -> Result<Program, Error> {
+    compile_with_limits(source, &Limits::default())
+}
+
+// Like `compile`, but also rejects programs exceeding any of the given limits.
+// Hosts evaluating untrusted input can inspect the `Error::Limits` this returns.
+pub fn compile_with_limits(source: &str, limits: &Limits) -> Result<Program, Error> {
+    let compile = || -> Result<Program, Error> {
+        let tokens = Lexer::new(source).tokenize_strict()?;
+        let program = Parser::from_spanned(tokens).parse()?;
+        limits.check(&program)?;
+        Ok(program)
+    };
+    compile().map_err(|error| error.with_source(source))
+}
+
+// Checks source code for lex and syntax errors without keeping a syntax tree,
+// for callers that only need to know whether the code is well-formed.
+pub fn validate(source: &str) -> Result<(), Vec<Diagnostic>> {
+    let tokens = Lexer::new(source)
+        .tokenize_strict()
+        .map_err(|error| vec![Diagnostic::from(&error)])?;
+
+    Parser::from_spanned(tokens)
+        .validate()
+        .map_err(|errors| errors.errors.iter().map(Diagnostic::from).collect())
 }
 
 #[cfg(test)]
@@ -27,6 +102,55 @@ mod tests {
         assert_eq!(program.statements.len(), 2);
     }
 
+    #[test]
+    fn test_compile_rejects_illegal_characters() {
+        let error = compile("let x = 5 $ 3;").unwrap_err();
+
+        match error {
+            Error::Lex { error, .. } => assert_eq!(
+                error,
+                LexError::IllegalCharacter {
+                    ch: '$',
+                    span: Span::new(10, 11)
+                }
+            ),
+            other => panic!("Expected a lex error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_with_limits() {
+        let limits = Limits::new().max_statements(1);
+        assert!(compile_with_limits("let x = 1;", &limits).is_ok());
+
+        let error = compile_with_limits("let x = 1; x;", &limits).unwrap_err();
+        match error {
+            Error::Limits { errors, .. } => {
+                assert_eq!(errors.errors[0].limit, limits::Limit::Statements)
+            }
+            other => panic!("Expected limit errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate("let x = 1; { x * 2; }"), Ok(()));
+
+        let lex_errors = validate("1 # 2;").unwrap_err();
+        assert_eq!(lex_errors.len(), 1);
+        assert_eq!(lex_errors[0].span, Some(Span::new(2, 3)));
+
+        let parse_errors = validate("let = 1; let y = ;").unwrap_err();
+        let expected: Vec<Diagnostic> = parse_source("let = 1; let y = ;")
+            .unwrap_err()
+            .errors
+            .iter()
+            .map(Diagnostic::from)
+            .collect();
+        assert_eq!(parse_errors.len(), 2);
+        assert_eq!(parse_errors, expected);
+    }
+
     #[test]
     fn test_parse_source_convenience() {
         let source = "let hello = 42;";
@@ -34,11 +158,25 @@ mod tests {
 
         assert_eq!(program.statements.len(), 1);
         match &program.statements[0] {
-            Stmt::Let { name, value } => {
+            Stmt::Let { name, value, .. } => {
                 assert_eq!(name, "hello");
                 assert_eq!(*value, Expr::Number(42));
             }
             _ => panic!("Expected let statement"),
         }
     }
+
+    #[cfg(feature = "quote")]
+    #[test]
+    fn test_quote_oxide_matches_the_parser() {
+        let offset = quote_oxide!(y * 2);
+        let statements = quote_oxide! {
+            let x = #offset + -(1, 2);
+            let (a, _) = ((x),);
+            { x / 3 - 1 - a; }
+        };
+
+        let source = "let x = y * 2 + -(1, 2); let (a, _) = ((x),); { x / 3 - 1 - a; }";
+        assert_eq!(statements, parse_source(source).unwrap().statements);
+    }
 }
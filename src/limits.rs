@@ -0,0 +1,286 @@
+use crate::metrics::{self, expression_depth};
+use crate::parser::{Expr, Program, Stmt};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Upper bounds a program must stay within before it is evaluated. Each limit
+/// is optional; `Limits::default()` accepts every program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Total number of statements, including those nested in blocks
+    pub max_statements: Option<usize>,
+    /// Number of nodes on the longest root-to-leaf path of any expression
+    pub max_expression_depth: Option<usize>,
+    /// Number of distinct identifiers, whether bound or referenced
+    pub max_identifiers: Option<usize>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_statements(mut self, limit: usize) -> Self {
+        self.max_statements = Some(limit);
+        self
+    }
+
+    pub fn max_expression_depth(mut self, limit: usize) -> Self {
+        self.max_expression_depth = Some(limit);
+        self
+    }
+
+    pub fn max_identifiers(mut self, limit: usize) -> Self {
+        self.max_identifiers = Some(limit);
+        self
+    }
+
+    /// Checks a program against every configured limit, reporting all violations
+    pub fn check(&self, program: &Program) -> Result<(), LimitErrors> {
+        let mut errors = Vec::new();
+
+        if let Some(maximum) = self.max_statements {
+            let actual = metrics::analyze(program).statements;
+            if actual > maximum {
+                errors.push(LimitError::new(Limit::Statements, maximum, actual, None));
+            }
+        }
+
+        if let Some(maximum) = self.max_expression_depth {
+            for (index, stmt) in program.statements.iter().enumerate() {
+                let actual = statement_expression_depth(stmt);
+                if actual > maximum {
+                    errors.push(LimitError::new(
+                        Limit::ExpressionDepth,
+                        maximum,
+                        actual,
+                        Some(index),
+                    ));
+                }
+            }
+        }
+
+        if let Some(maximum) = self.max_identifiers {
+            let actual = distinct_identifiers(program);
+            if actual > maximum {
+                errors.push(LimitError::new(Limit::Identifiers, maximum, actual, None));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(LimitErrors { errors })
+        }
+    }
+}
+
+/// The quantity a limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Statements,
+    ExpressionDepth,
+    Identifiers,
+}
+
+impl Limit {
+    /// Returns a stable identifier for the limit, suitable for machine consumption
+    pub fn code(&self) -> &'static str {
+        match self {
+            Limit::Statements => "max-statements",
+            Limit::ExpressionDepth => "max-expression-depth",
+            Limit::Identifiers => "max-identifiers",
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Statements => write!(f, "statements"),
+            Limit::ExpressionDepth => write!(f, "expression depth"),
+            Limit::Identifiers => write!(f, "distinct identifiers"),
+        }
+    }
+}
+
+/// A single exceeded limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitError {
+    pub limit: Limit,
+    pub maximum: usize,
+    pub actual: usize,
+    /// Index of the offending top-level statement, for limits that apply per statement
+    pub statement: Option<usize>,
+}
+
+impl LimitError {
+    pub fn new(limit: Limit, maximum: usize, actual: usize, statement: Option<usize>) -> Self {
+        Self {
+            limit,
+            maximum,
+            actual,
+            statement,
+        }
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} limit exceeded: {} exceeds the maximum of {}",
+            self.limit, self.actual, self.maximum
+        )?;
+        if let Some(index) = self.statement {
+            write!(f, " in statement {}", index + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+/// Every limit a program exceeded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitErrors {
+    pub errors: Vec<LimitError>,
+}
+
+impl fmt::Display for LimitErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.errors.len() == 1 {
+            write!(f, "{}", self.errors[0])
+        } else {
+            writeln!(f, "Limit errors:")?;
+            for (i, error) in self.errors.iter().enumerate() {
+                writeln!(f, "  {}: {}", i + 1, error)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl std::error::Error for LimitErrors {}
+
+// Deepest expression anywhere in a statement, including nested blocks
+fn statement_expression_depth(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::Destructure { value, .. } => expression_depth(value),
+        Stmt::Expression(expr) => expression_depth(expr),
+        Stmt::Block(statements) => statements
+            .iter()
+            .map(statement_expression_depth)
+            .max()
+            .unwrap_or(0),
+    }
+}
+
+fn distinct_identifiers(program: &Program) -> usize {
+    fn visit_statement<'a>(stmt: &'a Stmt, names: &mut HashSet<&'a str>) {
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                names.insert(name);
+                visit_expression(value, names);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                names.extend(pattern.bindings());
+                visit_expression(value, names);
+            }
+            Stmt::Expression(expr) => visit_expression(expr, names),
+            Stmt::Block(statements) => {
+                for stmt in statements {
+                    visit_statement(stmt, names);
+                }
+            }
+        }
+    }
+
+    fn visit_expression<'a>(expr: &'a Expr, names: &mut HashSet<&'a str>) {
+        match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {}
+            Expr::Identifier(name) => {
+                names.insert(name);
+            }
+            Expr::Binary { left, right, .. } => {
+                visit_expression(left, names);
+                visit_expression(right, names);
+            }
+            Expr::Unary { operand, .. } => visit_expression(operand, names),
+            Expr::Grouping(inner) => visit_expression(inner, names),
+            Expr::Tuple(elements) => {
+                for element in elements {
+                    visit_expression(element, names);
+                }
+            }
+        }
+    }
+
+    let mut names = HashSet::new();
+    for stmt in &program.statements {
+        visit_statement(stmt, &mut names);
+    }
+    names.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    #[test]
+    fn test_default_limits_accept_everything() {
+        let program = parse_source("let x = ((((1)))); { x; { x; } }").unwrap();
+
+        assert_eq!(Limits::default().check(&program), Ok(()));
+    }
+
+    #[test]
+    fn test_statement_limit() {
+        let program = parse_source("1; { 2; 3; }").unwrap();
+        let errors = Limits::new().max_statements(3).check(&program).unwrap_err();
+
+        assert_eq!(
+            errors.errors,
+            vec![LimitError::new(Limit::Statements, 3, 4, None)]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "statements limit exceeded: 4 exceeds the maximum of 3"
+        );
+    }
+
+    #[test]
+    fn test_expression_depth_limit_reports_each_statement() {
+        let program = parse_source("1 + 2; -(-(-3)); { let y = 1 * (2 - 3); }").unwrap();
+        let errors = Limits::new()
+            .max_expression_depth(3)
+            .check(&program)
+            .unwrap_err();
+
+        assert_eq!(
+            errors.errors,
+            vec![
+                LimitError::new(Limit::ExpressionDepth, 3, 6, Some(1)),
+                LimitError::new(Limit::ExpressionDepth, 3, 4, Some(2)),
+            ]
+        );
+        assert_eq!(errors.errors[0].limit.code(), "max-expression-depth");
+    }
+
+    #[test]
+    fn test_identifier_limit_counts_distinct_names() {
+        let program = parse_source("let a = b + b; a * c;").unwrap();
+        let limits = Limits::new().max_identifiers(3);
+
+        assert_eq!(limits.check(&program), Ok(()));
+        assert_eq!(
+            limits
+                .max_identifiers(2)
+                .check(&program)
+                .unwrap_err()
+                .errors,
+            vec![LimitError::new(Limit::Identifiers, 2, 3, None)]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::parser::{Expr, Program, Stmt, UnaryOp};
+use std::collections::HashMap;
+
+/// A suggestion to simplify or otherwise improve a piece of code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    /// Stable identifier of the rule that produced the lint
+    pub code: &'static str,
+    pub message: String,
+    /// The expression the flagged code can be replaced with
+    pub suggestion: String,
+}
+
+/// How a lint is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Not reported
+    Allow,
+    /// Reported without failing the check
+    Warn,
+    /// Reported as an error
+    Deny,
+}
+
+impl LintLevel {
+    /// Parses a level as written in configuration: `allow`, `warn` or `deny`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Codes of every lint rule
+pub const LINT_CODES: &[&str] = &["redundant-sign", "mixed-case-name"];
+
+/// Runs every lint rule over a program
+pub fn check(program: &Program) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for stmt in &program.statements {
+        check_statement(stmt, &mut lints);
+    }
+    check_name_case(program, &mut lints);
+    lints
+}
+
+// Names spelled differently only in case, which resolve to the same name
+// when a host ignores case. Each other spelling is flagged once, suggesting
+// the first.
+fn check_name_case(program: &Program, lints: &mut Vec<Lint>) {
+    let mut names = Vec::new();
+    for stmt in &program.statements {
+        statement_names(stmt, &mut names);
+    }
+
+    let mut first: HashMap<String, &str> = HashMap::new();
+    let mut flagged = Vec::new();
+    for name in names {
+        let spelling = *first.entry(name.to_lowercase()).or_insert(name);
+        if spelling != name && !flagged.contains(&name) {
+            flagged.push(name);
+            lints.push(Lint {
+                code: "mixed-case-name",
+                message: format!("`{}` is spelled `{}` elsewhere", name, spelling),
+                suggestion: spelling.to_string(),
+            });
+        }
+    }
+}
+
+// Every name bound or used by a statement, in source order
+fn statement_names<'a>(stmt: &'a Stmt, names: &mut Vec<&'a str>) {
+    match stmt {
+        Stmt::Let { name, value, .. } => {
+            names.push(name);
+            expression_names(value, names);
+        }
+        Stmt::Destructure { pattern, value, .. } => {
+            names.extend(pattern.bindings());
+            expression_names(value, names);
+        }
+        Stmt::Expression(expr) => expression_names(expr, names),
+        Stmt::Block(statements) => {
+            for stmt in statements {
+                statement_names(stmt, names);
+            }
+        }
+    }
+}
+
+fn expression_names<'a>(expr: &'a Expr, names: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Identifier(name) => names.push(name),
+        Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {}
+        Expr::Binary { left, right, .. } => {
+            expression_names(left, names);
+            expression_names(right, names);
+        }
+        Expr::Tuple(elements) => {
+            for element in elements {
+                expression_names(element, names);
+            }
+        }
+        Expr::Unary { operand, .. } => expression_names(operand, names),
+        Expr::Grouping(inner) => expression_names(inner, names),
+    }
+}
+
+fn check_statement(stmt: &Stmt, lints: &mut Vec<Lint>) {
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::Destructure { value, .. } => check_expression(value, lints),
+        Stmt::Expression(expr) => check_expression(expr, lints),
+        Stmt::Block(statements) => {
+            for stmt in statements {
+                check_statement(stmt, lints);
+            }
+        }
+    }
+}
+
+fn check_expression(expr: &Expr, lints: &mut Vec<Lint>) {
+    match expr {
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => {}
+        Expr::Binary { left, right, .. } => {
+            check_expression(left, lints);
+            check_expression(right, lints);
+        }
+        Expr::Tuple(elements) => {
+            for element in elements {
+                check_expression(element, lints);
+            }
+        }
+        Expr::Unary { operator, operand } => {
+            match operator {
+                UnaryOp::Plus => lints.push(Lint {
+                    code: "redundant-sign",
+                    message: format!("unary plus in `{}` has no effect", expr),
+                    suggestion: operand.to_string(),
+                }),
+                UnaryOp::Negate => {
+                    if let Expr::Unary {
+                        operator: UnaryOp::Negate,
+                        operand: inner,
+                    } = skip_groupings(operand)
+                    {
+                        lints.push(Lint {
+                            code: "redundant-sign",
+                            message: format!("double negation in `{}` cancels out", expr),
+                            suggestion: inner.to_string(),
+                        });
+                    }
+                }
+            }
+            check_expression(operand, lints);
+        }
+        Expr::Grouping(inner) => check_expression(inner, lints),
+    }
+}
+
+fn skip_groupings(mut expr: &Expr) -> &Expr {
+    while let Expr::Grouping(inner) = expr {
+        expr = inner;
+    }
+    expr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn suggestions(source: &str) -> Vec<String> {
+        check(&parse_source(source).unwrap())
+            .into_iter()
+            .map(|lint| lint.suggestion)
+            .collect()
+    }
+
+    #[test]
+    fn test_unary_plus() {
+        let lints = check(&parse_source("let x = +y;").unwrap());
+
+        assert_eq!(
+            lints,
+            vec![Lint {
+                code: "redundant-sign",
+                message: "unary plus in `(+y)` has no effect".to_string(),
+                suggestion: "y".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_double_negation() {
+        assert_eq!(suggestions("--10;"), vec!["10"]);
+        assert_eq!(suggestions("{ 1 - -(-(a + b)); }"), vec!["(a + b)"]);
+    }
+
+    #[test]
+    fn test_clean_code_has_no_lints() {
+        assert!(suggestions("let x = -y; 1 - -2; -(3 - 4);").is_empty());
+    }
+
+    #[test]
+    fn test_names_differing_only_in_case() {
+        let lints = check(&parse_source("let Total = 1; { total + TOTAL + total; }").unwrap());
+
+        assert_eq!(
+            lints
+                .iter()
+                .map(|lint| (lint.code, lint.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("mixed-case-name", "`total` is spelled `Total` elsewhere"),
+                ("mixed-case-name", "`TOTAL` is spelled `Total` elsewhere"),
+            ]
+        );
+        assert_eq!(suggestions("let (rate, x) = (1, 2); Rate;"), vec!["rate"]);
+        assert!(suggestions("let total = 1; total + subtotal;").is_empty());
+    }
+}
@@ -0,0 +1,453 @@
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{ParseError, Parser, Program};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// How deeply macro calls may expand into further macro calls
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// A problem in a macro definition or call, found while expanding
+#[derive(Debug, Clone, PartialEq)]
+pub enum MacroError {
+    InvalidDefinition {
+        message: String,
+        span: Span,
+    },
+    InvalidCall {
+        message: String,
+        span: Span,
+    },
+    /// A macro kept expanding into calls past [`MAX_EXPANSION_DEPTH`]
+    RecursionLimit {
+        name: String,
+        span: Span,
+    },
+}
+
+impl MacroError {
+    pub fn span(&self) -> Span {
+        match self {
+            MacroError::InvalidDefinition { span, .. }
+            | MacroError::InvalidCall { span, .. }
+            | MacroError::RecursionLimit { span, .. } => *span,
+        }
+    }
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::InvalidDefinition { message, span } => {
+                write!(f, "Invalid macro definition at {}: {}", span, message)
+            }
+            MacroError::InvalidCall { message, span } => {
+                write!(f, "Invalid macro call at {}: {}", span, message)
+            }
+            MacroError::RecursionLimit { name, span } => write!(
+                f,
+                "Macro `{}` at {} expands more than {} levels deep",
+                name, span, MAX_EXPANSION_DEPTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MacroError {}
+
+impl From<&MacroError> for Diagnostic {
+    fn from(error: &MacroError) -> Self {
+        Diagnostic::new(Some(error.span()), error.to_string())
+    }
+}
+
+/// One macro call that was expanded
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpansionSite {
+    pub name: String,
+    /// The call, from the macro's name to its closing parenthesis
+    pub call: Span,
+    /// Index of the expansion the call itself came from, if any
+    pub parent: Option<usize>,
+}
+
+/// Tokens with every macro definition removed and every call expanded.
+/// Tokens from a macro body keep their spans in the definition; each token
+/// also remembers the expansion it came from, for backtraces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expansion {
+    pub tokens: Vec<(Token, Span)>,
+    pub sites: Vec<ExpansionSite>,
+    origins: Vec<Option<usize>>,
+}
+
+impl Expansion {
+    /// Returns the calls that produced the token at `position`, innermost first
+    pub fn backtrace(&self, position: usize) -> Vec<&ExpansionSite> {
+        let mut sites = Vec::new();
+        let mut origin = self.origins.get(position).copied().flatten();
+        while let Some(index) = origin {
+            sites.push(&self.sites[index]);
+            origin = self.sites[index].parent;
+        }
+        sites
+    }
+
+    /// Describes a parse error in the expanded tokens, with one line of
+    /// backtrace for each macro call the offending token came from
+    pub fn diagnostic(&self, error: &ParseError) -> Diagnostic {
+        let mut message = error.to_string();
+        if let Some(position) = error.position() {
+            for site in self.backtrace(position) {
+                message.push_str(&format!(
+                    "\n  in expansion of macro `{}` at {}",
+                    site.name, site.call
+                ));
+            }
+        }
+        Diagnostic::new(error.span(), message)
+    }
+}
+
+/// Removes the macro definitions from a token stream and expands their calls.
+///
+/// `macro name(a, b) { ... }` defines a macro, and a later `name(x, y);`
+/// statement is replaced by the body, with each parameter replaced by its
+/// argument in parentheses. Names the body binds with `let` are renamed at
+/// each call so that they cannot capture or shadow the caller's names.
+pub fn expand(tokens: Vec<(Token, Span)>) -> Result<Expansion, MacroError> {
+    let mut expander = Expander::default();
+    expander.expand(&tokens, None, 0)?;
+    Ok(Expansion {
+        tokens: expander.tokens,
+        sites: expander.sites,
+        origins: expander.origins,
+    })
+}
+
+/// Lexes, expands macros in and parses `source`, reporting errors inside
+/// expansions with their backtraces
+pub fn parse_with_macros(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let tokens = Lexer::new(source)
+        .tokenize_strict()
+        .map_err(|error| vec![Diagnostic::from(&error)])?;
+    let expansion = expand(tokens).map_err(|error| vec![Diagnostic::from(&error)])?;
+
+    Parser::from_spanned(expansion.tokens.clone())
+        .parse()
+        .map_err(|errors| {
+            errors
+                .errors
+                .iter()
+                .map(|error| expansion.diagnostic(error))
+                .collect()
+        })
+}
+
+struct Macro {
+    params: Vec<String>,
+    /// Names bound by `let` in the body, renamed at each call
+    locals: HashSet<String>,
+    body: Vec<(Token, Span)>,
+}
+
+#[derive(Default)]
+struct Expander {
+    macros: HashMap<String, Macro>,
+    tokens: Vec<(Token, Span)>,
+    origins: Vec<Option<usize>>,
+    sites: Vec<ExpansionSite>,
+    /// Number of calls expanded so far, which makes renamed locals unique
+    calls: usize,
+}
+
+impl Expander {
+    fn expand(
+        &mut self,
+        input: &[(Token, Span)],
+        origin: Option<usize>,
+        depth: usize,
+    ) -> Result<(), MacroError> {
+        let mut i = 0;
+        while i < input.len() {
+            let next = input.get(i + 1).map(|(token, _)| token);
+            match &input[i].0 {
+                Token::Ident(keyword)
+                    if keyword == "macro" && matches!(next, Some(Token::Ident(_))) =>
+                {
+                    i = self.define(input, i + 1)?;
+                }
+                Token::Ident(name)
+                    if self.macros.contains_key(name) && next == Some(&Token::LeftParen) =>
+                {
+                    i = self.call(input, i, origin, depth)?;
+                }
+                token => {
+                    self.tokens.push((token.clone(), input[i].1));
+                    self.origins.push(origin);
+                    i += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Records the definition whose name is at `start`, returning the index
+    // just past its closing brace
+    fn define(&mut self, input: &[(Token, Span)], start: usize) -> Result<usize, MacroError> {
+        let invalid = |message: &str, index: usize| MacroError::InvalidDefinition {
+            message: message.to_string(),
+            span: span_at(input, index),
+        };
+        let Token::Ident(name) = &input[start].0 else {
+            return Err(invalid("expected a macro name", start));
+        };
+
+        let mut i = start + 1;
+        if input.get(i).map(|(token, _)| token) != Some(&Token::LeftParen) {
+            return Err(invalid("expected '(' after the macro name", i));
+        }
+        i += 1;
+
+        let mut params = Vec::new();
+        while input.get(i).map(|(token, _)| token) != Some(&Token::RightParen) {
+            match input.get(i).map(|(token, _)| token) {
+                Some(Token::Ident(param)) if !params.contains(param) => params.push(param.clone()),
+                Some(Token::Ident(_)) => return Err(invalid("duplicate parameter", i)),
+                _ => return Err(invalid("expected a parameter name", i)),
+            }
+            i += 1;
+            match input.get(i).map(|(token, _)| token) {
+                Some(Token::Comma) => i += 1,
+                Some(Token::RightParen) => {}
+                _ => return Err(invalid("expected ',' or ')' after a parameter", i)),
+            }
+        }
+        i += 1;
+
+        if input.get(i).map(|(token, _)| token) != Some(&Token::LeftBrace) {
+            return Err(invalid("expected '{' before the macro body", i));
+        }
+        let body_start = i + 1;
+        let body_end = closing(input, i, &Token::LeftBrace, &Token::RightBrace)
+            .ok_or_else(|| invalid("unclosed macro body", i))?;
+        let body = input[body_start..body_end].to_vec();
+
+        let locals = let_bound_names(&body)
+            .into_iter()
+            .filter(|local| !params.contains(local))
+            .collect();
+        self.macros.insert(
+            name.clone(),
+            Macro {
+                params,
+                locals,
+                body,
+            },
+        );
+        Ok(body_end + 1)
+    }
+
+    // Expands the call whose name is at `start`, returning the index just
+    // past it and the `;` that may follow it
+    fn call(
+        &mut self,
+        input: &[(Token, Span)],
+        start: usize,
+        origin: Option<usize>,
+        depth: usize,
+    ) -> Result<usize, MacroError> {
+        let Token::Ident(name) = &input[start].0 else {
+            unreachable!("macro calls start with the macro's name");
+        };
+        let close =
+            closing(input, start + 1, &Token::LeftParen, &Token::RightParen).ok_or_else(|| {
+                MacroError::InvalidCall {
+                    message: format!("unclosed arguments to macro `{}`", name),
+                    span: span_at(input, start),
+                }
+            })?;
+        let call = Span::new(input[start].1.start, input[close].1.end);
+
+        if depth >= MAX_EXPANSION_DEPTH {
+            return Err(MacroError::RecursionLimit {
+                name: name.clone(),
+                span: call,
+            });
+        }
+
+        let arguments = split_arguments(&input[start + 2..close]);
+        let definition = &self.macros[name];
+        if arguments.iter().any(|argument| argument.is_empty()) {
+            return Err(MacroError::InvalidCall {
+                message: format!("empty argument to macro `{}`", name),
+                span: call,
+            });
+        }
+        if arguments.len() != definition.params.len() {
+            return Err(MacroError::InvalidCall {
+                message: format!(
+                    "macro `{}` takes {} argument(s), found {}",
+                    name,
+                    definition.params.len(),
+                    arguments.len()
+                ),
+                span: call,
+            });
+        }
+
+        self.calls += 1;
+        let mut body = Vec::new();
+        for (token, span) in &definition.body {
+            match token {
+                Token::Ident(ident) if definition.params.contains(ident) => {
+                    let index = definition.params.iter().position(|p| p == ident).unwrap();
+                    let argument = arguments[index];
+                    body.push((Token::LeftParen, Span::new(span.start, span.start)));
+                    body.extend_from_slice(argument);
+                    body.push((Token::RightParen, Span::new(span.end, span.end)));
+                }
+                Token::Ident(ident) if definition.locals.contains(ident) => {
+                    let renamed = format!("{}__{}", ident, self.calls);
+                    body.push((Token::Ident(renamed), *span));
+                }
+                _ => body.push((token.clone(), *span)),
+            }
+        }
+
+        self.sites.push(ExpansionSite {
+            name: name.clone(),
+            call,
+            parent: origin,
+        });
+        let site = self.sites.len() - 1;
+        self.expand(&body, Some(site), depth + 1)?;
+
+        // The body supplies its own semicolons
+        match input.get(close + 1) {
+            Some((Token::Semicolon, _)) => Ok(close + 2),
+            _ => Ok(close + 1),
+        }
+    }
+}
+
+fn span_at(input: &[(Token, Span)], index: usize) -> Span {
+    input
+        .get(index)
+        .or(input.last())
+        .map(|(_, span)| *span)
+        .unwrap_or_default()
+}
+
+// Index of the token closing the delimiter opened at `open`
+fn closing(input: &[(Token, Span)], open: usize, left: &Token, right: &Token) -> Option<usize> {
+    let mut depth = 0;
+    for (index, (token, _)) in input.iter().enumerate().skip(open) {
+        if token == left {
+            depth += 1;
+        } else if token == right {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+// Splits call arguments at the commas outside any parentheses or braces
+fn split_arguments(tokens: &[(Token, Span)]) -> Vec<&[(Token, Span)]> {
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, (token, _)) in tokens.iter().enumerate() {
+        match token {
+            Token::LeftParen | Token::LeftBrace => depth += 1,
+            Token::RightParen | Token::RightBrace => depth -= 1,
+            Token::Comma if depth == 0 => {
+                arguments.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push(&tokens[start..]);
+    arguments
+}
+
+// Names between each `let` and its `=`, other than `_`
+fn let_bound_names(body: &[(Token, Span)]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut binding = false;
+    for (token, _) in body {
+        match token {
+            Token::Let => binding = true,
+            Token::Equals => binding = false,
+            Token::Ident(name) if binding && name != "_" => {
+                names.insert(name.clone());
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(source: &str) -> String {
+        parse_with_macros(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_calls_are_replaced_by_their_bodies() {
+        assert_eq!(
+            expanded("macro twice(x) { x; x; } twice(1 + 2); 3;"),
+            "(1 + 2);\n(1 + 2);\n3;\n"
+        );
+        assert_eq!(
+            expanded("macro add(a, b) { a + b; } macro double(n) { add(n, n); } double(4);"),
+            "((4) + (4));\n"
+        );
+    }
+
+    #[test]
+    fn test_body_bindings_are_hygienic() {
+        assert_eq!(
+            expanded("macro square(v) { let t = v; t * t; } let t = 3; square(t);"),
+            "let t = 3;\nlet t__1 = (t);\n(t__1 * t__1);\n"
+        );
+    }
+
+    #[test]
+    fn test_errors_in_expansions_have_backtraces() {
+        let source = "macro bad(x) { x + ; }\nmacro outer() { bad(1); }\nouter();";
+        let errors = parse_with_macros(source).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        let lines: Vec<&str> = errors[0].message.lines().collect();
+        assert_eq!(
+            &lines[1..],
+            [
+                "  in expansion of macro `bad` at 39..45",
+                "  in expansion of macro `outer` at 49..56",
+            ]
+        );
+        // The error itself points into the definition of `bad`
+        assert_eq!(errors[0].span, Some(Span::new(19, 20)));
+    }
+
+    #[test]
+    fn test_invalid_macros() {
+        let message = |source: &str| parse_with_macros(source).unwrap_err()[0].message.clone();
+
+        assert!(message("macro one(x) { x; } one(1, 2);").contains("takes 1 argument(s), found 2"));
+        assert!(message("macro m(x, x) { x; }").contains("duplicate parameter"));
+        assert!(message("macro loop() { loop(); } loop();").contains("levels deep"));
+    }
+}
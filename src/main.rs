@@ -1,76 +1,573 @@
-mod lexer;
+use oxide::format::{IndentStyle, format_source};
+use oxide::interpreter::{Environment, ValueFormatter, run_source};
+use oxide::manifest::{MANIFEST_NAME, Manifest};
+use oxide::suffix::LiteralSuffixes;
+use oxide::workspace::{self, FileDiagnostic, Severity};
+use oxide::{Error, Program, Span, compile, corpus, doc, grammar, metrics, reduce, schema};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
 
-use crate::lexer::{Lexer, Token};
+// The library's JSON value is crate-private, so the tool builds its own copy,
+// which does not need every variant
+#[allow(dead_code)]
+#[path = "json.rs"]
+mod json;
+
+use json::Json;
+
+/// Exit status when the input has errors; success is 0
+const EXIT_DIAGNOSTICS: i32 = 1;
+/// Exit status when the tool itself fails: bad usage, unreadable files or
+/// manifests, and missing features
+const EXIT_FAILURE: i32 = 2;
+
+/// How a command writes its result, set by flags every command accepts
+#[derive(Debug, Clone, Copy, Default)]
+struct Output {
+    /// Write only errors
+    quiet: bool,
+    /// Write the result as one JSON document on stdout, diagnostics included
+    json: bool,
+}
+
+impl Output {
+    // Writes a command's result as `json` or with `text`
+    fn result(&self, json: impl FnOnce() -> Json, text: impl FnOnce()) {
+        if self.json {
+            println!("{}", json().to_pretty_string());
+        } else if !self.quiet {
+            text();
+        }
+    }
+
+    // Reports a failure of the tool itself, as a JSON object with an `error`
+    // under `--json`, and exits
+    fn fail(&self, message: impl std::fmt::Display) -> ! {
+        if self.json {
+            let error = Json::object(vec![("error", message.to_string().as_str().into())]);
+            println!("{}", error.to_pretty_string());
+        } else {
+            eprintln!("{}", message);
+        }
+        process::exit(EXIT_FAILURE);
+    }
+}
 
 fn main() {
-    // Test cases to demonstrate lexer improvements
-    let test_inputs = vec![
-        "let x = 5;",
-        "let y = 10 + 20;",
-        "let result = (x * y) / 2;",
-        "let foo_bar = 123;",
-        "   let   spaced   =   42   ;   ",
-        "invalid@chars#here$",
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // Output flags may go anywhere before a `--`, after which arguments
+    // belong to another program
+    let mut output = Output::default();
+    let own = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    let mut index = 0;
+    args.retain(|arg| {
+        index += 1;
+        match arg.as_str() {
+            "--quiet" | "-q" if index <= own => output.quiet = true,
+            "--json" if index <= own => output.json = true,
+            _ => return true,
+        }
+        false
+    });
+
+    match args.first().map(String::as_str) {
+        Some("check") => run_check(&args[1..], output),
+        Some("fmt") => run_fmt(&args[1..], output),
+        Some("run") => run_program(&args[1..], output),
+        Some("metrics") => run_metrics(&args[1..], output),
+        Some("doc") => run_doc(&args[1..], output),
+        Some("reduce") => run_reduce(&args[1..], output),
+        // The schema is JSON already
+        Some("schema") if output.json || !output.quiet => println!("{}", schema::ast_schema()),
+        Some("schema") => {}
+        Some("grammar") => {
+            let ebnf = grammar::grammar_ebnf(&LiteralSuffixes::new());
+            output.result(
+                || Json::object(vec![("ebnf", ebnf.as_str().into())]),
+                || print!("{}", ebnf),
+            );
+        }
+        Some("corpus") => run_corpus(&args[1..], output),
+        Some("help") | Some("--help") | Some("-h") | None => print_usage(),
+        Some(command) => {
+            if !output.json {
+                print_usage();
+            }
+            output.fail(format!("Unknown command: {}", command));
+        }
+    }
+}
+
+fn print_usage() {
+    println!("Usage: oxide <command> [arguments] [--quiet] [--json]");
+    println!();
+    println!("  --quiet, -q     - Write only errors");
+    println!("  --json          - Write the result, diagnostics included, as JSON on stdout");
+    println!();
+    println!("Exit status: 0 on success, 1 if the input has errors, 2 if oxide itself failed,");
+    println!("such as on bad usage or a file that cannot be read, in every command");
+    println!();
+    println!("Commands:");
+    println!("  check [--mmap] [--ranges] [path...]");
+    println!("                  - Report errors in files, every .ox file under directories,");
+    println!("                    or the project described by the nearest oxide.toml;");
+    println!("                    --mmap memory-maps the files (needs the `mmap` feature)");
+    println!("  fmt [--indent-width <n>] [--tabs] [--max-width <n>] <file>");
+    println!("                  - Print a file formatted with the settings of the nearest");
+    println!("                    oxide.toml, overridden by the flags given");
+    println!("  run [--stats] <file>");
+    println!("                  - Run a file and print the value of its last expression;");
+    println!("                    --stats also reports the statements and steps it took");
+    println!("  metrics <file>  - Print size and complexity metrics for a file as JSON");
+    println!("  doc <file>      - Print Markdown documentation for a file's documented bindings");
+    println!("  reduce <file> [-- <command>...]");
+    println!("                  - Shrink a file that fails to compile, or that makes");
+    println!("                    <command> fail when given the file's path");
+    println!("  schema          - Print the JSON schema of serialized syntax trees");
+    println!("  grammar         - Print the grammar as EBNF");
+    println!("  corpus run [--record] <dir>");
+    println!("                  - Lex and parse every file in a corpus directory, failing on");
+    println!("                    panics and on diagnostics that differ from the recorded ones;");
+    println!("                    --record writes the diagnostics instead of comparing them");
+    println!("  help            - Show this help message");
+}
+
+// A diagnostic as JSON, with what is known of where it is
+fn diagnostic_json(
+    path: &str,
+    severity: Severity,
+    span: Option<Span>,
+    position: Option<(usize, usize)>,
+    message: &str,
+) -> Json {
+    let mut fields = vec![
+        ("path", path.into()),
+        ("severity", severity.to_string().as_str().into()),
     ];
+    if let Some((line, column)) = position {
+        fields.extend([("line", line.into()), ("column", column.into())]);
+    }
+    if let Some(span) = span {
+        fields.extend([("start", span.start.into()), ("end", span.end.into())]);
+    }
+    fields.push(("message", message.into()));
+    Json::object(fields)
+}
+
+fn file_diagnostic_json(diagnostic: &FileDiagnostic) -> Json {
+    diagnostic_json(
+        &diagnostic.path.display().to_string(),
+        diagnostic.severity,
+        diagnostic.span,
+        diagnostic.position,
+        &diagnostic.message,
+    )
+}
 
-    for (i, input) in test_inputs.iter().enumerate() {
-        println!("=== Test Case {} ===", i + 1);
-        println!("Input: {}", input);
-        println!("Tokens:");
+// Parses a file a command works on, lexing strictly as `check` does, or
+// reports its errors and exits
+fn parse_file(path: &str, source: &str, output: Output) -> Program {
+    compile(source).unwrap_or_else(|error| exit_with_errors(path, &error, output))
+}
+
+// Reports the errors of a file that a command needs to parse, then exits
+fn exit_with_errors(path: &str, error: &Error, output: Output) -> ! {
+    if output.json {
+        let diagnostics = error
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| {
+                diagnostic_json(
+                    path,
+                    Severity::Error,
+                    diagnostic.span,
+                    None,
+                    &diagnostic.message,
+                )
+            })
+            .collect();
+        println!(
+            "{}",
+            Json::object(vec![("diagnostics", Json::Array(diagnostics))]).to_pretty_string()
+        );
+    } else {
+        eprintln!("{}: {}", path, error);
+    }
+    process::exit(EXIT_DIAGNOSTICS);
+}
 
-        let mut lexer = Lexer::new(input);
+fn run_check(args: &[String], output: Output) {
+    let memory_map = args.iter().any(|arg| arg == "--mmap");
+    let ranges = args.iter().any(|arg| arg == "--ranges");
+    let args: Vec<&String> = args
+        .iter()
+        .filter(|arg| *arg != "--mmap" && *arg != "--ranges")
+        .collect();
 
-        // Method 1: Using next_token() in a loop
-        loop {
-            let token = lexer.next_token();
-            println!("  {:?}", token);
+    if args.iter().any(|arg| arg.starts_with("--")) {
+        output.fail("Usage: oxide check [--mmap] [--ranges] [path...]");
+    }
+    let targets: Vec<&Path> = args.iter().map(Path::new).collect();
 
-            if token == Token::EOF {
-                break;
+    if memory_map && !cfg!(feature = "mmap") {
+        output.fail("--mmap requires oxide to be built with the `mmap` feature");
+    }
+
+    // Settings come from the manifest of the project being checked, if any,
+    // found from the first path given
+    let start = match targets.first() {
+        Some(path) if path.is_dir() => path.to_path_buf(),
+        Some(path) => path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        None => env::current_dir().unwrap_or_default(),
+    };
+    let manifest = Manifest::find(&start).map(|path| match Manifest::load(&path) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            output.fail(format!("{}: {}", path.display(), error));
+        }
+    });
+    let mut options = manifest
+        .as_ref()
+        .map(Manifest::check_options)
+        .unwrap_or_default();
+    options.memory_map = memory_map;
+    options.ranges = ranges;
+
+    let mut files = Vec::new();
+    for target in &targets {
+        let found = if target.is_dir() {
+            workspace::source_files(target)
+        } else {
+            Ok(vec![target.to_path_buf()])
+        };
+        files.extend(found.unwrap_or_else(|error| {
+            output.fail(format!("Error reading {}: {}", target.display(), error));
+        }));
+    }
+    if targets.is_empty() {
+        let Some(manifest) = &manifest else {
+            output.fail(format!("No path given and no {} found", MANIFEST_NAME));
+        };
+        files = manifest.source_files().unwrap_or_else(|error| {
+            output.fail(format!("Error reading {}: {}", start.display(), error));
+        });
+    }
+
+    let report = workspace::check_files(&files, &options);
+    // An unreadable file fails the tool, as it does in every other command
+    if let Some((path, reason)) = report.unreadable().next() {
+        output.fail(format!("Error reading {}: {}", path.display(), reason));
+    }
+    let summary = format!(
+        "{} file(s) checked, {} error(s), {} warning(s)",
+        report.files.len(),
+        report.errors(),
+        report.warnings()
+    );
+    if output.json {
+        let diagnostics = report.diagnostics().map(file_diagnostic_json).collect();
+        let summary = Json::object(vec![
+            ("files", report.files.len().into()),
+            ("errors", report.errors().into()),
+            ("warnings", report.warnings().into()),
+        ]);
+        let result = Json::object(vec![
+            ("diagnostics", Json::Array(diagnostics)),
+            ("summary", summary),
+        ]);
+        println!("{}", result.to_pretty_string());
+    } else {
+        for diagnostic in report.diagnostics() {
+            if diagnostic.severity == Severity::Error || !output.quiet {
+                eprintln!("{}", diagnostic);
             }
         }
+        if !output.quiet {
+            println!("{}", summary);
+        }
+    }
+    if report.errors() > 0 {
+        process::exit(EXIT_DIAGNOSTICS);
+    }
+}
 
-        println!();
+fn run_fmt(args: &[String], output: Output) {
+    let usage = || -> ! {
+        output.fail("Usage: oxide fmt [--indent-width <n>] [--tabs] [--max-width <n>] <file>");
+    };
+    let number = |value: Option<&String>| -> usize {
+        value
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| usage())
+    };
+
+    let mut path = None;
+    let mut indent_width = None;
+    let mut tabs = false;
+    let mut max_width = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--indent-width" => indent_width = Some(number(args.next())),
+            "--max-width" => max_width = Some(number(args.next())),
+            "--tabs" => tabs = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.as_str()),
+            _ => usage(),
+        }
     }
+    let Some(path) = path else { usage() };
 
-    // Demonstrate iterator functionality
-    println!("=== Iterator Demo ===");
-    let input = "let sum = a + b;";
-    println!("Input: {}", input);
-    println!("Using iterator:");
+    let start = Path::new(path).parent().unwrap_or(Path::new("."));
+    let mut options = match Manifest::find(start) {
+        Some(manifest) => match Manifest::load(&manifest) {
+            Ok(loaded) => loaded.format,
+            Err(error) => {
+                output.fail(format!("{}: {}", manifest.display(), error));
+            }
+        },
+        None => Default::default(),
+    };
+    if let Some(width) = indent_width {
+        options.indent_width = width;
+    }
+    if tabs {
+        options.indent_style = IndentStyle::Tabs;
+    }
+    if let Some(width) = max_width {
+        options.max_width = width;
+    }
 
-    let lexer = Lexer::new(input);
-    for token in lexer {
-        println!("  {}", token);
+    let source = read_source(path, output);
+    parse_file(path, &source, output);
+    match format_source(&source, &options) {
+        Ok(formatted) => output.result(
+            || Json::object(vec![("formatted", formatted.as_str().into())]),
+            || print!("{}", formatted),
+        ),
+        Err(errors) => exit_with_errors(path, &Error::from(errors), output),
     }
+}
 
-    println!();
+fn run_program(args: &[String], output: Output) {
+    let (path, stats) = match args {
+        [path] => (path, false),
+        [flag, path] if flag == "--stats" => (path, true),
+        _ => {
+            output.fail("Usage: oxide run [--stats] <file>");
+        }
+    };
 
-    // Demonstrate tokenize() method
-    println!("=== Tokenize Method Demo ===");
-    let input = "let result = (10 - 5) * 2;";
-    println!("Input: {}", input);
+    let source = read_source(path, output);
+    let outcome = run_source(&source, &mut Environment::new());
+    // Positioned as `check` positions them
+    let diagnostics: Vec<FileDiagnostic> = outcome
+        .diagnostics
+        .iter()
+        .map(|diagnostic| FileDiagnostic {
+            path: path.into(),
+            severity: Severity::Error,
+            span: diagnostic.span,
+            position: diagnostic
+                .span
+                .map(|span| workspace::line_column(&source, span.start)),
+            message: diagnostic.message.clone(),
+        })
+        .collect();
+    if output.json {
+        let mut fields = Vec::new();
+        if let Some(value) = &outcome.value {
+            fields.push(("value", value.to_string().as_str().into()));
+        }
+        let diagnostics = diagnostics.iter().map(file_diagnostic_json).collect();
+        fields.push(("diagnostics", Json::Array(diagnostics)));
+        fields.push((
+            "statistics",
+            Json::object(vec![
+                ("statements", outcome.statistics.statements.into()),
+                ("steps", outcome.statistics.steps.into()),
+            ]),
+        ));
+        println!("{}", Json::object(fields).to_pretty_string());
+    } else {
+        if let (Some(value), false) = (&outcome.value, output.quiet) {
+            println!("{}", ValueFormatter::default().format(value));
+        }
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        if stats && !output.quiet {
+            eprintln!(
+                "{} statement(s), {} step(s)",
+                outcome.statistics.statements, outcome.statistics.steps
+            );
+        }
+    }
+    if !outcome.succeeded() {
+        process::exit(EXIT_DIAGNOSTICS);
+    }
+}
 
-    let mut lexer = Lexer::new(input);
-    let all_tokens = lexer.tokenize();
+fn run_metrics(args: &[String], output: Output) {
+    let path = file_argument(args, "metrics", output);
 
-    println!("All tokens at once: {:?}", all_tokens);
-    println!();
+    let source = read_source(path, output);
+    let program = parse_file(path, &source, output);
+    if !output.quiet || output.json {
+        println!("{}", metrics::analyze(&program).to_json());
+    }
+}
+
+fn run_doc(args: &[String], output: Output) {
+    let path = file_argument(args, "doc", output);
+
+    let source = read_source(path, output);
+    let title = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+
+    let program = parse_file(path, &source, output);
+    let markdown = doc::to_markdown(&program, &title);
+    output.result(
+        || Json::object(vec![("markdown", markdown.as_str().into())]),
+        || print!("{}", markdown),
+    );
+}
 
-    // Demonstrate lexer state methods
-    println!("=== Lexer State Demo ===");
-    let input = "abc 123";
-    println!("Input: {}", input);
+fn run_reduce(args: &[String], output: Output) {
+    let (path, command) = match args {
+        [path] => (path, None),
+        [path, separator, command @ ..] if separator == "--" && !command.is_empty() => {
+            (path, Some(command))
+        }
+        _ => {
+            output.fail("Usage: oxide reduce <file> [-- <command> [arguments]]");
+        }
+    };
+
+    let source = read_source(path, output);
+    let scratch = env::temp_dir().join(format!("oxide-reduce-{}.ox", process::id()));
+
+    // Whether a candidate still fails, or why that could not be found out
+    let fails = |candidate: &str| -> Result<bool, String> {
+        match command {
+            None => Ok(compile(candidate).is_err()),
+            Some([program, arguments @ ..]) => {
+                fs::write(&scratch, candidate)
+                    .map_err(|error| format!("Error writing {}: {}", scratch.display(), error))?;
+                Command::new(program)
+                    .args(arguments)
+                    .arg(&scratch)
+                    .output()
+                    .map(|output| !output.status.success())
+                    .map_err(|error| format!("Error running {}: {}", program, error))
+            }
+            Some([]) => unreachable!("command is checked to be non-empty"),
+        }
+    };
 
-    let mut lexer = Lexer::new(input);
+    // An input that does not fail has nothing to reduce: a misuse, not an
+    // error in the input
+    let failing = fails(&source);
+    let _ = fs::remove_file(&scratch);
+    match (failing, command) {
+        (Ok(true), _) => {}
+        (Err(message), _) => output.fail(message),
+        (Ok(false), None) => output.fail(format!(
+            "{}: compiles successfully, nothing to reduce",
+            path
+        )),
+        (Ok(false), Some(command)) => output.fail(format!(
+            "{}: `{}` succeeds on it, nothing to reduce",
+            path,
+            command.join(" ")
+        )),
+    }
+
+    let reduced = reduce::reduce(&source, |candidate| fails(candidate).unwrap_or(false));
+    let _ = fs::remove_file(&scratch);
+
+    output.result(
+        || Json::object(vec![("reduced", reduced.as_str().into())]),
+        || {
+            print!("{}", reduced);
+            if !reduced.ends_with('\n') {
+                println!();
+            }
+        },
+    );
+}
 
-    while !lexer.is_at_end() {
-        let pos = lexer.position();
-        let token = lexer.next_token();
-        println!("  Position {}: {:?}", pos, token);
+fn run_corpus(args: &[String], output: Output) {
+    let (dir, record) = match args {
+        [run, dir] if run == "run" => (dir, false),
+        [run, flag, dir] if run == "run" && flag == "--record" => (dir, true),
+        _ => {
+            output.fail("Usage: oxide corpus run [--record] <dir>");
+        }
+    };
+
+    let report = corpus::run_corpus(Path::new(dir), record).unwrap_or_else(|error| {
+        output.fail(format!("Error reading {}: {}", dir, error));
+    });
+
+    if output.json {
+        let failures = report
+            .failures
+            .iter()
+            .map(|(path, failure)| {
+                Json::object(vec![
+                    ("path", path.display().to_string().as_str().into()),
+                    ("failure", failure.to_string().as_str().into()),
+                ])
+            })
+            .collect();
+        let summary = Json::object(vec![
+            ("inputs", report.inputs.into()),
+            ("recorded", report.recorded.into()),
+            ("failures", Json::Array(failures)),
+        ]);
+        println!("{}", summary.to_pretty_string());
+    } else {
+        for (path, failure) in &report.failures {
+            eprintln!("{}: {}", path.display(), failure);
+        }
+        if !output.quiet {
+            println!(
+                "{} input(s), {} recorded, {} failed",
+                report.inputs,
+                report.recorded,
+                report.failures.len()
+            );
+        }
+    }
+    if !report.failures.is_empty() {
+        process::exit(EXIT_DIAGNOSTICS);
+    }
+}
+
+// Returns the single file argument of a command, exiting with usage if it is missing
+fn file_argument<'a>(args: &'a [String], command: &str, output: Output) -> &'a str {
+    match args {
+        [path] => path,
+        _ => {
+            output.fail(format!("Usage: oxide {} <file>", command));
+        }
+    }
+}
 
-        if token == Token::EOF {
-            break;
+// Reads a source file, exiting with a message if it cannot be read
+fn read_source(path: &str, output: Output) -> String {
+    match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            output.fail(format!("Error reading {}: {}", path, error));
         }
     }
 }
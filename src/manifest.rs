@@ -0,0 +1,435 @@
+use crate::format::{FormatOptions, IndentStyle};
+use crate::lint::{LINT_CODES, LintLevel};
+use crate::workspace::{self, CheckOptions};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of a project manifest
+pub const MANIFEST_NAME: &str = "oxide.toml";
+
+/// Language version implemented by this crate, and the only one a manifest may ask for
+pub const LANGUAGE_VERSION: &str = "0.1";
+
+/// Project settings read from an `oxide.toml`. The file is a small subset of
+/// TOML: sections, `key = value` pairs, strings, integers, arrays of strings
+/// and `#` comments.
+///
+/// ```toml
+/// [project]
+/// entry = "src/main.ox"
+/// source-dirs = ["src", "lib"]
+/// language-version = "0.1"
+///
+/// [lints]
+/// redundant-sign = "warn"
+///
+/// [format]
+/// indent-width = 4
+/// indent-style = "spaces"
+/// max-width = 80
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// Directory containing the manifest; relative paths are resolved against it
+    pub root: PathBuf,
+    pub entry: Option<PathBuf>,
+    /// Directories searched for `.ox` files; `src` if not given
+    pub source_dirs: Vec<PathBuf>,
+    pub language_version: String,
+    pub lints: BTreeMap<String, LintLevel>,
+    pub format: FormatOptions,
+}
+
+/// A manifest that could not be read or is not valid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestError {
+    /// One-based line the problem is on, if it is in the file's contents
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ManifestError {
+    fn at(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl Manifest {
+    /// Returns the path of the nearest manifest in `start` or one of its ancestors
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads and parses the manifest at `path`
+    pub fn load(path: &Path) -> Result<Self, ManifestError> {
+        let text = fs::read_to_string(path).map_err(|error| ManifestError {
+            line: None,
+            message: format!("could not read {}: {}", path.display(), error),
+        })?;
+        let root = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&text, root)
+    }
+
+    /// Parses manifest text for a project rooted at `root`
+    pub fn parse(text: &str, root: &Path) -> Result<Self, ManifestError> {
+        let mut manifest = Manifest {
+            root: root.to_path_buf(),
+            entry: None,
+            source_dirs: vec![root.join("src")],
+            language_version: LANGUAGE_VERSION.to_string(),
+            lints: BTreeMap::new(),
+            format: FormatOptions::default(),
+        };
+        let mut section = String::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                section = name.trim().to_string();
+                if !matches!(section.as_str(), "project" | "lints" | "format") {
+                    return Err(ManifestError::at(
+                        number,
+                        format!("unknown section [{}]", section),
+                    ));
+                }
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ManifestError::at(number, "expected `key = value`"))?;
+            let key = key.trim();
+            let value =
+                parse_value(value.trim()).map_err(|message| ManifestError::at(number, message))?;
+
+            manifest
+                .set(&section, key, value, root)
+                .map_err(|message| ManifestError::at(number, message))?;
+        }
+
+        Ok(manifest)
+    }
+
+    /// Returns the entry file and every `.ox` file in the source directories,
+    /// sorted and without duplicates. Source directories that do not exist are skipped.
+    pub fn source_files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for dir in self.source_dirs.iter().filter(|dir| dir.is_dir()) {
+            files.extend(workspace::source_files(dir)?);
+        }
+        files.extend(self.entry.clone());
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Check settings configured by the manifest
+    pub fn check_options(&self) -> CheckOptions {
+        CheckOptions {
+            lints: self.lints.clone(),
+            ..CheckOptions::default()
+        }
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: Value, root: &Path) -> Result<(), String> {
+        match (section, key, value) {
+            ("project", "entry", Value::String(path)) => self.entry = Some(root.join(path)),
+            ("project", "source-dirs", Value::Array(dirs)) => {
+                self.source_dirs = dirs.iter().map(|dir| root.join(dir)).collect();
+            }
+            ("project", "language-version", Value::String(version)) => {
+                if version != LANGUAGE_VERSION {
+                    return Err(format!(
+                        "language version {} is not supported; this is version {}",
+                        version, LANGUAGE_VERSION
+                    ));
+                }
+                self.language_version = version;
+            }
+            ("lints", code, Value::String(level)) => {
+                if !LINT_CODES.contains(&code) {
+                    return Err(format!("unknown lint `{}`", code));
+                }
+                let level = LintLevel::from_name(&level).ok_or_else(|| {
+                    format!(
+                        "lint level must be \"allow\", \"warn\" or \"deny\", not \"{}\"",
+                        level
+                    )
+                })?;
+                self.lints.insert(code.to_string(), level);
+            }
+            ("format", "indent-width", Value::Integer(width)) => {
+                self.format.indent_width = usize::try_from(width)
+                    .map_err(|_| "indent width cannot be negative".to_string())?;
+            }
+            ("format", "indent-style", Value::String(style)) => {
+                self.format.indent_style = match style.as_str() {
+                    "spaces" => IndentStyle::Spaces,
+                    "tabs" => IndentStyle::Tabs,
+                    _ => {
+                        return Err(format!(
+                            "indent style must be \"spaces\" or \"tabs\", not \"{}\"",
+                            style
+                        ));
+                    }
+                };
+            }
+            ("format", "max-width", Value::Integer(width)) => {
+                self.format.max_width = usize::try_from(width)
+                    .map_err(|_| "max width cannot be negative".to_string())?;
+            }
+            ("", key, _) => return Err(format!("`{}` must be inside a section", key)),
+            (section, key, value) if is_known(section, key) => {
+                return Err(format!("`{}` cannot be {}", key, value.describe()));
+            }
+            (section, key, _) => return Err(format!("unknown key `{}` in [{}]", key, section)),
+        }
+        Ok(())
+    }
+}
+
+fn is_known(section: &str, key: &str) -> bool {
+    matches!(
+        (section, key),
+        ("project", "entry" | "source-dirs" | "language-version")
+            | ("lints", _)
+            | ("format", "indent-width" | "indent-style" | "max-width")
+    )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Array(Vec<String>),
+}
+
+impl Value {
+    fn describe(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or("arrays must be closed on the same line")?
+            .trim();
+        if inner.is_empty() {
+            return Ok(Value::Array(Vec::new()));
+        }
+        return inner
+            .trim_end_matches(',')
+            .split(',')
+            .map(|item| match parse_value(item.trim())? {
+                Value::String(item) => Ok(item),
+                _ => Err("arrays may only contain strings".to_string()),
+            })
+            .collect::<Result<_, _>>()
+            .map(Value::Array);
+    }
+
+    if let Some(inner) = text.strip_prefix('"') {
+        let inner = inner.strip_suffix('"').ok_or("unterminated string")?;
+        return unescape(inner).map(Value::String);
+    }
+
+    text.parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("`{}` is not a string, integer or array", text))
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                other => return Err(format!("invalid escape `\\{}`", other.unwrap_or(' '))),
+            },
+            '"' => return Err("unexpected `\"` in string".to_string()),
+            ch => out.push(ch),
+        }
+    }
+    Ok(out)
+}
+
+// Drops a `#` comment, ignoring `#` inside strings
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Manifest, ManifestError> {
+        Manifest::parse(text, Path::new("/project"))
+    }
+
+    #[test]
+    fn test_full_manifest() {
+        let manifest = parse(
+            "# Example project\n\
+             [project]\n\
+             entry = \"src/main.ox\"   # where execution starts\n\
+             source-dirs = [\"src\", \"lib#1\",]\n\
+             language-version = \"0.1\"\n\
+             \n\
+             [lints]\n\
+             redundant-sign = \"deny\"\n\
+             \n\
+             [format]\n\
+             indent-width = 4\n\
+             indent-style = \"tabs\"\n\
+             max-width = 80\n",
+        )
+        .unwrap();
+
+        assert_eq!(manifest.entry, Some(PathBuf::from("/project/src/main.ox")));
+        assert_eq!(
+            manifest.source_dirs,
+            vec![
+                PathBuf::from("/project/src"),
+                PathBuf::from("/project/lib#1")
+            ]
+        );
+        assert_eq!(manifest.lints.get("redundant-sign"), Some(&LintLevel::Deny));
+        assert_eq!(manifest.format.indent_width, 4);
+        assert_eq!(manifest.format.indent_style, IndentStyle::Tabs);
+        assert_eq!(manifest.format.max_width, 80);
+        assert_eq!(manifest.check_options().lints, manifest.lints);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let manifest = parse("").unwrap();
+
+        assert_eq!(manifest.entry, None);
+        assert_eq!(manifest.source_dirs, vec![PathBuf::from("/project/src")]);
+        assert_eq!(manifest.language_version, LANGUAGE_VERSION);
+        assert_eq!(manifest.format, FormatOptions::default());
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+
+        assert_eq!(error("[package]"), "line 1: unknown section [package]");
+        assert_eq!(
+            error("entry = \"a.ox\""),
+            "line 1: `entry` must be inside a section"
+        );
+        assert_eq!(
+            error("[project]\nname = \"x\""),
+            "line 2: unknown key `name` in [project]"
+        );
+        assert_eq!(
+            error("[project]\nentry = 3"),
+            "line 2: `entry` cannot be an integer"
+        );
+        assert_eq!(
+            error("[project]\nlanguage-version = \"2.0\""),
+            "line 2: language version 2.0 is not supported; this is version 0.1"
+        );
+        assert_eq!(
+            error("[lints]\nshadowing = \"warn\""),
+            "line 2: unknown lint `shadowing`"
+        );
+        assert_eq!(
+            error("[lints]\nredundant-sign = \"loud\""),
+            "line 2: lint level must be \"allow\", \"warn\" or \"deny\", not \"loud\""
+        );
+        assert_eq!(
+            error("[format]\nindent-style = \"wide\""),
+            "line 2: indent style must be \"spaces\" or \"tabs\", not \"wide\""
+        );
+        assert_eq!(
+            error("[format]\nindent-width"),
+            "line 2: expected `key = value`"
+        );
+        assert_eq!(
+            error("[project]\nentry = \"a"),
+            "line 2: unterminated string"
+        );
+    }
+
+    #[test]
+    fn test_find_and_load() {
+        let root = std::env::temp_dir().join(format!("oxide-manifest-{}", std::process::id()));
+        let nested = root.join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            root.join(MANIFEST_NAME),
+            "[project]\nentry = \"main.ox\"\n[format]\nindent-width = 8\n",
+        )
+        .unwrap();
+        fs::write(nested.join("b.ox"), "").unwrap();
+        fs::write(root.join("src/a.ox"), "").unwrap();
+
+        let found = Manifest::find(&nested).unwrap();
+        let manifest = Manifest::load(&found).unwrap();
+        let files = manifest.source_files().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(found, root.join(MANIFEST_NAME));
+        assert_eq!(manifest.root, root);
+        assert_eq!(manifest.format.indent_width, 8);
+        assert_eq!(
+            files,
+            vec![
+                root.join("main.ox"),
+                root.join("src/a.ox"),
+                root.join("src/deep/b.ox")
+            ]
+        );
+    }
+}
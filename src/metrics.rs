@@ -0,0 +1,141 @@
+use crate::json::Json;
+use crate::parser::{Expr, Program, Stmt};
+
+/// Size and shape measurements for a parsed program
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metrics {
+    /// Total number of statements, including those nested in blocks
+    pub statements: usize,
+    pub let_statements: usize,
+    pub expression_statements: usize,
+    pub blocks: usize,
+    /// Deepest block nesting; top-level statements are at depth 0
+    pub max_nesting_depth: usize,
+    /// Largest number of nodes on a root-to-leaf path of any expression
+    pub max_expression_depth: usize,
+    /// McCabe complexity of the top-level code: one plus the number of decision points
+    pub cyclomatic_complexity: usize,
+}
+
+impl Metrics {
+    /// Serializes the metrics as a pretty-printed JSON object
+    pub fn to_json(&self) -> String {
+        Json::object(vec![
+            ("statements", self.statements.into()),
+            ("let_statements", self.let_statements.into()),
+            ("expression_statements", self.expression_statements.into()),
+            ("blocks", self.blocks.into()),
+            ("max_nesting_depth", self.max_nesting_depth.into()),
+            ("max_expression_depth", self.max_expression_depth.into()),
+            ("cyclomatic_complexity", self.cyclomatic_complexity.into()),
+        ])
+        .to_pretty_string()
+    }
+}
+
+/// Computes metrics for a whole program
+pub fn analyze(program: &Program) -> Metrics {
+    let mut metrics = Metrics {
+        // The language has no branching constructs yet, so every program has a single path
+        cyclomatic_complexity: 1,
+        ..Metrics::default()
+    };
+
+    for stmt in &program.statements {
+        visit_statement(stmt, 0, &mut metrics);
+    }
+
+    metrics
+}
+
+/// Returns the number of nodes on the longest root-to-leaf path of an expression
+pub fn expression_depth(expr: &Expr) -> usize {
+    match expr {
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => 1,
+        Expr::Binary { left, right, .. } => 1 + expression_depth(left).max(expression_depth(right)),
+        Expr::Unary { operand, .. } => 1 + expression_depth(operand),
+        Expr::Grouping(inner) => 1 + expression_depth(inner),
+        Expr::Tuple(elements) => 1 + elements.iter().map(expression_depth).max().unwrap_or(0),
+    }
+}
+
+fn visit_statement(stmt: &Stmt, depth: usize, metrics: &mut Metrics) {
+    metrics.statements += 1;
+
+    match stmt {
+        Stmt::Let { value, .. } | Stmt::Destructure { value, .. } => {
+            metrics.let_statements += 1;
+            visit_expression(value, metrics);
+        }
+        Stmt::Expression(expr) => {
+            metrics.expression_statements += 1;
+            visit_expression(expr, metrics);
+        }
+        Stmt::Block(statements) => {
+            metrics.blocks += 1;
+            metrics.max_nesting_depth = metrics.max_nesting_depth.max(depth + 1);
+            for stmt in statements {
+                visit_statement(stmt, depth + 1, metrics);
+            }
+        }
+    }
+}
+
+fn visit_expression(expr: &Expr, metrics: &mut Metrics) {
+    metrics.max_expression_depth = metrics.max_expression_depth.max(expression_depth(expr));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    #[test]
+    fn test_statement_counts() {
+        let program = parse_source("let x = 1; x + 2; { let y = 3; }").unwrap();
+        let metrics = analyze(&program);
+
+        assert_eq!(metrics.statements, 4);
+        assert_eq!(metrics.let_statements, 2);
+        assert_eq!(metrics.expression_statements, 1);
+        assert_eq!(metrics.blocks, 1);
+        assert_eq!(metrics.cyclomatic_complexity, 1);
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let program = parse_source("1; { 2; { { 3; } } } { 4; }").unwrap();
+
+        assert_eq!(analyze(&program).max_nesting_depth, 3);
+    }
+
+    #[test]
+    fn test_expression_depth() {
+        let program = parse_source("let a = 1; let b = -(1 + 2 * 3);").unwrap();
+
+        // Unary -> Grouping -> Binary(+) -> Binary(*) -> Number
+        assert_eq!(analyze(&program).max_expression_depth, 5);
+    }
+
+    #[test]
+    fn test_empty_program() {
+        let metrics = analyze(&Program::new());
+
+        assert_eq!(metrics.statements, 0);
+        assert_eq!(metrics.max_nesting_depth, 0);
+        assert_eq!(metrics.max_expression_depth, 0);
+    }
+
+    #[test]
+    fn test_json_output() {
+        let program = parse_source("let x = 1;").unwrap();
+        let json = analyze(&program).to_json();
+
+        assert!(json.starts_with("{\n  \"statements\": 1,"));
+        assert!(json.contains("\"max_expression_depth\": 1"));
+    }
+}
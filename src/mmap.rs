@@ -0,0 +1,72 @@
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// A source file mapped into memory rather than read into a `String`, which
+/// saves a copy of large inputs. Only available with the `mmap` feature.
+pub struct MappedSource {
+    // Mapping an empty file fails on some platforms, so empty files have no map
+    map: Option<Mmap>,
+}
+
+impl MappedSource {
+    /// Maps the file at `path`, failing if it is not valid UTF-8
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let map = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            // SAFETY: the map is read-only. Another process truncating or
+            // rewriting the file while it is mapped is outside what we can
+            // guard against, as with any memory-mapped input.
+            Some(unsafe { Mmap::map(&file)? })
+        };
+
+        let source = Self { map };
+        std::str::from_utf8(source.bytes())
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(source)
+    }
+
+    /// Returns the mapped text
+    pub fn as_str(&self) -> &str {
+        // The contents were validated as UTF-8 in `open`
+        std::str::from_utf8(self.bytes()).unwrap_or_default()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_maps_files() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("oxide-mmap-{}.ox", std::process::id()));
+        let empty = dir.join(format!("oxide-mmap-empty-{}.ox", std::process::id()));
+        let invalid = dir.join(format!("oxide-mmap-invalid-{}.ox", std::process::id()));
+        fs::write(&path, "let x = 1;").unwrap();
+        fs::write(&empty, "").unwrap();
+        fs::write(&invalid, b"\xff").unwrap();
+
+        let mapped = MappedSource::open(&path).map(|source| source.as_str().to_string());
+        let mapped_empty = MappedSource::open(&empty).map(|source| source.as_str().to_string());
+        let mapped_invalid = MappedSource::open(&invalid).map(|_| ());
+        for file in [&path, &empty, &invalid] {
+            fs::remove_file(file).unwrap();
+        }
+
+        assert_eq!(mapped.unwrap(), "let x = 1;");
+        assert_eq!(mapped_empty.unwrap(), "");
+        assert_eq!(
+            mapped_invalid.unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
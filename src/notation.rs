@@ -0,0 +1,292 @@
+//! Expressions in other notations than the source syntax: fully
+//! parenthesized infix, postfix (reverse Polish) and prefix (Polish), for
+//! teaching tools and stack-based evaluators, and a reader for postfix input.
+//!
+//! Postfix and prefix tokens are separated by spaces. Signs are written `u-`
+//! and `u+` to tell them from the binary operators, and `(n)` builds a tuple
+//! of the `n` operands before it in postfix, or after it in prefix. Groupings
+//! only affect how source text parses, so they are left out.
+
+use crate::lexer::{KEYWORDS, Span};
+use crate::parser::{BinaryOp, Expr, UnaryOp};
+use std::fmt;
+
+/// Renders `expr` with every operation in parentheses, as in `((1 + 2) * 3)`
+pub fn to_infix(expr: &Expr) -> String {
+    let mut expr = expr.clone();
+    expr.strip_groupings();
+    expr.to_string()
+}
+
+/// Renders `expr` in reverse Polish notation, as in `1 2 + 3 *`
+pub fn to_postfix(expr: &Expr) -> String {
+    let mut tokens = Vec::new();
+    postfix_tokens(expr, &mut tokens);
+    tokens.join(" ")
+}
+
+/// Renders `expr` in Polish notation, as in `* + 1 2 3`
+pub fn to_prefix(expr: &Expr) -> String {
+    let mut tokens = Vec::new();
+    prefix_tokens(expr, &mut tokens);
+    tokens.join(" ")
+}
+
+fn postfix_tokens(expr: &Expr, tokens: &mut Vec<String>) {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            postfix_tokens(left, tokens);
+            postfix_tokens(right, tokens);
+            tokens.push(operator.to_string());
+        }
+        Expr::Unary { operator, operand } => {
+            postfix_tokens(operand, tokens);
+            tokens.push(format!("u{}", operator));
+        }
+        Expr::Tuple(elements) => {
+            for element in elements {
+                postfix_tokens(element, tokens);
+            }
+            tokens.push(format!("({})", elements.len()));
+        }
+        Expr::Grouping(inner) => postfix_tokens(inner, tokens),
+        atom => tokens.push(atom.to_string()),
+    }
+}
+
+fn prefix_tokens(expr: &Expr, tokens: &mut Vec<String>) {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            tokens.push(operator.to_string());
+            prefix_tokens(left, tokens);
+            prefix_tokens(right, tokens);
+        }
+        Expr::Unary { operator, operand } => {
+            tokens.push(format!("u{}", operator));
+            prefix_tokens(operand, tokens);
+        }
+        Expr::Tuple(elements) => {
+            tokens.push(format!("({})", elements.len()));
+            for element in elements {
+                prefix_tokens(element, tokens);
+            }
+        }
+        Expr::Grouping(inner) => prefix_tokens(inner, tokens),
+        atom => tokens.push(atom.to_string()),
+    }
+}
+
+/// Why postfix input could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostfixError {
+    /// A token that is not a number, name, operator or tuple size
+    InvalidToken { token: String, span: Span },
+    /// An operator or tuple with fewer operands before it than it takes
+    MissingOperands {
+        token: String,
+        span: Span,
+        expected: usize,
+        found: usize,
+    },
+    /// The input left no expression, or several, instead of one
+    WrongResultCount(usize),
+}
+
+impl fmt::Display for PostfixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostfixError::InvalidToken { token, span } => {
+                write!(f, "`{}` at {} is not a postfix token", token, span)
+            }
+            PostfixError::MissingOperands {
+                token,
+                span,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` at {} takes {} operand(s) but {} precede it",
+                token, span, expected, found
+            ),
+            PostfixError::WrongResultCount(count) => {
+                write!(f, "expected one expression, found {}", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PostfixError {}
+
+/// Reads an expression written in reverse Polish notation, as produced by
+/// [`to_postfix`]. Numbers may carry a suffix, as in `10px` or `15%`; any is
+/// accepted, since there is no registry to check it against.
+pub fn parse_postfix(input: &str) -> Result<Expr, PostfixError> {
+    let mut stack: Vec<Expr> = Vec::new();
+
+    for (token, span) in words(input) {
+        let pop = |stack: &mut Vec<Expr>, expected: usize| {
+            if stack.len() < expected {
+                return Err(PostfixError::MissingOperands {
+                    token: token.to_string(),
+                    span,
+                    expected,
+                    found: stack.len(),
+                });
+            }
+            Ok(stack.split_off(stack.len() - expected))
+        };
+
+        let binary = BinaryOp::all()
+            .into_iter()
+            .find(|op| op.to_string() == token);
+        let unary = UnaryOp::all()
+            .into_iter()
+            .find(|op| format!("u{}", op) == token);
+        let size = token
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|size| size.parse::<usize>().ok());
+
+        let expr = if let Some(operator) = binary {
+            let mut operands = pop(&mut stack, 2)?;
+            let right = operands.pop().unwrap();
+            let left = operands.pop().unwrap();
+            Expr::binary(left, operator, right)
+        } else if let Some(operator) = unary {
+            let operand = pop(&mut stack, 1)?.pop().unwrap();
+            Expr::unary(operator, operand)
+        } else if let Some(size) = size {
+            Expr::tuple(pop(&mut stack, size)?)
+        } else {
+            atom(token).ok_or_else(|| PostfixError::InvalidToken {
+                token: token.to_string(),
+                span,
+            })?
+        };
+        stack.push(expr);
+    }
+
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap()),
+        count => Err(PostfixError::WrongResultCount(count)),
+    }
+}
+
+// The whitespace-separated words of `input` with their spans
+fn words(input: &str) -> impl Iterator<Item = (&str, Span)> {
+    input.split_whitespace().map(move |word| {
+        let start = word.as_ptr() as usize - input.as_ptr() as usize;
+        (word, Span::new(start, start + word.len()))
+    })
+}
+
+// A number, possibly suffixed, a float, or a name
+fn atom(token: &str) -> Option<Expr> {
+    // Floats are read as the lexer reads them, so `1e5` is not a suffix
+    if token.starts_with(|ch: char| ch.is_ascii_digit()) && token.contains(['.', 'e', 'E']) {
+        if let Some(value) = token.parse::<f64>().ok().filter(|value| value.is_finite()) {
+            return Some(Expr::Float(value));
+        }
+    }
+    let digits = token
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(token.len());
+    let (number, suffix) = token.split_at(digits);
+    let is_name = |text: &str| {
+        let mut chars = text.chars();
+        chars
+            .next()
+            .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+            && !KEYWORDS.contains(&text)
+    };
+
+    match (number.parse::<i64>().ok(), suffix) {
+        (Some(value), "") => Some(Expr::number(value)),
+        (Some(value), suffix) if suffix == "%" || is_name(suffix) => {
+            Some(Expr::suffixed(value, suffix.to_string()))
+        }
+        (None, name) if number.is_empty() && is_name(name) => {
+            Some(Expr::identifier(name.to_string()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::expression;
+
+    #[test]
+    fn test_notations() {
+        let expr = expression("(1 + 2) * -x / (a, (b)) - 3px");
+
+        assert_eq!(to_infix(&expr), "((((1 + 2) * (-x)) / (a, b)) - 3px)");
+        assert_eq!(to_postfix(&expr), "1 2 + x u- * a b (2) / 3px -");
+        assert_eq!(to_prefix(&expr), "- / * + 1 2 u- x (2) a b 3px");
+        assert_eq!(to_postfix(&expression("((7),)")), "7 (1)");
+        assert_eq!(to_prefix(&expression("+()")), "u+ (0)");
+    }
+
+    #[test]
+    fn test_postfix_round_trips() {
+        let sources = [
+            "1 - 2 - 3",
+            "1 - (2 - 3)",
+            "--x * +(y / 15)",
+            "((1, 2), (), (rate,), 10px)",
+            "0.5 * 2.5e-3 - -1.0",
+        ];
+        for source in sources {
+            let mut expr = expression(source);
+            expr.strip_groupings();
+            assert_eq!(parse_postfix(&to_postfix(&expr)), Ok(expr), "{}", source);
+        }
+        assert_eq!(
+            to_infix(&parse_postfix("  200 15% *\n").unwrap()),
+            "(200 * 15%)"
+        );
+    }
+
+    #[test]
+    fn test_postfix_errors() {
+        assert_eq!(
+            parse_postfix("1 +"),
+            Err(PostfixError::MissingOperands {
+                token: "+".to_string(),
+                span: Span::new(2, 3),
+                expected: 2,
+                found: 1,
+            })
+        );
+        assert_eq!(
+            parse_postfix("1 2 (3)").unwrap_err().to_string(),
+            "`(3)` at 4..7 takes 3 operand(s) but 2 precede it"
+        );
+        assert_eq!(
+            parse_postfix("x let").unwrap_err().to_string(),
+            "`let` at 2..5 is not a postfix token"
+        );
+        for invalid in ["1x2?", "99999999999999999999", "(-1)", "%", "-1"] {
+            assert!(
+                matches!(
+                    parse_postfix(invalid),
+                    Err(PostfixError::InvalidToken { .. })
+                ),
+                "{}",
+                invalid
+            );
+        }
+        assert_eq!(parse_postfix("1 2"), Err(PostfixError::WrongResultCount(2)));
+        assert_eq!(parse_postfix(" "), Err(PostfixError::WrongResultCount(0)));
+    }
+}
@@ -1,8 +1,14 @@
 use crate::lexer::Token;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expr {
     Number(i64),
+    /// A literal with a decimal point or an exponent, such as `3.14` or `1e5`
+    Float(f64),
+    /// An integer literal too large for 64 bits, as its decimal digits. Only
+    /// lexed with the `bigint` feature, and evaluated to a big integer.
+    BigNumber(String),
     Identifier(String),
     Binary {
         left: Box<Expr>,
@@ -14,9 +20,17 @@ pub enum Expr {
         operand: Box<Expr>,
     },
     Grouping(Box<Expr>),
+    /// `()`, `(a,)` or `(a, b, ...)`; a single parenthesized expression is a Grouping
+    Tuple(Vec<Expr>),
+    /// An integer literal with a suffix registered by the host, such as `10px`
+    Suffixed {
+        value: i64,
+        suffix: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOp {
     Add,
     Subtract,
@@ -24,19 +38,57 @@ pub enum BinaryOp {
     Divide,
 }
 
+/// Which side repeated operators of equal precedence group towards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` parses as `(a - b) - c`
+    Left,
+    /// `a ** b ** c` parses as `a ** (b ** c)`
+    Right,
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOp {
     Negate,
+    Plus,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stmt {
-    Let { name: String, value: Expr },
+    Let {
+        name: String,
+        value: Expr,
+        doc: Option<String>,
+    },
+    /// `let (a, _) = value;`, binding the parts of a value matched by a pattern
+    Destructure {
+        pattern: Pattern,
+        value: Expr,
+        doc: Option<String>,
+    },
     Expression(Expr),
     Block(Vec<Stmt>),
 }
 
+/// The shape a value is matched against, in destructuring lets and (once the
+/// language has them) match arms
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    /// `_`, which matches anything and binds nothing
+    Wildcard,
+    /// An integer literal, which matches only that value
+    Literal(i64),
+    /// A name, which matches anything and binds it
+    Identifier(String),
+    /// `()`, `(p,)` or `(p, q, ...)`, which matches a tuple of that length
+    Tuple(Vec<Pattern>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     pub statements: Vec<Stmt>,
 }
@@ -51,6 +103,19 @@ impl Program {
     pub fn add_statement(&mut self, stmt: Stmt) {
         self.statements.push(stmt);
     }
+
+    /// Appends every statement of another program, in order
+    pub fn extend(&mut self, other: Program) {
+        self.statements.extend(other.statements);
+    }
+
+    /// Removes every Grouping node. Precedence is already encoded in the shape
+    /// of the tree, so this does not change what the program means.
+    pub fn strip_groupings(&mut self) {
+        for stmt in &mut self.statements {
+            stmt.strip_groupings();
+        }
+    }
 }
 
 impl Default for Program {
@@ -87,6 +152,69 @@ impl Expr {
     pub fn grouping(expr: Expr) -> Self {
         Expr::Grouping(Box::new(expr))
     }
+
+    pub fn tuple(elements: Vec<Expr>) -> Self {
+        Expr::Tuple(elements)
+    }
+
+    pub fn suffixed(value: i64, suffix: String) -> Self {
+        Expr::Suffixed { value, suffix }
+    }
+
+    /// Removes every Grouping node from this expression; see [`Program::strip_groupings`]
+    pub fn strip_groupings(&mut self) {
+        match self {
+            Expr::Number(_)
+            | Expr::Float(_)
+            | Expr::BigNumber(_)
+            | Expr::Identifier(_)
+            | Expr::Suffixed { .. } => {}
+            Expr::Binary { left, right, .. } => {
+                left.strip_groupings();
+                right.strip_groupings();
+            }
+            Expr::Unary { operand, .. } => operand.strip_groupings(),
+            Expr::Tuple(elements) => {
+                for element in elements {
+                    element.strip_groupings();
+                }
+            }
+            Expr::Grouping(inner) => {
+                inner.strip_groupings();
+                let inner = std::mem::replace(inner.as_mut(), Expr::Number(0));
+                *self = inner;
+            }
+        }
+    }
+}
+
+impl Pattern {
+    pub fn identifier(name: String) -> Self {
+        Pattern::Identifier(name)
+    }
+
+    pub fn tuple(elements: Vec<Pattern>) -> Self {
+        Pattern::Tuple(elements)
+    }
+
+    /// Returns the names the pattern binds, from left to right
+    pub fn bindings(&self) -> Vec<&str> {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => Vec::new(),
+            Pattern::Identifier(name) => vec![name.as_str()],
+            Pattern::Tuple(elements) => elements.iter().flat_map(Pattern::bindings).collect(),
+        }
+    }
+
+    /// Returns true if the pattern matches every value of the right shape, as
+    /// the pattern of a `let` must. Only literals can fail to match.
+    pub fn is_irrefutable(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Identifier(_) => true,
+            Pattern::Literal(_) => false,
+            Pattern::Tuple(elements) => elements.iter().all(Pattern::is_irrefutable),
+        }
+    }
 }
 
 impl BinaryOp {
@@ -106,20 +234,81 @@ impl BinaryOp {
             BinaryOp::Multiply | BinaryOp::Divide => 2,
         }
     }
+
+    pub fn associativity(&self) -> Associativity {
+        match self {
+            BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide => {
+                Associativity::Left
+            }
+        }
+    }
+
+    /// Every binary operator, in declaration order
+    pub fn all() -> [BinaryOp; 4] {
+        [
+            BinaryOp::Add,
+            BinaryOp::Subtract,
+            BinaryOp::Multiply,
+            BinaryOp::Divide,
+        ]
+    }
 }
 
 impl UnaryOp {
     pub fn from_token(token: &Token) -> Option<Self> {
         match token {
             Token::Minus => Some(UnaryOp::Negate),
+            Token::Plus => Some(UnaryOp::Plus),
             _ => None,
         }
     }
+
+    /// Every unary operator, in declaration order
+    pub fn all() -> [UnaryOp; 2] {
+        [UnaryOp::Negate, UnaryOp::Plus]
+    }
 }
 
 impl Stmt {
     pub fn let_statement(name: String, value: Expr) -> Self {
-        Stmt::Let { name, value }
+        Stmt::Let {
+            name,
+            value,
+            doc: None,
+        }
+    }
+
+    pub fn documented_let(name: String, value: Expr, doc: String) -> Self {
+        Stmt::Let {
+            name,
+            value,
+            doc: Some(doc),
+        }
+    }
+
+    /// Returns the names this statement binds in its enclosing block
+    pub fn bound_names(&self) -> Vec<&str> {
+        match self {
+            Stmt::Let { name, .. } => vec![name.as_str()],
+            Stmt::Destructure { pattern, .. } => pattern.bindings(),
+            Stmt::Expression(_) | Stmt::Block(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the doc comment attached to this statement, if any
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Stmt::Let { doc, .. } | Stmt::Destructure { doc, .. } => doc.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn destructure(pattern: Pattern, value: Expr) -> Self {
+        Stmt::Destructure {
+            pattern,
+            value,
+            doc: None,
+        }
     }
 
     pub fn expression(expr: Expr) -> Self {
@@ -129,6 +318,19 @@ impl Stmt {
     pub fn block(statements: Vec<Stmt>) -> Self {
         Stmt::Block(statements)
     }
+
+    /// Removes every Grouping node from this statement; see [`Program::strip_groupings`]
+    pub fn strip_groupings(&mut self) {
+        match self {
+            Stmt::Let { value, .. } | Stmt::Destructure { value, .. } => value.strip_groupings(),
+            Stmt::Expression(expr) => expr.strip_groupings(),
+            Stmt::Block(statements) => {
+                for stmt in statements {
+                    stmt.strip_groupings();
+                }
+            }
+        }
+    }
 }
 
 // Display implementations for pretty printing
@@ -136,6 +338,9 @@ impl std::fmt::Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Expr::Number(n) => write!(f, "{}", n),
+            // Debug keeps the point or exponent that makes it a float again
+            Expr::Float(n) => write!(f, "{:?}", n),
+            Expr::BigNumber(digits) => write!(f, "{}", digits),
             Expr::Identifier(name) => write!(f, "{}", name),
             Expr::Binary {
                 left,
@@ -147,7 +352,47 @@ impl std::fmt::Display for Expr {
             Expr::Unary { operator, operand } => {
                 write!(f, "({}{})", operator, operand)
             }
-            Expr::Grouping(expr) => write!(f, "({})", expr),
+            // Binary and unary expressions already print their own parentheses,
+            // so only atoms need an extra pair. This keeps display -> parse ->
+            // display stable instead of adding a layer of parentheses each time.
+            Expr::Grouping(expr) => match expr.as_ref() {
+                Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Identifier(_) => {
+                    write!(f, "({})", expr)
+                }
+                _ => write!(f, "{}", expr),
+            },
+            Expr::Tuple(elements) => write_tuple(f, elements),
+            Expr::Suffixed { value, suffix } => write!(f, "{}{}", value, suffix),
+        }
+    }
+}
+
+/// Writes comma-separated elements in parentheses. A one-element tuple keeps
+/// its trailing comma, which is what makes it a tuple.
+fn write_tuple<T: std::fmt::Display>(
+    f: &mut std::fmt::Formatter<'_>,
+    elements: &[T],
+) -> std::fmt::Result {
+    write!(f, "(")?;
+    for (index, element) in elements.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", element)?;
+    }
+    if elements.len() == 1 {
+        write!(f, ",")?;
+    }
+    write!(f, ")")
+}
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Literal(n) => write!(f, "{}", n),
+            Pattern::Identifier(name) => write!(f, "{}", name),
+            Pattern::Tuple(elements) => write_tuple(f, elements),
         }
     }
 }
@@ -167,26 +412,47 @@ impl std::fmt::Display for UnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UnaryOp::Negate => write!(f, "-"),
+            UnaryOp::Plus => write!(f, "+"),
         }
     }
 }
 
-impl std::fmt::Display for Stmt {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Stmt {
+    /// Writes the statement with every line indented by `depth` levels
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+
+        if let Some(doc) = self.doc() {
+            for line in doc.split('\n') {
+                writeln!(f, "{}{}", indent, Token::DocComment(line.to_string()))?;
+            }
+        }
+
         match self {
-            Stmt::Let { name, value } => write!(f, "let {} = {};", name, value),
-            Stmt::Expression(expr) => write!(f, "{};", expr),
+            Stmt::Let { name, value, .. } => write!(f, "{}let {} = {};", indent, name, value),
+            Stmt::Destructure { pattern, value, .. } => {
+                write!(f, "{}let {} = {};", indent, pattern, value)
+            }
+            Stmt::Expression(expr) => write!(f, "{}{};", indent, expr),
+            Stmt::Block(statements) if statements.is_empty() => write!(f, "{}{{}}", indent),
             Stmt::Block(statements) => {
-                writeln!(f, "{{")?;
+                writeln!(f, "{}{{", indent)?;
                 for stmt in statements {
-                    writeln!(f, "  {}", stmt)?;
+                    stmt.fmt_indented(f, depth + 1)?;
+                    writeln!(f)?;
                 }
-                write!(f, "}}")
+                write!(f, "{}}}", indent)
             }
         }
     }
 }
 
+impl std::fmt::Display for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
 impl std::fmt::Display for Program {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for stmt in &self.statements {
@@ -195,3 +461,81 @@ impl std::fmt::Display for Program {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    #[test]
+    fn test_strip_groupings_preserves_precedence() {
+        let mut program = parse_source("let x = ((1 + 2)) * -(3);").unwrap();
+        program.strip_groupings();
+
+        assert_eq!(
+            program.statements[0],
+            Stmt::let_statement(
+                "x".to_string(),
+                Expr::binary(
+                    Expr::binary(Expr::number(1), BinaryOp::Add, Expr::number(2)),
+                    BinaryOp::Multiply,
+                    Expr::unary(UnaryOp::Negate, Expr::number(3)),
+                ),
+            )
+        );
+        assert_eq!(program.to_string(), "let x = ((1 + 2) * (-3));\n");
+    }
+
+    #[test]
+    fn test_nested_blocks_indent_cumulatively() {
+        let program = parse_source("{ 1; { /// Inner.\n let x = 2; { } { 3; } } }").unwrap();
+
+        assert_eq!(
+            program.to_string(),
+            "{\n  1;\n  {\n    /// Inner.\n    let x = 2;\n    {}\n    {\n      3;\n    }\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_display_is_stable_when_reparsed() {
+        let source = "/// Sum.\n///\nlet s = (a + (b)) * ((-c));\n{ { (1 - 2) - 3; } }";
+        let first = parse_source(source).unwrap().to_string();
+        let second = parse_source(&first).unwrap().to_string();
+
+        assert_eq!(first, second);
+        assert!(first.lines().all(|line| line == line.trim_end()));
+        assert!(first.starts_with("/// Sum.\n///\nlet s = ((a + (b)) * (-c));"));
+    }
+
+    #[test]
+    fn test_strip_groupings_in_blocks() {
+        let mut program = parse_source("{ (a); { ((b)); } }").unwrap();
+        program.strip_groupings();
+
+        assert_eq!(
+            program.statements[0],
+            Stmt::block(vec![
+                Stmt::expression(Expr::identifier("a".to_string())),
+                Stmt::block(vec![Stmt::expression(Expr::identifier("b".to_string()))]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_tuples_display_and_reparse() {
+        let source = "let (a, b) = ((), (1,), (x, (-y)));\nlet ((c,), _) = (a,);\n";
+        let program = parse_source(source).unwrap();
+
+        assert_eq!(program.to_string(), source);
+        assert_eq!(program.statements[0].bound_names(), ["a", "b"]);
+        assert_eq!(program.statements[1].bound_names(), ["c"]);
+    }
+
+    #[test]
+    fn test_extend_appends_in_order() {
+        let mut program = parse_source("let a = 1;").unwrap();
+        program.extend(parse_source("let b = a; { b; }").unwrap());
+
+        assert_eq!(program.to_string(), "let a = 1;\nlet b = a;\n{\n  b;\n}\n");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::lexer::Token;
+use crate::lexer::{Span, Token};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -7,73 +7,108 @@ pub enum ParseError {
         expected: Vec<String>,
         found: Token,
         position: usize,
+        span: Span,
     },
     UnexpectedEndOfInput {
         expected: Vec<String>,
+        span: Span,
     },
     InvalidExpression {
         message: String,
         position: usize,
+        span: Span,
     },
     InvalidStatement {
         message: String,
         position: usize,
+        span: Span,
     },
     MissingExpression {
         context: String,
         position: usize,
+        span: Span,
     },
     MissingSemicolon {
         position: usize,
+        span: Span,
     },
     InvalidOperator {
         operator: Token,
         position: usize,
+        span: Span,
+    },
+    /// The input ended before the delimiter opened at `open` was closed
+    UnclosedDelimiter {
+        delimiter: Token,
+        open: Span,
+        span: Span,
     },
 }
 
 impl ParseError {
-    pub fn unexpected_token(expected: Vec<&str>, found: Token, position: usize) -> Self {
+    pub fn unexpected_token(
+        expected: Vec<&str>,
+        found: Token,
+        position: usize,
+        span: Span,
+    ) -> Self {
         ParseError::UnexpectedToken {
             expected: expected.into_iter().map(|s| s.to_string()).collect(),
             found,
             position,
+            span,
         }
     }
 
-    pub fn unexpected_eof(expected: Vec<&str>) -> Self {
+    pub fn unexpected_eof(expected: Vec<&str>, span: Span) -> Self {
         ParseError::UnexpectedEndOfInput {
             expected: expected.into_iter().map(|s| s.to_string()).collect(),
+            span,
         }
     }
 
-    pub fn invalid_expression(message: &str, position: usize) -> Self {
+    pub fn invalid_expression(message: &str, position: usize, span: Span) -> Self {
         ParseError::InvalidExpression {
             message: message.to_string(),
             position,
+            span,
         }
     }
 
-    pub fn invalid_statement(message: &str, position: usize) -> Self {
+    pub fn invalid_statement(message: &str, position: usize, span: Span) -> Self {
         ParseError::InvalidStatement {
             message: message.to_string(),
             position,
+            span,
         }
     }
 
-    pub fn missing_expression(context: &str, position: usize) -> Self {
+    pub fn missing_expression(context: &str, position: usize, span: Span) -> Self {
         ParseError::MissingExpression {
             context: context.to_string(),
             position,
+            span,
         }
     }
 
-    pub fn missing_semicolon(position: usize) -> Self {
-        ParseError::MissingSemicolon { position }
+    pub fn missing_semicolon(position: usize, span: Span) -> Self {
+        ParseError::MissingSemicolon { position, span }
     }
 
-    pub fn invalid_operator(operator: Token, position: usize) -> Self {
-        ParseError::InvalidOperator { operator, position }
+    pub fn invalid_operator(operator: Token, position: usize, span: Span) -> Self {
+        ParseError::InvalidOperator {
+            operator,
+            position,
+            span,
+        }
+    }
+
+    pub fn unclosed_delimiter(delimiter: Token, open: Span, span: Span) -> Self {
+        ParseError::UnclosedDelimiter {
+            delimiter,
+            open,
+            span,
+        }
     }
 
     pub fn position(&self) -> Option<usize> {
@@ -82,9 +117,33 @@ impl ParseError {
             ParseError::InvalidExpression { position, .. } => Some(*position),
             ParseError::InvalidStatement { position, .. } => Some(*position),
             ParseError::MissingExpression { position, .. } => Some(*position),
-            ParseError::MissingSemicolon { position } => Some(*position),
+            ParseError::MissingSemicolon { position, .. } => Some(*position),
             ParseError::InvalidOperator { position, .. } => Some(*position),
             ParseError::UnexpectedEndOfInput { .. } => None,
+            ParseError::UnclosedDelimiter { .. } => None,
+        }
+    }
+
+    /// Returns the span of source text the error refers to
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken { span, .. } => Some(*span),
+            ParseError::InvalidExpression { span, .. } => Some(*span),
+            ParseError::InvalidStatement { span, .. } => Some(*span),
+            ParseError::MissingExpression { span, .. } => Some(*span),
+            ParseError::MissingSemicolon { span, .. } => Some(*span),
+            ParseError::InvalidOperator { span, .. } => Some(*span),
+            ParseError::UnexpectedEndOfInput { span, .. } => Some(*span),
+            ParseError::UnclosedDelimiter { span, .. } => Some(*span),
+        }
+    }
+
+    /// Returns a second location that explains the error, such as where an
+    /// unclosed delimiter was opened
+    pub fn related_span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnclosedDelimiter { open, .. } => Some(*open),
+            _ => None,
         }
     }
 }
@@ -96,17 +155,18 @@ impl fmt::Display for ParseError {
                 expected,
                 found,
                 position,
+                ..
             } => {
                 if expected.len() == 1 {
                     write!(
                         f,
-                        "Parse error at position {}: expected '{}', found '{}'",
+                        "Parse error at position {}: expected {}, found '{}'",
                         position, expected[0], found
                     )
                 } else if expected.len() == 2 {
                     write!(
                         f,
-                        "Parse error at position {}: expected '{}' or '{}', found '{}'",
+                        "Parse error at position {}: expected {} or {}, found '{}'",
                         position, expected[0], expected[1], found
                     )
                 } else {
@@ -119,11 +179,11 @@ impl fmt::Display for ParseError {
                     )
                 }
             }
-            ParseError::UnexpectedEndOfInput { expected } => {
+            ParseError::UnexpectedEndOfInput { expected, .. } => {
                 if expected.len() == 1 {
                     write!(
                         f,
-                        "Parse error: unexpected end of input, expected '{}'",
+                        "Parse error: unexpected end of input, expected {}",
                         expected[0]
                     )
                 } else {
@@ -134,29 +194,46 @@ impl fmt::Display for ParseError {
                     )
                 }
             }
-            ParseError::InvalidExpression { message, position } => {
+            ParseError::InvalidExpression {
+                message, position, ..
+            } => {
                 write!(f, "Parse error at position {}: {}", position, message)
             }
-            ParseError::InvalidStatement { message, position } => {
+            ParseError::InvalidStatement {
+                message, position, ..
+            } => {
                 write!(f, "Parse error at position {}: {}", position, message)
             }
-            ParseError::MissingExpression { context, position } => {
+            ParseError::MissingExpression {
+                context, position, ..
+            } => {
                 write!(
                     f,
                     "Parse error at position {}: missing expression in {}",
                     position, context
                 )
             }
-            ParseError::MissingSemicolon { position } => {
+            ParseError::MissingSemicolon { position, .. } => {
                 write!(f, "Parse error at position {}: missing semicolon", position)
             }
-            ParseError::InvalidOperator { operator, position } => {
+            ParseError::InvalidOperator {
+                operator, position, ..
+            } => {
                 write!(
                     f,
                     "Parse error at position {}: invalid operator '{}'",
                     position, operator
                 )
             }
+            ParseError::UnclosedDelimiter {
+                delimiter, open, ..
+            } => {
+                write!(
+                    f,
+                    "Parse error: unclosed '{}' opened at {}",
+                    delimiter, open
+                )
+            }
         }
     }
 }
@@ -165,7 +242,7 @@ impl std::error::Error for ParseError {}
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseErrors {
     pub errors: Vec<ParseError>,
 }
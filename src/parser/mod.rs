@@ -1,10 +1,13 @@
 pub mod ast;
 pub mod error;
 pub mod parse;
+pub mod trace;
+mod tree;
 
-pub use ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+pub use ast::{Associativity, BinaryOp, Expr, Pattern, Program, Stmt, UnaryOp};
 pub use error::{ParseError, ParseErrors, ParseResult};
 pub use parse::Parser;
+pub use trace::{ParseEvent, ParseTrace};
 
 // Convenience function to parse source code directly
 pub fn parse_source(source: &str) -> Result<Program, ParseErrors> {
@@ -1,21 +1,139 @@
-use super::ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+use super::ast::{Associativity, BinaryOp, Expr, Pattern, Program, Stmt, UnaryOp};
 use super::error::{ParseError, ParseErrors, ParseResult};
-use crate::lexer::{Lexer, Token};
+use super::trace::{ParseEvent, ParseTrace};
+use crate::calculator::PERCENT;
+use crate::lexer::{Lexer, Span, Token};
+use crate::suffix::LiteralSuffixes;
 
 pub struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
     current: usize,
+    errors: ParseErrors,
+    /// Descriptions of the tokens tried at the current position, reported if none matches
+    expected: Vec<String>,
+    /// Literal suffixes that turn a number and an adjacent name into one literal
+    suffixes: LiteralSuffixes,
+    /// Whether adjacent factors, as in `2x`, are multiplied
+    implicit_multiplication: bool,
+    /// Whether a number directly followed by `%` is a percent literal
+    percent_literals: bool,
+    /// Steps taken so far, when tracing is on
+    trace: Option<ParseTrace>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            spans: Vec::new(),
+            current: 0,
+            errors: ParseErrors::new(),
+            expected: Vec::new(),
+            suffixes: LiteralSuffixes::default(),
+            implicit_multiplication: false,
+            percent_literals: false,
+            trace: None,
+        }
+    }
+
+    /// Creates a parser over tokens paired with their source spans
+    pub fn from_spanned(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans) = tokens.into_iter().unzip();
+        Self {
+            tokens,
+            spans,
+            current: 0,
+            errors: ParseErrors::new(),
+            expected: Vec::new(),
+            suffixes: LiteralSuffixes::default(),
+            implicit_multiplication: false,
+            percent_literals: false,
+            trace: None,
+        }
+    }
+
+    /// Parses a number directly followed by a registered suffix, as in `10px`,
+    /// into an [`Expr::Suffixed`]. Only parsers built from spanned tokens can
+    /// tell a suffix from a name separated by whitespace.
+    pub fn with_suffixes(mut self, suffixes: &LiteralSuffixes) -> Self {
+        self.suffixes = suffixes.clone();
+        self
+    }
+
+    /// Parses a number or parenthesized expression followed by a name or
+    /// another parenthesized expression as their product, as in `2x` and
+    /// `(a + b)(a - b)`. The product is a single factor, so it binds more
+    /// tightly than `*`, `/` and signs: `1 / 2x` is `1 / (2 * x)`. A name is
+    /// never the left factor, which keeps `x y` and `f(x)` errors.
+    pub fn with_implicit_multiplication(mut self) -> Self {
+        self.implicit_multiplication = true;
+        self
+    }
+
+    /// Parses a number directly followed by `%`, as in `15%`, into an
+    /// [`Expr::Suffixed`] with the suffix [`PERCENT`]. Like suffixes, this
+    /// needs a parser built from spanned tokens.
+    pub fn with_percent_literals(mut self) -> Self {
+        self.percent_literals = true;
+        self
+    }
+
+    /// Records every rule entered and left, token consumed, backtrack and
+    /// error recovery, for [`Parser::take_trace`]. Meant for debugging the
+    /// grammar; tracing slows parsing down.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(ParseTrace::default());
+        self
+    }
+
+    /// Returns the steps recorded since tracing started or the trace was last
+    /// taken, or None if tracing is off
+    pub fn take_trace(&mut self) -> Option<ParseTrace> {
+        self.trace.as_mut().map(std::mem::take)
+    }
+
+    fn record(&mut self, event: ParseEvent) {
+        if let Some(trace) = &mut self.trace {
+            trace.events.push(event);
+        }
+    }
+
+    /// Runs the grammar rule `rule`, recording its entry and exit when tracing
+    fn traced<T>(
+        &mut self,
+        rule: &'static str,
+        parse: impl FnOnce(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        if self.trace.is_none() {
+            return parse(self);
+        }
+
+        self.record(ParseEvent::Enter {
+            rule,
+            position: self.current,
+        });
+        let result = parse(self);
+        self.record(ParseEvent::Exit {
+            rule,
+            position: self.current,
+            success: result.is_ok(),
+        });
+        result
     }
 
     pub fn from_source(source: &str) -> Self {
         let mut lexer = Lexer::new(source);
-        let tokens = lexer.tokenize();
-        Self::new(tokens)
+        Self::from_spanned(lexer.tokenize_spanned())
+    }
+
+    /// Returns the span of the token at the given index. Parsers built from bare
+    /// tokens have no source text, so every span is empty.
+    fn span_at(&self, index: usize) -> Span {
+        match self.spans.get(index) {
+            Some(span) => *span,
+            None => self.spans.last().copied().unwrap_or_default(),
+        }
     }
 
     /// Returns the current token without advancing
@@ -23,13 +141,6 @@ impl Parser {
         self.tokens.get(self.current).unwrap_or(&Token::EOF)
     }
 
-    /// Returns the token at the given offset from current position
-    fn peek_ahead(&self, offset: usize) -> &Token {
-        self.tokens
-            .get(self.current + offset)
-            .unwrap_or(&Token::EOF)
-    }
-
     /// Returns the previous token
     fn previous(&self) -> &Token {
         if self.current > 0 {
@@ -42,7 +153,13 @@ impl Parser {
     /// Advances to the next token and returns the previous one
     fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
+            if self.trace.is_some() {
+                let token = self.peek().clone();
+                let span = self.span_at(self.current);
+                self.record(ParseEvent::Consume { token, span });
+            }
             self.current += 1;
+            self.expected.clear();
         }
         self.previous()
     }
@@ -52,31 +169,50 @@ impl Parser {
         matches!(self.peek(), Token::EOF)
     }
 
-    /// Checks if the current token matches any of the given tokens
-    fn matches(&self, tokens: &[Token]) -> bool {
-        for token in tokens {
-            if std::mem::discriminant(self.peek()) == std::mem::discriminant(token) {
-                return true;
-            }
+    /// Records that a token matching `description` would be accepted at the current position
+    fn expect(&mut self, description: &str) {
+        if !self.expected.iter().any(|expected| expected == description) {
+            self.expected.push(description.to_string());
+        }
+    }
+
+    /// Checks if the current token has the same kind as `token`, recording it as expected if not
+    fn check(&mut self, token: &Token) -> bool {
+        if std::mem::discriminant(self.peek()) == std::mem::discriminant(token) {
+            true
+        } else {
+            self.expect(&describe(token));
+            false
+        }
+    }
+
+    /// Builds an error for the current token listing everything that was expected instead.
+    /// Running out of tokens produces an end-of-input error spanning the end of the source.
+    fn unexpected(&self) -> ParseError {
+        let expected = self.expected.iter().map(String::as_str).collect();
+        let span = self.span_at(self.current);
+
+        if self.is_at_end() {
+            ParseError::unexpected_eof(expected, span)
+        } else {
+            ParseError::unexpected_token(expected, self.peek().clone(), self.current, span)
         }
-        false
     }
 
     /// Consumes the current token if it matches the expected token
-    fn consume(&mut self, expected: Token, message: &str) -> ParseResult<&Token> {
-        if std::mem::discriminant(self.peek()) == std::mem::discriminant(&expected) {
+    fn consume(&mut self, expected: Token) -> ParseResult<&Token> {
+        if self.check(&expected) {
             Ok(self.advance())
         } else {
-            Err(ParseError::unexpected_token(
-                vec![&format!("{}", expected)],
-                self.peek().clone(),
-                self.current,
-            ))
+            Err(self.unexpected())
         }
     }
 
     /// Synchronizes the parser after an error by finding the next statement boundary
     fn synchronize(&mut self) {
+        self.record(ParseEvent::Recover {
+            position: self.current,
+        });
         self.advance();
 
         while !self.is_at_end() {
@@ -87,6 +223,8 @@ impl Parser {
             match self.peek() {
                 Token::Let => return,
                 Token::LeftBrace => return,
+                Token::RightBrace => return,
+                Token::DocComment(_) => return,
                 _ => {}
             }
 
@@ -94,81 +232,302 @@ impl Parser {
         }
     }
 
+    /// Attempts a sub-parse. If it fails, the cursor is rewound and any errors
+    /// recorded during the attempt are discarded, so the caller can try an
+    /// alternative production instead.
+    pub fn try_parse<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> Option<T> {
+        let start = self.current;
+        let error_count = self.errors.len();
+        let expected = self.expected.clone();
+
+        match parse(self) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.record(ParseEvent::Backtrack {
+                    from: self.current,
+                    to: start,
+                });
+                self.current = start;
+                self.errors.errors.truncate(error_count);
+                self.expected = expected;
+                None
+            }
+        }
+    }
+
     /// Parses a complete program
     pub fn parse(&mut self) -> Result<Program, ParseErrors> {
         let mut program = Program::new();
-        let mut errors = ParseErrors::new();
+        self.parse_append(&mut program)?;
+        Ok(program)
+    }
+
+    /// Parses the remaining input and appends its statements to an existing
+    /// program, returning how many were added. Nothing is appended if any
+    /// statement fails to parse, so the program stays valid.
+    pub fn parse_append(&mut self, program: &mut Program) -> Result<usize, ParseErrors> {
+        let mut statements = Vec::new();
+        let error_count = self.errors.len();
 
         while !self.is_at_end() {
             match self.statement() {
-                Ok(stmt) => program.add_statement(stmt),
+                Ok(stmt) => statements.push(stmt),
                 Err(error) => {
-                    errors.add(error);
+                    self.errors.add(error);
                     self.synchronize();
                 }
             }
         }
 
-        if errors.is_empty() {
-            Ok(program)
-        } else {
-            Err(errors)
+        match self.errors_since(error_count) {
+            None => {
+                let count = statements.len();
+                program.statements.extend(statements);
+                Ok(count)
+            }
+            Some(errors) => Err(errors),
+        }
+    }
+
+    /// Checks the remaining input for syntax errors, reporting the same errors
+    /// as [`Parser::parse`]. Each statement is dropped as soon as it has been
+    /// parsed, so no more than one statement's tree exists at a time.
+    pub fn validate(&mut self) -> Result<(), ParseErrors> {
+        let error_count = self.errors.len();
+
+        while !self.is_at_end() {
+            if let Err(error) = self.statement() {
+                self.errors.add(error);
+                self.synchronize();
+            }
+        }
+
+        match self.errors_since(error_count) {
+            None => Ok(()),
+            Some(errors) => Err(errors),
+        }
+    }
+
+    // Takes the errors recorded after the first `error_count`, leaving those
+    // of any parse this one is nested in, as under `try_parse`
+    fn errors_since(&mut self, error_count: usize) -> Option<ParseErrors> {
+        match self.errors.len() > error_count {
+            true => Some(ParseErrors {
+                errors: self.errors.errors.split_off(error_count),
+            }),
+            false => None,
         }
     }
 
     /// Parses a statement
     fn statement(&mut self) -> ParseResult<Stmt> {
-        match self.peek() {
-            Token::Let => self.let_statement(),
-            Token::LeftBrace => self.block_statement(),
-            _ => self.expression_statement(),
-        }
+        self.traced("statement", |parser| match parser.peek() {
+            Token::DocComment(_) => parser.documented_statement(),
+            Token::Let => parser.let_statement(None),
+            Token::LeftBrace => parser.block_statement(),
+            _ => {
+                parser.expect("'let'");
+                parser.expect("'{'");
+                parser.expression_statement()
+            }
+        })
     }
 
-    /// Parses a let statement: let identifier = expression;
-    fn let_statement(&mut self) -> ParseResult<Stmt> {
-        self.consume(Token::Let, "Expected 'let'")?;
+    /// Parses `///` doc comments followed by the declaration they document
+    fn documented_statement(&mut self) -> ParseResult<Stmt> {
+        self.traced("documented_statement", |parser| {
+            let mut lines = Vec::new();
+            while let Token::DocComment(text) = parser.peek() {
+                lines.push(text.clone());
+                parser.advance();
+            }
 
-        let name = match self.advance() {
-            Token::Ident(name) => name.clone(),
-            token => {
-                return Err(ParseError::unexpected_token(
-                    vec!["identifier"],
-                    token.clone(),
-                    self.current - 1,
+            match parser.peek() {
+                Token::Let => parser.let_statement(Some(lines.join("\n"))),
+                _ => Err(ParseError::invalid_statement(
+                    "doc comment is not followed by a declaration",
+                    parser.current,
+                    parser.span_at(parser.current),
+                )),
+            }
+        })
+    }
+
+    /// Parses a let statement: let identifier = expression; or the
+    /// destructuring form let pattern = expression;
+    fn let_statement(&mut self, doc: Option<String>) -> ParseResult<Stmt> {
+        self.traced("let_statement", |parser| {
+            parser.consume(Token::Let)?;
+
+            if matches!(parser.peek(), Token::Ident(name) if name != "_") {
+                let name = parser.identifier()?;
+                let value = parser.initializer()?;
+                return Ok(Stmt::Let { name, value, doc });
+            }
+
+            let start = parser.current;
+            let pattern = parser.pattern()?;
+            if !pattern.is_irrefutable() {
+                let span = Span::new(
+                    parser.span_at(start).start,
+                    parser.span_at(parser.current - 1).end,
+                );
+                return Err(ParseError::invalid_statement(
+                    &format!("pattern `{}` in a let binding can fail to match", pattern),
+                    start,
+                    span,
                 ));
             }
-        };
+            // A name bound twice by one pattern, reported where it repeats
+            let mut names = Vec::new();
+            for index in start..parser.current {
+                if let Token::Ident(name) = &parser.tokens[index] {
+                    if name != "_" && names.contains(&name) {
+                        return Err(ParseError::invalid_statement(
+                            &format!("`{}` is bound more than once in the same pattern", name),
+                            index,
+                            parser.span_at(index),
+                        ));
+                    }
+                    names.push(name);
+                }
+            }
 
-        self.consume(Token::Equals, "Expected '=' after variable name")?;
+            let value = parser.initializer()?;
+            Ok(Stmt::Destructure {
+                pattern,
+                value,
+                doc,
+            })
+        })
+    }
 
-        let value = self.expression()?;
+    /// Parses the `= expression;` that ends a let statement
+    fn initializer(&mut self) -> ParseResult<Expr> {
+        self.traced("initializer", |parser| {
+            parser.consume(Token::Equals)?;
 
-        self.consume(Token::Semicolon, "Expected ';' after variable declaration")?;
+            let value = parser.expression()?;
 
-        Ok(Stmt::let_statement(name, value))
+            parser.consume(Token::Semicolon)?;
+            Ok(value)
+        })
     }
 
-    /// Parses a block statement: { statements... }
-    fn block_statement(&mut self) -> ParseResult<Stmt> {
-        self.consume(Token::LeftBrace, "Expected '{'")?;
+    /// Parses a pattern: `_`, an integer literal, a name or a tuple of
+    /// patterns. As with tuple expressions, a one-element tuple needs a
+    /// trailing comma.
+    fn pattern(&mut self) -> ParseResult<Pattern> {
+        self.traced("pattern", |parser| match parser.peek().clone() {
+            Token::Ident(name) => {
+                parser.advance();
+                Ok(match name.as_str() {
+                    "_" => Pattern::Wildcard,
+                    _ => Pattern::identifier(name),
+                })
+            }
+            Token::Number(value) => {
+                parser.advance();
+                Ok(Pattern::Literal(value))
+            }
+            Token::Minus => {
+                parser.advance();
+                match parser.peek() {
+                    Token::Number(value) => {
+                        let value = -value;
+                        parser.advance();
+                        Ok(Pattern::Literal(value))
+                    }
+                    _ => {
+                        parser.expect("number");
+                        Err(parser.unexpected())
+                    }
+                }
+            }
+            Token::LeftParen => {
+                parser.advance();
 
-        let mut statements = Vec::new();
+                let mut elements = Vec::new();
+                while !parser.check(&Token::RightParen) {
+                    elements.push(parser.pattern()?);
+                    if !parser.check(&Token::Comma) {
+                        break;
+                    }
+                    parser.advance();
+                }
+
+                if elements.len() == 1 && !matches!(parser.previous(), Token::Comma) {
+                    return Err(parser.unexpected());
+                }
+                parser.consume(Token::RightParen)?;
+                Ok(Pattern::tuple(elements))
+            }
+            _ => {
+                parser.expect("identifier");
+                parser.expect("number");
+                parser.expect("'-'");
+                parser.expect("'('");
+                Err(parser.unexpected())
+            }
+        })
+    }
 
-        while !matches!(self.peek(), Token::RightBrace) && !self.is_at_end() {
-            statements.push(self.statement()?);
+    /// Consumes an identifier and returns its name
+    fn identifier(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Token::Ident(name) => {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => {
+                self.expect("identifier");
+                Err(self.unexpected())
+            }
         }
+    }
+
+    /// Parses a block statement: { statements... }. A statement that fails to
+    /// parse is recorded and skipped so the rest of the block is still checked,
+    /// and a block left open at the end of the input is closed there.
+    fn block_statement(&mut self) -> ParseResult<Stmt> {
+        self.traced("block_statement", |parser| {
+            let open = parser.span_at(parser.current);
+            parser.consume(Token::LeftBrace)?;
+
+            let mut statements = Vec::new();
+
+            while !matches!(parser.peek(), Token::RightBrace) && !parser.is_at_end() {
+                match parser.statement() {
+                    Ok(stmt) => statements.push(stmt),
+                    Err(error) => {
+                        parser.errors.add(error);
+                        parser.synchronize();
+                    }
+                }
+            }
 
-        self.consume(Token::RightBrace, "Expected '}' after block")?;
+            if parser.is_at_end() {
+                parser.errors.add(ParseError::unclosed_delimiter(
+                    Token::LeftBrace,
+                    open,
+                    parser.span_at(parser.current),
+                ));
+            } else {
+                parser.consume(Token::RightBrace)?;
+            }
 
-        Ok(Stmt::block(statements))
+            Ok(Stmt::block(statements))
+        })
     }
 
     /// Parses an expression statement: expression;
     fn expression_statement(&mut self) -> ParseResult<Stmt> {
-        let expr = self.expression()?;
-        self.consume(Token::Semicolon, "Expected ';' after expression")?;
-        Ok(Stmt::expression(expr))
+        self.traced("expression_statement", |parser| {
+            let expr = parser.expression()?;
+            parser.consume(Token::Semicolon)?;
+            Ok(Stmt::expression(expr))
+        })
     }
 
     /// Parses an expression using precedence climbing
@@ -178,48 +537,147 @@ impl Parser {
 
     /// Parses binary expressions with operator precedence
     fn binary_expression(&mut self, min_precedence: u8) -> ParseResult<Expr> {
-        let mut left = self.unary_expression()?;
+        self.traced("binary_expression", |parser| {
+            let mut left = parser.unary_expression()?;
+
+            while let Some(op) = BinaryOp::from_token(parser.peek()) {
+                if op.precedence() < min_precedence {
+                    break;
+                }
+
+                parser.advance(); // consume operator
 
-        while let Some(op) = BinaryOp::from_token(self.peek()) {
-            if op.precedence() < min_precedence {
-                break;
+                // A left-associative operator stops the right operand at operators of
+                // its own precedence, so they are picked up by this loop instead
+                let next_precedence = match op.associativity() {
+                    Associativity::Left => op.precedence() + 1,
+                    Associativity::Right => op.precedence(),
+                };
+                let right = parser.binary_expression(next_precedence)?;
+                left = Expr::binary(left, op, right);
             }
 
-            self.advance(); // consume operator
-            let right = self.binary_expression(op.precedence() + 1)?;
-            left = Expr::binary(left, op, right);
-        }
+            if BinaryOp::from_token(parser.peek()).is_none() {
+                parser.expect("operator");
+            }
 
-        Ok(left)
+            Ok(left)
+        })
     }
 
-    /// Parses unary expressions: -expression
+    /// Parses unary expressions: -expression or +expression. Signs nest, so
+    /// `--10` and `- -10` both mean -(-10).
     fn unary_expression(&mut self) -> ParseResult<Expr> {
-        if let Some(op) = UnaryOp::from_token(self.peek()) {
-            self.advance(); // consume operator
-            let operand = self.unary_expression()?;
-            Ok(Expr::unary(op, operand))
-        } else {
-            self.primary_expression()
+        self.traced("unary_expression", |parser| {
+            if let Some(op) = UnaryOp::from_token(parser.peek()) {
+                parser.advance(); // consume operator
+                let operand = parser.unary_expression()?;
+                Ok(Expr::unary(op, operand))
+            } else {
+                for op in UnaryOp::all() {
+                    parser.expect(&format!("'{}'", op));
+                }
+                parser.implicit_product()
+            }
+        })
+    }
+
+    /// Parses a primary expression and, with implicit multiplication on, the
+    /// factors that directly follow it
+    fn implicit_product(&mut self) -> ParseResult<Expr> {
+        let mut product = self.primary_expression()?;
+        if !self.implicit_multiplication {
+            return Ok(product);
+        }
+
+        // Only a number or a closing parenthesis ends a left factor
+        while matches!(
+            self.previous(),
+            Token::Number(_) | Token::Float(_) | Token::BigNumber(_) | Token::RightParen
+        ) && matches!(self.peek(), Token::Ident(_) | Token::LeftParen)
+        {
+            let factor = self.primary_expression()?;
+            product = Expr::binary(product, BinaryOp::Multiply, factor);
         }
+        Ok(product)
     }
 
     /// Parses primary expressions: numbers, identifiers, grouped expressions
+    /// and tuples
     fn primary_expression(&mut self) -> ParseResult<Expr> {
-        match self.advance().clone() {
-            Token::Number(value) => Ok(Expr::number(value)),
-            Token::Ident(name) => Ok(Expr::identifier(name)),
-            Token::LeftParen => {
-                let expr = self.expression()?;
-                self.consume(Token::RightParen, "Expected ')' after expression")?;
-                Ok(Expr::grouping(expr))
+        self.traced("primary_expression", |parser| {
+            match parser.peek().clone() {
+                Token::Number(value) => {
+                    parser.advance();
+                    let adjacent = !parser.spans.is_empty()
+                        && parser.span_at(parser.current - 1).end
+                            == parser.span_at(parser.current).start;
+                    match parser.peek().clone() {
+                        Token::Percent if parser.percent_literals && adjacent => {
+                            parser.advance();
+                            Ok(Expr::suffixed(value, PERCENT.to_string()))
+                        }
+                        Token::Ident(suffix) if parser.suffixes.contains(&suffix) && adjacent => {
+                            parser.advance();
+                            Ok(Expr::suffixed(value, suffix))
+                        }
+                        _ => Ok(Expr::number(value)),
+                    }
+                }
+                Token::Float(value) => {
+                    parser.advance();
+                    Ok(Expr::Float(value))
+                }
+                Token::BigNumber(digits) => {
+                    parser.advance();
+                    Ok(Expr::BigNumber(digits))
+                }
+                Token::Ident(name) => {
+                    parser.advance();
+                    Ok(Expr::identifier(name))
+                }
+                Token::LeftParen => {
+                    let open = parser.span_at(parser.current);
+                    parser.advance();
+
+                    // `(x)` is a grouping; a comma, or nothing at all, makes a tuple
+                    let mut elements = Vec::new();
+                    let mut is_tuple = true;
+                    while !parser.check(&Token::RightParen) {
+                        elements.push(parser.expression()?);
+                        if parser.check(&Token::Comma) {
+                            parser.advance();
+                        } else {
+                            is_tuple = elements.len() > 1;
+                            break;
+                        }
+                    }
+
+                    if parser.is_at_end() {
+                        return Err(ParseError::unclosed_delimiter(
+                            Token::LeftParen,
+                            open,
+                            parser.span_at(parser.current),
+                        ));
+                    }
+                    parser.consume(Token::RightParen)?;
+
+                    match elements.pop() {
+                        Some(expr) if !is_tuple => Ok(Expr::grouping(expr)),
+                        last => {
+                            elements.extend(last);
+                            Ok(Expr::tuple(elements))
+                        }
+                    }
+                }
+                _ => {
+                    parser.expect("number");
+                    parser.expect("identifier");
+                    parser.expect("'('");
+                    Err(parser.unexpected())
+                }
             }
-            token => Err(ParseError::unexpected_token(
-                vec!["number", "identifier", "'('"],
-                token,
-                self.current - 1,
-            )),
-        }
+        })
     }
 
     /// Returns the current position
@@ -233,10 +691,20 @@ impl Parser {
     }
 }
 
+/// Describes a token kind for "expected ..." messages
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(_) | Token::Float(_) | Token::BigNumber(_) => "number".to_string(),
+        Token::Ident(_) => "identifier".to_string(),
+        Token::DocComment(_) => "doc comment".to_string(),
+        Token::EOF => "end of input".to_string(),
+        token => format!("'{}'", token),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::Lexer;
 
     #[test]
     fn test_simple_let_statement() {
@@ -245,7 +713,7 @@ mod tests {
 
         assert_eq!(program.statements.len(), 1);
         match &program.statements[0] {
-            Stmt::Let { name, value } => {
+            Stmt::Let { name, value, .. } => {
                 assert_eq!(name, "x");
                 assert_eq!(*value, Expr::number(42));
             }
@@ -356,6 +824,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_unary_plus_and_repeated_signs() {
+        assert_eq!(
+            parse_expression("+42;"),
+            Expr::unary(UnaryOp::Plus, Expr::number(42))
+        );
+
+        let double_negation = Expr::unary(
+            UnaryOp::Negate,
+            Expr::unary(UnaryOp::Negate, Expr::number(10)),
+        );
+        assert_eq!(parse_expression("--10;"), double_negation);
+        assert_eq!(parse_expression("- -10;"), double_negation);
+        assert_eq!(
+            parse_expression("1 - -+x;"),
+            Expr::binary(
+                Expr::number(1),
+                BinaryOp::Subtract,
+                Expr::unary(UnaryOp::Negate, Expr::unary(UnaryOp::Plus, var("x"))),
+            )
+        );
+    }
+
     #[test]
     fn test_block_statement() {
         let mut parser = Parser::from_source("{ let x = 5; 42; }");
@@ -366,7 +857,7 @@ mod tests {
             Stmt::Block(statements) => {
                 assert_eq!(statements.len(), 2);
                 match &statements[0] {
-                    Stmt::Let { name, value } => {
+                    Stmt::Let { name, value, .. } => {
                         assert_eq!(name, "x");
                         assert_eq!(*value, Expr::number(5));
                     }
@@ -399,6 +890,410 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_doc_comment_attaches_to_let() {
+        let mut parser =
+            Parser::from_source("/// The answer.\n/// Computed slowly.\nlet x = 42; let y = 1;");
+        let program = parser.parse().unwrap();
+
+        assert_eq!(program.statements.len(), 2);
+        assert_eq!(
+            program.statements[0].doc(),
+            Some("The answer.\nComputed slowly.")
+        );
+        assert_eq!(program.statements[1].doc(), None);
+    }
+
+    #[test]
+    fn test_dangling_doc_comment() {
+        let mut parser = Parser::from_source("/// Nothing to document\n42;");
+        let errors = parser.parse().unwrap_err();
+
+        assert!(matches!(
+            errors.first(),
+            Some(ParseError::InvalidStatement { .. })
+        ));
+    }
+
+    #[test]
+    fn test_error_spans() {
+        let mut parser = Parser::from_source("let x = 1 22;\nlet = 2;");
+        let errors = parser.parse().unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors.errors[0].span(), Some(Span::new(10, 12)));
+        assert_eq!(errors.errors[1].span(), Some(Span::new(18, 19)));
+    }
+
+    #[test]
+    fn test_try_parse_rolls_back_on_failure() {
+        let mut parser = Parser::from_source("1 + ; 2;");
+        parser
+            .errors
+            .add(ParseError::missing_semicolon(0, Span::default()));
+
+        let result = parser.try_parse(|p| {
+            p.errors
+                .add(ParseError::missing_semicolon(1, Span::default()));
+            p.expression_statement()
+        });
+
+        assert_eq!(result, None);
+        assert_eq!(parser.position(), 0);
+        assert_eq!(parser.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_try_parse_commits_on_success() {
+        let mut parser = Parser::from_source("let a = 1; 2;");
+
+        let stmt = parser.try_parse(|p| p.let_statement(None));
+
+        assert_eq!(
+            stmt,
+            Some(Stmt::let_statement("a".to_string(), Expr::number(1)))
+        );
+        assert_eq!(parser.position(), 5);
+        assert_eq!(parser.try_parse(|p| p.let_statement(None)), None);
+        assert_eq!(parser.position(), 5);
+    }
+
+    fn expected_at_first_error(source: &str) -> Vec<String> {
+        match Parser::from_source(source).parse().unwrap_err().first() {
+            Some(ParseError::UnexpectedToken { expected, .. }) => expected.clone(),
+            other => panic!("Expected unexpected-token error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expected_set_after_operand() {
+        assert_eq!(
+            expected_at_first_error("let x = 1 2;"),
+            vec!["operator", "';'"]
+        );
+        assert_eq!(
+            expected_at_first_error("(1 + 2;"),
+            vec!["operator", "','", "')'"]
+        );
+    }
+
+    #[test]
+    fn test_expected_set_for_missing_operand() {
+        assert_eq!(
+            expected_at_first_error("let x = * 2;"),
+            vec!["'-'", "'+'", "number", "identifier", "'('"]
+        );
+        assert_eq!(
+            expected_at_first_error(");"),
+            vec!["'let'", "'{'", "'-'", "'+'", "number", "identifier", "'('"]
+        );
+        assert_eq!(
+            expected_at_first_error("let = 1;"),
+            vec!["identifier", "number", "'-'", "'('"]
+        );
+    }
+
+    #[test]
+    fn test_unexpected_end_of_input() {
+        let errors = Parser::from_source("let x = 1 +  ").parse().unwrap_err();
+
+        assert_eq!(
+            errors.first(),
+            Some(&ParseError::UnexpectedEndOfInput {
+                expected: vec![
+                    "'-'".to_string(),
+                    "'+'".to_string(),
+                    "number".to_string(),
+                    "identifier".to_string(),
+                    "'('".to_string()
+                ],
+                span: Span::new(13, 13),
+            })
+        );
+        assert_eq!(errors.first().unwrap().position(), None);
+    }
+
+    #[test]
+    fn test_unclosed_block_points_at_its_opening_brace() {
+        let errors = Parser::from_source("{ let x = 1;").parse().unwrap_err();
+
+        assert_eq!(
+            errors.errors,
+            vec![ParseError::unclosed_delimiter(
+                Token::LeftBrace,
+                Span::new(0, 1),
+                Span::new(12, 12)
+            )]
+        );
+        assert_eq!(errors.errors[0].related_span(), Some(Span::new(0, 1)));
+        assert_eq!(
+            errors.errors[0].to_string(),
+            "Parse error: unclosed '{' opened at 0..1"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_delimiters_nest() {
+        let errors = Parser::from_source("{ 1; { let y = (2 + 3")
+            .parse()
+            .unwrap_err();
+
+        let opened: Vec<_> = errors.errors.iter().map(|e| e.related_span()).collect();
+        assert_eq!(
+            opened,
+            vec![
+                Some(Span::new(15, 16)),
+                Some(Span::new(5, 6)),
+                Some(Span::new(0, 1))
+            ]
+        );
+        assert!(
+            errors
+                .errors
+                .iter()
+                .all(|e| e.span() == Some(Span::new(21, 21)))
+        );
+    }
+
+    #[test]
+    fn test_parenthesis_closed_by_wrong_token_is_an_ordinary_error() {
+        let errors = Parser::from_source("(1 + 2;").parse().unwrap_err();
+
+        assert_eq!(
+            expected_at_first_error("(1 + 2;"),
+            vec!["operator", "','", "')'"]
+        );
+        assert_eq!(errors.errors[0].related_span(), None);
+    }
+
+    #[test]
+    fn test_tuples_and_groupings() {
+        let program = Parser::from_source("(); (1,); (1); (1, a + 2,);")
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            program.statements,
+            vec![
+                Stmt::expression(Expr::tuple(vec![])),
+                Stmt::expression(Expr::tuple(vec![Expr::number(1)])),
+                Stmt::expression(Expr::grouping(Expr::number(1))),
+                Stmt::expression(Expr::tuple(vec![
+                    Expr::number(1),
+                    Expr::binary(
+                        Expr::identifier("a".to_string()),
+                        BinaryOp::Add,
+                        Expr::number(2)
+                    ),
+                ])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let program = Parser::from_source("let x = 3.14; -2.5e-3 * (1e5);")
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            program.statements[1],
+            Stmt::expression(Expr::binary(
+                Expr::unary(UnaryOp::Negate, Expr::Float(2.5e-3)),
+                BinaryOp::Multiply,
+                Expr::grouping(Expr::Float(1e5))
+            ))
+        );
+        assert_eq!(
+            program.to_string(),
+            "let x = 3.14;\n((-0.0025) * (100000.0));\n"
+        );
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let source = "/// Pair.\nlet (a, (_, b),) = (1, (2, 3)); let () = (); let _ = 4;";
+        let program = Parser::from_source(source).parse().unwrap();
+
+        assert_eq!(
+            program.statements[0],
+            Stmt::Destructure {
+                pattern: Pattern::tuple(vec![
+                    Pattern::identifier("a".to_string()),
+                    Pattern::tuple(vec![
+                        Pattern::Wildcard,
+                        Pattern::identifier("b".to_string())
+                    ]),
+                ]),
+                value: Expr::tuple(vec![
+                    Expr::number(1),
+                    Expr::tuple(vec![Expr::number(2), Expr::number(3)])
+                ]),
+                doc: Some("Pair.".to_string()),
+            }
+        );
+        assert_eq!(
+            program.statements[1],
+            Stmt::destructure(Pattern::tuple(vec![]), Expr::tuple(vec![]))
+        );
+        assert_eq!(
+            program.statements[2],
+            Stmt::destructure(Pattern::Wildcard, Expr::number(4))
+        );
+    }
+
+    #[test]
+    fn test_refutable_let_patterns_are_rejected() {
+        let errors = Parser::from_source("let (a, -1) = b;").parse().unwrap_err();
+
+        assert_eq!(
+            errors.errors,
+            vec![ParseError::invalid_statement(
+                "pattern `(a, -1)` in a let binding can fail to match",
+                1,
+                Span::new(4, 11)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_names_are_bound_once_per_pattern() {
+        let errors = Parser::from_source("let (a, (b, a)) = (1, (2, 3));")
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(
+            errors.errors,
+            vec![ParseError::invalid_statement(
+                "`a` is bound more than once in the same pattern",
+                7,
+                Span::new(12, 13)
+            )]
+        );
+        assert!(
+            Parser::from_source("let (_, _, a) = (1, 2, 3);")
+                .parse()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_single_pattern_tuple_needs_a_comma() {
+        assert!(Parser::from_source("let (a,) = (1,);").parse().is_ok());
+        assert_eq!(expected_at_first_error("let (a) = 1;"), vec!["','"]);
+        assert_eq!(
+            expected_at_first_error("let (a, b c) = 1;"),
+            vec!["','", "')'"]
+        );
+    }
+
+    #[test]
+    fn test_block_recovers_after_a_bad_statement() {
+        let errors = Parser::from_source("{ let = 1; 2 3; let ok = 4; } 5;")
+            .parse()
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors.errors[0].span(), Some(Span::new(6, 7)));
+        assert_eq!(errors.errors[1].span(), Some(Span::new(13, 14)));
+    }
+
+    fn parse_expression(source: &str) -> Expr {
+        match Parser::from_source(source)
+            .parse()
+            .unwrap()
+            .statements
+            .remove(0)
+        {
+            Stmt::Expression(expr) => expr,
+            stmt => panic!("Expected expression statement, got {:?}", stmt),
+        }
+    }
+
+    fn var(name: &str) -> Expr {
+        Expr::identifier(name.to_string())
+    }
+
+    #[test]
+    fn test_subtraction_chain_is_left_associative() {
+        assert_eq!(
+            parse_expression("1 - 2 - 3;"),
+            Expr::binary(
+                Expr::binary(Expr::number(1), BinaryOp::Subtract, Expr::number(2)),
+                BinaryOp::Subtract,
+                Expr::number(3),
+            )
+        );
+    }
+
+    #[test]
+    fn test_division_chain_is_left_associative() {
+        assert_eq!(
+            parse_expression("8 / 4 / 2 / 1;"),
+            Expr::binary(
+                Expr::binary(
+                    Expr::binary(Expr::number(8), BinaryOp::Divide, Expr::number(4)),
+                    BinaryOp::Divide,
+                    Expr::number(2),
+                ),
+                BinaryOp::Divide,
+                Expr::number(1),
+            )
+        );
+    }
+
+    #[test]
+    fn test_all_operator_pairs_group_by_precedence_and_associativity() {
+        for first in BinaryOp::all() {
+            for second in BinaryOp::all() {
+                let source = format!("a {} b {} c;", first, second);
+                let left_grouped = Expr::binary(
+                    Expr::binary(var("a"), first.clone(), var("b")),
+                    second.clone(),
+                    var("c"),
+                );
+                let right_grouped = Expr::binary(
+                    var("a"),
+                    first.clone(),
+                    Expr::binary(var("b"), second.clone(), var("c")),
+                );
+
+                let expected = if first.precedence() > second.precedence() {
+                    left_grouped
+                } else if first.precedence() < second.precedence() {
+                    right_grouped
+                } else {
+                    match first.associativity() {
+                        Associativity::Left => left_grouped,
+                        Associativity::Right => right_grouped,
+                    }
+                };
+
+                assert_eq!(parse_expression(&source), expected, "source: {}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new_delimiters_are_unexpected_until_their_grammar_exists() {
+        for (source, found) in [
+            ("1, 2;", Token::Comma),
+            ("x.y;", Token::Dot),
+            ("let a: 1;", Token::Colon),
+            ("a -> b;", Token::Arrow),
+            ("a => b;", Token::FatArrow),
+            ("@x;", Token::At),
+            ("15%;", Token::Percent),
+        ] {
+            match Parser::from_source(source).parse().unwrap_err().first() {
+                Some(ParseError::UnexpectedToken { found: token, .. }) => {
+                    assert_eq!(*token, found, "source: {}", source)
+                }
+                other => panic!("Expected unexpected-token error, got {:?}", other),
+            }
+        }
+    }
+
     #[test]
     fn test_operator_precedence() {
         let mut parser = Parser::from_source("2 + 3 * 4;");
@@ -429,4 +1324,48 @@ mod tests {
             _ => panic!("Expected binary expression"),
         }
     }
+
+    #[test]
+    fn test_parse_append_grows_program() {
+        let mut program = Parser::from_source("let x = 1;").parse().unwrap();
+
+        let added = Parser::from_source("let y = x; y + 1;")
+            .parse_append(&mut program)
+            .unwrap();
+
+        assert_eq!(added, 2);
+        assert_eq!(program.to_string(), "let x = 1;\nlet y = x;\n(y + 1);\n");
+    }
+
+    #[test]
+    fn test_parse_append_leaves_program_unchanged_on_error() {
+        let mut program = Parser::from_source("let x = 1;").parse().unwrap();
+
+        let errors = Parser::from_source("let y = 2; let = 3;")
+            .parse_append(&mut program)
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(program.statements.len(), 1);
+    }
+
+    #[test]
+    fn test_nested_parses_keep_earlier_errors() {
+        let earlier = ParseError::invalid_statement("earlier", 0, Span::new(0, 1));
+        let first_error = |errors: ParseErrors| errors.errors[0].clone();
+
+        let mut parser = Parser::from_source("let = 1;");
+        parser.errors.add(earlier.clone());
+        assert_eq!(parser.try_parse(|p| p.parse().map_err(first_error)), None);
+        assert_eq!(parser.errors.errors, vec![earlier.clone()]);
+
+        let mut parser = Parser::from_source("let x = 1;");
+        parser.errors.add(earlier.clone());
+        assert!(
+            parser
+                .try_parse(|p| p.parse().map_err(first_error))
+                .is_some()
+        );
+        assert_eq!(parser.errors.errors, vec![earlier]);
+    }
 }
@@ -0,0 +1,149 @@
+use crate::lexer::{Span, Token};
+use std::fmt::Write;
+
+/// One step the parser took, recorded by a parser built with
+/// [`super::Parser::with_trace`]. Positions are token indices.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseEvent {
+    /// A grammar rule started at `position`
+    Enter { rule: &'static str, position: usize },
+    /// A grammar rule finished at `position`, having parsed its construct or not
+    Exit {
+        rule: &'static str,
+        position: usize,
+        success: bool,
+    },
+    /// A token was consumed
+    Consume { token: Token, span: Span },
+    /// An attempted parse failed and the parser rewound from `from` to `to`
+    /// to try something else
+    Backtrack { from: usize, to: usize },
+    /// After an error, the parser skipped ahead from `position` to the start
+    /// of the next statement
+    Recover { position: usize },
+}
+
+/// Every step of a parse, in order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParseTrace {
+    pub events: Vec<ParseEvent>,
+}
+
+impl ParseTrace {
+    /// Renders the trace with each rule's steps indented beneath it, as in
+    /// `let_statement @0` followed by `  let 0..3`. Failed rules are marked.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut depth = 0;
+
+        for event in &self.events {
+            let indent = "  ".repeat(depth);
+            match event {
+                ParseEvent::Enter { rule, position } => {
+                    let _ = writeln!(out, "{}{} @{}", indent, rule, position);
+                    depth += 1;
+                }
+                ParseEvent::Exit {
+                    rule,
+                    position,
+                    success,
+                } => {
+                    depth = depth.saturating_sub(1);
+                    if !success {
+                        let indent = "  ".repeat(depth);
+                        let _ = writeln!(out, "{}✗ {} failed @{}", indent, rule, position);
+                    }
+                }
+                ParseEvent::Consume { token, span } => {
+                    let _ = writeln!(out, "{}{} {}", indent, token, span);
+                }
+                ParseEvent::Backtrack { from, to } => {
+                    let _ = writeln!(out, "{}↺ backtrack @{} to @{}", indent, from, to);
+                }
+                ParseEvent::Recover { position } => {
+                    let _ = writeln!(out, "{}↷ recover @{}", indent, position);
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::Parser;
+
+    fn trace(source: &str) -> String {
+        let mut parser = Parser::from_source(source).with_trace();
+        let _ = parser.parse();
+        parser.take_trace().unwrap_or_default().render()
+    }
+
+    #[test]
+    fn test_rules_nest_around_the_tokens_they_consume() {
+        assert_eq!(
+            trace("let x = 1;"),
+            "statement @0
+  let_statement @0
+    let 0..3
+    x 4..5
+    initializer @2
+      = 6..7
+      binary_expression @3
+        unary_expression @3
+          primary_expression @3
+            1 8..9
+      ; 9..10
+"
+        );
+    }
+
+    #[test]
+    fn test_failures_and_recovery() {
+        assert_eq!(
+            trace("-; 2;"),
+            "statement @0
+  expression_statement @0
+    binary_expression @0
+      unary_expression @0
+        - 0..1
+        unary_expression @1
+          primary_expression @1
+          ✗ primary_expression failed @1
+        ✗ unary_expression failed @1
+      ✗ unary_expression failed @1
+    ✗ binary_expression failed @1
+  ✗ expression_statement failed @1
+✗ statement failed @1
+↷ recover @1
+; 1..2
+statement @2
+  expression_statement @2
+    binary_expression @2
+      unary_expression @2
+        primary_expression @2
+          2 3..4
+    ; 4..5
+"
+        );
+    }
+
+    #[test]
+    fn test_backtracking() {
+        let mut parser = Parser::from_source("1 2").with_trace();
+        let attempt =
+            parser.try_parse(|parser| parser.parse().map_err(|errors| errors.errors[0].clone()));
+        assert!(attempt.is_none());
+
+        let trace = parser.take_trace().unwrap().render();
+        assert!(trace.ends_with("↺ backtrack @2 to @0\n"), "{}", trace);
+    }
+
+    #[test]
+    fn test_tracing_is_off_by_default() {
+        let mut parser = Parser::from_source("1;");
+        parser.parse().unwrap();
+
+        assert_eq!(parser.take_trace(), None);
+    }
+}
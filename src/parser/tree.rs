@@ -0,0 +1,231 @@
+use super::ast::{Expr, Program, Stmt};
+
+const BOLD_CYAN: &str = "\x1b[1;36m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const MAGENTA: &str = "\x1b[35m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+impl Program {
+    /// Renders the program as an indented tree drawn with box-drawing characters
+    pub fn render_tree(&self) -> String {
+        self.render_tree_with(false)
+    }
+
+    /// Renders the program tree, optionally colored with ANSI escape codes
+    pub fn render_tree_with(&self, color: bool) -> String {
+        let mut renderer = TreeRenderer::new(color);
+        renderer.line("", "Program", None, BOLD_CYAN);
+        renderer.children("", &self.statements, TreeRenderer::statement);
+        renderer.out
+    }
+}
+
+impl Stmt {
+    /// Renders the statement as a tree; see [`Program::render_tree`]
+    pub fn render_tree(&self) -> String {
+        let mut renderer = TreeRenderer::new(false);
+        renderer.statement(self, "", "");
+        renderer.out
+    }
+}
+
+impl Expr {
+    /// Renders the expression as a tree; see [`Program::render_tree`]
+    pub fn render_tree(&self) -> String {
+        let mut renderer = TreeRenderer::new(false);
+        renderer.expression(self, "", "");
+        renderer.out
+    }
+}
+
+struct TreeRenderer {
+    out: String,
+    color: bool,
+}
+
+impl TreeRenderer {
+    fn new(color: bool) -> Self {
+        Self {
+            out: String::new(),
+            color,
+        }
+    }
+
+    /// Writes a single node line: tree prefix, node kind and an optional detail
+    fn line(&mut self, prefix: &str, kind: &str, detail: Option<&str>, detail_color: &str) {
+        if self.color {
+            self.out.push_str(&format!(
+                "{}{}{}{}{}{}",
+                DIM, prefix, RESET, BOLD_CYAN, kind, RESET
+            ));
+            if let Some(detail) = detail {
+                self.out
+                    .push_str(&format!(" {}{}{}", detail_color, detail, RESET));
+            }
+        } else {
+            self.out.push_str(prefix);
+            self.out.push_str(kind);
+            if let Some(detail) = detail {
+                self.out.push(' ');
+                self.out.push_str(detail);
+            }
+        }
+        self.out.push('\n');
+    }
+
+    /// Renders each item as a child of the node whose continuation prefix is `indent`
+    fn children<T>(&mut self, indent: &str, items: &[T], render: fn(&mut Self, &T, &str, &str)) {
+        for (i, item) in items.iter().enumerate() {
+            let last = i + 1 == items.len();
+            let connector = format!("{}{}", indent, if last { "└── " } else { "├── " });
+            let continuation = format!("{}{}", indent, if last { "    " } else { "│   " });
+            render(self, item, &connector, &continuation);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, prefix: &str, indent: &str) {
+        match stmt {
+            Stmt::Let { name, value, doc } => {
+                self.line(prefix, "Let", Some(name), GREEN);
+                let mut children = Vec::new();
+                if let Some(doc) = doc {
+                    children.push(Child::Doc(doc));
+                }
+                children.push(Child::Expr(value));
+                self.children(indent, &children, Self::child);
+            }
+            Stmt::Destructure {
+                pattern,
+                value,
+                doc,
+            } => {
+                self.line(prefix, "Destructure", Some(&pattern.to_string()), GREEN);
+                let mut children = Vec::new();
+                if let Some(doc) = doc {
+                    children.push(Child::Doc(doc));
+                }
+                children.push(Child::Expr(value));
+                self.children(indent, &children, Self::child);
+            }
+            Stmt::Expression(expr) => {
+                self.line(prefix, "Expression", None, "");
+                self.children(indent, std::slice::from_ref(expr), Self::expression);
+            }
+            Stmt::Block(statements) => {
+                let count = match statements.len() {
+                    1 => "(1 statement)".to_string(),
+                    n => format!("({} statements)", n),
+                };
+                self.line(prefix, "Block", Some(&count), DIM);
+                self.children(indent, statements, Self::statement);
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr, prefix: &str, indent: &str) {
+        match expr {
+            Expr::Number(n) => self.line(prefix, "Number", Some(&n.to_string()), YELLOW),
+            Expr::Float(_) => self.line(prefix, "Float", Some(&expr.to_string()), YELLOW),
+            Expr::BigNumber(digits) => self.line(prefix, "BigNumber", Some(digits), YELLOW),
+            Expr::Identifier(name) => self.line(prefix, "Identifier", Some(name), GREEN),
+            Expr::Suffixed { .. } => self.line(prefix, "Suffixed", Some(&expr.to_string()), YELLOW),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                self.line(prefix, "Binary", Some(&operator.to_string()), MAGENTA);
+                self.children(indent, &[left.as_ref(), right.as_ref()], |r, e, p, i| {
+                    r.expression(e, p, i)
+                });
+            }
+            Expr::Unary { operator, operand } => {
+                self.line(prefix, "Unary", Some(&operator.to_string()), MAGENTA);
+                self.children(
+                    indent,
+                    std::slice::from_ref(operand.as_ref()),
+                    Self::expression,
+                );
+            }
+            Expr::Grouping(inner) => {
+                self.line(prefix, "Grouping", None, "");
+                self.children(
+                    indent,
+                    std::slice::from_ref(inner.as_ref()),
+                    Self::expression,
+                );
+            }
+            Expr::Tuple(elements) => {
+                self.line(prefix, "Tuple", Some(&format!("({})", elements.len())), DIM);
+                self.children(indent, elements, Self::expression);
+            }
+        }
+    }
+
+    fn child(&mut self, child: &Child<'_>, prefix: &str, indent: &str) {
+        match child {
+            Child::Doc(doc) => {
+                let summary = doc.lines().next().unwrap_or_default();
+                self.line(prefix, "Doc", Some(&format!("{:?}", summary)), DIM);
+            }
+            Child::Expr(expr) => self.expression(expr, prefix, indent),
+        }
+    }
+}
+
+/// Heterogeneous children of a let or destructuring node
+enum Child<'a> {
+    Doc(&'a str),
+    Expr(&'a Expr),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_source;
+
+    #[test]
+    fn test_render_program_tree() {
+        let program = parse_source("let x = 1 + -y; { 2; }").unwrap();
+
+        assert_eq!(
+            program.render_tree(),
+            "Program\n\
+             ├── Let x\n\
+             │   └── Binary +\n\
+             │       ├── Number 1\n\
+             │       └── Unary -\n\
+             │           └── Identifier y\n\
+             └── Block (1 statement)\n    \
+                 └── Expression\n        \
+                     └── Number 2\n"
+        );
+    }
+
+    #[test]
+    fn test_render_doc_and_grouping() {
+        let program = parse_source("/// Twice.\nlet t = (a);").unwrap();
+
+        assert_eq!(
+            program.statements[0].render_tree(),
+            "Let t\n├── Doc \"Twice.\"\n└── Grouping\n    └── Identifier a\n"
+        );
+    }
+
+    #[test]
+    fn test_colored_output() {
+        let program = parse_source("7;").unwrap();
+        let colored = program.render_tree_with(true);
+
+        assert!(colored.contains("\x1b[33m7\x1b[0m"));
+        assert_eq!(
+            colored
+                .replace("\x1b[1;36m", "")
+                .replace("\x1b[33m", "")
+                .replace("\x1b[2m", "")
+                .replace("\x1b[0m", ""),
+            program.render_tree()
+        );
+    }
+}
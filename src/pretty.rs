@@ -0,0 +1,165 @@
+//! A Wadler-style pretty printer. A document describes every way some text
+//! may be laid out; rendering picks, group by group from the outside in, the
+//! flat layout wherever it fits in the line width and breaks the rest.
+
+/// A layout-independent description of text
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Doc {
+    Text(String),
+    /// A space when its group is flat, a line break when it is broken
+    Line,
+    /// Nothing when its group is flat, a line break when it is broken
+    SoftLine,
+    /// Content laid out flat if it fits on the rest of the line, and broken
+    /// at its own lines otherwise
+    Group(Box<Doc>),
+    /// Content whose line breaks indent one level further
+    Nest(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub(crate) fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    pub(crate) fn group(doc: Doc) -> Self {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub(crate) fn nest(doc: Doc) -> Self {
+        Doc::Nest(Box::new(doc))
+    }
+}
+
+/// How a level of indentation is written, and how wide it is
+pub(crate) struct Indent<'a> {
+    pub unit: &'a str,
+    pub width: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// Lays `doc` out in lines of at most `width` columns where possible. The
+/// first line starts at nesting level `level`, after indentation the caller
+/// writes; later lines carry their own.
+pub(crate) fn render(doc: &Doc, width: usize, level: usize, indent: &Indent) -> String {
+    let mut out = String::new();
+    let mut column = level * indent.width;
+    let mut stack = vec![(level, Mode::Break, doc)];
+
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if *doc == Doc::Line {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine => {
+                out.push('\n');
+                out.push_str(&indent.unit.repeat(level));
+                column = level * indent.width;
+            }
+            Doc::Group(inner) => {
+                let remaining = width as isize - column as isize;
+                let mode = if fits(remaining, (level, Mode::Flat, inner), &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((level, mode, inner));
+            }
+            Doc::Nest(inner) => stack.push((level + 1, mode, inner)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+        }
+    }
+    out
+}
+
+// Whether `next` and whatever follows it up to the next line break fit in
+// `remaining` columns
+fn fits(mut remaining: isize, next: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![next];
+    let mut rest = rest.iter().rev();
+
+    while remaining >= 0 {
+        let Some((level, mode, doc)) = stack.pop().or_else(|| rest.next().copied()) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) => remaining -= text.chars().count() as isize,
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            Doc::Group(inner) | Doc::Nest(inner) => stack.push((level, mode, inner)),
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACES: Indent = Indent {
+        unit: "  ",
+        width: 2,
+    };
+
+    // `[a, b, c]` that breaks into one element per line
+    fn list(elements: &[&str]) -> Doc {
+        let mut items = Vec::new();
+        for (index, element) in elements.iter().enumerate() {
+            if index > 0 {
+                items.push(Doc::text(","));
+                items.push(Doc::Line);
+            }
+            items.push(Doc::text(*element));
+        }
+        Doc::group(Doc::Concat(vec![
+            Doc::text("["),
+            Doc::nest(Doc::Concat(
+                std::iter::once(Doc::SoftLine).chain(items).collect(),
+            )),
+            Doc::SoftLine,
+            Doc::text("]"),
+        ]))
+    }
+
+    #[test]
+    fn test_groups_stay_flat_when_they_fit() {
+        assert_eq!(render(&list(&["a", "b"]), 6, 0, &SPACES), "[a, b]");
+        assert_eq!(render(&list(&["a", "b"]), 5, 0, &SPACES), "[\n  a,\n  b\n]");
+    }
+
+    #[test]
+    fn test_outer_groups_break_first() {
+        let doc = Doc::group(Doc::Concat(vec![
+            Doc::text("x ="),
+            Doc::nest(Doc::Concat(vec![Doc::Line, list(&["one", "two"])])),
+        ]));
+
+        assert_eq!(render(&doc, 13, 0, &SPACES), "x =\n  [one, two]");
+        assert_eq!(
+            render(&doc, 12, 1, &SPACES),
+            "x =\n    [\n      one,\n      two\n    ]"
+        );
+    }
+
+    #[test]
+    fn test_text_after_a_group_counts_towards_its_line() {
+        let doc = Doc::Concat(vec![list(&["a", "b"]), Doc::text(";")]);
+
+        assert_eq!(render(&doc, 6, 0, &SPACES), "[\n  a,\n  b\n];");
+    }
+}
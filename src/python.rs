@@ -0,0 +1,177 @@
+//! Python bindings, built as an extension module named `oxide` with
+//! `maturin develop --features python`. Syntax trees are returned as nested
+//! dicts whose `kind` key names the node.
+
+use crate::parser::{Expr, Pattern, Stmt};
+use crate::workspace::check_source;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::Path;
+
+#[pymodule]
+fn oxide(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(check, module)?)?;
+    Ok(())
+}
+
+/// Parses source code into a list of statement dicts, raising ValueError on
+/// the first lex or parse error
+#[pyfunction]
+fn parse<'py>(py: Python<'py>, source: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let program =
+        crate::compile(source).map_err(|error| PyValueError::new_err(error.to_string()))?;
+    program
+        .statements
+        .iter()
+        .map(|stmt| statement_dict(py, stmt))
+        .collect()
+}
+
+/// Checks source code like `oxide check`, returning one dict per diagnostic
+#[pyfunction]
+fn check<'py>(py: Python<'py>, source: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    check_source(Path::new("<string>"), source)
+        .iter()
+        .map(|diagnostic| {
+            let dict = PyDict::new(py);
+            dict.set_item("severity", diagnostic.severity.to_string())?;
+            dict.set_item("message", &diagnostic.message)?;
+            dict.set_item("span", diagnostic.span.map(|span| (span.start, span.end)))?;
+            dict.set_item("line", diagnostic.position.map(|(line, _)| line))?;
+            dict.set_item("column", diagnostic.position.map(|(_, column)| column))?;
+            Ok(dict)
+        })
+        .collect()
+}
+
+fn node<'py>(py: Python<'py>, kind: &str) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("kind", kind)?;
+    Ok(dict)
+}
+
+fn statement_dict<'py>(py: Python<'py>, stmt: &Stmt) -> PyResult<Bound<'py, PyDict>> {
+    match stmt {
+        Stmt::Let { name, value, doc } => {
+            let dict = node(py, "Let")?;
+            dict.set_item("name", name)?;
+            dict.set_item("value", expression_dict(py, value)?)?;
+            dict.set_item("doc", doc)?;
+            Ok(dict)
+        }
+        Stmt::Destructure {
+            pattern,
+            value,
+            doc,
+        } => {
+            let dict = node(py, "Destructure")?;
+            dict.set_item("pattern", pattern_dict(py, pattern)?)?;
+            dict.set_item("value", expression_dict(py, value)?)?;
+            dict.set_item("doc", doc)?;
+            Ok(dict)
+        }
+        Stmt::Expression(expr) => {
+            let dict = node(py, "Expression")?;
+            dict.set_item("expression", expression_dict(py, expr)?)?;
+            Ok(dict)
+        }
+        Stmt::Block(statements) => {
+            let dict = node(py, "Block")?;
+            let statements = statements
+                .iter()
+                .map(|stmt| statement_dict(py, stmt))
+                .collect::<PyResult<Vec<_>>>()?;
+            dict.set_item("statements", statements)?;
+            Ok(dict)
+        }
+    }
+}
+
+fn expression_dict<'py>(py: Python<'py>, expr: &Expr) -> PyResult<Bound<'py, PyDict>> {
+    match expr {
+        Expr::Number(value) => {
+            let dict = node(py, "Number")?;
+            dict.set_item("value", value)?;
+            Ok(dict)
+        }
+        Expr::Float(value) => {
+            let dict = node(py, "Float")?;
+            dict.set_item("value", value)?;
+            Ok(dict)
+        }
+        Expr::BigNumber(digits) => {
+            let dict = node(py, "BigNumber")?;
+            dict.set_item("digits", digits)?;
+            Ok(dict)
+        }
+        Expr::Identifier(name) => {
+            let dict = node(py, "Identifier")?;
+            dict.set_item("name", name)?;
+            Ok(dict)
+        }
+        Expr::Suffixed { value, suffix } => {
+            let dict = node(py, "Suffixed")?;
+            dict.set_item("value", value)?;
+            dict.set_item("suffix", suffix)?;
+            Ok(dict)
+        }
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let dict = node(py, "Binary")?;
+            dict.set_item("operator", operator.to_string())?;
+            dict.set_item("left", expression_dict(py, left)?)?;
+            dict.set_item("right", expression_dict(py, right)?)?;
+            Ok(dict)
+        }
+        Expr::Unary { operator, operand } => {
+            let dict = node(py, "Unary")?;
+            dict.set_item("operator", operator.to_string())?;
+            dict.set_item("operand", expression_dict(py, operand)?)?;
+            Ok(dict)
+        }
+        Expr::Grouping(inner) => {
+            let dict = node(py, "Grouping")?;
+            dict.set_item("inner", expression_dict(py, inner)?)?;
+            Ok(dict)
+        }
+        Expr::Tuple(elements) => {
+            let dict = node(py, "Tuple")?;
+            let elements = elements
+                .iter()
+                .map(|element| expression_dict(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            dict.set_item("elements", elements)?;
+            Ok(dict)
+        }
+    }
+}
+
+fn pattern_dict<'py>(py: Python<'py>, pattern: &Pattern) -> PyResult<Bound<'py, PyDict>> {
+    match pattern {
+        Pattern::Wildcard => node(py, "Wildcard"),
+        Pattern::Literal(value) => {
+            let dict = node(py, "Literal")?;
+            dict.set_item("value", value)?;
+            Ok(dict)
+        }
+        Pattern::Identifier(name) => {
+            let dict = node(py, "Identifier")?;
+            dict.set_item("name", name)?;
+            Ok(dict)
+        }
+        Pattern::Tuple(elements) => {
+            let dict = node(py, "Tuple")?;
+            let elements = elements
+                .iter()
+                .map(|element| pattern_dict(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            dict.set_item("elements", elements)?;
+            Ok(dict)
+        }
+    }
+}
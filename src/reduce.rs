@@ -0,0 +1,270 @@
+use crate::lexer::{Lexer, Token};
+use crate::parse_source;
+use crate::parser::{Expr, Program, Stmt};
+
+/// Shrinks `source` while `interesting` keeps returning true for it, and
+/// returns the smallest interesting program found. If the source is not
+/// interesting to begin with it is returned unchanged.
+///
+/// Programs that parse are first simplified structurally, by removing
+/// statements, turning bindings into expression statements and replacing
+/// expressions with one of their operands. The
+/// result, or a source that does not parse, is then shrunk by deleting runs
+/// of tokens. Each candidate is passed to `interesting` as source text.
+pub fn reduce(source: &str, mut interesting: impl FnMut(&str) -> bool) -> String {
+    if !interesting(source) {
+        return source.to_string();
+    }
+
+    let mut best = source.to_string();
+
+    if let Ok(program) = parse_source(source) {
+        let printed = program.to_string();
+        if interesting(&printed) {
+            best = reduce_program(program, &mut interesting).to_string();
+        }
+    }
+
+    reduce_tokens(&best, &mut interesting)
+}
+
+// Applies the first interesting single-step simplification until none is left
+fn reduce_program(mut program: Program, interesting: &mut impl FnMut(&str) -> bool) -> Program {
+    'search: loop {
+        for candidate in statement_list_candidates(&program.statements) {
+            let candidate = Program {
+                statements: candidate,
+            };
+            if interesting(&candidate.to_string()) {
+                program = candidate;
+                continue 'search;
+            }
+        }
+        return program;
+    }
+}
+
+// Deletes ever smaller runs of tokens, keeping each deletion that stays interesting
+fn reduce_tokens(source: &str, interesting: &mut impl FnMut(&str) -> bool) -> String {
+    let mut tokens = token_texts(source);
+    let mut best = source.to_string();
+    let mut chunk = (tokens.len() / 2).max(1);
+
+    while !tokens.is_empty() {
+        let mut removed_any = false;
+        let mut start = 0;
+
+        while start < tokens.len() {
+            let end = (start + chunk).min(tokens.len());
+            let candidate: Vec<Token> = tokens[..start]
+                .iter()
+                .chain(&tokens[end..])
+                .cloned()
+                .collect();
+            let text = join_tokens(&candidate);
+
+            if interesting(&text) {
+                tokens = candidate;
+                best = text;
+                removed_any = true;
+            } else {
+                start += chunk;
+            }
+        }
+
+        if chunk == 1 && !removed_any {
+            break;
+        }
+        if !removed_any {
+            chunk = (chunk / 2).max(1);
+        }
+    }
+
+    best
+}
+
+fn token_texts(source: &str) -> Vec<Token> {
+    Lexer::new(source)
+        .tokenize()
+        .into_iter()
+        .filter(|token| *token != Token::EOF)
+        .collect()
+}
+
+// Writes tokens separated by spaces so that neighbours cannot merge; a doc
+// comment runs to the end of its line, so it is followed by a newline instead
+fn join_tokens(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push(' ');
+        }
+        out.push_str(&token.spelling());
+        if matches!(token, Token::DocComment(_)) {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// Every list obtained by removing or simplifying one statement
+fn statement_list_candidates(statements: &[Stmt]) -> Vec<Vec<Stmt>> {
+    let mut candidates = Vec::new();
+
+    for i in 0..statements.len() {
+        let mut without = statements.to_vec();
+        without.remove(i);
+        candidates.push(without);
+    }
+
+    for (i, stmt) in statements.iter().enumerate() {
+        for replacement in statement_candidates(stmt) {
+            let mut candidate = statements.to_vec();
+            candidate[i] = replacement;
+            candidates.push(candidate);
+        }
+    }
+
+    candidates
+}
+
+fn statement_candidates(stmt: &Stmt) -> Vec<Stmt> {
+    match stmt {
+        Stmt::Let { name, value, doc } => {
+            let mut candidates = vec![Stmt::Expression(value.clone())];
+            candidates.extend(
+                expression_candidates(value)
+                    .into_iter()
+                    .map(|value| Stmt::Let {
+                        name: name.clone(),
+                        value,
+                        doc: doc.clone(),
+                    }),
+            );
+            candidates
+        }
+        Stmt::Destructure {
+            pattern,
+            value,
+            doc,
+        } => {
+            let mut candidates = vec![Stmt::Expression(value.clone())];
+            candidates.extend(expression_candidates(value).into_iter().map(|value| {
+                Stmt::Destructure {
+                    pattern: pattern.clone(),
+                    value,
+                    doc: doc.clone(),
+                }
+            }));
+            candidates
+        }
+        Stmt::Expression(expr) => expression_candidates(expr)
+            .into_iter()
+            .map(Stmt::Expression)
+            .collect(),
+        Stmt::Block(statements) => statement_list_candidates(statements)
+            .into_iter()
+            .map(Stmt::Block)
+            .collect(),
+    }
+}
+
+// Every expression obtained by replacing one node with one of its operands
+fn expression_candidates(expr: &Expr) -> Vec<Expr> {
+    match expr {
+        Expr::Number(_)
+        | Expr::Float(_)
+        | Expr::BigNumber(_)
+        | Expr::Identifier(_)
+        | Expr::Suffixed { .. } => Vec::new(),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let mut candidates = vec![(**left).clone(), (**right).clone()];
+            candidates.extend(
+                expression_candidates(left)
+                    .into_iter()
+                    .map(|left| Expr::binary(left, operator.clone(), (**right).clone())),
+            );
+            candidates.extend(
+                expression_candidates(right)
+                    .into_iter()
+                    .map(|right| Expr::binary((**left).clone(), operator.clone(), right)),
+            );
+            candidates
+        }
+        Expr::Unary { operator, operand } => {
+            let mut candidates = vec![(**operand).clone()];
+            candidates.extend(
+                expression_candidates(operand)
+                    .into_iter()
+                    .map(|operand| Expr::unary(operator.clone(), operand)),
+            );
+            candidates
+        }
+        Expr::Grouping(inner) => {
+            let mut candidates = vec![(**inner).clone()];
+            candidates.extend(expression_candidates(inner).into_iter().map(Expr::grouping));
+            candidates
+        }
+        Expr::Tuple(elements) => {
+            let mut candidates = elements.clone();
+            for i in 0..elements.len() {
+                let mut without = elements.clone();
+                without.remove(i);
+                candidates.push(Expr::tuple(without));
+            }
+            for (i, element) in elements.iter().enumerate() {
+                for replacement in expression_candidates(element) {
+                    let mut candidate = elements.clone();
+                    candidate[i] = replacement;
+                    candidates.push(Expr::tuple(candidate));
+                }
+            }
+            candidates
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn test_uninteresting_source_is_unchanged() {
+        assert_eq!(reduce("let x = 1;", |_| false), "let x = 1;");
+    }
+
+    #[test]
+    fn test_structural_reduction() {
+        let source = "let a = 1; let b = (2 + a) * 3; { let c = 4; } b - 5;";
+
+        // Keep any valid program that still mentions `a`
+        let reduced = reduce(source, |candidate| {
+            parse_source(candidate).is_ok() && candidate.contains('a')
+        });
+
+        assert_eq!(reduced, "a;\n");
+    }
+
+    #[test]
+    fn test_parse_failure_is_preserved() {
+        let source = "let a = 1; let b = (a + 2; let c = 3; { c; }";
+        let still_fails = |candidate: &str| compile(candidate).is_err() && candidate.contains('(');
+
+        let reduced = reduce(source, still_fails);
+
+        assert_eq!(reduced, "(");
+        assert!(still_fails(&reduced));
+    }
+
+    #[test]
+    fn test_doc_comments_stay_on_their_own_line() {
+        let tokens = token_texts("/// Docs\nlet x = 1;");
+
+        assert_eq!(join_tokens(&tokens), "/// Docs\nlet x = 1 ;");
+    }
+}
@@ -0,0 +1,372 @@
+use crate::edit::{TextEdit, apply_edits};
+use crate::lexer::{Lexer, Span, Token};
+use crate::parse_source;
+use crate::parser::{Expr, Program, Stmt};
+use std::fmt;
+
+/// Why a refactoring could not be applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefactorError {
+    /// The source does not parse, so there is nothing safe to refactor
+    InvalidSource,
+    /// The selection is not a complete expression of the program
+    NotAnExpression,
+    /// The new name is not an identifier, or already used in the file
+    InvalidName(String),
+    /// There is no `let` binding at the cursor
+    NoBinding,
+    /// The binding is used a number of times other than once
+    NotSingleUse { name: String, uses: usize },
+    /// A name the refactoring depends on is bound again in between
+    Shadowed(String),
+    /// The edited program would not parse
+    BrokenResult,
+}
+
+impl fmt::Display for RefactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RefactorError::InvalidSource => write!(f, "the source contains syntax errors"),
+            RefactorError::NotAnExpression => write!(f, "the selection is not an expression"),
+            RefactorError::InvalidName(name) => {
+                write!(f, "'{}' cannot be used as a new name", name)
+            }
+            RefactorError::NoBinding => write!(f, "there is no binding at the cursor"),
+            RefactorError::NotSingleUse { name, uses } => {
+                write!(f, "'{}' is used {} times, not once", name, uses)
+            }
+            RefactorError::Shadowed(name) => write!(f, "'{}' is bound again before its use", name),
+            RefactorError::BrokenResult => write!(f, "the result would not parse"),
+        }
+    }
+}
+
+impl std::error::Error for RefactorError {}
+
+/// Moves the expression selected by `selection` into a new `let name` binding
+/// placed before the statement containing it, and puts `name` in its place.
+///
+/// The selection must cover a whole subexpression: `1 + 2` in `1 + 2 * 3` is
+/// rejected because extracting it would change the result.
+pub fn extract_variable(
+    source: &str,
+    selection: Span,
+    name: &str,
+) -> Result<Vec<TextEdit>, RefactorError> {
+    let original = parse_source(source).map_err(|_| RefactorError::InvalidSource)?;
+    let tokens = tokens(source);
+
+    if !matches!(single_token(name), Some(Token::Ident(_)))
+        || tokens
+            .iter()
+            .any(|(token, _)| *token == Token::Ident(name.to_string()))
+    {
+        return Err(RefactorError::InvalidName(name.to_string()));
+    }
+
+    let selection = trim_to_tokens(&tokens, selection).ok_or(RefactorError::NotAnExpression)?;
+    let selected = &source[selection.start..selection.end];
+
+    // Parenthesizing a whole subexpression leaves the tree unchanged
+    let wrapped = apply_edits(
+        source,
+        &[TextEdit::new(selection, format!("({})", selected))],
+    );
+    let unchanged = parse_source(&wrapped).is_ok_and(|program| same_structure(program, original));
+    if !unchanged {
+        return Err(RefactorError::NotAnExpression);
+    }
+
+    let start = statement_start(&tokens, selection.start);
+    let indent = line_indent(source, start);
+    let edits = vec![
+        TextEdit::new(
+            Span::new(start, start),
+            format!("let {} = {};\n{}", name, selected, indent),
+        ),
+        TextEdit::new(selection, name),
+    ];
+
+    validate(source, edits)
+}
+
+/// Replaces the single use of the binding whose name is at `offset` with its
+/// value, and removes the binding
+pub fn inline_variable(source: &str, offset: usize) -> Result<Vec<TextEdit>, RefactorError> {
+    parse_source(source).map_err(|_| RefactorError::InvalidSource)?;
+    let tokens = tokens(source);
+
+    let name_index = (1..tokens.len())
+        .find(|&i| {
+            let (token, span) = &tokens[i];
+            matches!(token, Token::Ident(_))
+                && tokens[i - 1].0 == Token::Let
+                && span.start <= offset
+                && offset <= span.end
+        })
+        .ok_or(RefactorError::NoBinding)?;
+    let Token::Ident(name) = &tokens[name_index].0 else {
+        return Err(RefactorError::NoBinding);
+    };
+
+    // The statement is `let name = value ;`, which parsing has already checked
+    let value_start = name_index + 2;
+    let semicolon = value_start
+        + tokens[value_start..]
+            .iter()
+            .position(|(token, _)| *token == Token::Semicolon)
+            .ok_or(RefactorError::NoBinding)?;
+    let value_span = Span::new(tokens[value_start].1.start, tokens[semicolon - 1].1.end);
+    let value_names: Vec<&Token> = tokens[value_start..semicolon]
+        .iter()
+        .map(|(token, _)| token)
+        .filter(|token| matches!(token, Token::Ident(_)))
+        .collect();
+
+    // The binding is visible until the end of its block. Names between a
+    // `let` and its `=` are bound there, by a name or a pattern, not used.
+    let mut depth = 0usize;
+    let mut naming = false;
+    let mut uses = Vec::new();
+    for (token, span) in &tokens[semicolon + 1..] {
+        match token {
+            Token::LeftBrace => depth += 1,
+            Token::RightBrace if depth == 0 => break,
+            Token::RightBrace => depth -= 1,
+            Token::Let => naming = true,
+            Token::Equals => naming = false,
+            Token::Ident(bound) if naming && (bound == name || value_names.contains(&token)) => {
+                return Err(RefactorError::Shadowed(bound.clone()));
+            }
+            Token::Ident(used) if !naming && used == name => uses.push(*span),
+            _ => {}
+        }
+    }
+
+    let [use_span] = uses[..] else {
+        return Err(RefactorError::NotSingleUse {
+            name: name.clone(),
+            uses: uses.len(),
+        });
+    };
+
+    let value = &source[value_span.start..value_span.end];
+    let replacement = if needs_parentheses(value) {
+        format!("({})", value)
+    } else {
+        value.to_string()
+    };
+
+    // Remove the statement, its doc comments and the whitespace after it
+    let mut first = name_index - 1;
+    while first > 0 && matches!(tokens[first - 1].0, Token::DocComment(_)) {
+        first -= 1;
+    }
+    let removal_start = line_start_if_blank(source, tokens[first].1.start);
+    let removal_end = line_end_if_blank(source, tokens[semicolon].1.end).unwrap_or_else(|| {
+        tokens
+            .get(semicolon + 1)
+            .map_or(source.len(), |(_, span)| span.start)
+    });
+
+    let edits = vec![
+        TextEdit::new(Span::new(removal_start, removal_end), ""),
+        TextEdit::new(use_span, replacement),
+    ];
+
+    validate(source, edits)
+}
+
+// Keeps the edits only if the edited source still parses
+fn validate(source: &str, edits: Vec<TextEdit>) -> Result<Vec<TextEdit>, RefactorError> {
+    match parse_source(&apply_edits(source, &edits)) {
+        Ok(_) => Ok(edits),
+        Err(_) => Err(RefactorError::BrokenResult),
+    }
+}
+
+fn tokens(source: &str) -> Vec<(Token, Span)> {
+    let mut tokens = Lexer::new(source).tokenize_spanned();
+    tokens.pop();
+    tokens
+}
+
+fn single_token(text: &str) -> Option<Token> {
+    let mut lexer = Lexer::new(text);
+    let token = lexer.next_token();
+    (lexer.next_token() == Token::EOF).then_some(token)
+}
+
+// Shrinks a selection to the tokens it covers, dropping surrounding whitespace
+fn trim_to_tokens(tokens: &[(Token, Span)], selection: Span) -> Option<Span> {
+    let mut covered = tokens
+        .iter()
+        .map(|(_, span)| *span)
+        .filter(|span| selection.start <= span.start && span.end <= selection.end);
+    let first = covered.next()?;
+    let last = covered.next_back().unwrap_or(first);
+    Some(Span::new(first.start, last.end))
+}
+
+// Start of the innermost statement containing `offset`: the first token after
+// the closest `;`, `{` or `}` before it
+fn statement_start(tokens: &[(Token, Span)], offset: usize) -> usize {
+    let boundary = tokens
+        .iter()
+        .rposition(|(token, span)| {
+            span.end <= offset
+                && matches!(
+                    token,
+                    Token::Semicolon | Token::LeftBrace | Token::RightBrace
+                )
+        })
+        .map_or(0, |index| index + 1);
+    tokens[boundary].1.start
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let before = &source[line_start..offset];
+    if before.trim().is_empty() { before } else { "" }
+}
+
+// Moves `offset` back to the start of its line if only whitespace precedes it
+fn line_start_if_blank(source: &str, offset: usize) -> usize {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    if source[line_start..offset].trim().is_empty() {
+        line_start
+    } else {
+        offset
+    }
+}
+
+// The end of `offset`'s line, past the newline, if only whitespace follows it
+fn line_end_if_blank(source: &str, offset: usize) -> Option<usize> {
+    let rest = &source[offset..];
+    let line_end = rest
+        .find('\n')
+        .map_or(source.len(), |index| offset + index + 1);
+    source[offset..line_end]
+        .trim()
+        .is_empty()
+        .then_some(line_end)
+}
+
+// Whether an expression's text must be parenthesized to be substituted as an operand
+fn needs_parentheses(expression: &str) -> bool {
+    match parse_source(&format!("{};", expression)) {
+        Ok(program) => !matches!(
+            program.statements.as_slice(),
+            [Stmt::Expression(
+                Expr::Number(_)
+                    | Expr::Identifier(_)
+                    | Expr::Grouping(_)
+                    | Expr::Tuple(_)
+                    | Expr::Unary { .. }
+            )]
+        ),
+        Err(_) => true,
+    }
+}
+
+fn same_structure(mut left: Program, mut right: Program) -> bool {
+    left.strip_groupings();
+    right.strip_groupings();
+    left == right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(source: &str, selected: &str, name: &str) -> Result<String, RefactorError> {
+        let start = source.find(selected).unwrap();
+        let edits = extract_variable(source, Span::new(start, start + selected.len()), name)?;
+        Ok(apply_edits(source, &edits))
+    }
+
+    fn inline(source: &str, name: &str) -> Result<String, RefactorError> {
+        let offset = source.find(&format!("let {}", name)).unwrap() + 4;
+        Ok(apply_edits(source, &inline_variable(source, offset)?))
+    }
+
+    #[test]
+    fn test_extract_variable() {
+        assert_eq!(
+            extract("let a = 1;\nlet total = a * (2 + 3);", "(2 + 3)", "sum").unwrap(),
+            "let a = 1;\nlet sum = (2 + 3);\nlet total = a * sum;"
+        );
+    }
+
+    #[test]
+    fn test_extract_inside_block_keeps_indentation() {
+        assert_eq!(
+            extract("{\n    1;\n    a * b + c;\n}", " a * b ", "product").unwrap(),
+            "{\n    1;\n    let product = a * b;\n    product + c;\n}"
+        );
+    }
+
+    #[test]
+    fn test_extract_rejects_partial_expressions_and_names_in_use() {
+        assert_eq!(
+            extract("1 + 2 * 3;", "1 + 2", "x"),
+            Err(RefactorError::NotAnExpression)
+        );
+        assert_eq!(
+            extract("let x = 1;", "= 1", "y"),
+            Err(RefactorError::NotAnExpression)
+        );
+        assert_eq!(
+            extract("let x = 1 + 2;", "1 + 2", "x"),
+            Err(RefactorError::InvalidName("x".to_string()))
+        );
+        assert_eq!(
+            extract("1 + 2;", "1", "let"),
+            Err(RefactorError::InvalidName("let".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_inline_variable() {
+        assert_eq!(
+            inline("let a = 1;\n/// Sum.\nlet sum = a + 2;\nsum * 3;", "sum").unwrap(),
+            "let a = 1;\n(a + 2) * 3;"
+        );
+        assert_eq!(inline("let a = b; { a; }", "a").unwrap(), "{ b; }");
+    }
+
+    #[test]
+    fn test_inline_rejects_unsafe_cases() {
+        assert_eq!(
+            inline("let a = 1; a + a;", "a"),
+            Err(RefactorError::NotSingleUse {
+                name: "a".to_string(),
+                uses: 2
+            })
+        );
+        assert_eq!(
+            inline("let y = a; { let a = 2; y; }", "y"),
+            Err(RefactorError::Shadowed("a".to_string()))
+        );
+        assert_eq!(
+            inline("let y = 1; let x = y; let (y, z) = (5, 6); x + y;", "x"),
+            Err(RefactorError::Shadowed("y".to_string()))
+        );
+        assert_eq!(
+            inline("let x = 1; let (_, (x, z)) = (5, (6, 7)); x + z;", "x"),
+            Err(RefactorError::Shadowed("x".to_string()))
+        );
+        assert_eq!(
+            inline_variable("let a = 1; a;", 11),
+            Err(RefactorError::NoBinding)
+        );
+    }
+
+    #[test]
+    fn test_extract_then_inline_round_trips() {
+        let source = "let r = (x - 1) / 2;";
+        let extracted = extract(source, "(x - 1)", "shifted").unwrap();
+
+        assert_eq!(inline(&extracted, "shifted").unwrap(), source);
+    }
+}
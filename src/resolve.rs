@@ -0,0 +1,399 @@
+//! Name resolution as a side table. The syntax tree has no node identities of
+//! its own, so an identifier is identified by its span: every use maps to the
+//! span of the name in the `let` that binds it.
+
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{Expr, ParseErrors, Program, Stmt, parse_source};
+use crate::rewrite::{Step, binding_spans, expression_spans};
+use std::collections::HashMap;
+
+/// Which binding each identifier of a program refers to, following the same
+/// scoping as [`crate::analysis::free_variables`]: a binding is visible to
+/// the statements after it in its own block and in nested blocks, and not in
+/// its own initializer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolutions {
+    /// Every bound name, in source order
+    definitions: Vec<Span>,
+    /// Every resolved use with its definition, in source order
+    uses: Vec<(Span, Span)>,
+    /// Uses no binding is visible to, in source order
+    unresolved: Vec<Span>,
+    /// Unresolved uses of a name bound inside a block that ended before
+    /// them, with that binding
+    out_of_scope: Vec<(Span, Span)>,
+    /// The canonical spelling of every identifier, in source order
+    spellings: Vec<(Span, String)>,
+}
+
+/// How identifiers are matched with bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameCase {
+    /// `Total` and `total` are different names
+    #[default]
+    Sensitive,
+    /// `Total` and `total` are the same name, as in spreadsheet formulas,
+    /// spelled canonically the given way
+    Insensitive(Spelling),
+}
+
+/// The canonical spelling of a name whose case is ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spelling {
+    /// As its binding spells it; an unresolved name as it is written
+    Definition,
+    Lowercase,
+    Uppercase,
+}
+
+impl Resolutions {
+    /// Returns the definition of the identifier at `span`, which may be any
+    /// part of the identifier, such as an empty span at the cursor. For the
+    /// name in a `let`, that is the name itself.
+    pub fn definition_of(&self, span: Span) -> Option<Span> {
+        let contains =
+            |identifier: &Span| identifier.start <= span.start && span.end <= identifier.end;
+        self.uses
+            .iter()
+            .find(|(used, _)| contains(used))
+            .map(|(_, definition)| *definition)
+            .or_else(|| self.definitions.iter().copied().find(contains))
+    }
+
+    /// Returns the uses that refer to the binding whose name is at `definition`
+    pub fn references_to(&self, definition: Span) -> Vec<Span> {
+        self.uses
+            .iter()
+            .filter(|(_, bound)| *bound == definition)
+            .map(|(used, _)| *used)
+            .collect()
+    }
+
+    /// Returns the names bound by every `let`, in source order
+    pub fn definitions(&self) -> &[Span] {
+        &self.definitions
+    }
+
+    /// Returns the uses of names that are not bound where they are used
+    pub fn unresolved(&self) -> &[Span] {
+        &self.unresolved
+    }
+
+    /// Returns the unresolved uses of names that are bound inside a block
+    /// that ends before the use, as in `{ let x = 1; } x;`, each with the
+    /// latest such binding. The name may still come from the host, but more
+    /// often the use was meant to be inside the block.
+    pub fn out_of_scope(&self) -> &[(Span, Span)] {
+        &self.out_of_scope
+    }
+
+    /// Returns the canonical spelling of the identifier at `span`, which may
+    /// be any part of it. Hosts that ignore case look names up by this
+    /// spelling. It is the identifier as written unless case is ignored.
+    pub fn canonical_name(&self, span: Span) -> Option<&str> {
+        self.spellings
+            .iter()
+            .find(|(identifier, _)| identifier.start <= span.start && span.end <= identifier.end)
+            .map(|(_, name)| name.as_str())
+    }
+}
+
+/// Resolves every identifier of `source` to the binding it refers to
+pub fn resolve(source: &str) -> Result<Resolutions, ParseErrors> {
+    resolve_with(source, NameCase::Sensitive)
+}
+
+/// Like [`resolve`], matching names as `case` says
+pub fn resolve_with(source: &str, case: NameCase) -> Result<Resolutions, ParseErrors> {
+    let program = parse_source(source)?;
+    let mut tokens = Lexer::new(source).tokenize_spanned();
+    tokens.pop();
+    Ok(resolve_program_with(&program, &tokens, case))
+}
+
+/// Resolves the identifiers of a parsed program, given its tokens without the
+/// end-of-file token
+pub(crate) fn resolve_program(program: &Program, tokens: &[(Token, Span)]) -> Resolutions {
+    resolve_program_with(program, tokens, NameCase::Sensitive)
+}
+
+fn resolve_program_with(
+    program: &Program,
+    tokens: &[(Token, Span)],
+    case: NameCase,
+) -> Resolutions {
+    let mut resolver = Resolver {
+        expressions: expression_spans(program, tokens),
+        bindings: binding_spans(program, tokens),
+        case,
+        scopes: Vec::new(),
+        closed: HashMap::new(),
+        resolutions: Resolutions::default(),
+    };
+    resolver.statements(&program.statements, &mut Vec::new());
+    resolver.resolutions
+}
+
+struct Resolver {
+    expressions: HashMap<(Vec<usize>, Vec<Step>), Span>,
+    bindings: HashMap<Vec<usize>, Vec<Span>>,
+    case: NameCase,
+    /// Names bound in each enclosing block, by their key, with their spans
+    /// and canonical spellings, innermost last
+    scopes: Vec<HashMap<String, (Span, String)>>,
+    /// The latest binding of each name in a block that has ended
+    closed: HashMap<String, (Span, String)>,
+    resolutions: Resolutions,
+}
+
+impl Resolver {
+    fn statements(&mut self, statements: &[Stmt], path: &mut Vec<usize>) {
+        self.scopes.push(HashMap::new());
+        for (index, stmt) in statements.iter().enumerate() {
+            path.push(index);
+            self.statement(stmt, path);
+            path.pop();
+        }
+        if let Some(scope) = self.scopes.pop() {
+            self.closed.extend(scope);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, path: &mut Vec<usize>) {
+        match stmt {
+            Stmt::Let { name, value, .. } => {
+                self.expression(value, path, &mut Vec::new());
+                self.bind(path, &[name.as_str()]);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.expression(value, path, &mut Vec::new());
+                self.bind(path, &pattern.bindings());
+            }
+            Stmt::Expression(expr) => self.expression(expr, path, &mut Vec::new()),
+            Stmt::Block(statements) => self.statements(statements, path),
+        }
+    }
+
+    // Brings the names a statement binds into scope, in source order so the
+    // last of two equal names in one pattern wins
+    fn bind(&mut self, path: &[usize], names: &[&str]) {
+        let spans = self.bindings.get(path).cloned().unwrap_or_default();
+        for (name, span) in names.iter().zip(spans) {
+            let spelling = self.spelling(name);
+            self.resolutions.definitions.push(span);
+            self.resolutions.spellings.push((span, spelling.clone()));
+            let key = self.key(name);
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(key, (span, spelling));
+            }
+        }
+    }
+
+    // What names are looked up by: the name itself, or with case folded
+    fn key(&self, name: &str) -> String {
+        match self.case {
+            NameCase::Sensitive => name.to_string(),
+            NameCase::Insensitive(_) => name.to_lowercase(),
+        }
+    }
+
+    // The canonical spelling of a name as bound or used where nothing binds it
+    fn spelling(&self, name: &str) -> String {
+        match self.case {
+            NameCase::Insensitive(Spelling::Lowercase) => name.to_lowercase(),
+            NameCase::Insensitive(Spelling::Uppercase) => name.to_uppercase(),
+            NameCase::Sensitive | NameCase::Insensitive(Spelling::Definition) => name.to_string(),
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr, statement: &[usize], steps: &mut Vec<Step>) {
+        let mut child = |resolver: &mut Self, expr: &Expr, step: Step| {
+            steps.push(step);
+            resolver.expression(expr, statement, steps);
+            steps.pop();
+        };
+        match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {}
+            Expr::Identifier(name) => {
+                let Some(span) = self.expressions.get(&(statement.to_vec(), steps.clone())) else {
+                    return;
+                };
+                let key = self.key(name);
+                let definition = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.get(&key).cloned());
+                let spelling = match definition {
+                    Some((definition, spelling)) => {
+                        self.resolutions.uses.push((*span, definition));
+                        spelling
+                    }
+                    None => {
+                        self.resolutions.unresolved.push(*span);
+                        if let Some((binding, _)) = self.closed.get(&key) {
+                            self.resolutions.out_of_scope.push((*span, *binding));
+                        }
+                        self.spelling(name)
+                    }
+                };
+                self.resolutions.spellings.push((*span, spelling));
+            }
+            Expr::Binary { left, right, .. } => {
+                child(self, left, Step::Left);
+                child(self, right, Step::Right);
+            }
+            Expr::Unary { operand, .. } => child(self, operand, Step::Operand),
+            Expr::Grouping(inner) => child(self, inner, Step::Inner),
+            Expr::Tuple(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    child(self, element, Step::Element(index));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The text of each span, for readable assertions
+    fn texts<'a>(source: &'a str, spans: &[Span]) -> Vec<&'a str> {
+        spans
+            .iter()
+            .map(|span| &source[span.start..span.end])
+            .collect()
+    }
+
+    #[test]
+    fn test_uses_resolve_to_their_binding() {
+        let source = "let rate = 2; let total = rate * 3; total + rate;";
+        let resolutions = resolve(source).unwrap();
+
+        assert_eq!(
+            resolutions.definition_of(Span::new(26, 30)),
+            Some(Span::new(4, 8))
+        );
+        assert_eq!(
+            resolutions.references_to(Span::new(4, 8)),
+            vec![Span::new(26, 30), Span::new(44, 48)]
+        );
+        assert_eq!(
+            texts(source, resolutions.definitions()),
+            vec!["rate", "total"]
+        );
+        assert!(resolutions.unresolved().is_empty());
+    }
+
+    #[test]
+    fn test_definition_of_a_position_inside_a_name() {
+        let resolutions = resolve("let value = 1; value;").unwrap();
+
+        assert_eq!(
+            resolutions.definition_of(Span::new(17, 17)),
+            Some(Span::new(4, 9))
+        );
+        assert_eq!(
+            resolutions.definition_of(Span::new(6, 6)),
+            Some(Span::new(4, 9))
+        );
+        assert_eq!(resolutions.definition_of(Span::new(12, 12)), None);
+    }
+
+    #[test]
+    fn test_shadowing_and_block_scopes() {
+        let source = "let x = 1; { let x = x + 1; x; } x;";
+        let resolutions = resolve(source).unwrap();
+
+        // The inner initializer and the use after the block see the outer `x`
+        assert_eq!(
+            resolutions.references_to(Span::new(4, 5)),
+            vec![Span::new(21, 22), Span::new(33, 34)]
+        );
+        assert_eq!(
+            resolutions.references_to(Span::new(17, 18)),
+            vec![Span::new(28, 29)]
+        );
+    }
+
+    #[test]
+    fn test_unbound_names_are_unresolved() {
+        let source = "let x = x + y; { let z = 1; } z;";
+        let resolutions = resolve(source).unwrap();
+
+        assert_eq!(texts(source, resolutions.unresolved()), vec!["x", "y", "z"]);
+        assert_eq!(resolutions.definition_of(Span::new(8, 9)), None);
+    }
+
+    #[test]
+    fn test_uses_after_the_binding_block_ended() {
+        let source = "{ let x = 1; { let y = x; } y; } x + y; let x = 2; x;";
+        let resolutions = resolve(source).unwrap();
+
+        assert_eq!(
+            resolutions.out_of_scope(),
+            &[
+                (Span::new(28, 29), Span::new(19, 20)),
+                (Span::new(33, 34), Span::new(6, 7)),
+                (Span::new(37, 38), Span::new(19, 20)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_destructuring_binds_each_name() {
+        let source = "/// Pair\nlet (a, (_, b)) = (1, (2, 3)); a * b;";
+        let resolutions = resolve(source).unwrap();
+
+        assert_eq!(texts(source, resolutions.definitions()), vec!["a", "b"]);
+        assert_eq!(
+            resolutions.definition_of(Span::new(40, 41)),
+            Some(Span::new(14, 15))
+        );
+        assert_eq!(
+            resolutions.definition_of(Span::new(44, 45)),
+            Some(Span::new(21, 22))
+        );
+    }
+
+    #[test]
+    fn test_names_are_case_sensitive_by_default() {
+        let source = "let Total = 1; total;";
+        let resolutions = resolve(source).unwrap();
+
+        assert_eq!(texts(source, resolutions.unresolved()), vec!["total"]);
+        assert_eq!(resolutions.canonical_name(Span::new(15, 20)), Some("total"));
+    }
+
+    #[test]
+    fn test_case_insensitive_names() {
+        let source = "let Total = 1; { let RATE = 2; total * rate; } Other;";
+        let spellings = |spelling| {
+            let resolutions = resolve_with(source, NameCase::Insensitive(spelling)).unwrap();
+            assert_eq!(
+                resolutions.definition_of(Span::new(31, 36)),
+                Some(Span::new(4, 9))
+            );
+            assert_eq!(texts(source, resolutions.unresolved()), vec!["Other"]);
+            [31, 39, 47].map(|start| {
+                resolutions
+                    .canonical_name(Span::new(start, start))
+                    .map(String::from)
+            })
+        };
+
+        assert_eq!(
+            spellings(Spelling::Definition),
+            ["Total", "RATE", "Other"].map(|name| Some(name.to_string()))
+        );
+        assert_eq!(
+            spellings(Spelling::Lowercase),
+            ["total", "rate", "other"].map(|name| Some(name.to_string()))
+        );
+        assert_eq!(
+            spellings(Spelling::Uppercase),
+            ["TOTAL", "RATE", "OTHER"].map(|name| Some(name.to_string()))
+        );
+    }
+}
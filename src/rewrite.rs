@@ -0,0 +1,673 @@
+use crate::edit::{TextEdit, apply_edits};
+use crate::format::{FormatOptions, binding_power, expression, format_program};
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{Associativity, Expr, Pattern, Program, Stmt, parse_source};
+use std::collections::HashMap;
+use std::fmt;
+
+/// One step from an expression to one of its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    Left,
+    Right,
+    Operand,
+    Inner,
+    Element(usize),
+}
+
+/// An AST-level edit. Statements are addressed by their indices, one per level
+/// of block nesting, so `[2, 0]` is the first statement of the block that is
+/// the third top-level statement. All paths refer to the original program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rewrite {
+    /// Replaces the expression reached by following `steps` from the value of
+    /// the statement at `statement`
+    ReplaceExpression {
+        statement: Vec<usize>,
+        steps: Vec<Step>,
+        with: Expr,
+    },
+    /// Inserts `stmt` before the statement at `statement`. The last index may
+    /// be one past the end of its block to append.
+    InsertStatement { statement: Vec<usize>, stmt: Stmt },
+    /// Removes the statement at `statement`, with its doc comment
+    RemoveStatement { statement: Vec<usize> },
+}
+
+/// Why a set of rewrites could not be turned into text edits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteError {
+    /// The source does not parse, so its nodes have no known positions
+    InvalidSource,
+    /// A path does not lead to a node of the program
+    NoSuchNode,
+    /// Two rewrites touch the same text
+    Overlapping,
+    /// The edited source would not parse back into the rewritten program
+    BrokenResult,
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::InvalidSource => write!(f, "the source contains syntax errors"),
+            RewriteError::NoSuchNode => write!(f, "the path does not lead to a node"),
+            RewriteError::Overlapping => write!(f, "two rewrites change the same text"),
+            RewriteError::BrokenResult => {
+                write!(f, "the result would not parse as the rewritten program")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RewriteError {}
+
+/// Turns AST-level rewrites into the smallest text edits that make them:
+/// only new nodes are printed, so the formatting and comments of everything
+/// else survive untouched. Replacements are parenthesized where their new
+/// context would otherwise regroup them.
+pub fn rewrite(source: &str, rewrites: &[Rewrite]) -> Result<Vec<TextEdit>, RewriteError> {
+    let original = parse_source(source).map_err(|_| RewriteError::InvalidSource)?;
+    let mut tokens = Lexer::new(source).tokenize_spanned();
+    tokens.pop();
+    let spans = SpanMap::new(&original, &tokens);
+
+    let mut expected = original.clone();
+    let mut edits = Vec::new();
+    for rewrite in rewrites {
+        edits.push(spans.edit(source, &original, rewrite)?);
+    }
+    edits.sort_by_key(|edit| (edit.span.start, edit.span.end));
+    if edits
+        .windows(2)
+        .any(|pair| pair[0].span.end > pair[1].span.start)
+    {
+        return Err(RewriteError::Overlapping);
+    }
+
+    // Removals and insertions shift indices, so they are applied to the
+    // expected program last and back to front
+    let mut statement_edits: Vec<&Rewrite> = Vec::new();
+    for rewrite in rewrites {
+        match rewrite {
+            Rewrite::ReplaceExpression {
+                statement,
+                steps,
+                with,
+            } => {
+                *expression_at(statement_at(&mut expected, statement)?, steps)? = with.clone();
+            }
+            _ => statement_edits.push(rewrite),
+        }
+    }
+    // A removal goes first so an insertion at the same index stays in place
+    statement_edits.sort_by(|a, b| {
+        target(b).cmp(target(a)).then_with(|| {
+            matches!(a, Rewrite::InsertStatement { .. })
+                .cmp(&matches!(b, Rewrite::InsertStatement { .. }))
+        })
+    });
+    for rewrite in statement_edits {
+        let (index, parent) = target(rewrite)
+            .split_last()
+            .ok_or(RewriteError::NoSuchNode)?;
+        let block = block_at(&mut expected.statements, parent)?;
+        match rewrite {
+            Rewrite::InsertStatement { stmt, .. } => block.insert(*index, stmt.clone()),
+            _ => {
+                block.remove(*index);
+            }
+        }
+    }
+
+    let mut result =
+        parse_source(&apply_edits(source, &edits)).map_err(|_| RewriteError::BrokenResult)?;
+    result.strip_groupings();
+    expected.strip_groupings();
+    if result == expected {
+        Ok(edits)
+    } else {
+        Err(RewriteError::BrokenResult)
+    }
+}
+
+fn target(rewrite: &Rewrite) -> &Vec<usize> {
+    match rewrite {
+        Rewrite::ReplaceExpression { statement, .. }
+        | Rewrite::InsertStatement { statement, .. }
+        | Rewrite::RemoveStatement { statement } => statement,
+    }
+}
+
+fn block_at<'a>(
+    mut statements: &'a mut Vec<Stmt>,
+    path: &[usize],
+) -> Result<&'a mut Vec<Stmt>, RewriteError> {
+    for index in path {
+        match statements.get_mut(*index) {
+            Some(Stmt::Block(inner)) => statements = inner,
+            _ => return Err(RewriteError::NoSuchNode),
+        }
+    }
+    Ok(statements)
+}
+
+fn statement_at<'a>(
+    program: &'a mut Program,
+    path: &[usize],
+) -> Result<&'a mut Stmt, RewriteError> {
+    let (index, parent) = path.split_last().ok_or(RewriteError::NoSuchNode)?;
+    block_at(&mut program.statements, parent)?
+        .get_mut(*index)
+        .ok_or(RewriteError::NoSuchNode)
+}
+
+fn expression_at<'a>(stmt: &'a mut Stmt, steps: &[Step]) -> Result<&'a mut Expr, RewriteError> {
+    let mut expr = match stmt {
+        Stmt::Let { value, .. } | Stmt::Destructure { value, .. } | Stmt::Expression(value) => {
+            value
+        }
+        Stmt::Block(_) => return Err(RewriteError::NoSuchNode),
+    };
+    for step in steps {
+        expr = match (expr, step) {
+            (Expr::Binary { left, .. }, Step::Left) => left,
+            (Expr::Binary { right, .. }, Step::Right) => right,
+            (Expr::Unary { operand, .. }, Step::Operand) => operand,
+            (Expr::Grouping(inner), Step::Inner) => inner,
+            (Expr::Tuple(elements), Step::Element(index)) => {
+                elements.get_mut(*index).ok_or(RewriteError::NoSuchNode)?
+            }
+            _ => return Err(RewriteError::NoSuchNode),
+        };
+    }
+    Ok(expr)
+}
+
+/// The source spans of a program's statements and expressions, found by
+/// walking its tokens in step with the AST they parsed into
+struct SpanMap {
+    statements: HashMap<Vec<usize>, Span>,
+    expressions: HashMap<(Vec<usize>, Vec<Step>), Span>,
+    /// Where each block's statements end: just inside its closing brace, or
+    /// the end of the source for the top level
+    block_ends: HashMap<Vec<usize>, usize>,
+    /// The names each `let` binds, in source order
+    bindings: HashMap<Vec<usize>, Vec<Span>>,
+}
+
+impl SpanMap {
+    fn new(program: &Program, tokens: &[(Token, Span)]) -> Self {
+        let mut walker = Walker {
+            tokens,
+            current: 0,
+            last_end: 0,
+            map: SpanMap {
+                statements: HashMap::new(),
+                expressions: HashMap::new(),
+                block_ends: HashMap::new(),
+                bindings: HashMap::new(),
+            },
+        };
+        let mut path = Vec::new();
+        walker.statements(&program.statements, &mut path);
+        let end = tokens.last().map_or(0, |(_, span)| span.end);
+        walker.map.block_ends.insert(Vec::new(), end);
+        walker.map
+    }
+
+    fn edit(
+        &self,
+        source: &str,
+        original: &Program,
+        rewrite: &Rewrite,
+    ) -> Result<TextEdit, RewriteError> {
+        match rewrite {
+            Rewrite::ReplaceExpression {
+                statement,
+                steps,
+                with,
+            } => {
+                let span = self
+                    .expressions
+                    .get(&(statement.clone(), steps.clone()))
+                    .ok_or(RewriteError::NoSuchNode)?;
+                let mut parent = original.clone();
+                let minimum = match steps.split_last() {
+                    Some((step, parent_steps)) => {
+                        let stmt = statement_at(&mut parent, statement)?;
+                        minimum_binding_power(expression_at(stmt, parent_steps)?, *step)
+                    }
+                    None => 0,
+                };
+                let text = if binding_power(with) < minimum {
+                    format!("({})", expression(with))
+                } else {
+                    expression(with)
+                };
+                Ok(TextEdit::new(*span, text))
+            }
+            Rewrite::InsertStatement { statement, stmt } => {
+                let (index, parent) = statement.split_last().ok_or(RewriteError::NoSuchNode)?;
+                let text = statement_text(stmt);
+                if let Some(span) = self.statements.get(statement) {
+                    let indent = line_indent(source, span.start);
+                    let text = indent_lines(&text, indent);
+                    return Ok(TextEdit::new(
+                        Span::new(span.start, span.start),
+                        format!("{}\n{}", text, indent),
+                    ));
+                }
+
+                let end = *self
+                    .block_ends
+                    .get(parent)
+                    .ok_or(RewriteError::NoSuchNode)?;
+                let previous = match index.checked_sub(1) {
+                    Some(previous) => {
+                        let mut path = parent.to_vec();
+                        path.push(previous);
+                        Some(self.statements.get(&path).ok_or(RewriteError::NoSuchNode)?)
+                    }
+                    None => None,
+                };
+                match previous {
+                    Some(span) => {
+                        let indent = line_indent(source, span.start);
+                        Ok(TextEdit::new(
+                            Span::new(span.end, span.end),
+                            format!("\n{}{}", indent, indent_lines(&text, indent)),
+                        ))
+                    }
+                    None if parent.is_empty() => {
+                        Ok(TextEdit::new(Span::new(end, end), format!("{}\n", text)))
+                    }
+                    None => Ok(TextEdit::new(Span::new(end, end), format!(" {} ", text))),
+                }
+            }
+            Rewrite::RemoveStatement { statement } => {
+                let span = self
+                    .statements
+                    .get(statement)
+                    .ok_or(RewriteError::NoSuchNode)?;
+                let start = line_start_if_blank(source, span.start);
+                let end = match line_end_if_blank(source, span.end) {
+                    Some(end) if start < span.start || start == 0 => end,
+                    _ => span.end,
+                };
+                Ok(TextEdit::new(Span::new(start, end), ""))
+            }
+        }
+    }
+}
+
+/// The spans of every statement of a program, keyed by statement path, doc
+/// comments included
+pub(crate) fn statement_spans(
+    program: &Program,
+    tokens: &[(Token, Span)],
+) -> HashMap<Vec<usize>, Span> {
+    SpanMap::new(program, tokens).statements
+}
+
+/// The spans of every expression of a program, keyed by statement path and
+/// the steps from the statement's value
+pub(crate) fn expression_spans(
+    program: &Program,
+    tokens: &[(Token, Span)],
+) -> HashMap<(Vec<usize>, Vec<Step>), Span> {
+    SpanMap::new(program, tokens).expressions
+}
+
+/// The spans of the names bound by each `let` of a program, keyed by
+/// statement path, in source order
+pub(crate) fn binding_spans(
+    program: &Program,
+    tokens: &[(Token, Span)],
+) -> HashMap<Vec<usize>, Vec<Span>> {
+    SpanMap::new(program, tokens).bindings
+}
+
+struct Walker<'a> {
+    tokens: &'a [(Token, Span)],
+    current: usize,
+    last_end: usize,
+    map: SpanMap,
+}
+
+impl Walker<'_> {
+    fn statements(&mut self, statements: &[Stmt], path: &mut Vec<usize>) {
+        for (index, stmt) in statements.iter().enumerate() {
+            path.push(index);
+            self.statement(stmt, path);
+            path.pop();
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, path: &mut Vec<usize>) {
+        let start = self.start();
+        match stmt {
+            Stmt::Let { value, .. } => {
+                self.skip_doc_comments();
+                self.advance(); // let
+                self.binding(path);
+                self.advance(); // =
+                self.expression(value, path, &mut Vec::new());
+                self.advance(); // ;
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                self.skip_doc_comments();
+                self.advance(); // let
+                self.pattern(pattern, path);
+                self.advance(); // =
+                self.expression(value, path, &mut Vec::new());
+                self.advance(); // ;
+            }
+            Stmt::Expression(expr) => {
+                self.expression(expr, path, &mut Vec::new());
+                self.advance(); // ;
+            }
+            Stmt::Block(statements) => {
+                self.advance(); // {
+                self.statements(statements, path);
+                let end = self.start();
+                self.map.block_ends.insert(path.clone(), end);
+                self.advance(); // }
+            }
+        }
+        let span = Span::new(start, self.last_end);
+        self.map.statements.insert(path.clone(), span);
+    }
+
+    fn expression(&mut self, expr: &Expr, statement: &[usize], steps: &mut Vec<Step>) {
+        let start = self.start();
+        let mut child = |walker: &mut Self, expr: &Expr, step: Step| {
+            steps.push(step);
+            walker.expression(expr, statement, steps);
+            steps.pop();
+        };
+        match expr {
+            Expr::Number(_) | Expr::Float(_) | Expr::BigNumber(_) | Expr::Identifier(_) => {
+                self.advance()
+            }
+            Expr::Suffixed { .. } => self.advance_by(2),
+            Expr::Binary { left, right, .. } => {
+                child(self, left, Step::Left);
+                self.advance(); // operator
+                child(self, right, Step::Right);
+            }
+            Expr::Unary { operand, .. } => {
+                self.advance(); // operator
+                child(self, operand, Step::Operand);
+            }
+            Expr::Grouping(inner) => {
+                self.advance(); // (
+                child(self, inner, Step::Inner);
+                self.advance(); // )
+            }
+            Expr::Tuple(elements) => {
+                self.advance(); // (
+                for (index, element) in elements.iter().enumerate() {
+                    child(self, element, Step::Element(index));
+                    self.skip(&Token::Comma);
+                }
+                self.advance(); // )
+            }
+        }
+        let span = Span::new(start, self.last_end);
+        self.map
+            .expressions
+            .insert((statement.to_vec(), steps.clone()), span);
+    }
+
+    fn pattern(&mut self, pattern: &Pattern, statement: &[usize]) {
+        match pattern {
+            Pattern::Wildcard => self.advance(),
+            Pattern::Identifier(_) => self.binding(statement),
+            Pattern::Literal(_) => {
+                self.skip(&Token::Minus);
+                self.advance();
+            }
+            Pattern::Tuple(elements) => {
+                self.advance(); // (
+                for element in elements {
+                    self.pattern(element, statement);
+                    self.skip(&Token::Comma);
+                }
+                self.advance(); // )
+            }
+        }
+    }
+
+    fn binding(&mut self, statement: &[usize]) {
+        if let Some((_, span)) = self.tokens.get(self.current) {
+            self.map
+                .bindings
+                .entry(statement.to_vec())
+                .or_default()
+                .push(*span);
+        }
+        self.advance();
+    }
+
+    fn skip_doc_comments(&mut self) {
+        while matches!(
+            self.tokens.get(self.current),
+            Some((Token::DocComment(_), _))
+        ) {
+            self.advance();
+        }
+    }
+
+    fn skip(&mut self, token: &Token) {
+        if self.tokens.get(self.current).map(|(token, _)| token) == Some(token) {
+            self.advance();
+        }
+    }
+
+    fn start(&self) -> usize {
+        self.tokens
+            .get(self.current)
+            .map_or(self.last_end, |(_, span)| span.start)
+    }
+
+    fn advance(&mut self) {
+        if let Some((_, span)) = self.tokens.get(self.current) {
+            self.last_end = span.end;
+            self.current += 1;
+        }
+    }
+
+    fn advance_by(&mut self, count: usize) {
+        for _ in 0..count {
+            self.advance();
+        }
+    }
+}
+
+// The binding power an expression needs to stand unparenthesized as the child
+// of `parent` reached by `step`
+fn minimum_binding_power(parent: &Expr, step: Step) -> u8 {
+    match (parent, step) {
+        (Expr::Binary { operator, .. }, Step::Left | Step::Right) => {
+            let precedence = operator.precedence();
+            match (operator.associativity(), step) {
+                (Associativity::Left, Step::Right) | (Associativity::Right, Step::Left) => {
+                    precedence + 1
+                }
+                _ => precedence,
+            }
+        }
+        (Expr::Unary { .. }, Step::Operand) => binding_power(parent),
+        _ => 0,
+    }
+}
+
+// A statement printed by the formatter, without its trailing newline
+fn statement_text(stmt: &Stmt) -> String {
+    let program = Program {
+        statements: vec![stmt.clone()],
+    };
+    format_program(&program, &FormatOptions::default())
+        .trim_end()
+        .to_string()
+}
+
+// Indents every line but the first, which goes where the edit is made
+fn indent_lines(text: &str, indent: &str) -> String {
+    text.split('\n')
+        .collect::<Vec<_>>()
+        .join(&format!("\n{}", indent))
+}
+
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let before = &source[line_start..offset];
+    if before.trim().is_empty() { before } else { "" }
+}
+
+// Moves `offset` back to the start of its line if only whitespace precedes it
+fn line_start_if_blank(source: &str, offset: usize) -> usize {
+    let line_start = source[..offset].rfind('\n').map_or(0, |index| index + 1);
+    if source[line_start..offset].trim().is_empty() {
+        line_start
+    } else {
+        offset
+    }
+}
+
+// The end of `offset`'s line, past the newline, if only whitespace follows it
+fn line_end_if_blank(source: &str, offset: usize) -> Option<usize> {
+    let rest = &source[offset..];
+    let line_end = rest
+        .find('\n')
+        .map_or(source.len(), |index| offset + index + 1);
+    source[offset..line_end]
+        .trim()
+        .is_empty()
+        .then_some(line_end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::BinaryOp;
+
+    fn apply(source: &str, rewrites: &[Rewrite]) -> String {
+        apply_edits(source, &rewrite(source, rewrites).unwrap())
+    }
+
+    fn replace(statement: &[usize], steps: &[Step], with: Expr) -> Rewrite {
+        Rewrite::ReplaceExpression {
+            statement: statement.to_vec(),
+            steps: steps.to_vec(),
+            with,
+        }
+    }
+
+    fn var(name: &str) -> Expr {
+        Expr::identifier(name.to_string())
+    }
+
+    #[test]
+    fn test_replacement_keeps_surrounding_text() {
+        let source = "/// Scale\nlet x  =  1 +   2; // two\n{\n    x *  3;\n}\n";
+        let edits = rewrite(source, &[replace(&[1, 0], &[Step::Right], var("y"))]).unwrap();
+
+        assert_eq!(edits, vec![TextEdit::new(Span::new(47, 48), "y")]);
+        assert_eq!(
+            apply_edits(source, &edits),
+            "/// Scale\nlet x  =  1 +   2; // two\n{\n    x *  y;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_replacement_is_parenthesized_to_keep_its_place() {
+        let sum = Expr::binary(var("a"), BinaryOp::Add, var("b"));
+
+        assert_eq!(
+            apply("x * y;", &[replace(&[0], &[Step::Right], sum.clone())]),
+            "x * (a + b);"
+        );
+        assert_eq!(
+            apply("x - y;", &[replace(&[0], &[Step::Left], sum.clone())]),
+            "a + b - y;"
+        );
+        assert_eq!(
+            apply("x - y;", &[replace(&[0], &[Step::Right], sum.clone())]),
+            "x - (a + b);"
+        );
+        assert_eq!(
+            apply("-x;", &[replace(&[0], &[Step::Operand], sum)]),
+            "-(a + b);"
+        );
+    }
+
+    #[test]
+    fn test_insert_and_remove_statements() {
+        let source = "let a = 1; // kept\n{\n    /// Doc\n    let b = a;\n    b;\n}\n";
+        let stmt = Stmt::let_statement("c".to_string(), Expr::number(2));
+
+        assert_eq!(
+            apply(
+                source,
+                &[Rewrite::InsertStatement {
+                    statement: vec![1, 0],
+                    stmt: stmt.clone(),
+                }]
+            ),
+            "let a = 1; // kept\n{\n    let c = 2;\n    /// Doc\n    let b = a;\n    b;\n}\n"
+        );
+        assert_eq!(
+            apply(
+                source,
+                &[Rewrite::InsertStatement {
+                    statement: vec![1, 2],
+                    stmt: Stmt::block(vec![stmt.clone()]),
+                }]
+            ),
+            "let a = 1; // kept\n{\n    /// Doc\n    let b = a;\n    b;\n    {\n      let c = 2;\n    }\n}\n"
+        );
+        assert_eq!(
+            apply(
+                source,
+                &[
+                    Rewrite::RemoveStatement {
+                        statement: vec![1, 0],
+                    },
+                    replace(&[1, 1], &[], Expr::number(0)),
+                ]
+            ),
+            "let a = 1; // kept\n{\n    0;\n}\n"
+        );
+        assert_eq!(
+            apply(
+                "{}",
+                &[Rewrite::InsertStatement {
+                    statement: vec![0, 0],
+                    stmt,
+                }]
+            ),
+            "{ let c = 2; }"
+        );
+    }
+
+    #[test]
+    fn test_invalid_rewrites() {
+        assert_eq!(
+            rewrite("x;", &[replace(&[0], &[Step::Left], var("y"))]),
+            Err(RewriteError::NoSuchNode)
+        );
+        assert_eq!(rewrite("let = 1;", &[]), Err(RewriteError::InvalidSource));
+        assert_eq!(
+            rewrite(
+                "x + y;",
+                &[
+                    replace(&[0], &[], var("z")),
+                    replace(&[0], &[Step::Left], var("y")),
+                ]
+            ),
+            Err(RewriteError::Overlapping)
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! The JSON schema of the syntax tree as the `serde` feature serializes it.
+//!
+//! Enums use serde's default representation: a unit variant is its name as a
+//! string, and any other variant an object with the variant's name as its only
+//! key, such as `{"Number": 1}` or `{"Binary": {"left": ..., ...}}`.
+//!
+//! The schema only grows. A new version may add variants and optional fields,
+//! but never removes, renames or retypes anything an earlier version has, so
+//! a tool written against one version can read trees from any later one by
+//! ignoring what it does not know. Every published version is kept in the
+//! repository's `schema/` directory, and the tests check the current schema
+//! against each of them.
+
+use crate::json::Json;
+
+/// Version of the schema returned by [`ast_schema`], raised whenever it grows
+pub const AST_SCHEMA_VERSION: usize = 2;
+
+/// Returns the JSON schema of a serialized [`crate::Program`], pretty-printed
+pub fn ast_schema() -> String {
+    let definitions = vec![
+        (
+            "Program",
+            object(vec![("statements", array_of(reference("Stmt")))], &[]),
+        ),
+        (
+            "Stmt",
+            one_of(vec![
+                variant(
+                    "Let",
+                    object(
+                        vec![
+                            ("name", string()),
+                            ("value", reference("Expr")),
+                            ("doc", nullable_string()),
+                        ],
+                        &["doc"],
+                    ),
+                ),
+                variant(
+                    "Destructure",
+                    object(
+                        vec![
+                            ("pattern", reference("Pattern")),
+                            ("value", reference("Expr")),
+                            ("doc", nullable_string()),
+                        ],
+                        &["doc"],
+                    ),
+                ),
+                variant("Expression", reference("Expr")),
+                variant("Block", array_of(reference("Stmt"))),
+            ]),
+        ),
+        (
+            "Expr",
+            one_of(vec![
+                variant("Number", integer()),
+                variant("Identifier", string()),
+                variant(
+                    "Binary",
+                    object(
+                        vec![
+                            ("left", reference("Expr")),
+                            ("operator", reference("BinaryOp")),
+                            ("right", reference("Expr")),
+                        ],
+                        &[],
+                    ),
+                ),
+                variant(
+                    "Unary",
+                    object(
+                        vec![
+                            ("operator", reference("UnaryOp")),
+                            ("operand", reference("Expr")),
+                        ],
+                        &[],
+                    ),
+                ),
+                variant("Grouping", reference("Expr")),
+                variant("Tuple", array_of(reference("Expr"))),
+                variant(
+                    "Suffixed",
+                    object(vec![("value", integer()), ("suffix", string())], &[]),
+                ),
+                variant("Float", number()),
+            ]),
+        ),
+        (
+            "Pattern",
+            one_of(vec![
+                unit_variant("Wildcard"),
+                variant("Literal", integer()),
+                variant("Identifier", string()),
+                variant("Tuple", array_of(reference("Pattern"))),
+            ]),
+        ),
+        (
+            "BinaryOp",
+            names(&["Add", "Subtract", "Multiply", "Divide"]),
+        ),
+        ("UnaryOp", names(&["Negate", "Plus"])),
+    ];
+
+    Json::object(vec![
+        (
+            "$schema",
+            "https://json-schema.org/draft/2020-12/schema".into(),
+        ),
+        (
+            "title",
+            Json::String(format!("Oxide syntax tree, version {}", AST_SCHEMA_VERSION)),
+        ),
+        ("version", AST_SCHEMA_VERSION.into()),
+        ("$ref", "#/$defs/Program".into()),
+        ("$defs", Json::object(definitions)),
+    ])
+    .to_pretty_string()
+}
+
+fn schema_type(name: &str) -> Json {
+    Json::object(vec![("type", name.into())])
+}
+
+fn string() -> Json {
+    schema_type("string")
+}
+
+fn integer() -> Json {
+    schema_type("integer")
+}
+
+fn number() -> Json {
+    schema_type("number")
+}
+
+fn nullable_string() -> Json {
+    Json::object(vec![(
+        "type",
+        Json::Array(vec!["string".into(), "null".into()]),
+    )])
+}
+
+fn reference(definition: &str) -> Json {
+    Json::object(vec![(
+        "$ref",
+        Json::String(format!("#/$defs/{}", definition)),
+    )])
+}
+
+fn array_of(items: Json) -> Json {
+    Json::object(vec![("type", "array".into()), ("items", items)])
+}
+
+fn one_of(variants: Vec<Json>) -> Json {
+    Json::object(vec![("oneOf", Json::Array(variants))])
+}
+
+fn names(names: &[&str]) -> Json {
+    Json::object(vec![(
+        "enum",
+        Json::Array(names.iter().map(|&name| name.into()).collect()),
+    )])
+}
+
+// An object with the given properties, all required except `optional`. Other
+// properties are allowed, so later versions can add optional fields.
+fn object(properties: Vec<(&str, Json)>, optional: &[&str]) -> Json {
+    let required = properties
+        .iter()
+        .map(|(name, _)| *name)
+        .filter(|name| !optional.contains(name))
+        .map(Json::from)
+        .collect();
+    Json::object(vec![
+        ("type", "object".into()),
+        ("properties", Json::object(properties)),
+        ("required", Json::Array(required)),
+    ])
+}
+
+// An enum variant with data: an object whose only key is the variant's name
+fn variant(name: &str, data: Json) -> Json {
+    Json::object(vec![
+        ("type", "object".into()),
+        ("properties", Json::object(vec![(name, data)])),
+        ("required", Json::Array(vec![name.into()])),
+        ("additionalProperties", Json::Bool(false)),
+    ])
+}
+
+fn unit_variant(name: &str) -> Json {
+    Json::object(vec![("const", name.into())])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every published version, oldest first
+    const PUBLISHED: &[&str] = &[
+        include_str!("../schema/ast-v1.json"),
+        include_str!("../schema/ast-v2.json"),
+    ];
+
+    #[test]
+    fn test_current_version_is_published() {
+        assert_eq!(PUBLISHED.len(), AST_SCHEMA_VERSION);
+        assert_eq!(
+            PUBLISHED[AST_SCHEMA_VERSION - 1].trim_end(),
+            ast_schema(),
+            "the schema changed: publish it as a new version in schema/"
+        );
+    }
+
+    #[test]
+    fn test_schema_only_grows() {
+        use serde_json::Value;
+
+        // Whether everything `old` says is still said by `new`. A property
+        // list may grow, but which properties are required may not change.
+        fn contains(old: &Value, new: &Value, key: &str) -> bool {
+            match (old, new) {
+                (Value::Object(old), Value::Object(new)) => old.iter().all(|(key, value)| {
+                    key == "title"
+                        || key == "version"
+                        || new.get(key).is_some_and(|new| contains(value, new, key))
+                }),
+                (Value::Array(old), Value::Array(new)) if key == "required" => old == new,
+                (Value::Array(old), Value::Array(new)) => {
+                    old.len() <= new.len()
+                        && old.iter().zip(new).all(|(old, new)| contains(old, new, ""))
+                }
+                _ => old == new,
+            }
+        }
+
+        let current: Value = serde_json::from_str(&ast_schema()).unwrap();
+        for (version, published) in PUBLISHED.iter().enumerate() {
+            let published: Value = serde_json::from_str(published).unwrap();
+            assert!(
+                contains(&published, &current, ""),
+                "the schema no longer contains version {}",
+                version + 1
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialized_trees_use_the_documented_shape() {
+        use crate::parser::{Program, parse_source};
+
+        let program = parse_source("/// Pair\nlet (a, _) = (1, -x); { a * (2); }").unwrap();
+        let json = serde_json::to_string(&program).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"statements":[{"Destructure":{"pattern":{"Tuple":[{"Identifier":"a"},"Wildcard"]},"value":{"Tuple":[{"Number":1},{"Unary":{"operator":"Negate","operand":{"Identifier":"x"}}}]},"doc":"Pair"}},{"Block":[{"Expression":{"Binary":{"left":{"Identifier":"a"},"operator":"Multiply","right":{"Grouping":{"Number":2}}}}}]}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Program>(&json).unwrap(), program);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::error::Error;
+use crate::lexer::{KEYWORDS, Lexer};
+use crate::parser::{Parser, Program};
+use std::collections::BTreeMap;
+
+/// Literal suffixes an embedder accepts after integer literals, such as `px`
+/// in `10px`, each with the name of the host type its literals produce.
+/// Without a registration, a number directly followed by a name is a lex
+/// error.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LiteralSuffixes {
+    types: BTreeMap<String, String>,
+}
+
+impl LiteralSuffixes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts `suffix` after integer literals, producing values of the host
+    /// type `type_name`. A suffix must be spelled like an identifier that is
+    /// not a keyword; anything else is ignored.
+    pub fn register(mut self, suffix: &str, type_name: &str) -> Self {
+        if is_valid_suffix(suffix) {
+            self.types.insert(suffix.to_string(), type_name.to_string());
+        }
+        self
+    }
+
+    pub fn contains(&self, suffix: &str) -> bool {
+        self.types.contains_key(suffix)
+    }
+
+    /// Returns the host type registered for a suffix
+    pub fn type_name(&self, suffix: &str) -> Option<&str> {
+        self.types.get(suffix).map(String::as_str)
+    }
+
+    /// Returns every registered suffix in sorted order
+    pub fn suffixes(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+}
+
+// Whether `suffix` lexes as a single identifier, so it can follow a number
+fn is_valid_suffix(suffix: &str) -> bool {
+    let mut chars = suffix.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+        && !KEYWORDS.contains(&suffix)
+}
+
+/// Lexes strictly and parses `source`, accepting the registered suffixes
+pub fn parse_with_suffixes(source: &str, suffixes: &LiteralSuffixes) -> Result<Program, Error> {
+    let parse = || -> Result<Program, Error> {
+        let tokens = Lexer::new(source)
+            .with_suffixes(suffixes)
+            .tokenize_strict()?;
+        Ok(Parser::from_spanned(tokens)
+            .with_suffixes(suffixes)
+            .parse()?)
+    };
+    parse().map_err(|error| error.with_source(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{LexError, Span};
+    use crate::parser::{BinaryOp, Expr, Stmt};
+
+    fn units() -> LiteralSuffixes {
+        LiteralSuffixes::new()
+            .register("px", "Length")
+            .register("s", "Duration")
+    }
+
+    #[test]
+    fn test_registered_suffixes_parse_as_suffixed_literals() {
+        let program = parse_with_suffixes("let w = 10px * 2;", &units()).unwrap();
+
+        assert_eq!(
+            program.statements[0],
+            Stmt::let_statement(
+                "w".to_string(),
+                Expr::binary(
+                    Expr::suffixed(10, "px".to_string()),
+                    BinaryOp::Multiply,
+                    Expr::number(2)
+                )
+            )
+        );
+        assert_eq!(program.to_string(), "let w = (10px * 2);\n");
+    }
+
+    #[test]
+    fn test_unknown_suffixes_are_rejected() {
+        match parse_with_suffixes("3s + 4em;", &units()) {
+            Err(Error::Lex { error, .. }) => assert_eq!(
+                error,
+                LexError::InvalidNumberSuffix {
+                    suffix: "em".to_string(),
+                    span: Span::new(5, 8)
+                }
+            ),
+            other => panic!("expected a lex error, got {:?}", other),
+        }
+        // A suffix must touch its number
+        assert!(parse_with_suffixes("3 s;", &units()).is_err());
+    }
+
+    #[test]
+    fn test_registration() {
+        let suffixes = units().register("let", "Nothing").register("2x", "Nothing");
+
+        assert_eq!(suffixes.suffixes().collect::<Vec<_>>(), vec!["px", "s"]);
+        assert_eq!(suffixes.type_name("s"), Some("Duration"));
+        assert!(!suffixes.contains("em"));
+    }
+}
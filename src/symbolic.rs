@@ -0,0 +1,395 @@
+//! Lightweight algebra over expressions: substituting for a variable,
+//! expanding products over sums, collecting like terms and differentiating
+//! polynomials.
+//!
+//! Integers are treated as unbounded, as in [`crate::analysis::equivalent`]:
+//! a rearranged expression means the same, but may overflow where the
+//! original did not, or the other way round. Divisions truncate, so they are
+//! never distributed; like floats, quantities and tuples they are kept as
+//! opaque factors, with their own operands worked on.
+
+use crate::analysis::free_variables;
+use crate::format;
+use crate::parser::{BinaryOp, Expr, UnaryOp};
+use std::collections::BTreeMap;
+
+/// Replaces every occurrence of the variable `name` in `expr` with
+/// `replacement`
+pub fn subst(expr: &Expr, name: &str, replacement: &Expr) -> Expr {
+    match expr {
+        Expr::Identifier(identifier) if identifier == name => replacement.clone(),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => Expr::binary(
+            subst(left, name, replacement),
+            operator.clone(),
+            subst(right, name, replacement),
+        ),
+        Expr::Unary { operator, operand } => {
+            Expr::unary(operator.clone(), subst(operand, name, replacement))
+        }
+        Expr::Grouping(inner) => Expr::grouping(subst(inner, name, replacement)),
+        Expr::Tuple(elements) => Expr::tuple(
+            elements
+                .iter()
+                .map(|element| subst(element, name, replacement))
+                .collect(),
+        ),
+        leaf => leaf.clone(),
+    }
+}
+
+/// Distributes products over sums and differences, so `(a + b) * (c - 2)`
+/// becomes `a * c - a * 2 + b * c - b * 2`. Signs move onto the terms they
+/// apply to and groupings are dropped; nothing is folded or reordered. The
+/// result can be exponentially larger than `expr`.
+pub fn expand(expr: &Expr) -> Expr {
+    let mut terms = signed_terms(expr)
+        .into_iter()
+        .map(|(negative, factors)| (negative, product_of(factors)));
+    let (negative, first) = terms.next().expect("every expression has a term");
+    let first = match negative {
+        true => Expr::unary(UnaryOp::Negate, first),
+        false => first,
+    };
+    terms.fold(first, |sum, (negative, term)| {
+        let operator = match negative {
+            true => BinaryOp::Subtract,
+            false => BinaryOp::Add,
+        };
+        Expr::binary(sum, operator, term)
+    })
+}
+
+// The terms of `expr` with products distributed, each as its factors and
+// whether it is subtracted
+fn signed_terms(expr: &Expr) -> Vec<(bool, Vec<Expr>)> {
+    match expr {
+        Expr::Binary {
+            left,
+            operator: operator @ (BinaryOp::Add | BinaryOp::Subtract),
+            right,
+        } => {
+            let subtracted = *operator == BinaryOp::Subtract;
+            let mut terms = signed_terms(left);
+            terms.extend(
+                signed_terms(right)
+                    .into_iter()
+                    .map(|(negative, factors)| (negative != subtracted, factors)),
+            );
+            terms
+        }
+        Expr::Binary {
+            left,
+            operator: BinaryOp::Multiply,
+            right,
+        } => {
+            let right = signed_terms(right);
+            signed_terms(left)
+                .into_iter()
+                .flat_map(|(a, left)| {
+                    right.iter().map(move |(b, right)| {
+                        (a != *b, left.iter().chain(right).cloned().collect())
+                    })
+                })
+                .collect()
+        }
+        Expr::Binary {
+            left,
+            operator: BinaryOp::Divide,
+            right,
+        } => vec![(
+            false,
+            vec![Expr::binary(expand(left), BinaryOp::Divide, expand(right))],
+        )],
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => signed_terms(operand)
+            .into_iter()
+            .map(|(negative, factors)| (!negative, factors))
+            .collect(),
+        Expr::Unary {
+            operator: UnaryOp::Plus,
+            operand: inner,
+        }
+        | Expr::Grouping(inner) => signed_terms(inner),
+        Expr::Tuple(elements) => vec![(
+            false,
+            vec![Expr::tuple(elements.iter().map(expand).collect())],
+        )],
+        leaf => vec![(false, vec![leaf.clone()])],
+    }
+}
+
+fn product_of(factors: Vec<Expr>) -> Expr {
+    let mut factors = factors.into_iter();
+    let first = factors.next().expect("every term has a factor");
+    factors.fold(first, |product, factor| {
+        Expr::binary(product, BinaryOp::Multiply, factor)
+    })
+}
+
+/// Expands `expr` and adds up like terms, folding constants into their
+/// coefficients, so `x * 2 + 3 * (x + 1) - 3` becomes `5 * x`. Terms come out
+/// highest degree first, then by their factors' names, with the constant
+/// last. Terms that cancel are dropped even if evaluating them would fail, as
+/// `x / 0 - x / 0` would. If a coefficient overflows, `expr` is returned
+/// unchanged.
+pub fn collect_terms(expr: &Expr) -> Expr {
+    let mut atoms = BTreeMap::new();
+    match polynomial(expr, &mut atoms) {
+        Some(polynomial) => render(polynomial, &atoms),
+        None => expr.clone(),
+    }
+}
+
+/// Differentiates a polynomial `expr` with respect to `name`, with the terms
+/// of the result collected as by [`collect_terms`]. Returns None if `name`
+/// appears inside a division or tuple, which have no derivative here, or if
+/// a coefficient overflows.
+pub fn differentiate(expr: &Expr, name: &str) -> Option<Expr> {
+    let mut atoms = BTreeMap::new();
+    let polynomial = polynomial(expr, &mut atoms)?;
+    let opaque = |atom: &Expr| !matches!(atom, Expr::Identifier(_));
+    if atoms
+        .values()
+        .any(|atom| opaque(atom) && free_variables(atom).contains(name))
+    {
+        return None;
+    }
+
+    let mut derivative = Polynomial::new();
+    for (mut factors, coefficient) in polynomial {
+        let power = factors.iter().filter(|factor| *factor == name).count();
+        if let Some(index) = factors.iter().position(|factor| factor == name) {
+            factors.remove(index);
+            add(
+                &mut derivative,
+                factors,
+                coefficient.checked_mul(power as i64)?,
+            )?;
+        }
+    }
+    Some(render(derivative, &atoms))
+}
+
+/// A sum of products, each its sorted factors with a nonzero coefficient.
+/// Factors are keys into a table of atoms: a variable's key is its name and
+/// any other atom's its source text.
+type Polynomial = BTreeMap<Vec<String>, i64>;
+
+fn polynomial(expr: &Expr, atoms: &mut BTreeMap<String, Expr>) -> Option<Polynomial> {
+    match expr {
+        Expr::Number(value) => {
+            let mut constant = Polynomial::new();
+            add(&mut constant, Vec::new(), *value)?;
+            Some(constant)
+        }
+        Expr::Identifier(name) => Some(atom(name.clone(), expr.clone(), atoms)),
+        Expr::Grouping(inner)
+        | Expr::Unary {
+            operator: UnaryOp::Plus,
+            operand: inner,
+        } => polynomial(inner, atoms),
+        Expr::Unary {
+            operator: UnaryOp::Negate,
+            operand,
+        } => sum(Polynomial::new(), polynomial(operand, atoms)?, -1),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => {
+            let (left, right) = (polynomial(left, atoms)?, polynomial(right, atoms)?);
+            match operator {
+                BinaryOp::Add => sum(left, right, 1),
+                BinaryOp::Subtract => sum(left, right, -1),
+                BinaryOp::Multiply => product(&left, &right),
+                BinaryOp::Divide => match (constant(&left), constant(&right)) {
+                    (Some(a), Some(b)) if b != 0 => {
+                        polynomial(&Expr::number(a.checked_div(b)?), atoms)
+                    }
+                    _ => {
+                        let (left, right) = (render(left, atoms), render(right, atoms));
+                        let quotient = Expr::binary(left, BinaryOp::Divide, right);
+                        Some(atom(format::expression(&quotient), quotient, atoms))
+                    }
+                },
+            }
+        }
+        Expr::Tuple(elements) => {
+            let tuple = Expr::tuple(elements.iter().map(collect_terms).collect());
+            Some(atom(format::expression(&tuple), tuple, atoms))
+        }
+        Expr::Float(_) | Expr::BigNumber(_) | Expr::Suffixed { .. } => {
+            Some(atom(format::expression(expr), expr.clone(), atoms))
+        }
+    }
+}
+
+fn atom(key: String, expr: Expr, atoms: &mut BTreeMap<String, Expr>) -> Polynomial {
+    atoms.insert(key.clone(), expr);
+    Polynomial::from([(vec![key], 1)])
+}
+
+fn add(polynomial: &mut Polynomial, factors: Vec<String>, coefficient: i64) -> Option<()> {
+    let total = polynomial.get(&factors).copied().unwrap_or(0);
+    match total.checked_add(coefficient)? {
+        0 => polynomial.remove(&factors),
+        total => polynomial.insert(factors, total),
+    };
+    Some(())
+}
+
+fn sum(mut left: Polynomial, right: Polynomial, sign: i64) -> Option<Polynomial> {
+    for (factors, coefficient) in right {
+        add(&mut left, factors, coefficient.checked_mul(sign)?)?;
+    }
+    Some(left)
+}
+
+fn product(left: &Polynomial, right: &Polynomial) -> Option<Polynomial> {
+    let mut result = Polynomial::new();
+    for (a, x) in left {
+        for (b, y) in right {
+            let mut factors: Vec<String> = a.iter().chain(b).cloned().collect();
+            factors.sort();
+            add(&mut result, factors, x.checked_mul(*y)?)?;
+        }
+    }
+    Some(result)
+}
+
+// The value of a polynomial without factors
+fn constant(polynomial: &Polynomial) -> Option<i64> {
+    match polynomial.iter().next() {
+        None => Some(0),
+        Some((factors, value)) if factors.is_empty() && polynomial.len() == 1 => Some(*value),
+        _ => None,
+    }
+}
+
+fn render(polynomial: Polynomial, atoms: &BTreeMap<String, Expr>) -> Expr {
+    let mut terms: Vec<_> = polynomial.into_iter().collect();
+    terms.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+    let mut sum: Option<Expr> = None;
+    for (factors, coefficient) in terms {
+        // The most negative coefficient has no positive counterpart
+        let negative = coefficient < 0 && coefficient != i64::MIN;
+        let coefficient = if negative { -coefficient } else { coefficient };
+        let mut factors = factors.iter().map(|key| atoms[key].clone());
+        let first = match coefficient {
+            1 => factors.next(),
+            _ => None,
+        }
+        .unwrap_or(Expr::number(coefficient));
+        let term = factors.fold(first, |product, factor| {
+            Expr::binary(product, BinaryOp::Multiply, factor)
+        });
+        sum = Some(match (sum, negative) {
+            (None, false) => term,
+            (None, true) => Expr::unary(UnaryOp::Negate, term),
+            (Some(sum), false) => Expr::binary(sum, BinaryOp::Add, term),
+            (Some(sum), true) => Expr::binary(sum, BinaryOp::Subtract, term),
+        });
+    }
+    sum.unwrap_or(Expr::number(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{Equivalence, equivalent};
+    use crate::testing::expression;
+
+    #[test]
+    fn test_subst() {
+        let expr = expression("x * (x + y) - (x, z)");
+        let result = subst(&expr, "x", &expression("a + 1"));
+
+        assert_eq!(
+            format::expression(&result),
+            "(a + 1) * (a + 1 + y) - (a + 1, z)"
+        );
+        assert_eq!(subst(&expr, "w", &expression("0")), expr);
+    }
+
+    #[test]
+    fn test_expand() {
+        for (source, expanded) in [
+            ("(a + b) * (c - 2)", "a * c - a * 2 + b * c - b * 2"),
+            ("-(x - y) * z", "-(x * z) + y * z"),
+            ("2 * (a + (b))", "2 * a + 2 * b"),
+            ("(a + b) / (c * (d + 1))", "(a + b) / (c * d + c * 1)"),
+            ("((a + b) * c, 1)", "(a * c + b * c, 1)"),
+        ] {
+            let result = expand(&expression(source));
+            assert_eq!(format::expression(&result), expanded, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_collect_terms() {
+        for (source, collected) in [
+            ("x * 2 + 3 * (x + 1) - 3", "5 * x"),
+            ("(x + 1) * (x - 1)", "x * x - 1"),
+            ("(a - b) * (a + b) + b * b", "a * a"),
+            ("y * x - x * y", "0"),
+            ("-x + 2 - 5", "-x - 3"),
+            ("(x + x) / 2 + x / 2 + 8 / 4", "2 * x / 2 + x / 2 + 2"),
+            ("1.5 * x + x * 1.5 + 2px", "2 * 1.5 * x + 2px"),
+        ] {
+            let result = collect_terms(&expression(source));
+            assert_eq!(format::expression(&result), collected, "{}", source);
+        }
+
+        let overflowing = expression("9223372036854775807 * x + x");
+        assert_eq!(collect_terms(&overflowing), overflowing);
+    }
+
+    #[test]
+    fn test_differentiate() {
+        let derivative = |source, name| {
+            differentiate(&expression(source), name).map(|expr| format::expression(&expr))
+        };
+
+        assert_eq!(
+            derivative("3 * x * x * x - 2 * x * y + 7", "x"),
+            Some("9 * x * x - 2 * y".to_string())
+        );
+        assert_eq!(
+            derivative("(x + 1) * (x + 1)", "x"),
+            Some("2 * x + 2".to_string())
+        );
+        assert_eq!(derivative("y / 2 + x", "x"), Some("1".to_string()));
+        assert_eq!(derivative("y * y", "x"), Some("0".to_string()));
+        assert_eq!(derivative("x / 2", "x"), None);
+        assert_eq!(derivative("(x, 1) * 2", "x"), None);
+    }
+
+    #[test]
+    fn test_rearrangements_keep_their_meaning() {
+        for source in [
+            "(a + b) * (a - b) * -(c + 1)",
+            "2 * (x - 3) * (x + y) - x / (y + 1) * 4",
+            "(x - y) * (x - y) - (x * x + y * y)",
+        ] {
+            let expr = expression(source);
+            for rearranged in [expand(&expr), collect_terms(&expr)] {
+                assert!(
+                    matches!(
+                        equivalent(&expr, &rearranged),
+                        Equivalence::Proven | Equivalence::Probable { .. }
+                    ),
+                    "{} and {}",
+                    source,
+                    format::expression(&rearranged)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::lexer::{Lexer, Span, Token};
+
+/// What a symbol declares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A `let` binding
+    Binding,
+    /// A block containing bindings
+    Block,
+}
+
+/// A declaration in a document, for outlines and breadcrumbs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole declaration: a `let` statement or a block, braces included
+    pub span: Span,
+    /// The part to highlight when the symbol is selected, such as a binding's name
+    pub selection_span: Span,
+    /// Symbols declared directly inside this one
+    pub children: Vec<Symbol>,
+}
+
+/// Returns the bindings of a document as a tree in which each block that
+/// declares something contains its bindings. Like completion, this works from
+/// tokens alone, so an outline is available while the code does not parse.
+pub fn document_symbols(source: &str) -> Vec<Symbol> {
+    // Open blocks, innermost last, with the span of their `{`
+    let mut frames: Vec<(Span, Vec<Symbol>)> = vec![(Span::default(), Vec::new())];
+    // The bindings of a `let` whose statement has not ended yet, with the
+    // block depth it is in and where it starts
+    let mut pending: Option<(Vec<Symbol>, usize, usize)> = None;
+    // Whether the scan is between a `let` and its `=`, where names are declared
+    let mut naming = false;
+    let mut last_end = 0;
+
+    for (token, span) in Lexer::new(source).tokenize_spanned() {
+        if token == Token::EOF {
+            break;
+        }
+
+        match &token {
+            Token::Let => {
+                naming = true;
+                pending = Some((Vec::new(), frames.len(), span.start));
+            }
+            // Each name in a pattern is a binding of its own
+            Token::Ident(name) if naming && name != "_" => {
+                if let Some((symbols, _, start)) = &mut pending {
+                    symbols.push(Symbol {
+                        name: name.clone(),
+                        kind: SymbolKind::Binding,
+                        span: Span::new(*start, span.end),
+                        selection_span: span,
+                        children: Vec::new(),
+                    });
+                }
+            }
+            Token::Equals => naming = false,
+            Token::Semicolon => {
+                if let Some((symbols, depth, _)) =
+                    pending.take_if(|(_, depth, _)| *depth == frames.len())
+                {
+                    end_statement(&mut frames[depth - 1].1, symbols, span.end);
+                }
+                naming = false;
+            }
+            Token::LeftBrace => frames.push((span, Vec::new())),
+            Token::RightBrace if frames.len() > 1 => {
+                let (open, children) = frames.pop().unwrap_or_default();
+                close_block(&mut frames, open, span.end, children);
+            }
+            _ => {}
+        }
+
+        last_end = span.end;
+    }
+
+    // Whatever is still open runs to the end of the input
+    if let Some((symbols, depth, _)) = pending.take_if(|(_, depth, _)| *depth <= frames.len()) {
+        end_statement(&mut frames[depth - 1].1, symbols, last_end);
+    }
+    while frames.len() > 1 {
+        let (open, children) = frames.pop().unwrap_or_default();
+        close_block(&mut frames, open, last_end, children);
+    }
+
+    frames.pop().map(|(_, symbols)| symbols).unwrap_or_default()
+}
+
+// Adds the bindings of a `let` statement ending at `end` to its block
+fn end_statement(block: &mut Vec<Symbol>, symbols: Vec<Symbol>, end: usize) {
+    block.extend(symbols.into_iter().map(|mut symbol| {
+        symbol.span.end = end;
+        symbol
+    }));
+}
+
+// Adds a finished block to its parent, unless it declares nothing
+fn close_block(frames: &mut [(Span, Vec<Symbol>)], open: Span, end: usize, children: Vec<Symbol>) {
+    if children.is_empty() {
+        return;
+    }
+    if let Some((_, parent)) = frames.last_mut() {
+        parent.push(Symbol {
+            name: "{ }".to_string(),
+            kind: SymbolKind::Block,
+            span: Span::new(open.start, end),
+            selection_span: open,
+            children,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Renders the symbol tree one symbol per line, indented by depth
+    fn outline(symbols: &[Symbol], depth: usize, source: &str, out: &mut String) {
+        for symbol in symbols {
+            out.push_str(&format!(
+                "{}{:?} {} `{}`\n",
+                "  ".repeat(depth),
+                symbol.kind,
+                symbol.name,
+                &source[symbol.span.start..symbol.span.end]
+            ));
+            outline(&symbol.children, depth + 1, source, out);
+        }
+    }
+
+    fn outline_of(source: &str) -> String {
+        let mut out = String::new();
+        outline(&document_symbols(source), 0, source, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let source = "let a = 1; { 2; } { let b = a; { let c = b; } } let d = 4;";
+
+        assert_eq!(
+            outline_of(source),
+            "Binding a `let a = 1;`\n\
+             Block { } `{ let b = a; { let c = b; } }`\n  \
+               Binding b `let b = a;`\n  \
+               Block { } `{ let c = b; }`\n    \
+                 Binding c `let c = b;`\n\
+             Binding d `let d = 4;`\n"
+        );
+    }
+
+    #[test]
+    fn test_selection_span_is_the_name() {
+        let symbols = document_symbols("let width = 3;");
+
+        assert_eq!(symbols[0].selection_span, Span::new(4, 9));
+        assert_eq!(symbols[0].span, Span::new(0, 14));
+    }
+
+    #[test]
+    fn test_pattern_names_are_symbols() {
+        let source = "let (a, (_, b)) = (1, (2, 3)); { let (c,) = (a,); }";
+
+        assert_eq!(
+            outline_of(source),
+            "Binding a `let (a, (_, b)) = (1, (2, 3));`\n\
+             Binding b `let (a, (_, b)) = (1, (2, 3));`\n\
+             Block { } `{ let (c,) = (a,); }`\n  \
+               Binding c `let (c,) = (a,);`\n"
+        );
+        assert_eq!(
+            document_symbols(source)[1].selection_span,
+            Span::new(12, 13)
+        );
+    }
+
+    #[test]
+    fn test_incomplete_code() {
+        assert_eq!(
+            outline_of("let a = 1; { let b = (a +"),
+            "Binding a `let a = 1;`\n\
+             Block { } `{ let b = (a +`\n  \
+               Binding b `let b = (a +`\n"
+        );
+    }
+}
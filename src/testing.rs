@@ -0,0 +1,314 @@
+use crate::parser::{Expr, Program, Stmt};
+use std::fmt::Debug;
+
+/// Asserts that two ASTs are equal. On mismatch, panics with the path to the
+/// first differing node and both versions of that node, rather than the Debug
+/// dump of both trees that `assert_eq!` prints.
+#[track_caller]
+pub fn assert_ast_eq<T: AstNode + ?Sized>(actual: &T, expected: &T) {
+    if let Some(difference) = first_difference(actual, expected) {
+        panic!("{}", difference);
+    }
+}
+
+/// The first place two ASTs differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// Route from the root to the differing node, such as `statements[1].value.left`
+    pub path: String,
+    pub actual: String,
+    pub expected: String,
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "ASTs differ at {}", self.path)?;
+        writeln!(f, "  actual:   {}", self.actual)?;
+        write!(f, "  expected: {}", self.expected)
+    }
+}
+
+/// Returns where two ASTs first differ, in depth-first order, or None if they are equal
+pub fn first_difference<T: AstNode + ?Sized>(actual: &T, expected: &T) -> Option<Difference> {
+    let mut path = Vec::new();
+    actual
+        .diff(expected, &mut path)
+        .map(|(actual, expected)| Difference {
+            path: render_path(&path),
+            actual,
+            expected,
+        })
+}
+
+/// AST nodes that can be compared structurally
+pub trait AstNode {
+    /// Compares two nodes, pushing the route to the first difference onto
+    /// `path` and returning descriptions of both sides there
+    fn diff(&self, other: &Self, path: &mut Vec<String>) -> Option<(String, String)>;
+}
+
+impl AstNode for Program {
+    fn diff(&self, other: &Self, path: &mut Vec<String>) -> Option<(String, String)> {
+        path.push("statements".to_string());
+        diff_lists(&self.statements, &other.statements, path)
+    }
+}
+
+impl AstNode for [Stmt] {
+    fn diff(&self, other: &Self, path: &mut Vec<String>) -> Option<(String, String)> {
+        diff_lists(self, other, path)
+    }
+}
+
+impl AstNode for Stmt {
+    fn diff(&self, other: &Self, path: &mut Vec<String>) -> Option<(String, String)> {
+        match (self, other) {
+            (
+                Stmt::Let { name, value, doc },
+                Stmt::Let {
+                    name: other_name,
+                    value: other_value,
+                    doc: other_doc,
+                },
+            ) => field("name", name, other_name, path)
+                .or_else(|| field("doc", doc, other_doc, path))
+                .or_else(|| child("value", value, other_value, path)),
+            (
+                Stmt::Destructure {
+                    pattern,
+                    value,
+                    doc,
+                },
+                Stmt::Destructure {
+                    pattern: other_pattern,
+                    value: other_value,
+                    doc: other_doc,
+                },
+            ) => field("pattern", pattern, other_pattern, path)
+                .or_else(|| field("doc", doc, other_doc, path))
+                .or_else(|| child("value", value, other_value, path)),
+            (Stmt::Expression(expr), Stmt::Expression(other_expr)) => {
+                child("expr", expr, other_expr, path)
+            }
+            (Stmt::Block(statements), Stmt::Block(other_statements)) => {
+                path.push("block".to_string());
+                match diff_lists(statements, other_statements, path) {
+                    None => {
+                        path.pop();
+                        None
+                    }
+                    found => found,
+                }
+            }
+            _ => Some((summary_stmt(self), summary_stmt(other))),
+        }
+    }
+}
+
+impl AstNode for Expr {
+    fn diff(&self, other: &Self, path: &mut Vec<String>) -> Option<(String, String)> {
+        match (self, other) {
+            (
+                Expr::Binary {
+                    left,
+                    operator,
+                    right,
+                },
+                Expr::Binary {
+                    left: other_left,
+                    operator: other_operator,
+                    right: other_right,
+                },
+            ) => field("operator", operator, other_operator, path)
+                .or_else(|| child("left", left.as_ref(), other_left.as_ref(), path))
+                .or_else(|| child("right", right.as_ref(), other_right.as_ref(), path)),
+            (
+                Expr::Unary { operator, operand },
+                Expr::Unary {
+                    operator: other_operator,
+                    operand: other_operand,
+                },
+            ) => field("operator", operator, other_operator, path)
+                .or_else(|| child("operand", operand.as_ref(), other_operand.as_ref(), path)),
+            (Expr::Grouping(inner), Expr::Grouping(other_inner)) => {
+                child("inner", inner.as_ref(), other_inner.as_ref(), path)
+            }
+            (Expr::Tuple(elements), Expr::Tuple(other_elements))
+                if elements.len() == other_elements.len() =>
+            {
+                elements.iter().zip(other_elements).enumerate().find_map(
+                    |(index, (element, other_element))| {
+                        child(&format!("[{}]", index), element, other_element, path)
+                    },
+                )
+            }
+            _ if self == other => None,
+            _ => Some((format!("{:?}", self), format!("{:?}", other))),
+        }
+    }
+}
+
+fn diff_lists(
+    actual: &[Stmt],
+    expected: &[Stmt],
+    path: &mut Vec<String>,
+) -> Option<(String, String)> {
+    for (index, (left, right)) in actual.iter().zip(expected).enumerate() {
+        path.push(format!("[{}]", index));
+        if let Some(found) = left.diff(right, path) {
+            return Some(found);
+        }
+        path.pop();
+    }
+
+    if actual.len() != expected.len() {
+        let index = actual.len().min(expected.len());
+        let describe = |statements: &[Stmt]| match statements.get(index) {
+            Some(stmt) => summary_stmt(stmt),
+            None => format!("nothing ({} statements)", statements.len()),
+        };
+        path.push(format!("[{}]", index));
+        return Some((describe(actual), describe(expected)));
+    }
+
+    None
+}
+
+// Compares a leaf field, recording its name in the path if it differs
+fn field<T: PartialEq + Debug>(
+    name: &str,
+    actual: &T,
+    expected: &T,
+    path: &mut Vec<String>,
+) -> Option<(String, String)> {
+    (actual != expected).then(|| {
+        path.push(name.to_string());
+        (format!("{:?}", actual), format!("{:?}", expected))
+    })
+}
+
+// Compares a child node, leaving its name in the path only if it differs
+fn child<T: AstNode + ?Sized>(
+    name: &str,
+    actual: &T,
+    expected: &T,
+    path: &mut Vec<String>,
+) -> Option<(String, String)> {
+    path.push(name.to_string());
+    let found = actual.diff(expected, path);
+    if found.is_none() {
+        path.pop();
+    }
+    found
+}
+
+/// Parses `source` as one expression, without its `;`, with `px` registered
+/// as a `Length` suffix: the fixture of the unit tests for passes over
+/// expressions
+#[cfg(test)]
+pub(crate) fn expression(source: &str) -> Expr {
+    let units = crate::suffix::LiteralSuffixes::new().register("px", "Length");
+    match crate::suffix::parse_with_suffixes(&format!("{};", source), &units)
+        .unwrap()
+        .statements
+        .remove(0)
+    {
+        Stmt::Expression(expr) => expr,
+        other => panic!("expected an expression statement, got {:?}", other),
+    }
+}
+
+// One line describing a statement, since a whole block's Debug output is long
+fn summary_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Block(statements) => format!("Block with {} statement(s)", statements.len()),
+        stmt => stmt.to_string(),
+    }
+}
+
+fn render_path(path: &[String]) -> String {
+    let mut rendered = String::new();
+    for segment in path {
+        if !segment.starts_with('[') && !rendered.is_empty() {
+            rendered.push('.');
+        }
+        rendered.push_str(segment);
+    }
+    if rendered.is_empty() {
+        rendered.push_str("the root");
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_source;
+
+    fn difference(actual: &str, expected: &str) -> Option<Difference> {
+        first_difference(
+            &parse_source(actual).unwrap(),
+            &parse_source(expected).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_equal_programs() {
+        let program = parse_source("let a = 1; { a * 2; }").unwrap();
+
+        assert_ast_eq(&program, &program.clone());
+        assert_eq!(difference("1 + 2;", "1 + 2;"), None);
+    }
+
+    #[test]
+    fn test_path_to_differing_leaf() {
+        assert_eq!(
+            difference(
+                "let a = 1; let b = (x + 2) * 3;",
+                "let a = 1; let b = (x + 4) * 3;"
+            ),
+            Some(Difference {
+                path: "statements[1].value.left.inner.right".to_string(),
+                actual: "Number(2)".to_string(),
+                expected: "Number(4)".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_differing_operators_and_kinds() {
+        let operator = difference("a - b;", "a + b;").unwrap();
+        assert_eq!(operator.path, "statements[0].expr.operator");
+        assert_eq!(
+            (operator.actual.as_str(), operator.expected.as_str()),
+            ("Subtract", "Add")
+        );
+
+        let kind = difference("{ let x = 1; }", "{ x; }").unwrap();
+        assert_eq!(kind.path, "statements[0].block[0]");
+        assert_eq!(
+            (kind.actual.as_str(), kind.expected.as_str()),
+            ("let x = 1;", "x;")
+        );
+    }
+
+    #[test]
+    fn test_missing_statements() {
+        let missing = difference("1;", "1; { 2; }").unwrap();
+
+        assert_eq!(missing.path, "statements[1]");
+        assert_eq!(missing.actual, "nothing (1 statements)");
+        assert_eq!(missing.expected, "Block with 1 statement(s)");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "ASTs differ at statements[0].name\n  actual:   \"a\"\n  expected: \"b\""
+    )]
+    fn test_assert_ast_eq_panics_with_diff() {
+        assert_ast_eq(
+            &parse_source("let a = 1;").unwrap(),
+            &parse_source("let b = 1;").unwrap(),
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Types of expressions and bindings, inferred once so the REPL, inlay hints
+//! and editor tooling all describe a program the same way.
+
+use crate::error::Error;
+use crate::lexer::{Lexer, Span, Token};
+use crate::parser::{BinaryOp, Expr, Pattern, Program, Stmt};
+use crate::resolve::{Resolutions, resolve_program};
+use crate::rewrite::{Step, binding_spans, expression_spans};
+use crate::suffix::{LiteralSuffixes, parse_with_suffixes};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The type of a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// A plain integer
+    Int,
+    /// A floating-point number
+    Float,
+    /// A host type produced by a registered literal suffix, such as `Length`
+    /// for `10px`
+    Named(String),
+    Tuple(Vec<Type>),
+    /// A type that cannot be inferred: the use of an unbound name, or an
+    /// operation on values that do not support it
+    Unknown,
+}
+
+impl Type {
+    /// Returns the type as it is shown to users, such as `(Int, Length)`
+    pub fn display(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Tuple(elements) => {
+                write!(f, "(")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if elements.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Type::Unknown => write!(f, "_"),
+        }
+    }
+}
+
+/// The inferred type of every expression and bound name of a program, keyed
+/// by span
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeMap {
+    /// Expressions, each after the expressions inside it
+    expressions: Vec<(Span, Type)>,
+    /// Bound names, in source order
+    bindings: Vec<(Span, Type)>,
+}
+
+impl TypeMap {
+    /// Returns the type of the innermost expression or bound name containing
+    /// `span`, which may be empty to ask about a cursor position
+    pub fn type_of(&self, span: Span) -> Option<&Type> {
+        let contains = |(node, _): &&(Span, Type)| node.start <= span.start && span.end <= node.end;
+        self.bindings
+            .iter()
+            .find(contains)
+            .or_else(|| {
+                self.expressions
+                    .iter()
+                    .filter(contains)
+                    .min_by_key(|(node, _)| node.end - node.start)
+            })
+            .map(|(_, ty)| ty)
+    }
+
+    /// Returns every bound name with its type, in source order
+    pub fn bindings(&self) -> &[(Span, Type)] {
+        &self.bindings
+    }
+}
+
+/// Parses `source`, accepting the registered literal suffixes, and infers the
+/// type of each of its expressions and bindings.
+///
+/// Arithmetic on integers gives an integer. A suffixed value can be added to
+/// or subtracted from another of the same type, scaled by an integer, and
+/// divided by another of the same type to give an integer. Anything else,
+/// such as arithmetic on tuples, has the unknown type.
+pub fn type_map(source: &str, suffixes: &LiteralSuffixes) -> Result<TypeMap, Error> {
+    let program = parse_with_suffixes(source, suffixes)?;
+    let mut tokens = Lexer::new(source)
+        .with_suffixes(suffixes)
+        .tokenize_spanned();
+    tokens.pop();
+    Ok(infer(&program, &tokens, suffixes))
+}
+
+fn infer(program: &Program, tokens: &[(Token, Span)], suffixes: &LiteralSuffixes) -> TypeMap {
+    let mut inference = Inference {
+        suffixes,
+        expressions: expression_spans(program, tokens),
+        bindings: binding_spans(program, tokens),
+        definitions: HashMap::new(),
+        resolutions: resolve_program(program, tokens),
+        map: TypeMap::default(),
+    };
+    inference.statements(&program.statements, &mut Vec::new());
+    inference.map
+}
+
+struct Inference<'a> {
+    suffixes: &'a LiteralSuffixes,
+    expressions: HashMap<(Vec<usize>, Vec<Step>), Span>,
+    bindings: HashMap<Vec<usize>, Vec<Span>>,
+    /// The type of each bound name seen so far
+    definitions: HashMap<Span, Type>,
+    resolutions: Resolutions,
+    map: TypeMap,
+}
+
+impl Inference<'_> {
+    fn statements(&mut self, statements: &[Stmt], path: &mut Vec<usize>) {
+        for (index, stmt) in statements.iter().enumerate() {
+            path.push(index);
+            self.statement(stmt, path);
+            path.pop();
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, path: &mut Vec<usize>) {
+        match stmt {
+            Stmt::Let { value, .. } => {
+                let ty = self.expression(value, path, &mut Vec::new());
+                self.bind(path, vec![ty]);
+            }
+            Stmt::Destructure { pattern, value, .. } => {
+                let ty = self.expression(value, path, &mut Vec::new());
+                let mut types = Vec::new();
+                destructure(pattern, &ty, &mut types);
+                self.bind(path, types);
+            }
+            Stmt::Expression(expr) => {
+                self.expression(expr, path, &mut Vec::new());
+            }
+            Stmt::Block(statements) => self.statements(statements, path),
+        }
+    }
+
+    // Records the types of the names a statement binds, in source order
+    fn bind(&mut self, path: &[usize], types: Vec<Type>) {
+        let spans = self.bindings.get(path).cloned().unwrap_or_default();
+        for (span, ty) in spans.into_iter().zip(types) {
+            self.definitions.insert(span, ty.clone());
+            self.map.bindings.push((span, ty));
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr, statement: &[usize], steps: &mut Vec<Step>) -> Type {
+        let span = self
+            .expressions
+            .get(&(statement.to_vec(), steps.clone()))
+            .copied();
+        let mut child = |inference: &mut Self, expr: &Expr, step: Step| {
+            steps.push(step);
+            let ty = inference.expression(expr, statement, steps);
+            steps.pop();
+            ty
+        };
+
+        let ty = match expr {
+            Expr::Number(_) | Expr::BigNumber(_) => Type::Int,
+            Expr::Float(_) => Type::Float,
+            Expr::Suffixed { suffix, .. } => self
+                .suffixes
+                .type_name(suffix)
+                .map_or(Type::Unknown, |name| Type::Named(name.to_string())),
+            Expr::Identifier(_) => span
+                .and_then(|span| self.resolutions.definition_of(span))
+                .and_then(|definition| self.definitions.get(&definition).cloned())
+                .unwrap_or(Type::Unknown),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = child(self, left, Step::Left);
+                let right = child(self, right, Step::Right);
+                binary(&left, operator, &right)
+            }
+            Expr::Unary { operand, .. } => match child(self, operand, Step::Operand) {
+                Type::Tuple(_) => Type::Unknown,
+                ty => ty,
+            },
+            Expr::Grouping(inner) => child(self, inner, Step::Inner),
+            Expr::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, element)| child(self, element, Step::Element(index)))
+                    .collect(),
+            ),
+        };
+
+        if let Some(span) = span {
+            self.map.expressions.push((span, ty.clone()));
+        }
+        ty
+    }
+}
+
+fn binary(left: &Type, operator: &BinaryOp, right: &Type) -> Type {
+    match (left, operator, right) {
+        (Type::Int, _, Type::Int) => Type::Int,
+        // An integer mixed with a float is converted to one
+        (Type::Int | Type::Float, _, Type::Int | Type::Float) => Type::Float,
+        (Type::Named(a), BinaryOp::Add | BinaryOp::Subtract, Type::Named(b)) if a == b => {
+            left.clone()
+        }
+        (Type::Named(a), BinaryOp::Divide, Type::Named(b)) if a == b => Type::Int,
+        (Type::Named(_), BinaryOp::Multiply | BinaryOp::Divide, Type::Int) => left.clone(),
+        (Type::Int, BinaryOp::Multiply, Type::Named(_)) => right.clone(),
+        _ => Type::Unknown,
+    }
+}
+
+// The types of the names a pattern binds, in source order, when it matches
+// a value of type `ty`
+fn destructure(pattern: &Pattern, ty: &Type, types: &mut Vec<Type>) {
+    match (pattern, ty) {
+        (Pattern::Identifier(_), _) => types.push(ty.clone()),
+        (Pattern::Tuple(patterns), Type::Tuple(elements)) if patterns.len() == elements.len() => {
+            for (pattern, element) in patterns.iter().zip(elements) {
+                destructure(pattern, element, types);
+            }
+        }
+        (pattern, _) => types.extend(pattern.bindings().iter().map(|_| Type::Unknown)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units() -> LiteralSuffixes {
+        LiteralSuffixes::new().register("px", "Length")
+    }
+
+    // The displayed type of the innermost node covering the first occurrence
+    // of `text`
+    fn type_at(source: &str, text: &str) -> String {
+        let start = source.find(text).unwrap();
+        let span = Span::new(start, start + text.len());
+        type_map(source, &units())
+            .unwrap()
+            .type_of(span)
+            .map_or("none".to_string(), Type::display)
+    }
+
+    #[test]
+    fn test_types_of_expressions() {
+        let source = "let w = 10px * 2; (w + 1px, w / 5px, 3 - 1); w + 2;";
+
+        assert_eq!(type_at(source, "10px * 2"), "Length");
+        assert_eq!(type_at(source, "w + 1px"), "Length");
+        assert_eq!(type_at(source, "w / 5px"), "Int");
+        assert_eq!(
+            type_at(source, "(w + 1px, w / 5px, 3 - 1)"),
+            "(Length, Int, Int)"
+        );
+        assert_eq!(type_at(source, "w + 2"), "_");
+    }
+
+    #[test]
+    fn test_floats_widen_integer_arithmetic() {
+        let source = "let r = 2.5; (r * 2, 1 + 1, r / 2.0);";
+
+        assert_eq!(type_at(source, "r * 2"), "Float");
+        assert_eq!(
+            type_at(source, "(r * 2, 1 + 1, r / 2.0)"),
+            "(Float, Int, Float)"
+        );
+    }
+
+    #[test]
+    fn test_innermost_node_at_a_position() {
+        let source = "let x = 1; (x, 2px);";
+        let types = type_map(source, &units()).unwrap();
+        let at = |offset| types.type_of(Span::new(offset, offset)).map(Type::display);
+
+        assert_eq!(at(4).as_deref(), Some("Int"));
+        assert_eq!(at(12).as_deref(), Some("Int"));
+        assert_eq!(at(16).as_deref(), Some("Length"));
+        assert_eq!(at(14).as_deref(), Some("(Int, Length)"));
+        assert_eq!(at(1), None);
+    }
+
+    #[test]
+    fn test_destructured_bindings() {
+        let source = "let (a, (b, _)) = (1px, (2, 3)); let (c, d) = a;";
+        let types: Vec<String> = type_map(source, &units())
+            .unwrap()
+            .bindings()
+            .iter()
+            .map(|(_, ty)| ty.display())
+            .collect();
+
+        assert_eq!(types, vec!["Length", "Int", "_", "_"]);
+    }
+
+    #[test]
+    fn test_shadowed_bindings_keep_their_own_types() {
+        let types = type_map("let x = 1px; { let x = (x, 1); x; } x;", &units()).unwrap();
+        let at = |offset| types.type_of(Span::new(offset, offset)).map(Type::display);
+
+        assert_eq!(at(24).as_deref(), Some("Length"));
+        assert_eq!(at(31).as_deref(), Some("(Length, Int)"));
+        assert_eq!(at(36).as_deref(), Some("Length"));
+        assert_eq!(Type::Tuple(vec![Type::Int]).display(), "(Int,)");
+    }
+}
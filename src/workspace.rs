@@ -0,0 +1,521 @@
+use crate::analysis::{arity_mismatches, check_ranges};
+use crate::fold::{ConstLimits, check_constants};
+use crate::lexer::{Lexer, Span};
+use crate::lint::{self, LintLevel};
+use crate::parser::Parser;
+use crate::resolve::resolve_program;
+use crate::rewrite;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// File extension of Oxide source files
+pub const SOURCE_EXTENSION: &str = "ox";
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in one file of a workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiagnostic {
+    pub path: PathBuf,
+    pub severity: Severity,
+    /// Byte range of the problem in the file, when known
+    pub span: Option<Span>,
+    /// One-based line and column of the start of the span, when known
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl fmt::Display for FileDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// What to report besides syntax errors
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CheckOptions {
+    /// Level of each lint by code; lints that are not listed are allowed
+    pub lints: BTreeMap<String, LintLevel>,
+    /// Memory-map files instead of reading them; has no effect unless the
+    /// crate is built with the `mmap` feature
+    pub memory_map: bool,
+    /// Bounds on evaluating constant expressions during the check
+    pub const_limits: ConstLimits,
+    /// Also propagate value ranges through bindings, warning about division
+    /// by zero and overflow that no binding of the free names avoids
+    pub ranges: bool,
+}
+
+/// Returns every `.ox` file under `dir`, recursively, in sorted order.
+/// Hidden directories and `target` are skipped.
+pub fn source_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_source_files(dir, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                collect_source_files(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == SOURCE_EXTENSION) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Lexes and parses `source`, attributing every error to `path`
+pub fn check_source(path: &Path, source: &str) -> Vec<FileDiagnostic> {
+    check_source_with(path, source, &CheckOptions::default())
+}
+
+/// Like [`check_source`], and also reports the lints `options` enables for
+/// sources that parse. Destructuring lets that cannot match their value are
+/// always errors, as is constant arithmetic that would fail whenever it ran,
+/// which is reported at the failing subexpression. Uses of a name that is
+/// only bound in a block that has already ended are warnings.
+pub fn check_source_with(path: &Path, source: &str, options: &CheckOptions) -> Vec<FileDiagnostic> {
+    let error = |span: Option<Span>, message: String| FileDiagnostic {
+        path: path.to_path_buf(),
+        severity: Severity::Error,
+        span,
+        position: Some(line_column(source, span.map_or(0, |span| span.start))),
+        message,
+    };
+
+    let tokens = match Lexer::new(source).tokenize_strict() {
+        Ok(tokens) => tokens,
+        Err(lex_error) => return vec![error(lex_error.span(), lex_error.to_string())],
+    };
+
+    let program = match Parser::from_spanned(tokens.clone()).parse() {
+        Ok(program) => program,
+        Err(errors) => {
+            return errors
+                .errors
+                .iter()
+                .map(|parse_error| error(parse_error.span(), parse_error.to_string()))
+                .collect();
+        }
+    };
+
+    // Checks and lints are found on the AST, which does not record source positions
+    let unpositioned = |severity, message| FileDiagnostic {
+        path: path.to_path_buf(),
+        severity,
+        span: None,
+        position: None,
+        message,
+    };
+
+    let expression_spans = rewrite::expression_spans(&program, &tokens);
+    let constants = check_constants(&program, &options.const_limits);
+    let failing: Vec<Vec<usize>> = constants
+        .iter()
+        .map(|(statement, _)| statement.clone())
+        .collect();
+    let constants = constants.into_iter().map(|(statement, const_error)| {
+        let span = expression_spans.get(&(statement, const_error.steps.clone()));
+        error(span.copied(), const_error.to_string())
+    });
+
+    // Statements whose constants already fail are not reported twice
+    let ranges = if options.ranges {
+        check_ranges(&program)
+    } else {
+        Vec::new()
+    };
+    let ranges = ranges
+        .into_iter()
+        .filter(|(statement, _)| !failing.contains(statement))
+        .map(|(statement, const_error)| {
+            let span = expression_spans.get(&(statement, const_error.steps.clone()));
+            FileDiagnostic {
+                severity: Severity::Warning,
+                ..error(
+                    span.copied(),
+                    format!("{} for every value of the names it uses", const_error),
+                )
+            }
+        });
+
+    let resolutions = resolve_program(&program, &tokens);
+    let out_of_scope = resolutions
+        .out_of_scope()
+        .iter()
+        .map(|&(used, binding)| {
+            let (line, column) = line_column(source, binding.start);
+            FileDiagnostic {
+                severity: Severity::Warning,
+                ..error(
+                    Some(used),
+                    format!(
+                        "`{}` may be unbound here: its binding at {}:{} is inside a block that has ended",
+                        &source[used.start..used.end],
+                        line,
+                        column
+                    ),
+                )
+            }
+        });
+
+    let mismatches = arity_mismatches(&program)
+        .into_iter()
+        .map(|mismatch| unpositioned(Severity::Error, mismatch.to_string()));
+
+    let lints = lint::check(&program).into_iter().filter_map(|found| {
+        let severity = match options.lints.get(found.code)? {
+            LintLevel::Allow => return None,
+            LintLevel::Warn => Severity::Warning,
+            LintLevel::Deny => Severity::Error,
+        };
+        Some(unpositioned(
+            severity,
+            format!(
+                "[{}] {}; use `{}`",
+                found.code, found.message, found.suggestion
+            ),
+        ))
+    });
+
+    constants
+        .chain(ranges)
+        .chain(out_of_scope)
+        .chain(mismatches)
+        .chain(lints)
+        .collect()
+}
+
+/// Checks one file; a file that cannot be read is reported as a diagnostic
+pub fn check_file(path: &Path, options: &CheckOptions) -> Vec<FileDiagnostic> {
+    #[cfg(feature = "mmap")]
+    if options.memory_map {
+        return match crate::mmap::MappedSource::open(path) {
+            Ok(source) => check_source_with(path, source.as_str(), options),
+            Err(error) => vec![unreadable(path, error)],
+        };
+    }
+
+    match fs::read_to_string(path) {
+        Ok(source) => check_source_with(path, &source, options),
+        Err(error) => vec![unreadable(path, error)],
+    }
+}
+
+const UNREADABLE: &str = "could not read file: ";
+
+fn unreadable(path: &Path, error: io::Error) -> FileDiagnostic {
+    FileDiagnostic {
+        path: path.to_path_buf(),
+        severity: Severity::Error,
+        span: None,
+        position: None,
+        message: format!("{}{}", UNREADABLE, error),
+    }
+}
+
+/// The diagnostics of a set of files
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CheckReport {
+    /// Each file checked with its diagnostics, in the order the files were
+    /// given
+    pub files: Vec<(PathBuf, Vec<FileDiagnostic>)>,
+}
+
+impl CheckReport {
+    /// Every diagnostic, grouped by file
+    pub fn diagnostics(&self) -> impl Iterator<Item = &FileDiagnostic> {
+        self.files.iter().flat_map(|(_, diagnostics)| diagnostics)
+    }
+
+    /// The files that could not be read, each with why. They are among the
+    /// diagnostics too, for callers that report them alongside the rest.
+    pub fn unreadable(&self) -> impl Iterator<Item = (&Path, &str)> {
+        self.diagnostics()
+            .filter(|diagnostic| diagnostic.position.is_none())
+            .filter_map(|diagnostic| {
+                let reason = diagnostic.message.strip_prefix(UNREADABLE)?;
+                Some((diagnostic.path.as_path(), reason))
+            })
+    }
+
+    pub fn errors(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warnings(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+}
+
+/// Checks files in parallel, on as many threads as the machine has cores.
+/// The report is the same however the work was scheduled: files keep the
+/// order they were given in, and each file's diagnostics their own order.
+pub fn check_files(paths: &[PathBuf], options: &CheckOptions) -> CheckReport {
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(paths.len());
+    let next = AtomicUsize::new(0);
+    let mut results = vec![Vec::new(); paths.len()];
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut checked = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(index) else {
+                            return checked;
+                        };
+                        checked.push((index, check_file(path, options)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            let checked = worker
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload));
+            for (index, diagnostics) in checked {
+                results[index] = diagnostics;
+            }
+        }
+    });
+
+    CheckReport {
+        files: paths.iter().cloned().zip(results).collect(),
+    }
+}
+
+/// Checks every source file under `dir`, returning the diagnostics grouped by
+/// file in path order
+pub fn check_dir(dir: &Path, options: &CheckOptions) -> io::Result<Vec<FileDiagnostic>> {
+    let report = check_files(&source_files(dir)?, options);
+    Ok(report.diagnostics().cloned().collect())
+}
+
+/// One-based line and column of a byte offset, counting columns in characters
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("oxide-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn write(&self, relative: &str, contents: &str) {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_check_source_positions() {
+        let diagnostics = check_source(Path::new("main.ox"), "let a = 1;\nlet = 2;");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position, Some((2, 5)));
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .starts_with("main.ox:2:5: error: ")
+        );
+    }
+
+    #[test]
+    fn test_lex_errors_are_reported() {
+        let diagnostics = check_source(Path::new("a.ox"), "1 $ 2;");
+
+        assert_eq!(diagnostics[0].span, Some(Span::new(2, 3)));
+        assert_eq!(diagnostics[0].position, Some((1, 3)));
+    }
+
+    #[test]
+    fn test_arity_mismatches_are_errors() {
+        let diagnostics = check_source(Path::new("a.ox"), "let (x, y) = (1, 2, 3);");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert!(
+            diagnostics[0]
+                .to_string()
+                .starts_with("a.ox: error: pattern `(x, y)`")
+        );
+    }
+
+    #[test]
+    fn test_failing_constants_are_errors_at_the_subexpression() {
+        let diagnostics = check_source(
+            Path::new("a.ox"),
+            "let x = 1;\nx + 10 / (x - x) + 4 / (2 - 2);",
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Some(Span::new(30, 41)));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "a.ox:2:20: error: this divides by zero"
+        );
+    }
+
+    #[test]
+    fn test_range_failures_are_opt_in_warnings() {
+        let source = "let d = n * 0;\n10 / d + 1 / 0;\n(n + 1) / (d * 2);";
+        let options = CheckOptions {
+            ranges: true,
+            ..CheckOptions::default()
+        };
+        let messages: Vec<String> = check_source_with(Path::new("a.ox"), source, &options)
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+
+        assert_eq!(check_source(Path::new("a.ox"), source).len(), 1);
+        assert_eq!(
+            messages,
+            vec![
+                "a.ox:2:10: error: this divides by zero",
+                "a.ox:3:1: warning: this divides by zero for every value of the names it uses",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_uses_after_the_binding_block_are_warnings() {
+        let diagnostics = check_source(Path::new("a.ox"), "{\n  let total = 1;\n}\ntotal * 2;");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].span, Some(Span::new(21, 26)));
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "a.ox:4:1: warning: `total` may be unbound here: its binding at 2:7 is inside a block that has ended"
+        );
+    }
+
+    #[test]
+    fn test_check_dir_discovers_source_files() {
+        let dir = TempDir::new("check-dir");
+        dir.write("good.ox", "let a = 1;");
+        dir.write("nested/bad.ox", "let b = ;");
+        dir.write("notes.txt", "let = ;");
+        dir.write(".hidden/bad.ox", "let = ;");
+
+        assert_eq!(
+            source_files(&dir.0).unwrap(),
+            vec![dir.0.join("good.ox"), dir.0.join("nested/bad.ox")]
+        );
+
+        let diagnostics = check_dir(&dir.0, &CheckOptions::default()).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].path, dir.0.join("nested/bad.ox"));
+    }
+
+    #[test]
+    fn test_check_files_keeps_the_given_order() {
+        let dir = TempDir::new("check-files");
+        let mut paths = Vec::new();
+        for index in 0..40 {
+            let name = format!("{}.ox", index);
+            dir.write(&name, &format!("let a = ;\n{} / 0;", index));
+            paths.push(dir.0.join(name));
+        }
+        dir.write("warn.ox", "{ let b = 1; } b;");
+        paths.push(dir.0.join("warn.ox"));
+        paths.push(dir.0.join("missing.ox"));
+
+        let report = check_files(&paths, &CheckOptions::default());
+        let checked: Vec<&PathBuf> = report.files.iter().map(|(path, _)| path).collect();
+        assert_eq!(checked, paths.iter().collect::<Vec<_>>());
+        let sequential: Vec<FileDiagnostic> = paths
+            .iter()
+            .flat_map(|path| check_file(path, &CheckOptions::default()))
+            .collect();
+        assert_eq!(
+            report.diagnostics().cloned().collect::<Vec<_>>(),
+            sequential
+        );
+        assert_eq!((report.errors(), report.warnings()), (41, 1));
+        let unreadable: Vec<&Path> = report.unreadable().map(|(path, _)| path).collect();
+        assert_eq!(unreadable, [paths[41].as_path()]);
+    }
+
+    #[test]
+    fn test_lints_follow_their_levels() {
+        let source = "let a = +1; --a;";
+        let mut options = CheckOptions::default();
+
+        assert!(check_source_with(Path::new("a.ox"), source, &options).is_empty());
+
+        options
+            .lints
+            .insert("redundant-sign".to_string(), LintLevel::Warn);
+        let diagnostics = check_source_with(Path::new("a.ox"), source, &options);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "a.ox: warning: [redundant-sign] unary plus in `(+1)` has no effect; use `1`"
+        );
+    }
+}
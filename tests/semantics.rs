@@ -0,0 +1,162 @@
+//! Runs the operator semantics corpus in `tests/semantics` against every
+//! engine that evaluates Oxide, so they all agree on what each expression
+//! means.
+//!
+//! Each corpus line is an expression statement followed by its expected
+//! outcome, as in `1 + 2 * 3; // => 7`. An outcome is an integer, `overflow`
+//! or `division by zero`. Lines that are blank or only a comment are skipped.
+
+use oxide::analysis::check_ranges;
+use oxide::compiled::CompiledExpr;
+use oxide::fold::{ConstErrorKind, ConstLimits, evaluate_constant, fold_expression};
+use oxide::interpreter::{Environment, RuntimeError, Value};
+use oxide::{Expr, Program, Stmt, parse_source};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Value(i64),
+    Overflow,
+    DivisionByZero,
+}
+
+impl Outcome {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "overflow" => Some(Outcome::Overflow),
+            "division by zero" => Some(Outcome::DivisionByZero),
+            _ => text.parse().ok().map(Outcome::Value),
+        }
+    }
+
+    fn from_error(kind: ConstErrorKind) -> Self {
+        match kind {
+            ConstErrorKind::DivisionByZero => Outcome::DivisionByZero,
+            _ => Outcome::Overflow,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Value(value) => write!(f, "{}", value),
+            Outcome::Overflow => write!(f, "overflow"),
+            Outcome::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+/// An engine's outcome for an expression, or None where the engine leaves
+/// the expression for another one to decide
+type Engine = fn(&Expr) -> Option<Outcome>;
+
+const ENGINES: &[(&str, Engine)] = &[
+    ("interpreter", interpretation),
+    ("compiled", compiled_evaluation),
+    ("evaluate_constant", constant_evaluation),
+    ("fold_expression", folding),
+    ("check_ranges", range_analysis),
+];
+
+fn interpretation(expr: &Expr) -> Option<Outcome> {
+    match Environment::new().evaluate(expr) {
+        Ok(Value::Int(value)) => Some(Outcome::Value(value)),
+        Ok(_) => None,
+        Err(RuntimeError::Overflow) => Some(Outcome::Overflow),
+        Err(RuntimeError::DivisionByZero) => Some(Outcome::DivisionByZero),
+        Err(_) => None,
+    }
+}
+
+fn compiled_evaluation(expr: &Expr) -> Option<Outcome> {
+    match CompiledExpr::new(expr).evaluate(&[]) {
+        Ok(Value::Int(value)) => Some(Outcome::Value(value)),
+        Ok(_) => None,
+        Err(RuntimeError::Overflow) => Some(Outcome::Overflow),
+        Err(RuntimeError::DivisionByZero) => Some(Outcome::DivisionByZero),
+        Err(_) => None,
+    }
+}
+
+fn constant_evaluation(expr: &Expr) -> Option<Outcome> {
+    match evaluate_constant(expr, &ConstLimits::default()) {
+        Ok(value) => value.map(Outcome::Value),
+        Err(error) => Some(Outcome::from_error(error.kind)),
+    }
+}
+
+// Folding leaves operations that fail in place for the runtime to report
+fn folding(expr: &Expr) -> Option<Outcome> {
+    match fold_expression(expr.clone()) {
+        Expr::Number(value) => Some(Outcome::Value(value)),
+        _ => None,
+    }
+}
+
+// Range analysis only reports failures
+fn range_analysis(expr: &Expr) -> Option<Outcome> {
+    let program = Program {
+        statements: vec![Stmt::Expression(expr.clone())],
+    };
+    check_ranges(&program)
+        .into_iter()
+        .next()
+        .map(|(_, error)| Outcome::from_error(error.kind))
+}
+
+#[test]
+fn test_engines_agree_with_the_corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/semantics");
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ox"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no corpus files in {}", dir.display());
+
+    let mut failures = Vec::new();
+    let mut cases = 0;
+    for file in &files {
+        let source = fs::read_to_string(file).unwrap();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            let location = format!("{}:{}", file.display(), index + 1);
+            let (code, expected) = line
+                .split_once("// =>")
+                .unwrap_or_else(|| panic!("{}: missing `// => outcome`", location));
+            let expected = Outcome::parse(expected.trim())
+                .unwrap_or_else(|| panic!("{}: unknown outcome `{}`", location, expected.trim()));
+            let program = parse_source(code)
+                .unwrap_or_else(|errors| panic!("{}: {}", location, errors.errors[0]));
+            let [Stmt::Expression(expr)] = &program.statements[..] else {
+                panic!("{}: expected one expression statement", location);
+            };
+
+            cases += 1;
+            let mut decided = false;
+            for (engine, evaluate) in ENGINES {
+                match evaluate(expr) {
+                    Some(outcome) if outcome != expected => failures.push(format!(
+                        "{}: {} gives {}, expected {}",
+                        location, engine, outcome, expected
+                    )),
+                    Some(_) => decided = true,
+                    None => {}
+                }
+            }
+            if !decided {
+                failures.push(format!("{}: no engine evaluates this", location));
+            }
+        }
+    }
+
+    assert!(cases > 0);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}